@@ -0,0 +1,8 @@
+//! Hosts the core in its own process for the subprocess backend of the shell.
+
+use app_core::CoreApp;
+use shared_types::{AppState, Message};
+
+fn main() {
+    hot_reload_interface::serve::<CoreApp, AppState, Message>();
+}
@@ -1,9 +1,10 @@
+use hot_reload_interface::{
+    hot_reload_core, stylesheet, AppInterface, CoreCommand, CoreSubscription, RemoteApp, ViewNode,
+};
 use iced::widget::{button, column, Text};
 use iced::{Alignment, Element, Length};
 use log::trace;
-use log::LevelFilter;
-use shared_types::{AppInterface, AppState, Message};
-use simplelog::{ConfigBuilder, SimpleLogger};
+use shared_types::{AppState, Message};
 
 /// The implementation of the AppInterface
 #[repr(C)]
@@ -11,8 +12,9 @@ pub struct CoreApp {
     pub state: AppState,
 }
 
-impl AppInterface for CoreApp {
-    fn update(&mut self, message: Message) {
+#[hot_reload_core]
+impl AppInterface<AppState, Message> for CoreApp {
+    fn update(&mut self, message: Message) -> Vec<CoreCommand<Message>> {
         match message {
             Message::Increment => {
                 trace!("Increment!");
@@ -22,18 +24,25 @@ impl AppInterface for CoreApp {
                 trace!("Decrement!");
                 self.state.counter -= 1
             }
-            Message::Reload => (), // handled in the ShellApp
-            Message::Tick => (),
         }
+
+        Vec::new()
     }
 
     fn view(&self) -> Element<'static, Message> {
+        let style = stylesheet();
+
         iced::widget::Container::new(
             column![
-                button("+").on_press(Message::Increment),
-                Text::new(format!("Counter: {}", self.state.counter)),
-                button("-").on_press(Message::Decrement),
+                button("+")
+                    .padding(style.padding)
+                    .on_press(Message::Increment),
+                Text::new(format!("Counter: {}", self.state.counter)).size(style.text_size),
+                button("-")
+                    .padding(style.padding)
+                    .on_press(Message::Decrement),
             ]
+            .spacing(style.spacing)
             .align_x(Alignment::Center),
         )
         .center(Length::Fill)
@@ -43,32 +52,66 @@ impl AppInterface for CoreApp {
     fn state(&self) -> &AppState {
         &self.state
     }
+
+    fn subscriptions(&self) -> Vec<CoreSubscription<Message>> {
+        vec![
+            CoreSubscription::key_press("ArrowUp", Message::Increment),
+            CoreSubscription::key_press("ArrowDown", Message::Decrement),
+        ]
+    }
 }
 
-/// Creates the CoreApp instance with initial state `state`.
-#[unsafe(no_mangle)]
-pub extern "C" fn create_app(state: AppState) -> *mut Box<dyn AppInterface> {
-    let log_config = ConfigBuilder::new()
-        .set_max_level(LevelFilter::Trace)
-        .set_time_level(LevelFilter::Trace)
-        .add_filter_allow_str("app_core")
-        .add_filter_allow_str("app_shell")
-        .build();
+/// The same application for the subprocess backend, see `src/bin/remote.rs`.
+impl RemoteApp<AppState, Message> for CoreApp {
+    fn update(&mut self, message: Message) {
+        let _ = AppInterface::update(self, message);
+    }
+
+    fn view(&self) -> ViewNode<Message> {
+        let button = |label: &str, message| ViewNode::Button {
+            content: Box::new(ViewNode::Text {
+                content: label.to_string(),
+                size: None,
+            }),
+            on_press: Some(message),
+        };
 
-    let _ = SimpleLogger::init(LevelFilter::Trace, log_config);
+        ViewNode::Center {
+            content: Box::new(ViewNode::Column {
+                children: vec![
+                    button("+", Message::Increment),
+                    ViewNode::Text {
+                        content: format!("Counter: {}", self.state.counter),
+                        size: None,
+                    },
+                    button("-", Message::Decrement),
+                ],
+                spacing: 0.0,
+                centered: true,
+            }),
+        }
+    }
 
-    trace!("Create app");
-    let app = CoreApp { state };
+    fn state(&self) -> &AppState {
+        &self.state
+    }
+}
+
+// Built with `cargo build -p app_core --target wasm32-unknown-unknown`, the
+// core can be run by the wasm backend of the shell.
+#[cfg(target_arch = "wasm32")]
+hot_reload_interface::export_wasm_core!(CoreApp, AppState, Message);
 
-    let boxed: Box<dyn AppInterface> = Box::new(app);
-    Box::into_raw(Box::new(boxed))
+/// Creates the CoreApp instance with initial state `state`.
+impl From<AppState> for CoreApp {
+    fn from(state: AppState) -> Self {
+        trace!("Create app");
+        CoreApp { state }
+    }
 }
 
-/// Destoroys the memory allocated for the core instance.
-#[unsafe(no_mangle)]
-pub extern "C" fn destroy_app(ptr: *mut Box<dyn AppInterface>) {
-    trace!("Destroy app");
-    if !ptr.is_null() {
-        drop(unsafe { Box::from_raw(ptr) });
+impl Drop for CoreApp {
+    fn drop(&mut self) {
+        trace!("Destroy app");
     }
 }
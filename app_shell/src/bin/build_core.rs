@@ -0,0 +1,71 @@
+//! Runs `cargo build` and marks the dynamic libraries it produced ready, for
+//! shells started with `--require-ready-marker`.
+//!
+//! All arguments are passed on to cargo, e.g. `build_core -p app_core --release`.
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode, Stdio};
+
+fn main() -> ExitCode {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    // The diagnostics are still rendered to stderr, only the artifacts are
+    // reported as JSON.
+    let mut child = match Command::new(cargo)
+        .arg("build")
+        .arg("--message-format=json-render-diagnostics")
+        .args(std::env::args_os().skip(1))
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("Failed to run cargo: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut libraries = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+
+            if message["reason"] == "compiler-artifact" {
+                libraries.extend(
+                    message["filenames"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|filename| filename.as_str())
+                        .map(PathBuf::from)
+                        .filter(|path| {
+                            path.extension().and_then(|extension| extension.to_str())
+                                == Some(std::env::consts::DLL_EXTENSION)
+                        }),
+                );
+            }
+        }
+    }
+
+    match child.wait() {
+        Ok(status) if status.success() => (),
+        Ok(_) => return ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Failed to wait for cargo: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    for library in &libraries {
+        if let Err(e) = iced_hot_reload::mark_ready(library) {
+            eprintln!("Failed to mark {} ready: {}", library.display(), e);
+            return ExitCode::FAILURE;
+        }
+        eprintln!("Marked {} ready", library.display());
+    }
+
+    ExitCode::SUCCESS
+}
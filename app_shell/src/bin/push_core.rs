@@ -0,0 +1,32 @@
+//! Pushes a rebuilt core to a shell started with `--listen`, e.g. one
+//! running on a device.
+
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Pushes a core library to a shell listening for pushes.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// The address the shell listens on, e.g. `192.168.1.20:7979`.
+    addr: String,
+
+    /// The core library to push, e.g. `target/aarch64-unknown-linux-gnu/debug/libapp_core.so`.
+    lib: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match iced_hot_reload::push_library(args.addr.as_str(), &args.lib) {
+        Ok(()) => {
+            println!("Pushed {} to {}", args.lib.display(), args.addr);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to push {}: {}", args.lib.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,20 +1,405 @@
-mod shellapp;
-
+#[cfg(not(feature = "static"))]
+use clap::Parser;
+#[cfg(all(debug_assertions, not(feature = "static")))]
+use iced_hot_reload::CheckedAllocator;
+#[cfg(not(feature = "static"))]
+use iced_hot_reload::{
+    discover_cores, BuildProfile, CoreLayout, FocusCheck, HotReload, LogCapture, UnloadPolicy,
+};
 use log::LevelFilter;
-use shellapp::ShellApp;
+use shared_types::Message;
 use simplelog::{ConfigBuilder, SimpleLogger};
+#[cfg(all(debug_assertions, not(feature = "static")))]
+use std::alloc::System;
+#[cfg(not(feature = "static"))]
+use std::net::SocketAddr;
+#[cfg(not(feature = "static"))]
+use std::path::PathBuf;
+#[cfg(not(feature = "static"))]
+use std::time::Duration;
+
+/// Catches memory freed by the wrong allocator across the library boundary
+/// in debug builds. A statically linked core declares the global allocator
+/// itself.
+#[cfg(all(debug_assertions, not(feature = "static")))]
+#[global_allocator]
+static ALLOCATOR: CheckedAllocator<System> = CheckedAllocator::new(System);
+
+#[cfg(not(any(feature = "hot-reload", feature = "static")))]
+compile_error!("Enable the `hot-reload` feature, or `static` to link the core into the binary");
+
+/// Hosts a hot-reloadable core and reloads it whenever it is rebuilt.
+#[cfg(not(feature = "static"))]
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// The name of the core library, without prefix or extension. Without it,
+    /// the cores of the workspace are listed to pick one from.
+    #[arg(long)]
+    lib: Option<String>,
+
+    /// The directory containing the core library, `target/<profile>` by default.
+    #[arg(long)]
+    lib_dir: Option<PathBuf>,
+
+    /// The name of the exported function creating the core.
+    #[arg(long, default_value = "create_app")]
+    create_symbol: String,
+
+    /// The name of the exported function destroying the core.
+    #[arg(long, default_value = "destroy_app")]
+    destroy_symbol: String,
+
+    /// Polls the library every given number of milliseconds instead of watching it.
+    #[arg(long)]
+    poll_ms: Option<u64>,
+
+    /// Also checks the library for changes when the window gains the focus.
+    #[arg(long)]
+    check_on_focus: bool,
+
+    /// Only checks the library for changes when the window gains the focus,
+    /// without watching or polling it.
+    #[arg(long, conflicts_with_all = ["check_on_focus", "poll_ms"])]
+    only_on_focus: bool,
+
+    /// Loads (and rebuilds) the core with this cargo profile, e.g. `dev` or
+    /// `dev-fast`, instead of the profile of the shell.
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Rebuilds the core with the fast `hot-reload` profile (no optimizations,
+    /// line tables only) instead of the profile of the shell.
+    #[arg(long, conflicts_with = "profile")]
+    fast_build: bool,
+
+    /// Rebuilds the core with the cranelift backend, which requires nightly.
+    #[arg(long, requires = "fast_build")]
+    cranelift: bool,
+
+    /// Unloads replaced libraries only after this many milliseconds.
+    #[arg(long, conflicts_with_all = ["never_unload", "leak_libraries"])]
+    unload_after_ms: Option<u64>,
+
+    /// Never unloads replaced libraries before the shell exits.
+    #[arg(long, conflicts_with = "leak_libraries")]
+    never_unload: bool,
+
+    /// Keeps libraries with thread-local storage loaded, whose destructors
+    /// may run after the core was replaced; the others are unloaded as usual.
+    #[arg(long, conflicts_with_all = ["never_unload", "leak_libraries"])]
+    keep_alive: bool,
+
+    /// Never unloads any library, keeps the copies on disk and logs where
+    /// each is mapped, so sanitizers and backtraces symbolize old cores.
+    #[arg(long)]
+    leak_libraries: bool,
+
+    /// Reports the allocations left behind by every unloaded library, counted
+    /// in debug builds.
+    #[arg(long)]
+    track_leaks: bool,
+
+    /// Reloads the core this many times in a row, then exits, failing if a reload fails, the
+    /// core panics or the memory keeps growing.
+    #[arg(long)]
+    soak: Option<u32>,
+
+    /// Dispatches random messages from this recording between the reloads of `--soak`.
+    #[arg(long, requires = "soak")]
+    soak_messages: Option<PathBuf>,
+
+    /// Keeps this many replaced libraries loaded regardless of the unload policy.
+    #[arg(long, default_value_t = 0)]
+    keep_libraries: usize,
+
+    /// Receives cores pushed with `push_core` on this address, e.g.
+    /// `0.0.0.0:7979`, instead of watching the library.
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+
+    /// The directory pushed cores are received in, `staging` next to the library by default.
+    #[arg(long, requires = "listen")]
+    staging_dir: Option<PathBuf>,
+
+    /// Starts a control server for editor plugins and scripts, on
+    /// `127.0.0.1:7878` unless another address is given.
+    #[arg(long, num_args = 0..=1, default_missing_value = "127.0.0.1:7878")]
+    control: Option<SocketAddr>,
+
+    /// Appends the reload events to this file as JSON lines.
+    #[arg(long)]
+    event_log: Option<PathBuf>,
+
+    /// Reloads only on request of external tooling: SIGUSR1 (SIGTERM exits) on
+    /// Unix, `reload` (`shutdown`) on the pipe `\\.\pipe\iced_hot_reload_<lib>` on Windows.
+    #[arg(long)]
+    external_commands: bool,
+
+    /// Reloads the library only once it was marked ready, e.g. by building it with
+    /// `cargo run -p app_shell --bin build_core -- -p <lib>`.
+    #[arg(long)]
+    require_ready_marker: bool,
+
+    /// How many times loading a changed library is attempted before giving up.
+    #[arg(long, default_value_t = 5)]
+    reload_attempts: u32,
+
+    /// Takes a state snapshot after this many messages, to step through in the dev overlay.
+    #[arg(long)]
+    snapshot_every: Option<u32>,
+
+    /// Records every message handled by the core to this file.
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Replays the messages recorded to this file into every freshly loaded core.
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Adds a console sending typed in messages to the core to the dev overlay.
+    #[arg(long)]
+    console: bool,
+
+    /// Describes the view in this RON file, e.g. `app_shell/counter.ron`,
+    /// instead of building it with the view of the core.
+    #[arg(long)]
+    view_file: Option<PathBuf>,
+
+    /// Watches this directory of images and fonts, e.g. `assets`, so
+    /// changing them doesn't require a restart.
+    #[arg(long)]
+    assets: Option<PathBuf>,
+
+    /// Loads the widget styling from this TOML or JSON file, e.g.
+    /// `app_shell/stylesheet.toml`, and reloads it when it is saved.
+    #[arg(long)]
+    stylesheet: Option<PathBuf>,
+
+    /// Translates the strings of the core with the Fluent and gettext
+    /// catalogs in this directory, e.g. `locales`, and reloads them when
+    /// they change.
+    #[arg(long)]
+    locales: Option<PathBuf>,
+
+    /// The locale the strings are translated to.
+    #[arg(long, default_value = "en-US")]
+    locale: String,
+
+    /// Starts from the initial state instead of the persisted one.
+    #[arg(long)]
+    fresh: bool,
 
+    /// Opens the window with the default geometry instead of the one it had
+    /// when the shell last ran, and doesn't remember it.
+    #[arg(long)]
+    forget_window: bool,
+
+    /// Runs the core in a child process started from this executable, e.g.
+    /// `target/debug/remote`, instead of loading it as a library.
+    #[arg(long)]
+    subprocess: Option<PathBuf>,
+
+    /// Composes the cores of these libraries side by side, e.g.
+    /// `--cores sidebar_core,editor_core`, instead of running a single one.
+    #[arg(long, value_delimiter = ',', conflicts_with = "subprocess")]
+    cores: Vec<String>,
+
+    /// Shows one of the `--cores` at a time, chosen from a tab bar, instead
+    /// of all of them side by side.
+    #[arg(long, requires = "cores")]
+    routed: bool,
+
+    /// Runs the core compiled to wasm from this module, e.g.
+    /// `target/wasm32-unknown-unknown/debug/app_core.wasm`.
+    #[cfg(feature = "wasm")]
+    #[arg(long, conflicts_with = "subprocess")]
+    wasm: Option<PathBuf>,
+
+    /// Interprets the core from this Rhai script, e.g. `app_shell/counter.rhai`,
+    /// reloading it whenever it is saved.
+    #[cfg(feature = "script")]
+    #[arg(long, conflicts_with = "subprocess")]
+    script: Option<PathBuf>,
+}
+
+/// Runs app_core linked into the binary, without any hot reloading.
+#[cfg(feature = "static")]
 fn main() -> iced::Result {
+    let log_config = ConfigBuilder::new()
+        .set_max_level(LevelFilter::Info)
+        .build();
+    let _ = SimpleLogger::init(LevelFilter::Info, log_config);
+
+    iced_hot_reload::run_static::<app_core::CoreApp, shared_types::AppState, Message>("Application")
+}
+
+#[cfg(not(feature = "static"))]
+fn main() -> iced::Result {
+    let args = Args::parse();
+
     let log_config = ConfigBuilder::new()
         .set_max_level(LevelFilter::Trace)
         .set_time_level(LevelFilter::Trace)
-        .add_filter_allow_str("app_core")
-        .add_filter_allow_str("app_shell")
         .build();
 
-    let _ = SimpleLogger::init(LevelFilter::Trace, log_config);
+    // The levels can be changed in the dev overlay (F12) while the shell runs.
+    let _ = LogCapture::new(SimpleLogger::new(LevelFilter::Trace, log_config))
+        .default_level(LevelFilter::Off)
+        .level("app_core", LevelFilter::Trace)
+        .level("app_shell", LevelFilter::Trace)
+        .level("iced_hot_reload", LevelFilter::Trace)
+        .level("iced_hot_reload::loader", LevelFilter::Trace)
+        .install();
+
+    let mut hot_reload = HotReload::<Message>::builder()
+        .title("Application")
+        .create_symbol(args.create_symbol)
+        .destroy_symbol(args.destroy_symbol)
+        .persist_state("app_state.json")
+        .fresh(args.fresh)
+        .remember_window(!args.forget_window)
+        .external_commands(args.external_commands)
+        .require_ready_marker(args.require_ready_marker)
+        .reload_attempts(args.reload_attempts)
+        .keep_libraries(args.keep_libraries)
+        .track_leaks(args.track_leaks);
+
+    if let Some(lib) = &args.lib {
+        hot_reload = hot_reload.lib_name(lib);
+    }
+
+    if let Some(lib_dir) = args.lib_dir {
+        hot_reload = hot_reload.lib_dir(lib_dir);
+    }
+
+    if let Some(profile) = &args.profile {
+        hot_reload = hot_reload.core_profile(profile);
+    }
+
+    if args.leak_libraries {
+        hot_reload = hot_reload.unload_policy(UnloadPolicy::Leak);
+    } else if args.never_unload {
+        hot_reload = hot_reload.unload_policy(UnloadPolicy::Never);
+    } else {
+        let grace_period = Duration::from_millis(args.unload_after_ms.unwrap_or(0));
+        if args.keep_alive {
+            hot_reload = hot_reload.unload_policy(UnloadPolicy::KeepAlive(grace_period));
+        } else if args.unload_after_ms.is_some() {
+            hot_reload = hot_reload.unload_policy(UnloadPolicy::After(grace_period));
+        }
+    }
+
+    if let Some(poll_ms) = args.poll_ms {
+        hot_reload = hot_reload.poll_interval(Duration::from_millis(poll_ms));
+    }
+
+    if args.only_on_focus {
+        hot_reload = hot_reload.check_on_focus(FocusCheck::Only);
+    } else if args.check_on_focus {
+        hot_reload = hot_reload.check_on_focus(FocusCheck::Also);
+    }
+
+    if let Some(path) = args.record {
+        hot_reload = hot_reload.record_messages(path);
+    }
+
+    if let Some(path) = args.replay {
+        hot_reload = hot_reload.replay_messages(path);
+    }
+
+    if let Some(reloads) = args.soak {
+        hot_reload = hot_reload.soak_test(reloads);
+    }
+
+    if let Some(path) = args.soak_messages {
+        hot_reload = hot_reload.soak_messages(path);
+    }
+
+    if args.console {
+        hot_reload = hot_reload.message_console();
+    }
+
+    if let Some(path) = args.view_file {
+        hot_reload = hot_reload.view_file(path);
+    }
+
+    if let Some(dir) = args.assets {
+        hot_reload = hot_reload.watch_assets(dir);
+    }
+
+    if let Some(path) = args.stylesheet {
+        hot_reload = hot_reload.stylesheet(path);
+    }
+
+    if let Some(dir) = args.locales {
+        hot_reload = hot_reload.localization(dir, args.locale);
+    }
+
+    if let Some(messages) = args.snapshot_every {
+        hot_reload = hot_reload.snapshot_every(messages);
+    }
+
+    if let Some(addr) = args.control {
+        hot_reload = hot_reload.control_server(addr);
+    }
+
+    if let Some(path) = args.event_log {
+        hot_reload = hot_reload.event_log(path);
+    }
+
+    if let Some(addr) = args.listen {
+        hot_reload = hot_reload.receive_pushes(addr);
+        if let Some(staging_dir) = args.staging_dir {
+            hot_reload = hot_reload.staging_dir(staging_dir);
+        }
+    } else if let Some(lib) = &args.lib {
+        // Cores living in this workspace are rebuilt by the shell itself.
+        let src_dir = PathBuf::from(lib).join("src");
+        if src_dir.is_dir() {
+            hot_reload = hot_reload.rebuild_on_change(lib, src_dir);
+            if args.fast_build {
+                let profile = BuildProfile::fast();
+                hot_reload = hot_reload.build_profile(if args.cranelift {
+                    profile.cranelift()
+                } else {
+                    profile
+                });
+            }
+        }
+    }
+
+    if !args.cores.is_empty() {
+        for lib in args.cores {
+            hot_reload = hot_reload.core(lib.clone(), lib);
+        }
+        let layout = if args.routed {
+            CoreLayout::Routed
+        } else {
+            CoreLayout::Row
+        };
+        return hot_reload.run_cores(layout);
+    }
+
+    #[cfg(feature = "wasm")]
+    if let Some(module) = args.wasm {
+        return hot_reload.run_wasm(module);
+    }
+
+    #[cfg(feature = "script")]
+    if let Some(script) = args.script {
+        return hot_reload.run_script(script);
+    }
 
-    iced::application("Application", ShellApp::update, ShellApp::view)
-        .subscription(ShellApp::subscription)
-        .run()
+    match (args.subprocess, args.lib) {
+        (Some(executable), _) => hot_reload.run_subprocess(executable),
+        (None, Some(_)) => hot_reload.run(),
+        (None, None) => match discover_cores() {
+            Ok(cores) => hot_reload.run_picker(cores),
+            Err(e) => {
+                log::error!("Failed to discover the cores of the workspace: {}", e);
+                hot_reload.run()
+            }
+        },
+    }
 }
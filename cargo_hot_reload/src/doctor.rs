@@ -0,0 +1,42 @@
+use std::io::{self, Error};
+
+/// Arguments of `cargo hot-reload doctor`.
+#[derive(clap::Args, Debug)]
+pub struct DoctorArgs {
+    /// Checks the release build of the cores.
+    #[arg(long, conflicts_with = "profile")]
+    release: bool,
+
+    /// Checks the build of the cores with a custom cargo profile.
+    #[arg(long)]
+    profile: Option<String>,
+}
+
+/// Runs `cargo hot-reload doctor`, failing if any check failed.
+pub fn run(args: DoctorArgs) -> io::Result<()> {
+    let profile = match (args.release, args.profile) {
+        (true, _) => "release".to_string(),
+        (false, Some(profile)) => profile,
+        (false, None) => "debug".to_string(),
+    };
+
+    let checks = iced_hot_reload::doctor(&profile);
+    for check in &checks {
+        match &check.fix {
+            None => println!("[ok]   {}", check.description),
+            Some(fix) => {
+                println!("[FAIL] {}", check.description);
+                println!("       {}", fix);
+            }
+        }
+    }
+
+    match checks.iter().filter(|check| !check.is_ok()).count() {
+        0 => Ok(()),
+        failed => Err(Error::other(format!(
+            "{} of {} checks failed",
+            failed,
+            checks.len()
+        ))),
+    }
+}
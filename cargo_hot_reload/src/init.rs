@@ -0,0 +1,255 @@
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use toml_edit::{value, Array, DocumentMut, InlineTable, Item, Table};
+
+/// The crates generated for a new project, with the files of each one.
+const TEMPLATES: [(&str, [(&str, &str); 2]); 3] = [
+    (
+        "types",
+        [
+            ("Cargo.toml", include_str!("../templates/types/Cargo.toml")),
+            ("src/lib.rs", include_str!("../templates/types/lib.rs")),
+        ],
+    ),
+    (
+        "core",
+        [
+            ("Cargo.toml", include_str!("../templates/core/Cargo.toml")),
+            ("src/lib.rs", include_str!("../templates/core/lib.rs")),
+        ],
+    ),
+    (
+        "shell",
+        [
+            ("Cargo.toml", include_str!("../templates/shell/Cargo.toml")),
+            ("src/main.rs", include_str!("../templates/shell/main.rs")),
+        ],
+    ),
+];
+
+/// Arguments of `cargo hot-reload init`.
+#[derive(clap::Args, Debug)]
+pub struct InitArgs {
+    /// The directory to set up. Without a package manifest there, a workspace
+    /// of `<name>_types`, `<name>_core` and `<name>_shell` is generated;
+    /// otherwise the package is converted into a hot-reloadable core.
+    #[arg(long, default_value = ".")]
+    path: PathBuf,
+
+    /// The prefix of the generated crates, the name of the directory by default.
+    #[arg(long)]
+    name: Option<String>,
+
+    /// The iced_hot_reload checkout the crates depend on. Required, since
+    /// the directory this tool was built in is gone after `cargo install`.
+    #[arg(long)]
+    hot_reload_path: PathBuf,
+
+    /// The state type of the converted package.
+    #[arg(long, default_value = "State")]
+    state: String,
+
+    /// The message type of the converted package.
+    #[arg(long, default_value = "Message")]
+    message: String,
+
+    /// The update function of the converted package.
+    #[arg(long, default_value = "update")]
+    update: String,
+
+    /// The view function of the converted package.
+    #[arg(long, default_value = "view")]
+    view: String,
+}
+
+/// Runs `cargo hot-reload init`.
+pub fn run(args: InitArgs) -> io::Result<()> {
+    let manifest_path = args.path.join("Cargo.toml");
+    let manifest = match fs::read_to_string(&manifest_path) {
+        Ok(manifest) => Some(manifest.parse::<DocumentMut>().map_err(Error::other)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let hot_reload_path = fs::canonicalize(&args.hot_reload_path)?;
+    // Forward slashes are valid on Windows too and need no escaping in TOML.
+    let hot_reload_path = hot_reload_path.to_string_lossy().replace('\\', "/");
+
+    match manifest {
+        Some(manifest) if manifest.contains_key("package") => {
+            convert_package(&args, &manifest_path, manifest, &hot_reload_path)
+        }
+        manifest => scaffold_workspace(&args, &manifest_path, manifest, &hot_reload_path),
+    }
+}
+
+/// Generates the types, core and shell crates from the templates and adds
+/// them to the workspace, which is created if there is none yet.
+fn scaffold_workspace(
+    args: &InitArgs,
+    manifest_path: &Path,
+    manifest: Option<DocumentMut>,
+    hot_reload_path: &str,
+) -> io::Result<()> {
+    let name = match &args.name {
+        Some(name) => name.clone(),
+        None => fs::canonicalize(&args.path)?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| Error::other("Pass the name of the crates with --name"))?,
+    }
+    .replace('-', "_");
+
+    let mut members = Vec::new();
+    for (suffix, files) in TEMPLATES {
+        let member = format!("{}_{}", name, suffix);
+        let dir = args.path.join(&member);
+        if dir.exists() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists", dir.display()),
+            ));
+        }
+
+        for (file, template) in files {
+            let path = dir.join(file);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let content = template
+                .replace("{{name}}", &name)
+                .replace("{{hot_reload_path}}", hot_reload_path);
+            fs::write(&path, content)?;
+        }
+
+        println!("Created {}", dir.display());
+        members.push(member);
+    }
+
+    let mut manifest = manifest.unwrap_or_default();
+    let workspace = manifest
+        .entry("workspace")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| Error::other("`workspace` is not a table"))?;
+    let existing = workspace
+        .entry("members")
+        .or_insert_with(|| value(Array::new()))
+        .as_array_mut()
+        .ok_or_else(|| Error::other("`workspace.members` is not an array"))?;
+    existing.extend(members);
+    if !workspace.contains_key("resolver") {
+        workspace["resolver"] = value("3");
+    }
+    fs::write(manifest_path, manifest.to_string())?;
+
+    println!("Run it with `cargo run -p {}_shell`", name);
+    Ok(())
+}
+
+/// Builds the package as a dynamic library as well, depends on the hot
+/// reload crates and exports the `update` and `view` functions of its
+/// library with `export_app!`.
+fn convert_package(
+    args: &InitArgs,
+    manifest_path: &Path,
+    mut manifest: DocumentMut,
+    hot_reload_path: &str,
+) -> io::Result<()> {
+    let lib_path = args.path.join("src").join("lib.rs");
+    let lib = match fs::read_to_string(&lib_path) {
+        Ok(lib) => lib,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "{} not found; move {}, {}, {} and {} into the library of the package first",
+                    lib_path.display(),
+                    args.state,
+                    args.message,
+                    args.update,
+                    args.view
+                ),
+            ));
+        }
+        Err(e) => return Err(e),
+    };
+
+    let package = manifest["package"]["name"]
+        .as_str()
+        .ok_or_else(|| Error::other("`package.name` is missing"))?
+        .to_string();
+    let lib_name = manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .and_then(Item::as_str)
+        .map_or_else(|| package.replace('-', "_"), str::to_string);
+
+    let lib_table = manifest
+        .entry("lib")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| Error::other("`lib` is not a table"))?;
+    let crate_types = lib_table
+        .entry("crate-type")
+        .or_insert(value(Array::new()))
+        .as_array_mut()
+        .ok_or_else(|| Error::other("`lib.crate-type` is not an array"))?;
+    for crate_type in ["cdylib", "rlib"] {
+        if !crate_types
+            .iter()
+            .any(|existing| existing.as_str() == Some(crate_type))
+        {
+            crate_types.push(crate_type);
+        }
+    }
+
+    let dependencies = manifest
+        .entry("dependencies")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .ok_or_else(|| Error::other("`dependencies` is not a table"))?;
+    for dependency in ["hot_reload_interface", "iced_hot_reload"] {
+        if !dependencies.contains_key(dependency) {
+            let mut path = InlineTable::new();
+            path.insert("path", format!("{}/{}", hot_reload_path, dependency).into());
+            dependencies.insert(dependency, value(path));
+        }
+    }
+
+    fs::write(manifest_path, manifest.to_string())?;
+    println!("Updated {}", manifest_path.display());
+
+    if lib.contains("export_app!") {
+        println!("{} already exports the application", lib_path.display());
+    } else {
+        let separator = if lib.ends_with('\n') { "" } else { "\n" };
+        fs::write(
+            &lib_path,
+            format!(
+                "{}{}\nhot_reload_interface::export_app!({}, {}, {}, {});\n",
+                lib, separator, args.state, args.message, args.update, args.view
+            ),
+        )?;
+        println!("Exported the application from {}", lib_path.display());
+    }
+
+    println!(
+        "\n{} must implement Serialize, Deserialize and Default, and {} must not borrow from it.\n\
+         Run the application in src/main.rs with:\n\n    \
+         iced_hot_reload::HotReload::<{lib}::{message}>::builder()\n        \
+         .lib_name(\"{lib}\")\n        \
+         .rebuild_on_change(\"{package}\", \"src\")\n        \
+         .run_app({lib}::{update}, {lib}::{view})",
+        args.state,
+        args.view,
+        lib = lib_name,
+        message = args.message,
+        package = package,
+        update = args.update,
+        view = args.view,
+    );
+
+    Ok(())
+}
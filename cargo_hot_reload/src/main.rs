@@ -0,0 +1,55 @@
+//! `cargo hot-reload`, setting projects up for hot reloading.
+//!
+//! Install it with `cargo install --path cargo_hot_reload`, then run
+//! `cargo hot-reload init` in an empty directory to generate a workspace of
+//! types, core and shell crates, or in an existing iced application to export
+//! its `update` and `view` functions as a hot-reloadable core. When a core
+//! doesn't load, `cargo hot-reload doctor` points out the usual causes.
+
+mod doctor;
+mod init;
+
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+
+/// Cargo passes the name of the subcommand on as the first argument.
+#[derive(Parser, Debug)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    HotReload(HotReloadArgs),
+}
+
+/// Sets projects up for hot reloading with iced_hot_reload.
+#[derive(clap::Args, Debug)]
+#[command(version, about)]
+struct HotReloadArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generates a types/core/shell workspace, or converts an existing
+    /// package into a hot-reloadable core.
+    Init(init::InitArgs),
+
+    /// Checks the workspace for the usual reasons a core fails to load.
+    Doctor(doctor::DoctorArgs),
+}
+
+fn main() -> ExitCode {
+    let Cargo::HotReload(args) = Cargo::parse();
+
+    let result = match args.command {
+        Command::Init(args) => init::run(args),
+        Command::Doctor(args) => doctor::run(args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use hot_reload_interface::{hot_reload_core, AppInterface, CoreCommand};
+use iced::widget::{button, column, Text};
+use iced::{Alignment, Element, Length};
+use {{name}}_types::{Message, State};
+
+/// The logic and the view of the application, reloaded whenever it is rebuilt.
+#[repr(C)]
+pub struct Core {
+    state: State,
+}
+
+#[hot_reload_core]
+impl AppInterface<State, Message> for Core {
+    fn update(&mut self, message: Message) -> Vec<CoreCommand<Message>> {
+        match message {
+            Message::Increment => self.state.counter += 1,
+            Message::Decrement => self.state.counter -= 1,
+        }
+
+        Vec::new()
+    }
+
+    fn view(&self) -> Element<'static, Message> {
+        iced::widget::Container::new(
+            column![
+                button("+").on_press(Message::Increment),
+                Text::new(format!("Counter: {}", self.state.counter)),
+                button("-").on_press(Message::Decrement),
+            ]
+            .align_x(Alignment::Center),
+        )
+        .center(Length::Fill)
+        .into()
+    }
+
+    fn state(&self) -> &State {
+        &self.state
+    }
+}
+
+impl From<State> for Core {
+    fn from(state: State) -> Self {
+        Self { state }
+    }
+}
@@ -0,0 +1,9 @@
+use {{name}}_types::Message;
+
+fn main() -> iced::Result {
+    iced_hot_reload::HotReload::<Message>::builder()
+        .title("{{name}}")
+        .lib_name("{{name}}_core")
+        .rebuild_on_change("{{name}}_core", "{{name}}_core/src")
+        .run()
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// The messages of the application, shared by the shell and the core.
+#[repr(C)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Message {
+    Increment,
+    Decrement,
+}
+
+/// The state of the application, handed from one core to the next on every
+/// reload. With `#[serde(default)]` fields can be added without losing the
+/// rest of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub counter: i32,
+}
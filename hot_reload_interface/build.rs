@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Captures the version of the compiler building this crate and the version
+/// of iced it is built against, so that the shell can compare them with the
+/// ones that built the core.
+fn main() {
+    println!(
+        "cargo:rustc-env=HOT_RELOAD_RUSTC_VERSION={}",
+        rustc_version()
+    );
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    println!("cargo:rustc-env=HOT_RELOAD_ICED_VERSION={}", iced_version());
+}
+
+/// Describes the compiler with its version and the full hash of the commit it
+/// was built from, as reported by `rustc -vV`.
+///
+/// The short hash of `rustc --version` is missing from compilers built
+/// without git information, e.g. by distributions, and two such builds of
+/// the same release may still lay out std types differently.
+fn rustc_version() -> String {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let Some(output) = Command::new(rustc)
+        .arg("-vV")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+    else {
+        return "unknown".to_string();
+    };
+
+    let version = output.lines().next().unwrap_or("unknown").trim();
+    let commit_hash = output
+        .lines()
+        .find_map(|line| line.strip_prefix("commit-hash: "))
+        .unwrap_or("unknown")
+        .trim();
+
+    format!("{}, commit {}", version, commit_hash)
+}
+
+/// Reads the version of iced from the lock file of the workspace being built.
+///
+/// Build scripts aren't told the versions of the dependencies, so the lock
+/// file is looked for above the output directory, which is usually inside
+/// the workspace, and above this crate. If the workspace locks several
+/// versions of iced, all of them are listed.
+fn iced_version() -> String {
+    let out_dir = std::env::var_os("OUT_DIR").map(PathBuf::from);
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+
+    let lock_file = out_dir
+        .iter()
+        .chain(manifest_dir.iter())
+        .flat_map(|dir| dir.ancestors())
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file());
+
+    let Some(lock_file) = lock_file else {
+        return "unknown".to_string();
+    };
+    println!("cargo:rerun-if-changed={}", lock_file.display());
+
+    let versions = locked_versions(&lock_file, "iced");
+    if versions.is_empty() {
+        "unknown".to_string()
+    } else {
+        versions.join(", ")
+    }
+}
+
+/// The versions of the package `name` in `lock_file`.
+fn locked_versions(lock_file: &Path, name: &str) -> Vec<String> {
+    let Ok(lock) = std::fs::read_to_string(lock_file) else {
+        return Vec::new();
+    };
+
+    let name_line = format!("name = \"{}\"", name);
+    let mut lines = lock.lines();
+    let mut versions = Vec::new();
+    while let Some(line) = lines.next() {
+        if line.trim() != name_line {
+            continue;
+        }
+
+        // The version follows the name in each `[[package]]` entry.
+        if let Some(version) = lines
+            .next()
+            .and_then(|line| line.trim().strip_prefix("version = "))
+        {
+            versions.push(version.trim_matches('"').to_string());
+        }
+    }
+
+    versions
+}
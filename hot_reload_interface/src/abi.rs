@@ -0,0 +1,128 @@
+use std::ffi::{c_char, CStr, CString};
+use std::mem::{align_of, size_of};
+use std::sync::OnceLock;
+
+/// The version of the contract between shell and core.
+///
+/// Bumped whenever [`crate::RawApp`], [`crate::AppVTable`] or the signatures
+/// of the exported functions change.
+pub const ABI_VERSION: u32 = 15;
+
+/// The name of the exported function returning the [`AbiInfo`] of a core.
+pub const ABI_VERSION_SYMBOL: &str = "hot_reload_abi_version";
+
+/// The version and commit hash of the compiler that built this crate, NUL
+/// terminated.
+const RUSTC_VERSION: &str = concat!(env!("HOT_RELOAD_RUSTC_VERSION"), "\0");
+
+/// The version of iced locked for the workspace that built this crate.
+const ICED_VERSION: &str = env!("HOT_RELOAD_ICED_VERSION");
+
+/// Describes the iced this crate was built against: its version and the
+/// renderer, whose type depends on the enabled features and determines the
+/// layout of every `Element` crossing the library boundary, and whether iced
+/// is linked statically or shared through `iced_dylib`: a core with its own
+/// copy of iced doesn't share the shell's global state, e.g. its fonts.
+fn iced_build() -> &'static CStr {
+    static ICED_BUILD: OnceLock<CString> = OnceLock::new();
+
+    ICED_BUILD.get_or_init(|| {
+        let build = format!(
+            "iced {} with {}, linked {}",
+            ICED_VERSION,
+            std::any::type_name::<iced::Renderer>(),
+            if cfg!(feature = "dynamic-iced") {
+                "shared"
+            } else {
+                "statically"
+            }
+        );
+        CString::new(build).unwrap_or_default()
+    })
+}
+
+/// Returns the [`AbiInfo`] the core was built with.
+pub type AbiVersionFn = unsafe extern "C" fn() -> AbiInfo;
+
+/// Describes how a core was built, so the shell can refuse to load a core
+/// whose memory layout may differ from its own.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AbiInfo {
+    /// The [`ABI_VERSION`] the core was built against.
+    pub abi_version: u32,
+
+    /// The NUL terminated version and commit hash (`rustc -vV`) of the
+    /// compiler that built the core.
+    pub rustc_version: *const c_char,
+
+    /// A hash of the name and layout of the message type.
+    pub schema_hash: u64,
+
+    /// The NUL terminated version and renderer of iced the core was built against.
+    pub iced_build: *const c_char,
+}
+
+impl AbiInfo {
+    /// The `AbiInfo` of the current build for messages `M`.
+    pub fn current<M>() -> Self {
+        Self {
+            abi_version: ABI_VERSION,
+            rustc_version: RUSTC_VERSION.as_ptr().cast(),
+            schema_hash: schema_hash::<M>(),
+            iced_build: iced_build().as_ptr(),
+        }
+    }
+
+    /// The compiler version as a string.
+    ///
+    /// # Safety
+    ///
+    /// `rustc_version` must point to a NUL terminated string which outlives
+    /// the returned reference, i.e. the library it came from must stay loaded.
+    pub unsafe fn rustc_version(&self) -> &str {
+        if self.rustc_version.is_null() {
+            return "unknown";
+        }
+
+        unsafe { CStr::from_ptr(self.rustc_version) }
+            .to_str()
+            .unwrap_or("unknown")
+    }
+
+    /// The version and renderer of iced as a string.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`Self::rustc_version`].
+    pub unsafe fn iced_build(&self) -> &str {
+        if self.iced_build.is_null() {
+            return "unknown";
+        }
+
+        unsafe { CStr::from_ptr(self.iced_build) }
+            .to_str()
+            .unwrap_or("unknown")
+    }
+}
+
+/// Hashes the name, size and alignment of `M` with FNV-1a.
+///
+/// The hash is deterministic across builds, so a change to the message type
+/// in the core without rebuilding the shell is detected. The state isn't
+/// part of the hash: it crosses the boundary serialized and may change freely.
+pub fn schema_hash<M>() -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let layout = format!(
+        "{}:{}:{}",
+        std::any::type_name::<M>(),
+        size_of::<M>(),
+        align_of::<M>(),
+    );
+
+    layout.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
@@ -0,0 +1,356 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io::Write;
+use std::mem::{align_of, size_of};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// The name of the optional exported function handing the shell's allocator
+/// to the core.
+///
+/// The shell calls it right after checking the ABI of the library, before
+/// any other function of the core. Cores built before it was introduced keep
+/// allocating on their own.
+pub const SET_ALLOCATOR_SYMBOL: &str = "hot_reload_set_allocator";
+
+/// Makes the core allocate through the [`HostAllocator`] of the shell.
+pub type SetAllocatorFn = unsafe extern "C" fn(*const HostAllocator);
+
+/// The global allocator of the shell as seen by the core: function pointers
+/// into the shell forwarding to `std::alloc`.
+///
+/// Memory crosses the library boundary in both directions: the elements of
+/// the view are allocated by the core and dropped by the shell, and messages
+/// go the other way. Each library picks its own global allocator, so a core
+/// or shell with a `#[global_allocator]` of its own frees memory with an
+/// allocator that didn't allocate it. With the host allocator installed
+/// through [`SharedAllocator`], both sides use the shell's.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HostAllocator {
+    /// The generation the allocations of the core are attributed to, see
+    /// [`live_allocations`].
+    generation: usize,
+    alloc: unsafe extern "C" fn(usize, usize, usize) -> *mut u8,
+    alloc_zeroed: unsafe extern "C" fn(usize, usize, usize) -> *mut u8,
+    dealloc: unsafe extern "C" fn(*mut u8, usize, usize),
+    realloc: unsafe extern "C" fn(usize, *mut u8, usize, usize, usize) -> *mut u8,
+}
+
+impl HostAllocator {
+    /// Creates the host allocator of the calling library, i.e. of the shell.
+    pub fn new() -> Self {
+        Self::for_generation(0)
+    }
+
+    /// Creates the host allocator of the calling library, attributing the
+    /// allocations made through it to `generation`, e.g. the number of the
+    /// library the core was loaded from.
+    pub fn for_generation(generation: usize) -> Self {
+        Self {
+            generation,
+            alloc: host_alloc,
+            alloc_zeroed: host_alloc_zeroed,
+            dealloc: host_dealloc,
+            realloc: host_realloc,
+        }
+    }
+}
+
+impl Default for HostAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The allocator of the shell, once it was installed.
+static HOST_ALLOCATOR: OnceLock<HostAllocator> = OnceLock::new();
+
+/// Installs `host` as the allocator [`SharedAllocator`] forwards to, i.e. of
+/// the core. Does nothing if an allocator was installed already.
+///
+/// # Safety
+///
+/// `host` has to be null or point to a valid [`HostAllocator`]. The shell
+/// it belongs to has to outlive the core.
+pub unsafe fn install_host_allocator(host: *const HostAllocator) {
+    if host.is_null() {
+        return;
+    }
+
+    let _ = HOST_ALLOCATOR.set(unsafe { *host });
+}
+
+/// The global allocator of a core, generated by `#[hot_reload_core]`, which
+/// forwards to the allocator of the shell once it was installed with
+/// [`install_host_allocator`], and to the system allocator before.
+///
+/// The shell installs its allocator before calling anything else in the
+/// core, so memory freed on either side was allocated by the same allocator.
+/// Linked into a binary instead, e.g. a release build of the shell, it is
+/// the system allocator.
+pub struct SharedAllocator;
+
+impl SharedAllocator {
+    fn host() -> Option<&'static HostAllocator> {
+        HOST_ALLOCATOR.get()
+    }
+}
+
+unsafe impl GlobalAlloc for SharedAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match Self::host() {
+            Some(host) => unsafe { (host.alloc)(host.generation, layout.size(), layout.align()) },
+            None => unsafe { System.alloc(layout) },
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        match Self::host() {
+            Some(host) => unsafe {
+                (host.alloc_zeroed)(host.generation, layout.size(), layout.align())
+            },
+            None => unsafe { System.alloc_zeroed(layout) },
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        match Self::host() {
+            Some(host) => unsafe { (host.dealloc)(ptr, layout.size(), layout.align()) },
+            None => unsafe { System.dealloc(ptr, layout) },
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        match Self::host() {
+            Some(host) => unsafe {
+                (host.realloc)(
+                    host.generation,
+                    ptr,
+                    layout.size(),
+                    layout.align(),
+                    new_size,
+                )
+            },
+            None => unsafe { System.realloc(ptr, layout, new_size) },
+        }
+    }
+}
+
+thread_local! {
+    /// The generation a [`CheckedAllocator`] attributes allocations made on
+    /// this thread to: the shell's own unless a core allocates.
+    static ALLOCATING_FOR: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Runs `allocate` with its allocations attributed to `generation`.
+fn attributed_to<T>(generation: usize, allocate: impl FnOnce() -> T) -> T {
+    let previous = ALLOCATING_FOR.try_with(|current| current.replace(generation));
+    let result = allocate();
+    if let Ok(previous) = previous {
+        let _ = ALLOCATING_FOR.try_with(|current| current.set(previous));
+    }
+    result
+}
+
+unsafe extern "C" fn host_alloc(generation: usize, size: usize, align: usize) -> *mut u8 {
+    attributed_to(generation, || unsafe {
+        std::alloc::alloc(Layout::from_size_align_unchecked(size, align))
+    })
+}
+
+unsafe extern "C" fn host_alloc_zeroed(generation: usize, size: usize, align: usize) -> *mut u8 {
+    attributed_to(generation, || unsafe {
+        std::alloc::alloc_zeroed(Layout::from_size_align_unchecked(size, align))
+    })
+}
+
+unsafe extern "C" fn host_dealloc(ptr: *mut u8, size: usize, align: usize) {
+    unsafe { std::alloc::dealloc(ptr, Layout::from_size_align_unchecked(size, align)) }
+}
+
+unsafe extern "C" fn host_realloc(
+    generation: usize,
+    ptr: *mut u8,
+    size: usize,
+    align: usize,
+    new_size: usize,
+) -> *mut u8 {
+    attributed_to(generation, || unsafe {
+        std::alloc::realloc(
+            ptr,
+            Layout::from_size_align_unchecked(size, align),
+            new_size,
+        )
+    })
+}
+
+/// Marks the memory handed out by [`CheckedAllocator`], right in front of
+/// each allocation.
+const ALLOCATION_TAG: usize = 0x1ced_a11c;
+
+/// Whether a [`CheckedAllocator`] is the global allocator of this library.
+static CHECKED: AtomicBool = AtomicBool::new(false);
+
+/// How many generations [`CheckedAllocator`] counts the live allocations of
+/// apart. Generation `n` shares its counts with `n + GENERATIONS`.
+const GENERATIONS: usize = 256;
+
+/// The live allocations of each generation, indexed by the generation
+/// modulo [`GENERATIONS`].
+static LIVE: [LiveCounter; GENERATIONS] = [const { LiveCounter::new() }; GENERATIONS];
+
+/// The number and size of the live allocations of a generation.
+struct LiveCounter {
+    count: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+impl LiveCounter {
+    const fn new() -> Self {
+        Self {
+            count: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    fn of(generation: usize) -> &'static Self {
+        &LIVE[generation % GENERATIONS]
+    }
+}
+
+/// The allocations attributed to a generation which weren't freed yet, see
+/// [`live_allocations`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiveAllocations {
+    /// How many allocations are alive.
+    pub count: usize,
+
+    /// How many bytes they take, without the allocator's overhead.
+    pub bytes: usize,
+}
+
+/// Returns the live allocations a [`CheckedAllocator`] attributed to
+/// `generation`, e.g. the allocations a core loaded from a library left
+/// behind once the library was unloaded. Generation `0` is the shell's own.
+///
+/// Only counted while a `CheckedAllocator` is the global allocator of the
+/// calling library, see [`checked_allocator_installed`]. The counts of the
+/// most recent 256 generations are kept apart.
+pub fn live_allocations(generation: usize) -> LiveAllocations {
+    let counter = LiveCounter::of(generation);
+    LiveAllocations {
+        count: counter.count.load(Ordering::Relaxed),
+        bytes: counter.bytes.load(Ordering::Relaxed),
+    }
+}
+
+/// A global allocator for debug builds of the shell which catches memory
+/// freed across the library boundary by the wrong allocator.
+///
+/// It also counts the live allocations of each generation of the core,
+/// handed to it with [`HostAllocator::for_generation`], so memory a core
+/// leaked shows up once its library was unloaded, see [`live_allocations`].
+///
+/// Every allocation is tagged, and freeing memory without the tag, which
+/// `A` didn't hand out through this allocator, aborts with a message
+/// instead of corrupting the heap. That happens when a core allocates on
+/// its own instead of through the shell, e.g. because it wasn't built with
+/// `#[hot_reload_core]` or sets a `#[global_allocator]` of its own; the
+/// shell refuses to load such cores while this allocator is installed.
+///
+/// ```ignore
+/// #[cfg(debug_assertions)]
+/// #[global_allocator]
+/// static ALLOCATOR: CheckedAllocator<System> = CheckedAllocator::new(System);
+/// ```
+pub struct CheckedAllocator<A> {
+    inner: A,
+}
+
+impl<A> CheckedAllocator<A> {
+    /// Wraps `inner`, which does the actual allocating.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    /// The size of the header holding the generation and the tag, which
+    /// keeps the allocation aligned as requested.
+    fn header_size(layout: Layout) -> usize {
+        layout.align().max(2 * size_of::<usize>())
+    }
+
+    /// The layout of the allocation including the header.
+    fn tagged_layout(layout: Layout) -> Option<Layout> {
+        let size = layout.size().checked_add(Self::header_size(layout))?;
+        Layout::from_size_align(size, layout.align().max(align_of::<usize>())).ok()
+    }
+}
+
+/// Whether the global allocator of the calling library is a
+/// [`CheckedAllocator`], as far as it allocated anything yet.
+pub fn checked_allocator_installed() -> bool {
+    CHECKED.load(Ordering::Relaxed)
+}
+
+/// Reports memory freed by the wrong allocator and aborts, without
+/// allocating.
+fn foreign_free() -> ! {
+    let _ = std::io::stderr().write_all(
+        b"CheckedAllocator: freed memory it didn't allocate; a core allocates on its own \
+          instead of through the shell's allocator\n",
+    );
+    std::process::abort()
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CheckedAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !CHECKED.load(Ordering::Relaxed) {
+            CHECKED.store(true, Ordering::Relaxed);
+        }
+
+        let Some(tagged) = Self::tagged_layout(layout) else {
+            return ptr::null_mut();
+        };
+        let base = unsafe { self.inner.alloc(tagged) };
+        if base.is_null() {
+            return base;
+        }
+
+        let generation = ALLOCATING_FOR.try_with(Cell::get).unwrap_or(0);
+        let counter = LiveCounter::of(generation);
+        counter.count.fetch_add(1, Ordering::Relaxed);
+        counter.bytes.fetch_add(layout.size(), Ordering::Relaxed);
+
+        unsafe {
+            let ptr = base.add(Self::header_size(layout));
+            ptr.cast::<usize>().sub(1).write(ALLOCATION_TAG);
+            ptr.cast::<usize>().sub(2).write(generation);
+            ptr
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let tag = unsafe { ptr.cast::<usize>().sub(1) };
+        if unsafe { tag.read() } != ALLOCATION_TAG {
+            foreign_free();
+        }
+
+        // Cleared, so freeing the memory twice is caught as well.
+        unsafe { tag.write(0) };
+
+        let generation = unsafe { ptr.cast::<usize>().sub(2).read() };
+        let counter = LiveCounter::of(generation);
+        counter.count.fetch_sub(1, Ordering::Relaxed);
+        counter.bytes.fetch_sub(layout.size(), Ordering::Relaxed);
+
+        let Some(tagged) = Self::tagged_layout(layout) else {
+            foreign_free();
+        };
+        unsafe {
+            self.inner
+                .dealloc(ptr.sub(Self::header_size(layout)), tagged)
+        };
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{AppInterface, CoreCommand};
+use iced::Element;
+
+/// Adapts the state and the `update` and `view` functions of a plain iced
+/// application to [`AppInterface`], so it can be hot reloaded without being
+/// restructured into a core.
+///
+/// The view has to be `'static`, i.e. it can't borrow from the state, as the
+/// elements outlive the call into the core. Export the application as a core
+/// with [`crate::export_app`].
+pub struct FnApp<S, M> {
+    state: S,
+    update: fn(&mut S, M),
+    view: fn(&S) -> Element<'static, M>,
+}
+
+impl<S, M> FnApp<S, M> {
+    pub fn new(state: S, update: fn(&mut S, M), view: fn(&S) -> Element<'static, M>) -> Self {
+        Self {
+            state,
+            update,
+            view,
+        }
+    }
+}
+
+impl<S, M> AppInterface<S, M> for FnApp<S, M> {
+    fn update(&mut self, message: M) -> Vec<CoreCommand<M>> {
+        (self.update)(&mut self.state, message);
+        Vec::new()
+    }
+
+    fn view(&self) -> Element<'static, M> {
+        (self.view)(&self.state)
+    }
+
+    fn state(&self) -> &S {
+        &self.state
+    }
+}
+
+/// Exports the `update` and `view` functions of a plain iced application as
+/// a hot-reloadable core, see [`FnApp`].
+///
+/// Build the crate as a `cdylib` as well as an `rlib`, and run it with
+/// `HotReloadBuilder::run_app` of `iced_hot_reload`, which falls back to
+/// the linked functions if the library wasn't built. The state has to
+/// implement `Serialize`, `DeserializeOwned` and `Default`.
+///
+/// ```ignore
+/// hot_reload_interface::export_app!(State, Message, update, view);
+/// ```
+#[macro_export]
+macro_rules! export_app {
+    ($state:ty, $message:ty, $update:path, $view:path) => {
+        /// The application exported as a hot-reloadable core.
+        pub struct HotReloadApp($crate::FnApp<$state, $message>);
+
+        impl ::std::convert::From<$state> for HotReloadApp {
+            fn from(state: $state) -> Self {
+                Self($crate::FnApp::new(state, $update, $view))
+            }
+        }
+
+        #[$crate::hot_reload_core]
+        impl $crate::AppInterface<$state, $message> for HotReloadApp {
+            fn update(
+                &mut self,
+                message: $message,
+            ) -> ::std::vec::Vec<$crate::CoreCommand<$message>> {
+                $crate::AppInterface::update(&mut self.0, message)
+            }
+
+            fn view(&self) -> ::iced::Element<'static, $message> {
+                $crate::AppInterface::view(&self.0)
+            }
+
+            fn state(&self) -> &$state {
+                $crate::AppInterface::state(&self.0)
+            }
+        }
+    };
+}
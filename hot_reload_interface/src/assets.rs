@@ -0,0 +1,43 @@
+use iced::widget::image::Handle;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::SystemTime;
+
+/// The handles created by [`image_handle`], with the modification time of
+/// the file they were read at.
+static IMAGES: OnceLock<Mutex<HashMap<PathBuf, (Option<SystemTime>, Handle)>>> = OnceLock::new();
+
+/// A handle to the image at `path` which follows changes of the file.
+///
+/// The renderer caches images by the id of their handle, and a handle
+/// created with `Handle::from_path` keeps its id when the file is rewritten,
+/// so the shell would show the old image until it is restarted. This handle
+/// is read from the bytes of the file instead and replaced by a new one
+/// whenever the file is modified, which makes the renderer load it again.
+pub fn image_handle(path: impl AsRef<Path>) -> Handle {
+    let path = path.as_ref();
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    let mut images = IMAGES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    match images.get(path) {
+        Some((read_at, handle)) if *read_at == modified => handle.clone(),
+        _ => {
+            let handle = match std::fs::read(path) {
+                Ok(bytes) => Handle::from_bytes(bytes),
+                Err(e) => {
+                    log::error!("Failed to read {}: {}", path.display(), e);
+                    Handle::from_path(path)
+                }
+            };
+            images.insert(path.to_path_buf(), (modified, handle.clone()));
+            handle
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::ops::BitOr;
+
+/// The name of the optional exported function returning the [`Capabilities`]
+/// of a core.
+///
+/// Cores built before it was introduced don't export it, in which case the
+/// shell infers their capabilities from the symbols they export.
+pub const CAPABILITIES_SYMBOL: &str = "hot_reload_capabilities";
+
+/// Returns the [`Capabilities`] of the core.
+pub type CapabilitiesFn = unsafe extern "C" fn() -> Capabilities;
+
+/// The optional parts of the contract a core implements, as bitflags.
+///
+/// The shell only calls into what a core declares, so as the contract grows
+/// older and newer cores are hosted side by side. Bits unknown to the shell
+/// belong to newer cores and are ignored.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// No optional parts at all.
+    pub const NONE: Self = Self(0);
+
+    /// The core provides the window title, theme or scale factor.
+    pub const THEME: Self = Self(1 << 0);
+
+    /// The core declares subscriptions.
+    pub const SUBSCRIPTIONS: Self = Self(1 << 1);
+
+    /// The core hands its state over serialized, so it survives reloads.
+    pub const SERIALIZED_STATE: Self = Self(1 << 2);
+
+    /// The core exports a migration for states of older schema versions.
+    pub const MIGRATIONS: Self = Self(1 << 3);
+
+    /// The core provides the views of windows of its own.
+    pub const WINDOWS: Self = Self(1 << 4);
+
+    /// The core turns the results of its commands into messages.
+    pub const COMMAND_RESULTS: Self = Self(1 << 5);
+
+    /// The core listens to the events of the window.
+    pub const EVENTS: Self = Self(1 << 6);
+
+    /// The core wants to know when it is reloaded or unloaded.
+    pub const LIFECYCLE: Self = Self(1 << 7);
+
+    /// The names of the capabilities, for logs.
+    const NAMES: [(Self, &'static str); 8] = [
+        (Self::THEME, "theme"),
+        (Self::SUBSCRIPTIONS, "subscriptions"),
+        (Self::SERIALIZED_STATE, "serialized state"),
+        (Self::MIGRATIONS, "migrations"),
+        (Self::WINDOWS, "windows"),
+        (Self::COMMAND_RESULTS, "command results"),
+        (Self::EVENTS, "events"),
+        (Self::LIFECYCLE, "lifecycle hooks"),
+    ];
+
+    /// The capabilities with the raw `bits`.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// The raw bits of the capabilities.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether all of `other` is part of the capabilities.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The capabilities together with `other`.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// The names of the known capabilities contained, e.g. `["theme", "migrations"]`.
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(capability, _)| self.contains(*capability))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        self.union(other)
+    }
+}
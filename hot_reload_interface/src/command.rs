@@ -0,0 +1,168 @@
+use crate::subscription::KeyName;
+use std::ffi::c_void;
+use std::time::Duration;
+
+/// The name of a window opened by the core, e.g. `"inspector"`, in the same
+/// fixed-size buffer as a [`KeyName`].
+pub type WindowName = KeyName;
+
+/// The name the core gives a command producing a result, e.g. `"open"`, to
+/// tell the results apart in `AppInterface::command_result`.
+pub type RequestName = KeyName;
+
+/// A command returned by the core from `AppInterface::update`, executed by
+/// the shell.
+///
+/// Futures and iced tasks can't cross the library boundary, and couldn't
+/// outlive a reload of the library anyway, so the core only describes what
+/// it needs done. Commands producing a result route it back into the core
+/// as a message.
+///
+/// The strings of the commands move to the shell like the messages do,
+/// which is sound because shell and core are built by the same compiler,
+/// see [`crate::AbiInfo`].
+#[repr(C, u8)]
+#[derive(Debug, Clone)]
+pub enum CoreCommand<M> {
+    /// Sends `message` to the core right after the current update.
+    Message { message: M },
+
+    /// Sends `message` to the core after `delay_ms` milliseconds, without
+    /// blocking the UI.
+    After { delay_ms: u64, message: M },
+
+    /// Closes the window of the shell.
+    CloseWindow,
+
+    /// Opens the window `name` with the given size, showing the
+    /// `AppInterface::window_view` of the core for it. Focuses the window if
+    /// it is open already.
+    ///
+    /// The windows belong to the shell, so they stay open across reloads.
+    OpenWindow {
+        name: WindowName,
+        width: f32,
+        height: f32,
+    },
+
+    /// Closes the window `name` opened with [`CoreCommand::OpenWindow`].
+    CloseNamedWindow { name: WindowName },
+
+    /// Copies `text` to the clipboard.
+    CopyToClipboard { text: String },
+
+    /// Opens `url` in the browser, or a path in the application registered
+    /// for it.
+    OpenUrl { url: String },
+
+    /// Asks the user for a file in a dialog titled `title`, showing the
+    /// files with one of the comma-separated `extensions`, or all files if
+    /// there are none.
+    ///
+    /// The [`CommandResult`] is passed to `AppInterface::command_result`
+    /// under `request`, by whichever core is loaded once the user is done.
+    PickFile {
+        request: RequestName,
+        title: String,
+        extensions: String,
+    },
+
+    /// Exits the shell, after it persisted the state, destroyed the core
+    /// and unloaded the libraries.
+    Exit,
+}
+
+impl<M> CoreCommand<M> {
+    /// Sends `message` to the core right after the current update.
+    pub fn message(message: M) -> Self {
+        Self::Message { message }
+    }
+
+    /// Sends `message` to the core after `delay`.
+    pub fn after(delay: Duration, message: M) -> Self {
+        Self::After {
+            delay_ms: delay.as_millis() as u64,
+            message,
+        }
+    }
+
+    /// Opens the window `name` with the given size, see [`WindowName`].
+    pub fn open_window(name: &str, size: iced::Size) -> Self {
+        Self::OpenWindow {
+            name: WindowName::new(name),
+            width: size.width,
+            height: size.height,
+        }
+    }
+
+    /// Closes the window `name` opened with [`CoreCommand::open_window`].
+    pub fn close_named_window(name: &str) -> Self {
+        Self::CloseNamedWindow {
+            name: WindowName::new(name),
+        }
+    }
+
+    /// Copies `text` to the clipboard.
+    pub fn copy_to_clipboard(text: impl Into<String>) -> Self {
+        Self::CopyToClipboard { text: text.into() }
+    }
+
+    /// Opens `url` in the browser.
+    pub fn open_url(url: impl Into<String>) -> Self {
+        Self::OpenUrl { url: url.into() }
+    }
+
+    /// Asks the user for a file with one of `extensions`, e.g.
+    /// `pick_file("open", "Open a document", &["txt", "md"])`. The result is
+    /// passed to `AppInterface::command_result` under `request`.
+    pub fn pick_file(request: &str, title: impl Into<String>, extensions: &[&str]) -> Self {
+        Self::PickFile {
+            request: RequestName::new(request),
+            title: title.into(),
+            extensions: extensions.join(","),
+        }
+    }
+}
+
+/// The result of a command the shell ran for the core, see
+/// [`CoreCommand::PickFile`].
+#[repr(C, u8)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandResult {
+    /// The user picked the file at `path`.
+    FilePicked { path: String },
+
+    /// The user closed the dialog without picking anything.
+    Cancelled,
+
+    /// The shell couldn't run the command.
+    Failed { error: String },
+}
+
+/// Receives the commands returned by the core.
+///
+/// The core moves every [`CoreCommand`] to `push`, which stores it in
+/// memory owned by the shell.
+#[repr(C)]
+pub struct CommandWriter<M> {
+    /// The shell's list, passed back to `push`.
+    pub context: *mut c_void,
+
+    /// Moves the command behind the pointer to the list in `context`.
+    pub push: unsafe extern "C" fn(*mut c_void, *mut CoreCommand<M>),
+}
+
+impl<M> CommandWriter<M> {
+    /// Creates a writer appending to `commands`.
+    pub fn new(commands: &mut Vec<CoreCommand<M>>) -> Self {
+        Self {
+            context: (commands as *mut Vec<CoreCommand<M>>).cast(),
+            push: push_to_vec::<M>,
+        }
+    }
+}
+
+unsafe extern "C" fn push_to_vec<M>(context: *mut c_void, command: *mut CoreCommand<M>) {
+    let commands = unsafe { &mut *context.cast::<Vec<CoreCommand<M>>>() };
+    commands.push(unsafe { command.read() });
+}
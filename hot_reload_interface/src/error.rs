@@ -0,0 +1,59 @@
+use std::any::Any;
+
+/// The capacity of an [`ErrorMessage`] in bytes.
+const ERROR_MESSAGE_CAPACITY: usize = 512;
+
+/// A fixed-size, C-compatible buffer carrying an error message from the core
+/// to the shell.
+///
+/// The buffer lives on the shell's stack, so no memory allocated by one side
+/// has to be freed by the other. Longer messages are truncated.
+#[repr(C)]
+pub struct ErrorMessage {
+    len: usize,
+    bytes: [u8; ERROR_MESSAGE_CAPACITY],
+}
+
+impl ErrorMessage {
+    /// Creates an empty message.
+    pub fn new() -> Self {
+        Self {
+            len: 0,
+            bytes: [0; ERROR_MESSAGE_CAPACITY],
+        }
+    }
+
+    /// Replaces the message with `message`, truncated on a character boundary.
+    pub fn set(&mut self, message: &str) {
+        let mut len = message.len().min(ERROR_MESSAGE_CAPACITY);
+        while !message.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.bytes[..len].copy_from_slice(&message.as_bytes()[..len]);
+        self.len = len;
+    }
+
+    /// The message as a string.
+    pub fn as_str(&self) -> &str {
+        let len = self.len.min(ERROR_MESSAGE_CAPACITY);
+        std::str::from_utf8(&self.bytes[..len]).unwrap_or("invalid error message")
+    }
+}
+
+impl Default for ErrorMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Extracts the message from the payload of a caught panic.
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
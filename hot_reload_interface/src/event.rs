@@ -0,0 +1,82 @@
+use crate::subscription::KeyName;
+use iced::keyboard::Modifiers;
+
+/// An event of the main window forwarded to `AppInterface::on_event`.
+///
+/// Keyboard and mouse events are only forwarded if no widget captured them,
+/// so typing into a text input doesn't trigger the shortcuts of the core.
+#[repr(C, u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoreEvent {
+    /// The window was resized to the logical size.
+    WindowResized { width: f32, height: f32 },
+
+    /// The window gained the focus.
+    WindowFocused,
+
+    /// The window lost the focus.
+    WindowUnfocused,
+
+    /// The user asked to close the window.
+    ///
+    /// Returning a message for it keeps the window open, e.g. to ask about
+    /// unsaved changes first. The core closes the window later with
+    /// `CoreCommand::CloseWindow` or `CoreCommand::Exit`.
+    CloseRequested,
+
+    /// The key was pressed, named like in `CoreSubscription::KeyPress`.
+    KeyPressed { key: KeyName, modifiers: u32 },
+
+    /// The key was released.
+    KeyReleased { key: KeyName, modifiers: u32 },
+
+    /// The cursor moved to the logical position in the window.
+    CursorMoved { x: f32, y: f32 },
+
+    /// The mouse button was pressed.
+    MousePressed { button: MouseButton },
+
+    /// The mouse button was released.
+    MouseReleased { button: MouseButton },
+
+    /// The mouse wheel scrolled by the lines or pixels, depending on the
+    /// device.
+    WheelScrolled { x: f32, y: f32 },
+}
+
+impl CoreEvent {
+    /// The modifiers held during a key event, none for the other events.
+    pub fn modifiers(&self) -> Modifiers {
+        match self {
+            Self::KeyPressed { modifiers, .. } | Self::KeyReleased { modifiers, .. } => {
+                Modifiers::from_bits_truncate(*modifiers)
+            }
+            _ => Modifiers::empty(),
+        }
+    }
+}
+
+/// A button of the mouse, see [`CoreEvent::MousePressed`].
+#[repr(C, u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl From<iced::mouse::Button> for MouseButton {
+    fn from(button: iced::mouse::Button) -> Self {
+        match button {
+            iced::mouse::Button::Left => Self::Left,
+            iced::mouse::Button::Right => Self::Right,
+            iced::mouse::Button::Middle => Self::Middle,
+            iced::mouse::Button::Back => Self::Back,
+            iced::mouse::Button::Forward => Self::Forward,
+            iced::mouse::Button::Other(other) => Self::Other(other),
+        }
+    }
+}
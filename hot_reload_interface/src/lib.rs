@@ -0,0 +1,684 @@
+use iced::Element;
+use serde::Serialize;
+use std::ffi::c_void;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+mod abi;
+mod allocator;
+mod app;
+#[cfg(feature = "image")]
+mod assets;
+mod capabilities;
+mod command;
+mod error;
+mod event;
+mod localization;
+mod logging;
+mod presentation;
+mod reload;
+mod remote;
+mod self_test;
+mod state;
+mod stylesheet;
+mod subscription;
+mod wasm;
+
+// Links the shared iced, see `iced_dylib`.
+#[cfg(feature = "dynamic-iced")]
+#[allow(unused_imports)]
+use iced_dylib;
+
+pub use abi::{schema_hash, AbiInfo, AbiVersionFn, ABI_VERSION, ABI_VERSION_SYMBOL};
+pub use allocator::{
+    checked_allocator_installed, install_host_allocator, live_allocations, CheckedAllocator,
+    HostAllocator, LiveAllocations, SetAllocatorFn, SharedAllocator, SET_ALLOCATOR_SYMBOL,
+};
+pub use app::FnApp;
+#[cfg(feature = "image")]
+pub use assets::image_handle;
+pub use capabilities::{Capabilities, CapabilitiesFn, CAPABILITIES_SYMBOL};
+pub use command::{CommandResult, CommandWriter, CoreCommand, RequestName, WindowName};
+pub use error::{panic_message, ErrorMessage};
+pub use event::{CoreEvent, MouseButton};
+pub use hot_reload_macros::hot_reload_core;
+pub use localization::{
+    install_host_catalog, set_host_catalog, tr, tr_with, CatalogLookup, HostCatalog, SetCatalogFn,
+    SET_CATALOG_SYMBOL,
+};
+pub use logging::{install_host_logger, HostLogger, SetLoggerFn, SET_LOGGER_SYMBOL};
+pub use presentation::{write_presentation, Presentation, PresentationFn, PRESENTATION_SYMBOL};
+pub use reload::ReloadInfo;
+pub use remote::{handle_request, serve, RemoteApp, Request, Response, ViewNode};
+pub use self_test::{run_self_test, SelfTestFn, SELF_TEST_SYMBOL};
+pub use serde_json;
+pub use state::{
+    deserialize_state, run_migration, serialize_state, MigrateFn, StateVersionFn, StateWriter,
+    MIGRATE_SYMBOL, STATE_VERSION_SYMBOL,
+};
+pub use stylesheet::{
+    install_host_stylesheet, parse_color, set_host_stylesheet, stylesheet, HostStylesheet,
+    SetStylesheetFn, StylePalette, Stylesheet, SET_STYLESHEET_SYMBOL,
+};
+pub use subscription::{CoreSubscription, KeyName, SubscriptionWriter};
+
+/// The default name of the exported function creating the core instance.
+pub const CREATE_SYMBOL: &str = "create_app";
+
+/// The default name of the exported function destroying the core instance.
+pub const DESTROY_SYMBOL: &str = "destroy_app";
+
+/// Creates the core instance from the serialized state of the previous one.
+///
+/// The state is passed as a pointer and a length; an empty state means the
+/// core starts from its default state. Returns a null [`RawApp`] if the core
+/// couldn't be created, with the reason written to the [`ErrorMessage`].
+pub type CreateFn<M> = unsafe extern "C" fn(*const u8, usize, *mut ErrorMessage) -> RawApp<M>;
+
+/// Destroys the core instance returned by [`CreateFn`].
+pub type DestroyFn<M> = unsafe extern "C" fn(RawApp<M>);
+
+/// Represents the contract between app and core.
+///
+/// `S` is the application state handed over from one core instance to the
+/// next on every reload, `M` is the message type of the application. Both
+/// are defined by the application; the shell only sees the state in its
+/// serialized form and never inspects the messages.
+///
+/// The trait is only used inside the core. What crosses the library
+/// boundary is a [`RawApp`], which doesn't depend on the layout of Rust
+/// trait objects.
+pub trait AppInterface<S, M> {
+    /// Handles `message` and returns the commands the shell should execute.
+    fn update(&mut self, message: M) -> Vec<CoreCommand<M>>;
+    fn view(&self) -> Element<'static, M>;
+    fn state(&self) -> &S;
+
+    /// The window title, the shell's title by default.
+    fn title(&self) -> Option<String> {
+        None
+    }
+
+    /// The theme of the window, the shell's theme by default.
+    fn theme(&self) -> Option<iced::Theme> {
+        None
+    }
+
+    /// The scale factor of the window, the shell's scale factor by default.
+    fn scale_factor(&self) -> Option<f64> {
+        None
+    }
+
+    /// The subscriptions of the core, none by default.
+    fn subscriptions(&self) -> Vec<CoreSubscription<M>> {
+        Vec::new()
+    }
+
+    /// The view of the window `window` opened with
+    /// [`CoreCommand::open_window`], the view of the main window by default.
+    fn window_view(&self, _window: &str) -> Element<'static, M> {
+        self.view()
+    }
+
+    /// Turns the result of the command named `request`, e.g. of
+    /// [`CoreCommand::pick_file`], into a message. Results are dropped by
+    /// default.
+    fn command_result(&self, _request: &str, _result: &CommandResult) -> Option<M> {
+        None
+    }
+
+    /// Turns an event of the window into a message, e.g. a shortcut or a
+    /// close request to confirm. Events are ignored by default.
+    fn on_event(&self, _event: &CoreEvent) -> Option<M> {
+        None
+    }
+
+    /// Called right before the instance is destroyed, after its state was
+    /// handed to the next core or persisted on exit, e.g. to flush caches
+    /// or close files. Does nothing by default.
+    fn on_before_unload(&mut self) {}
+
+    /// Called right after the instance was created from the state of the
+    /// core replaced by a reload or rollback, but not on the first start,
+    /// e.g. to re-derive caches the state doesn't carry or to announce the
+    /// new version. Returns the commands the shell should execute, none by
+    /// default.
+    fn on_after_reload(&mut self, _reload: &ReloadInfo) -> Vec<CoreCommand<M>> {
+        Vec::new()
+    }
+}
+
+/// The functions operating on the opaque data of a [`RawApp`].
+///
+/// Arguments and results are passed through pointers, so that every entry
+/// has a C-compatible signature regardless of `M`. Panics in the core never
+/// unwind into the shell: the functions return `false` and describe the
+/// panic in the [`ErrorMessage`] instead.
+///
+/// Only the signatures are C-compatible, this is no stable C ABI: the
+/// messages, the [`Element`] of the view and the [`CoreCommand`]s collected
+/// into the shell's `Vec` cross the boundary as Rust types, whose layout is
+/// only the same if the shell and the core were built with the same
+/// compiler against the same iced and message type. The check of the
+/// [`AbiInfo`] exported by the core, comparing its ABI version and schema
+/// hash, is the only guard against a mismatch.
+#[repr(C)]
+pub struct AppVTable<M> {
+    /// Handles the message behind the pointer, taking ownership of it, and
+    /// moves the resulting commands into the [`CommandWriter`].
+    pub update:
+        unsafe extern "C" fn(*mut c_void, *mut M, *mut CommandWriter<M>, *mut ErrorMessage) -> bool,
+
+    /// Writes the current view into the uninitialized element behind the pointer.
+    pub view:
+        unsafe extern "C" fn(*const c_void, *mut Element<'static, M>, *mut ErrorMessage) -> bool,
+
+    /// Serializes the current state into the [`StateWriter`].
+    pub save_state:
+        unsafe extern "C" fn(*const c_void, *mut StateWriter, *mut ErrorMessage) -> bool,
+
+    /// Moves the current subscriptions into the [`SubscriptionWriter`].
+    pub subscriptions:
+        unsafe extern "C" fn(*const c_void, *mut SubscriptionWriter<M>, *mut ErrorMessage) -> bool,
+
+    /// Writes the view of the window named by the [`KeyName`] into the
+    /// uninitialized element behind the pointer.
+    pub window_view: unsafe extern "C" fn(
+        *const c_void,
+        *const KeyName,
+        *mut Element<'static, M>,
+        *mut ErrorMessage,
+    ) -> bool,
+
+    /// Writes the message for the [`CommandResult`] of the request named by
+    /// the [`KeyName`] into the uninitialized option behind the pointer.
+    pub command_result: unsafe extern "C" fn(
+        *const c_void,
+        *const KeyName,
+        *const CommandResult,
+        *mut Option<M>,
+        *mut ErrorMessage,
+    ) -> bool,
+
+    /// Writes the message for the [`CoreEvent`] into the uninitialized
+    /// option behind the pointer.
+    pub on_event: unsafe extern "C" fn(
+        *const c_void,
+        *const CoreEvent,
+        *mut Option<M>,
+        *mut ErrorMessage,
+    ) -> bool,
+
+    /// Runs [`AppInterface::on_before_unload`].
+    pub before_unload: unsafe extern "C" fn(*mut c_void, *mut ErrorMessage) -> bool,
+
+    /// Runs [`AppInterface::on_after_reload`] with the [`ReloadInfo`] and
+    /// moves the resulting commands into the [`CommandWriter`].
+    pub after_reload: unsafe extern "C" fn(
+        *mut c_void,
+        *const ReloadInfo,
+        *mut CommandWriter<M>,
+        *mut ErrorMessage,
+    ) -> bool,
+
+    /// Drops the core instance and frees the data, swallowing a panic of its
+    /// destructor. The data must not be used afterwards.
+    pub drop: unsafe extern "C" fn(*mut c_void),
+}
+
+/// An FFI-safe handle to a core instance: an opaque data pointer plus the
+/// vtable of the functions operating on it.
+///
+/// Both pointers are null if the creation of the core failed.
+#[repr(C)]
+pub struct RawApp<M> {
+    data: *mut c_void,
+    vtable: *const AppVTable<M>,
+}
+
+impl<M> RawApp<M> {
+    /// Moves `app` to the heap and builds the vtable for its type.
+    pub fn new<T, S>(app: T) -> Self
+    where
+        T: AppInterface<S, M>,
+        S: Serialize,
+    {
+        Self {
+            data: Box::into_raw(Box::new(app)).cast(),
+            vtable: &AppVTable {
+                update: update::<T, S, M>,
+                view: view::<T, S, M>,
+                save_state: save_state::<T, S, M>,
+                subscriptions: subscriptions::<T, S, M>,
+                window_view: window_view::<T, S, M>,
+                command_result: command_result::<T, S, M>,
+                on_event: on_event::<T, S, M>,
+                before_unload: before_unload::<T, S, M>,
+                after_reload: after_reload::<T, S, M>,
+                drop: drop_app::<T>,
+            },
+        }
+    }
+
+    /// A handle signalling that the core couldn't be created.
+    pub fn null() -> Self {
+        Self {
+            data: std::ptr::null_mut(),
+            vtable: std::ptr::null(),
+        }
+    }
+
+    /// Whether this handle doesn't point to a core instance.
+    pub fn is_null(&self) -> bool {
+        self.data.is_null() || self.vtable.is_null()
+    }
+
+    /// Forwards `message` to the core and returns the commands it requested.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn update(&mut self, message: M) -> Result<Vec<CoreCommand<M>>, String> {
+        let mut message = ManuallyDrop::new(message);
+        let mut commands = Vec::new();
+        let mut writer = CommandWriter::new(&mut commands);
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).update)(self.data, &mut *message, &mut writer, &mut error) } {
+            Ok(commands)
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Builds the view of the core.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn view(&self) -> Result<Element<'static, M>, String> {
+        let mut element = MaybeUninit::uninit();
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).view)(self.data, element.as_mut_ptr(), &mut error) } {
+            Ok(unsafe { element.assume_init() })
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Serializes the current state of the core.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn save_state(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        let mut writer = StateWriter::new(&mut bytes);
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).save_state)(self.data, &mut writer, &mut error) } {
+            Ok(bytes)
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Collects the subscriptions declared by the core.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn subscriptions(&self) -> Result<Vec<CoreSubscription<M>>, String> {
+        let mut subscriptions = Vec::new();
+        let mut writer = SubscriptionWriter::new(&mut subscriptions);
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).subscriptions)(self.data, &mut writer, &mut error) } {
+            Ok(subscriptions)
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Builds the view of the window `window` of the core.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn window_view(&self, window: &str) -> Result<Element<'static, M>, String> {
+        let name = KeyName::new(window);
+        let mut element = MaybeUninit::uninit();
+        let mut error = ErrorMessage::new();
+        if unsafe {
+            ((*self.vtable).window_view)(self.data, &name, element.as_mut_ptr(), &mut error)
+        } {
+            Ok(unsafe { element.assume_init() })
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Asks the core for the message of `result`, the result of the command
+    /// named `request`.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn command_result(
+        &self,
+        request: &str,
+        result: &CommandResult,
+    ) -> Result<Option<M>, String> {
+        let request = KeyName::new(request);
+        let mut message = MaybeUninit::uninit();
+        let mut error = ErrorMessage::new();
+        if unsafe {
+            ((*self.vtable).command_result)(
+                self.data,
+                &request,
+                result,
+                message.as_mut_ptr(),
+                &mut error,
+            )
+        } {
+            Ok(unsafe { message.assume_init() })
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Asks the core for the message of `event`.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn on_event(&self, event: &CoreEvent) -> Result<Option<M>, String> {
+        let mut message = MaybeUninit::uninit();
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).on_event)(self.data, event, message.as_mut_ptr(), &mut error) }
+        {
+            Ok(unsafe { message.assume_init() })
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Tells the core it is about to be destroyed.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn before_unload(&mut self) -> Result<(), String> {
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).before_unload)(self.data, &mut error) } {
+            Ok(())
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Tells the core it was created by the `reload` and returns the
+    /// commands it requested.
+    ///
+    /// Returns the panic message if the core panicked.
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and its library must still be loaded.
+    pub unsafe fn after_reload(
+        &mut self,
+        reload: &ReloadInfo,
+    ) -> Result<Vec<CoreCommand<M>>, String> {
+        let mut commands = Vec::new();
+        let mut writer = CommandWriter::new(&mut commands);
+        let mut error = ErrorMessage::new();
+        if unsafe { ((*self.vtable).after_reload)(self.data, reload, &mut writer, &mut error) } {
+            Ok(commands)
+        } else {
+            Err(error.as_str().to_string())
+        }
+    }
+
+    /// Drops the core instance through its vtable, without having to know
+    /// its type. Does nothing for a null handle.
+    ///
+    /// # Safety
+    ///
+    /// The library the handle was created by must still be loaded.
+    pub unsafe fn destroy(self) {
+        if !self.is_null() {
+            unsafe { ((*self.vtable).drop)(self.data) };
+        }
+    }
+
+    /// Borrows the core instance created by [`RawApp::new`].
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and must have been created from a `T`.
+    pub unsafe fn as_ref<T>(&self) -> &T {
+        unsafe { &*self.data.cast::<T>() }
+    }
+
+    /// Takes back the core instance created by [`RawApp::new`].
+    ///
+    /// # Safety
+    ///
+    /// The handle must not be null and must have been created from a `T`.
+    pub unsafe fn into_box<T>(self) -> Box<T> {
+        unsafe { Box::from_raw(self.data.cast()) }
+    }
+}
+
+unsafe extern "C" fn drop_app<T>(data: *mut c_void) {
+    let app = unsafe { Box::from_raw(data.cast::<T>()) };
+    let _ = catch_unwind(AssertUnwindSafe(move || drop(app)));
+}
+
+unsafe extern "C" fn update<T: AppInterface<S, M>, S, M>(
+    data: *mut c_void,
+    message: *mut M,
+    writer: *mut CommandWriter<M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &mut *data.cast::<T>() };
+    let message = unsafe { message.read() };
+
+    match catch_unwind(AssertUnwindSafe(|| app.update(message))) {
+        Ok(commands) => {
+            let writer = unsafe { &*writer };
+            for command in commands {
+                let mut command = ManuallyDrop::new(command);
+                unsafe { (writer.push)(writer.context, &mut *command) };
+            }
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn view<T: AppInterface<S, M>, S, M>(
+    data: *const c_void,
+    element: *mut Element<'static, M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &*data.cast::<T>() };
+
+    match catch_unwind(AssertUnwindSafe(|| app.view())) {
+        Ok(view) => {
+            unsafe { element.write(view) };
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn window_view<T: AppInterface<S, M>, S, M>(
+    data: *const c_void,
+    window: *const KeyName,
+    element: *mut Element<'static, M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &*data.cast::<T>() };
+    let window = unsafe { &*window };
+
+    match catch_unwind(AssertUnwindSafe(|| app.window_view(window.as_str()))) {
+        Ok(view) => {
+            unsafe { element.write(view) };
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn command_result<T: AppInterface<S, M>, S, M>(
+    data: *const c_void,
+    request: *const KeyName,
+    result: *const CommandResult,
+    message: *mut Option<M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &*data.cast::<T>() };
+    let (request, result) = unsafe { (&*request, &*result) };
+
+    match catch_unwind(AssertUnwindSafe(|| {
+        app.command_result(request.as_str(), result)
+    })) {
+        Ok(result_message) => {
+            unsafe { message.write(result_message) };
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn on_event<T: AppInterface<S, M>, S, M>(
+    data: *const c_void,
+    event: *const CoreEvent,
+    message: *mut Option<M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &*data.cast::<T>() };
+    let event = unsafe { &*event };
+
+    match catch_unwind(AssertUnwindSafe(|| app.on_event(event))) {
+        Ok(event_message) => {
+            unsafe { message.write(event_message) };
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn before_unload<T: AppInterface<S, M>, S, M>(
+    data: *mut c_void,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &mut *data.cast::<T>() };
+
+    match catch_unwind(AssertUnwindSafe(|| app.on_before_unload())) {
+        Ok(()) => true,
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn after_reload<T: AppInterface<S, M>, S, M>(
+    data: *mut c_void,
+    reload: *const ReloadInfo,
+    writer: *mut CommandWriter<M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &mut *data.cast::<T>() };
+    let reload = unsafe { &*reload };
+
+    match catch_unwind(AssertUnwindSafe(|| app.on_after_reload(reload))) {
+        Ok(commands) => {
+            let writer = unsafe { &*writer };
+            for command in commands {
+                let mut command = ManuallyDrop::new(command);
+                unsafe { (writer.push)(writer.context, &mut *command) };
+            }
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn save_state<T: AppInterface<S, M>, S: Serialize, M>(
+    data: *const c_void,
+    writer: *mut StateWriter,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &*data.cast::<T>() };
+
+    match catch_unwind(AssertUnwindSafe(|| serialize_state(app.state()))) {
+        Ok(Ok(bytes)) => {
+            let writer = unsafe { &*writer };
+            unsafe { (writer.write)(writer.context, bytes.as_ptr(), bytes.len()) };
+            true
+        }
+        Ok(Err(message)) => {
+            unsafe { (*error).set(&message) };
+            false
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn subscriptions<T: AppInterface<S, M>, S, M>(
+    data: *const c_void,
+    writer: *mut SubscriptionWriter<M>,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { &*data.cast::<T>() };
+
+    match catch_unwind(AssertUnwindSafe(|| app.subscriptions())) {
+        Ok(subscriptions) => {
+            let writer = unsafe { &*writer };
+            for subscription in subscriptions {
+                let mut subscription = ManuallyDrop::new(subscription);
+                unsafe { (writer.push)(writer.context, &mut *subscription) };
+            }
+            true
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
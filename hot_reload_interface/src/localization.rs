@@ -0,0 +1,141 @@
+use crate::logging::FfiStr;
+use crate::state::StateWriter;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+/// The name of the optional exported function handing the shell's
+/// translation catalog to the core.
+///
+/// The shell calls it right after loading the library, like
+/// [`crate::SET_LOGGER_SYMBOL`]. Cores built before it was introduced show
+/// the keys of their strings.
+pub const SET_CATALOG_SYMBOL: &str = "hot_reload_set_catalog";
+
+/// Makes the core translate its strings through the [`HostCatalog`] of the
+/// shell.
+pub type SetCatalogFn = unsafe extern "C" fn(*const HostCatalog);
+
+/// Looks up the translation of the key with the named arguments, or returns
+/// `None` if the catalog has none.
+pub type CatalogLookup = dyn Fn(&str, &[(&str, &str)]) -> Option<String> + Send + Sync;
+
+/// The catalog of the calling library. Only the shell's copy is ever set.
+static HOST: RwLock<Option<Box<CatalogLookup>>> = RwLock::new(None);
+
+/// Sets the catalog the cores translate their strings with.
+///
+/// Called by the shell whenever the catalog files change.
+pub fn set_host_catalog(lookup: Box<CatalogLookup>) {
+    *HOST.write().unwrap_or_else(PoisonError::into_inner) = Some(lookup);
+}
+
+/// The translation catalog of the shell as seen by the core: a function
+/// pointer into the shell.
+///
+/// The core asks the shell for every string, so a saved catalog applies on
+/// the next view.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HostCatalog {
+    /// Writes the translation of the key, with the arguments as a JSON
+    /// object of strings, into the writer. Returns `false` if there is none.
+    lookup: extern "C" fn(FfiStr, FfiStr, *mut StateWriter) -> bool,
+}
+
+impl HostCatalog {
+    /// Creates the host catalog of the calling library, i.e. of the shell.
+    pub fn new() -> Self {
+        Self {
+            lookup: host_lookup,
+        }
+    }
+}
+
+impl Default for HostCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The host catalog installed in the calling library, i.e. in the core.
+static INSTALLED: OnceLock<HostCatalog> = OnceLock::new();
+
+/// Installs `host` as the catalog of the calling library, i.e. of the core.
+///
+/// # Safety
+///
+/// `host` has to be null or point to a valid [`HostCatalog`]. The shell it
+/// belongs to has to outlive the core.
+pub unsafe fn install_host_catalog(host: *const HostCatalog) {
+    if host.is_null() {
+        return;
+    }
+
+    let _ = INSTALLED.set(unsafe { *host });
+}
+
+/// Translates the string `key` with the catalog of the shell, e.g.
+/// `tr("counter-title")`.
+///
+/// Returns `key` itself if the catalog has no translation for it.
+pub fn tr(key: &str) -> String {
+    tr_with(key, &[])
+}
+
+/// Translates the string `key` with the named arguments, e.g.
+/// `tr_with("counter", &[("count", &count.to_string())])`.
+///
+/// Returns `key` itself if the catalog has no translation for it.
+pub fn tr_with(key: &str, args: &[(&str, &str)]) -> String {
+    let Some(host) = INSTALLED.get() else {
+        // Cores linked into the shell share the shell's copy of this crate.
+        return lookup(key, args).unwrap_or_else(|| key.to_string());
+    };
+
+    let args: serde_json::Map<_, _> = args
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string().into()))
+        .collect();
+    let args = serde_json::Value::Object(args).to_string();
+
+    let mut translation = Vec::new();
+    if (host.lookup)(
+        FfiStr::new(key),
+        FfiStr::new(&args),
+        &mut StateWriter::new(&mut translation),
+    ) {
+        String::from_utf8(translation).unwrap_or_else(|_| key.to_string())
+    } else {
+        key.to_string()
+    }
+}
+
+/// Looks `key` up in the catalog of the calling library.
+fn lookup(key: &str, args: &[(&str, &str)]) -> Option<String> {
+    let host = HOST.read().unwrap_or_else(PoisonError::into_inner);
+    host.as_ref().and_then(|lookup| lookup(key, args))
+}
+
+extern "C" fn host_lookup(key: FfiStr, args: FfiStr, writer: *mut StateWriter) -> bool {
+    if writer.is_null() {
+        return false;
+    }
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let args: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(unsafe { args.as_str() }).unwrap_or_default();
+        let args: Vec<(&str, &str)> = args
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.as_str()?)))
+            .collect();
+
+        let Some(translation) = lookup(unsafe { key.as_str() }, &args) else {
+            return false;
+        };
+
+        let writer = unsafe { &*writer };
+        unsafe { (writer.write)(writer.context, translation.as_ptr(), translation.len()) };
+        true
+    }))
+    .unwrap_or(false)
+}
@@ -0,0 +1,208 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// The name of the optional exported function handing the shell's logger to
+/// the core.
+///
+/// The shell calls it right after loading the library, before any core is
+/// created. Cores built before it was introduced keep logging on their own.
+pub const SET_LOGGER_SYMBOL: &str = "hot_reload_set_logger";
+
+/// Makes the core log through the [`HostLogger`] of the shell.
+pub type SetLoggerFn = unsafe extern "C" fn(*const HostLogger);
+
+/// A borrowed string crossing the library boundary.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct FfiStr {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl FfiStr {
+    pub(crate) fn new(s: &str) -> Self {
+        Self {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// Represents `None` with a null pointer.
+    fn from_option(s: Option<&str>) -> Self {
+        s.map_or(
+            Self {
+                ptr: std::ptr::null(),
+                len: 0,
+            },
+            Self::new,
+        )
+    }
+
+    /// # Safety
+    ///
+    /// The string has to outlive `'a`.
+    unsafe fn as_option<'a>(self) -> Option<&'a str> {
+        if self.ptr.is_null() {
+            return None;
+        }
+
+        let bytes = unsafe { std::slice::from_raw_parts(self.ptr, self.len) };
+        std::str::from_utf8(bytes).ok()
+    }
+
+    /// # Safety
+    ///
+    /// The string has to outlive `'a`.
+    pub(crate) unsafe fn as_str<'a>(self) -> &'a str {
+        unsafe { self.as_option() }.unwrap_or_default()
+    }
+}
+
+/// A log record of the core, in a C-compatible form. The strings are only
+/// valid during the call it is passed to.
+#[repr(C)]
+struct FfiRecord {
+    /// The level as the discriminant of [`Level`].
+    level: usize,
+    target: FfiStr,
+    message: FfiStr,
+    module_path: FfiStr,
+    file: FfiStr,
+
+    /// The line, or `0` if unknown.
+    line: u32,
+}
+
+/// The logger of the shell as seen by the core: function pointers into the
+/// shell forwarding to whatever logger the shell installed.
+///
+/// Each library has its own copy of the `log` crate, so a core calling
+/// `log::set_logger` itself competes with the shell's configuration. With
+/// the host logger, the records of the core go through the shell's filters
+/// and sinks instead, e.g. the log pane of the dev overlay.
+///
+/// The core asks the shell about every record, so levels changed while the
+/// shell runs apply to the core right away.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HostLogger {
+    enabled: extern "C" fn(usize, FfiStr) -> bool,
+    log: extern "C" fn(*const FfiRecord),
+    flush: extern "C" fn(),
+}
+
+impl HostLogger {
+    /// Creates the host logger of the calling library, i.e. of the shell.
+    pub fn new() -> Self {
+        Self {
+            enabled: host_enabled,
+            log: host_log,
+            flush: host_flush,
+        }
+    }
+}
+
+impl Default for HostLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Installs `logger` as the logger of the calling library, i.e. of the core.
+/// Does nothing if the core already installed a logger.
+///
+/// # Safety
+///
+/// `logger` has to be null or point to a valid [`HostLogger`]. The shell
+/// it belongs to has to outlive the core.
+pub unsafe fn install_host_logger(logger: *const HostLogger) {
+    if logger.is_null() {
+        return;
+    }
+
+    let host = unsafe { *logger };
+    let _ = catch_unwind(move || {
+        if log::set_boxed_logger(Box::new(ForwardingLogger(host))).is_ok() {
+            log::set_max_level(LevelFilter::Trace);
+        }
+    });
+}
+
+/// The logger of the core, forwarding every record to the shell.
+struct ForwardingLogger(HostLogger);
+
+impl Log for ForwardingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        (self.0.enabled)(metadata.level() as usize, FfiStr::new(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = record.args().to_string();
+        let record = FfiRecord {
+            level: record.level() as usize,
+            target: FfiStr::new(record.target()),
+            message: FfiStr::new(&message),
+            module_path: FfiStr::from_option(record.module_path()),
+            file: FfiStr::from_option(record.file()),
+            line: record.line().unwrap_or(0),
+        };
+
+        (self.0.log)(&record);
+    }
+
+    fn flush(&self) {
+        (self.0.flush)();
+    }
+}
+
+extern "C" fn host_enabled(level: usize, target: FfiStr) -> bool {
+    catch_unwind(|| {
+        let metadata = Metadata::builder()
+            .level(level_of(level))
+            .target(unsafe { target.as_str() })
+            .build();
+        log::logger().enabled(&metadata)
+    })
+    .unwrap_or(false)
+}
+
+extern "C" fn host_log(record: *const FfiRecord) {
+    if record.is_null() {
+        return;
+    }
+
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let record = unsafe { &*record };
+        let message = unsafe { record.message.as_str() };
+
+        log::logger().log(
+            &Record::builder()
+                .level(level_of(record.level))
+                .target(unsafe { record.target.as_str() })
+                .args(format_args!("{}", message))
+                .module_path(unsafe { record.module_path.as_option() })
+                .file(unsafe { record.file.as_option() })
+                .line((record.line > 0).then_some(record.line))
+                .build(),
+        );
+    }));
+}
+
+extern "C" fn host_flush() {
+    let _ = catch_unwind(|| log::logger().flush());
+}
+
+/// Converts the discriminant of a [`Level`], falling back to `Trace`.
+fn level_of(level: usize) -> Level {
+    match level {
+        1 => Level::Error,
+        2 => Level::Warn,
+        3 => Level::Info,
+        4 => Level::Debug,
+        _ => Level::Trace,
+    }
+}
@@ -0,0 +1,136 @@
+use crate::error::{panic_message, ErrorMessage};
+use crate::{AppInterface, RawApp};
+use iced::Theme;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// The name of the optional exported function describing how the core wants
+/// the window to be presented.
+///
+/// Cores built before it was introduced don't export it, in which case the
+/// shell falls back to its own title, theme and scale factor.
+pub const PRESENTATION_SYMBOL: &str = "hot_reload_presentation";
+
+/// Fills the [`Presentation`] of the core behind the [`RawApp`]. Returns
+/// `false` and fills the [`ErrorMessage`] if the core panicked.
+pub type PresentationFn<M> =
+    unsafe extern "C" fn(*const RawApp<M>, *mut Presentation, *mut ErrorMessage) -> bool;
+
+/// The capacity of the title of a [`Presentation`] in bytes.
+const TITLE_CAPACITY: usize = 256;
+
+/// The window title, theme and scale factor requested by the core, in a
+/// C-compatible form.
+#[repr(C)]
+pub struct Presentation {
+    title_len: usize,
+    title: [u8; TITLE_CAPACITY],
+    has_title: bool,
+
+    /// The index of the theme in `Theme::ALL`, or `-1` for none.
+    theme: i32,
+
+    /// The scale factor, or a non-positive value for none.
+    scale_factor: f64,
+}
+
+impl Presentation {
+    /// Creates a presentation leaving everything to the shell.
+    pub fn new() -> Self {
+        Self {
+            title_len: 0,
+            title: [0; TITLE_CAPACITY],
+            has_title: false,
+            theme: -1,
+            scale_factor: 0.0,
+        }
+    }
+
+    /// The title requested by the core.
+    pub fn title(&self) -> Option<&str> {
+        let len = self.title_len.min(TITLE_CAPACITY);
+        self.has_title
+            .then(|| std::str::from_utf8(&self.title[..len]).unwrap_or_default())
+    }
+
+    /// Requests `title`, truncated on a character boundary.
+    pub fn set_title(&mut self, title: &str) {
+        let mut len = title.len().min(TITLE_CAPACITY);
+        while !title.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.title[..len].copy_from_slice(&title.as_bytes()[..len]);
+        self.title_len = len;
+        self.has_title = true;
+    }
+
+    /// The theme requested by the core.
+    pub fn theme(&self) -> Option<Theme> {
+        usize::try_from(self.theme)
+            .ok()
+            .and_then(|index| Theme::ALL.get(index))
+            .cloned()
+    }
+
+    /// Requests `theme`. Only the built-in themes listed in `Theme::ALL` can
+    /// cross the library boundary, custom themes are ignored.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = Theme::ALL
+            .iter()
+            .position(|candidate| candidate == theme)
+            .map_or(-1, |index| index as i32);
+    }
+
+    /// The scale factor requested by the core.
+    pub fn scale_factor(&self) -> Option<f64> {
+        (self.scale_factor > 0.0).then_some(self.scale_factor)
+    }
+
+    /// Requests `scale_factor`.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+}
+
+impl Default for Presentation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fills `presentation` from the core `T` behind `app`, catching panics.
+///
+/// Called by the function generated with `#[hot_reload_core]`.
+///
+/// # Safety
+///
+/// `app` must point to a non-null handle created from a `T`, and
+/// `presentation` and `error` must be valid for writes.
+pub unsafe fn write_presentation<T: AppInterface<S, M>, S, M>(
+    app: *const RawApp<M>,
+    presentation: *mut Presentation,
+    error: *mut ErrorMessage,
+) -> bool {
+    let app = unsafe { (*app).as_ref::<T>() };
+    let presentation = unsafe { &mut *presentation };
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        if let Some(title) = app.title() {
+            presentation.set_title(&title);
+        }
+        if let Some(theme) = app.theme() {
+            presentation.set_theme(&theme);
+        }
+        if let Some(scale_factor) = app.scale_factor() {
+            presentation.set_scale_factor(scale_factor);
+        }
+    }));
+
+    match result {
+        Ok(()) => true,
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Describes the reload which created the instance, passed to
+/// `AppInterface::on_after_reload`, e.g. to show a "reloaded v3" toast.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReloadInfo {
+    /// How many times the shell swapped in a new instance in this session,
+    /// including this one.
+    pub reloads: u32,
+
+    /// Whether the instance replaces a panicked one, created from the
+    /// previous library by a rollback.
+    pub rolled_back: bool,
+
+    /// When the library of the instance was built, in milliseconds since
+    /// the Unix epoch, 0 if unknown.
+    pub built_at_ms: u64,
+
+    /// When the library of the replaced instance was built, in milliseconds
+    /// since the Unix epoch, 0 if unknown.
+    pub previous_built_at_ms: u64,
+}
+
+impl ReloadInfo {
+    /// Describes the reload number `reloads` from a library built at
+    /// `previous_built_at` to one built at `built_at`.
+    pub fn new(
+        reloads: u32,
+        rolled_back: bool,
+        built_at: Option<SystemTime>,
+        previous_built_at: Option<SystemTime>,
+    ) -> Self {
+        Self {
+            reloads,
+            rolled_back,
+            built_at_ms: to_millis(built_at),
+            previous_built_at_ms: to_millis(previous_built_at),
+        }
+    }
+
+    /// When the library of the instance was built, if known.
+    pub fn built_at(&self) -> Option<SystemTime> {
+        from_millis(self.built_at_ms)
+    }
+
+    /// When the library of the replaced instance was built, if known.
+    pub fn previous_built_at(&self) -> Option<SystemTime> {
+        from_millis(self.previous_built_at_ms)
+    }
+}
+
+fn to_millis(time: Option<SystemTime>) -> u64 {
+    time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_millis() as u64)
+}
+
+fn from_millis(millis: u64) -> Option<SystemTime> {
+    (millis != 0).then(|| UNIX_EPOCH + Duration::from_millis(millis))
+}
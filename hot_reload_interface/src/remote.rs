@@ -0,0 +1,224 @@
+use crate::error::panic_message;
+use crate::state::{deserialize_state, serialize_state};
+use iced::widget::{button, text, Column, Container, Row, Space};
+use iced::{Alignment, Element, Length};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// A serializable description of a view, for cores running in a separate
+/// process.
+///
+/// iced elements can't leave the process that built them, so a core hosted
+/// by the subprocess backend describes its view with these nodes and the
+/// shell builds the actual widgets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ViewNode<M> {
+    /// Lays out `children` vertically.
+    Column {
+        children: Vec<ViewNode<M>>,
+        #[serde(default)]
+        spacing: f32,
+        #[serde(default)]
+        centered: bool,
+    },
+
+    /// Lays out `children` horizontally.
+    Row {
+        children: Vec<ViewNode<M>>,
+        #[serde(default)]
+        spacing: f32,
+        #[serde(default)]
+        centered: bool,
+    },
+
+    /// Shows `content`, with the default text size if `size` is `None`.
+    Text { content: String, size: Option<f32> },
+
+    /// A button showing `content`, sending `on_press` when pressed.
+    Button {
+        content: Box<ViewNode<M>>,
+        on_press: Option<M>,
+    },
+
+    /// Empty space of the given size.
+    Space { width: f32, height: f32 },
+
+    /// Centers `content` in the available space.
+    Center { content: Box<ViewNode<M>> },
+}
+
+impl<M: Clone + 'static> ViewNode<M> {
+    /// Builds the widgets described by the node.
+    pub fn into_element(self) -> Element<'static, M> {
+        match self {
+            ViewNode::Column {
+                children,
+                spacing,
+                centered,
+            } => {
+                let column = Column::with_children(children.into_iter().map(Self::into_element))
+                    .spacing(spacing);
+                if centered {
+                    column.align_x(Alignment::Center).into()
+                } else {
+                    column.into()
+                }
+            }
+            ViewNode::Row {
+                children,
+                spacing,
+                centered,
+            } => {
+                let row = Row::with_children(children.into_iter().map(Self::into_element))
+                    .spacing(spacing);
+                if centered {
+                    row.align_y(Alignment::Center).into()
+                } else {
+                    row.into()
+                }
+            }
+            ViewNode::Text { content, size } => match size {
+                Some(size) => text(content).size(size).into(),
+                None => text(content).into(),
+            },
+            ViewNode::Button { content, on_press } => button(content.into_element())
+                .on_press_maybe(on_press)
+                .into(),
+            ViewNode::Space { width, height } => Space::new(width, height).into(),
+            ViewNode::Center { content } => Container::new(content.into_element())
+                .center(Length::Fill)
+                .into(),
+        }
+    }
+}
+
+/// A request sent by the shell to a core running in a separate process.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request<M> {
+    /// Creates the core from the serialized state. Always sent first.
+    Init(Vec<u8>),
+
+    /// Forwards a message to the core.
+    Update(M),
+
+    /// Asks for the current view.
+    View,
+
+    /// Asks for the serialized state.
+    SaveState,
+
+    /// Asks the process to exit.
+    Shutdown,
+}
+
+/// The answer of a core running in a separate process to a [`Request`].
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response<M> {
+    /// The request was handled.
+    Done,
+
+    /// The current view.
+    View(ViewNode<M>),
+
+    /// The serialized state.
+    State(Vec<u8>),
+
+    /// The core panicked or the request couldn't be handled.
+    Error(String),
+}
+
+/// The contract of a core hosted in a separate process.
+///
+/// It mirrors [`crate::AppInterface`], except that the view is described
+/// with [`ViewNode`]s.
+pub trait RemoteApp<S, M> {
+    fn update(&mut self, message: M);
+    fn view(&self) -> ViewNode<M>;
+    fn state(&self) -> &S;
+}
+
+/// Runs the core `T` in the current process, answering the requests the
+/// shell writes to stdin on stdout, one JSON document per line.
+///
+/// Call it from the `main` of the binary hosting the core. Nothing else may
+/// be written to stdout, so log to stderr instead.
+pub fn serve<T, S, M>()
+where
+    T: RemoteApp<S, M> + From<S>,
+    S: Serialize + DeserializeOwned + Default,
+    M: Serialize + DeserializeOwned,
+{
+    let stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    let mut app: Option<T> = None;
+
+    for line in stdin.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let response = match serde_json::from_str::<Request<M>>(&line) {
+            Ok(Request::Shutdown) => break,
+            Ok(request) => handle(&mut app, request),
+            Err(e) => Response::Error(format!("Invalid request: {}", e)),
+        };
+
+        let written = serde_json::to_writer(&mut stdout, &response)
+            .map_err(std::io::Error::from)
+            .and_then(|()| stdout.write_all(b"\n"))
+            .and_then(|()| stdout.flush());
+        if written.is_err() {
+            break;
+        }
+    }
+}
+
+/// Handles a JSON encoded [`Request`] for the core in `app` and returns the
+/// JSON encoded [`Response`].
+///
+/// Used by the functions exported with [`crate::export_wasm_core`].
+pub fn handle_request<T, S, M>(app: &mut Option<T>, request: &[u8]) -> Vec<u8>
+where
+    T: RemoteApp<S, M> + From<S>,
+    S: Serialize + DeserializeOwned + Default,
+    M: Serialize + DeserializeOwned,
+{
+    let response = match serde_json::from_slice::<Request<M>>(request) {
+        Ok(request) => handle(app, request),
+        Err(e) => Response::Error(format!("Invalid request: {}", e)),
+    };
+
+    serde_json::to_vec(&response).unwrap_or_else(|e| {
+        serde_json::to_vec(&Response::<M>::Error(format!("Invalid response: {}", e)))
+            .unwrap_or_default()
+    })
+}
+
+fn handle<T, S, M>(app: &mut Option<T>, request: Request<M>) -> Response<M>
+where
+    T: RemoteApp<S, M> + From<S>,
+    S: Serialize + DeserializeOwned + Default,
+{
+    let result = catch_unwind(AssertUnwindSafe(|| match (request, app.as_mut()) {
+        (Request::Init(state), _) => {
+            let state: S = deserialize_state(&state).unwrap_or_default();
+            *app = Some(T::from(state));
+            Response::Done
+        }
+        (Request::Update(message), Some(app)) => {
+            app.update(message);
+            Response::Done
+        }
+        (Request::View, Some(app)) => Response::View(app.view()),
+        (Request::SaveState, Some(app)) => match serialize_state(app.state()) {
+            Ok(state) => Response::State(state),
+            Err(e) => Response::Error(e),
+        },
+        (Request::Shutdown, _) => Response::Done,
+        (_, None) => Response::Error("The core was not initialized".to_string()),
+    }));
+
+    result.unwrap_or_else(|payload| Response::Error(panic_message(&*payload).to_string()))
+}
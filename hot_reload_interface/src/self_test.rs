@@ -0,0 +1,30 @@
+use crate::error::{panic_message, ErrorMessage};
+use std::panic::catch_unwind;
+
+/// The name of the optional exported function checking a rebuilt core
+/// before the shell swaps it in, see `hot_reload_core(self_test = ...)`.
+pub const SELF_TEST_SYMBOL: &str = "hot_reload_self_test";
+
+/// Runs the self test of the core. Returns `false` if it failed, with the
+/// reason written to the [`ErrorMessage`].
+pub type SelfTestFn = unsafe extern "C" fn(*mut ErrorMessage) -> bool;
+
+/// Runs `test`, the self test of the core, for the exported [`SelfTestFn`].
+///
+/// A panicking test counts as failed.
+///
+/// # Safety
+///
+/// `error` has to be null or point to a valid [`ErrorMessage`].
+pub unsafe fn run_self_test(error: *mut ErrorMessage, test: fn() -> Result<(), String>) -> bool {
+    let message = match catch_unwind(test) {
+        Ok(Ok(())) => return true,
+        Ok(Err(e)) => e,
+        Err(payload) => format!("The self test panicked: {}", panic_message(&*payload)),
+    };
+
+    if let Some(error) = unsafe { error.as_mut() } {
+        error.set(&message);
+    }
+    false
+}
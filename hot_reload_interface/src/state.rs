@@ -0,0 +1,115 @@
+use crate::error::{panic_message, ErrorMessage};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ffi::c_void;
+use std::panic::catch_unwind;
+
+/// The name of the exported function returning the state schema version of a core.
+pub const STATE_VERSION_SYMBOL: &str = "hot_reload_state_version";
+
+/// The name of the optional exported function migrating the state of an
+/// older schema version.
+pub const MIGRATE_SYMBOL: &str = "migrate_state";
+
+/// Returns the version of the state schema, as declared with
+/// `#[hot_reload_core(state_version = ...)]`.
+pub type StateVersionFn = unsafe extern "C" fn() -> u32;
+
+/// Migrates the serialized state of the given older schema version into the
+/// [`StateWriter`]. Returns `false` and fills the [`ErrorMessage`] on failure.
+pub type MigrateFn =
+    unsafe extern "C" fn(u32, *const u8, usize, *mut StateWriter, *mut ErrorMessage) -> bool;
+
+/// Receives the serialized state from the core.
+///
+/// The core hands its serialized state to `write`, which copies the bytes
+/// into memory owned by the shell. This way no allocation ever crosses the
+/// library boundary.
+#[repr(C)]
+pub struct StateWriter {
+    /// The shell's buffer, passed back to `write`.
+    pub context: *mut c_void,
+
+    /// Appends `len` bytes starting at the pointer to the buffer in `context`.
+    pub write: unsafe extern "C" fn(*mut c_void, *const u8, usize),
+}
+
+impl StateWriter {
+    /// Creates a writer appending to `buffer`.
+    pub fn new(buffer: &mut Vec<u8>) -> Self {
+        Self {
+            context: (buffer as *mut Vec<u8>).cast(),
+            write: write_to_vec,
+        }
+    }
+}
+
+unsafe extern "C" fn write_to_vec(context: *mut c_void, bytes: *const u8, len: usize) {
+    let buffer = unsafe { &mut *context.cast::<Vec<u8>>() };
+    buffer.extend_from_slice(unsafe { std::slice::from_raw_parts(bytes, len) });
+}
+
+/// Serializes the state transferred to the next core instance.
+pub fn serialize_state<S: Serialize>(state: &S) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(state).map_err(|e| format!("Failed to serialize the state: {}", e))
+}
+
+/// Deserializes the state transferred from the previous core instance.
+///
+/// Empty `bytes` mean there is no previous state and yield `S::default()`.
+/// Fields missing from `bytes` are filled in by serde, so annotate the state
+/// with `#[serde(default)]` to let it gain fields between reloads.
+pub fn deserialize_state<S: DeserializeOwned + Default>(bytes: &[u8]) -> Result<S, String> {
+    if bytes.is_empty() {
+        return Ok(S::default());
+    }
+
+    serde_json::from_slice(bytes).map_err(|e| format!("Failed to deserialize the state: {}", e))
+}
+
+/// Runs the migration `migrate` on the serialized `state` of a core whose
+/// state schema had version `old_version`, writing the result to `writer`.
+///
+/// This is the body of the `migrate_state` function generated by
+/// `#[hot_reload_core(migrate = ...)]`.
+///
+/// # Safety
+///
+/// `state` must point to `len` readable bytes, `writer` and `error` must be valid.
+pub unsafe fn run_migration(
+    old_version: u32,
+    state: *const u8,
+    len: usize,
+    writer: *mut StateWriter,
+    error: *mut ErrorMessage,
+    migrate: fn(u32, serde_json::Value) -> Result<serde_json::Value, String>,
+) -> bool {
+    let bytes: &[u8] = if state.is_null() || len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(state, len) }
+    };
+
+    let result = catch_unwind(|| {
+        let value = serde_json::from_slice(bytes)
+            .map_err(|e| format!("Failed to parse the state: {}", e))?;
+        let migrated = migrate(old_version, value)?;
+        serde_json::to_vec(&migrated).map_err(|e| format!("Failed to serialize the state: {}", e))
+    });
+
+    match result {
+        Ok(Ok(bytes)) => {
+            let writer = unsafe { &*writer };
+            unsafe { (writer.write)(writer.context, bytes.as_ptr(), bytes.len()) };
+            true
+        }
+        Ok(Err(message)) => {
+            unsafe { (*error).set(&message) };
+            false
+        }
+        Err(payload) => {
+            unsafe { (*error).set(panic_message(&*payload)) };
+            false
+        }
+    }
+}
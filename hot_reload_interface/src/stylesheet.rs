@@ -0,0 +1,223 @@
+use crate::state::StateWriter;
+use iced::theme::Palette;
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Mutex, OnceLock, PoisonError, RwLock};
+
+/// The name of the optional exported function handing the shell's
+/// stylesheet to the core.
+///
+/// The shell calls it right after loading the library, like
+/// [`crate::SET_LOGGER_SYMBOL`]. Cores built before it was introduced see
+/// the default stylesheet.
+pub const SET_STYLESHEET_SYMBOL: &str = "hot_reload_set_stylesheet";
+
+/// Makes the core read the stylesheet through the [`HostStylesheet`] of the
+/// shell.
+pub type SetStylesheetFn = unsafe extern "C" fn(*const HostStylesheet);
+
+/// Widget styling loaded by the shell from a watched file, so it can be
+/// changed without recompiling the core.
+///
+/// The core reads it with [`stylesheet`] while building its view, e.g.
+/// `container(content).padding(stylesheet().padding)`. Every field is
+/// optional in the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stylesheet {
+    /// The colors of the theme.
+    pub palette: StylePalette,
+
+    /// The radius of rounded borders.
+    pub border_radius: f32,
+
+    /// The padding of containers and buttons.
+    pub padding: f32,
+
+    /// The spacing between the children of rows and columns.
+    pub spacing: f32,
+
+    /// The default text size.
+    pub text_size: f32,
+}
+
+impl Default for Stylesheet {
+    fn default() -> Self {
+        Self {
+            palette: StylePalette::default(),
+            border_radius: 2.0,
+            padding: 5.0,
+            spacing: 10.0,
+            text_size: 16.0,
+        }
+    }
+}
+
+impl Stylesheet {
+    /// The theme with the colors of the palette, or `None` if the palette
+    /// sets none of them.
+    ///
+    /// The colors missing from the palette are taken from the dark theme.
+    pub fn theme(&self) -> Option<Theme> {
+        if self.palette == StylePalette::default() {
+            return None;
+        }
+
+        let base = Theme::Dark.palette();
+        let color = |hex: &Option<String>, default: Color| {
+            hex.as_deref().and_then(parse_color).unwrap_or(default)
+        };
+
+        Some(Theme::custom(
+            "Stylesheet".to_string(),
+            Palette {
+                background: color(&self.palette.background, base.background),
+                text: color(&self.palette.text, base.text),
+                primary: color(&self.palette.primary, base.primary),
+                success: color(&self.palette.success, base.success),
+                danger: color(&self.palette.danger, base.danger),
+            },
+        ))
+    }
+}
+
+/// The colors of a [`Stylesheet`] as hex strings, e.g. `"#3b82f6"`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StylePalette {
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub primary: Option<String>,
+    pub success: Option<String>,
+    pub danger: Option<String>,
+}
+
+/// Parses a color written as `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+    Some(Color::from_rgba8(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha as f32 / 255.0,
+    ))
+}
+
+/// The stylesheet of the calling library with the number of times it was
+/// set. Only the shell's copy is ever set.
+static HOST: RwLock<(u64, Option<Stylesheet>)> = RwLock::new((0, None));
+
+/// Sets the stylesheet the cores read through their [`HostStylesheet`].
+///
+/// Called by the shell whenever the stylesheet file changes.
+pub fn set_host_stylesheet(stylesheet: Stylesheet) {
+    let mut host = HOST.write().unwrap_or_else(PoisonError::into_inner);
+    host.0 += 1;
+    host.1 = Some(stylesheet);
+}
+
+/// The stylesheet of the shell as seen by the core: function pointers into
+/// the shell.
+///
+/// The core asks the shell whether the stylesheet changed every time it
+/// reads it, so a saved stylesheet file applies on the next view.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HostStylesheet {
+    /// How many times the stylesheet was set.
+    generation: extern "C" fn() -> u64,
+
+    /// Writes the stylesheet as JSON into the writer.
+    read: extern "C" fn(*mut StateWriter),
+}
+
+impl HostStylesheet {
+    /// Creates the host stylesheet of the calling library, i.e. of the shell.
+    pub fn new() -> Self {
+        Self {
+            generation: host_generation,
+            read: host_read,
+        }
+    }
+}
+
+impl Default for HostStylesheet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The host stylesheet installed in the calling library, i.e. in the core.
+static INSTALLED: OnceLock<HostStylesheet> = OnceLock::new();
+
+/// The last stylesheet read through the installed host stylesheet, with its
+/// generation.
+static CACHE: Mutex<Option<(u64, Stylesheet)>> = Mutex::new(None);
+
+/// Installs `host` as the stylesheet of the calling library, i.e. of the
+/// core.
+///
+/// # Safety
+///
+/// `host` has to be null or point to a valid [`HostStylesheet`]. The shell
+/// it belongs to has to outlive the core.
+pub unsafe fn install_host_stylesheet(host: *const HostStylesheet) {
+    if host.is_null() {
+        return;
+    }
+
+    let _ = INSTALLED.set(unsafe { *host });
+}
+
+/// The stylesheet loaded by the shell, or the default one if the shell has
+/// none.
+///
+/// Cores linked into the shell, e.g. with `run_static`, share the shell's
+/// copy of this crate and read its stylesheet directly.
+pub fn stylesheet() -> Stylesheet {
+    let Some(host) = INSTALLED.get() else {
+        let host = HOST.read().unwrap_or_else(PoisonError::into_inner);
+        return host.1.clone().unwrap_or_default();
+    };
+
+    let generation = (host.generation)();
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    match cache.as_ref() {
+        Some((cached, stylesheet)) if *cached == generation => stylesheet.clone(),
+        _ => {
+            let mut json = Vec::new();
+            (host.read)(&mut StateWriter::new(&mut json));
+            let stylesheet = serde_json::from_slice(&json).unwrap_or_else(|e| {
+                log::error!("Failed to read the stylesheet of the shell: {}", e);
+                Stylesheet::default()
+            });
+            *cache = Some((generation, stylesheet.clone()));
+            stylesheet
+        }
+    }
+}
+
+extern "C" fn host_generation() -> u64 {
+    HOST.read().unwrap_or_else(PoisonError::into_inner).0
+}
+
+extern "C" fn host_read(writer: *mut StateWriter) {
+    if writer.is_null() {
+        return;
+    }
+
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        let host = HOST.read().unwrap_or_else(PoisonError::into_inner);
+        let json = serde_json::to_vec(&host.1.clone().unwrap_or_default()).unwrap_or_default();
+        let writer = unsafe { &*writer };
+        unsafe { (writer.write)(writer.context, json.as_ptr(), json.len()) };
+    }));
+}
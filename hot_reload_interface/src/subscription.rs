@@ -0,0 +1,120 @@
+use std::ffi::c_void;
+use std::time::Duration;
+
+/// The capacity of a [`KeyName`] in bytes.
+const KEY_NAME_CAPACITY: usize = 32;
+
+/// The name of a key, e.g. `"a"`, `"Enter"` or `"ArrowUp"`, in a fixed-size,
+/// C-compatible buffer.
+///
+/// Characters are named by themselves, named keys by their iced
+/// `keyboard::key::Named` variant. The buffer also holds the names of the
+/// windows of the core, see [`crate::WindowName`].
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct KeyName {
+    len: u8,
+    bytes: [u8; KEY_NAME_CAPACITY],
+}
+
+impl KeyName {
+    /// Creates the key name `name`, truncated on a character boundary.
+    pub fn new(name: &str) -> Self {
+        let mut len = name.len().min(KEY_NAME_CAPACITY);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut bytes = [0; KEY_NAME_CAPACITY];
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+
+        Self {
+            len: len as u8,
+            bytes,
+        }
+    }
+
+    /// The name as a string.
+    pub fn as_str(&self) -> &str {
+        let len = (self.len as usize).min(KEY_NAME_CAPACITY);
+        std::str::from_utf8(&self.bytes[..len]).unwrap_or("")
+    }
+}
+
+impl std::fmt::Debug for KeyName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// A subscription declared by the core.
+///
+/// iced subscriptions can't cross the library boundary, so the core only
+/// describes them and the shell sets them up. The shell asks the core for
+/// its subscriptions after every update and after every reload, so they are
+/// re-established with the new core automatically.
+#[repr(C, u8)]
+#[derive(Debug, Clone)]
+pub enum CoreSubscription<M> {
+    /// Sends `message` every `interval_ms` milliseconds.
+    Every { interval_ms: u64, message: M },
+
+    /// Sends `message` whenever the key named `key` is pressed.
+    KeyPress { key: KeyName, message: M },
+
+    /// Sends `message` whenever files in the assets directory watched by the
+    /// shell change, see `HotReloadBuilder::watch_assets`.
+    AssetsChanged { message: M },
+}
+
+impl<M> CoreSubscription<M> {
+    /// Sends `message` every `interval`.
+    pub fn every(interval: Duration, message: M) -> Self {
+        Self::Every {
+            interval_ms: interval.as_millis() as u64,
+            message,
+        }
+    }
+
+    /// Sends `message` whenever the key named `key` is pressed, see [`KeyName`].
+    pub fn key_press(key: &str, message: M) -> Self {
+        Self::KeyPress {
+            key: KeyName::new(key),
+            message,
+        }
+    }
+
+    /// Sends `message` whenever the assets of the application change, e.g.
+    /// to drop the images or data the core read from them.
+    pub fn assets_changed(message: M) -> Self {
+        Self::AssetsChanged { message }
+    }
+}
+
+/// Receives the subscriptions declared by the core.
+///
+/// The core moves every [`CoreSubscription`] to `push`, which stores it in
+/// memory owned by the shell, like [`crate::StateWriter`] does for the state.
+#[repr(C)]
+pub struct SubscriptionWriter<M> {
+    /// The shell's list, passed back to `push`.
+    pub context: *mut c_void,
+
+    /// Moves the subscription behind the pointer to the list in `context`.
+    pub push: unsafe extern "C" fn(*mut c_void, *mut CoreSubscription<M>),
+}
+
+impl<M> SubscriptionWriter<M> {
+    /// Creates a writer appending to `subscriptions`.
+    pub fn new(subscriptions: &mut Vec<CoreSubscription<M>>) -> Self {
+        Self {
+            context: (subscriptions as *mut Vec<CoreSubscription<M>>).cast(),
+            push: push_to_vec::<M>,
+        }
+    }
+}
+
+unsafe extern "C" fn push_to_vec<M>(context: *mut c_void, subscription: *mut CoreSubscription<M>) {
+    let subscriptions = unsafe { &mut *context.cast::<Vec<CoreSubscription<M>>>() };
+    subscriptions.push(unsafe { subscription.read() });
+}
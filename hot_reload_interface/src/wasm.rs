@@ -0,0 +1,60 @@
+/// Exports the functions the wasm backend of the shell drives a core with.
+///
+/// Build the crate as a `cdylib` for `wasm32-unknown-unknown` and invoke
+/// the macro with the types of the core, which must implement
+/// [`crate::RemoteApp`]. The shell exchanges the same JSON encoded requests
+/// and responses as with the subprocess backend, through the memory of the
+/// module:
+///
+/// * `hot_reload_alloc(len) -> ptr` reserves a buffer for a request.
+/// * `hot_reload_handle(ptr, len) -> u64` handles the request in the buffer,
+///   frees it and returns the response buffer as `ptr << 32 | len`.
+/// * `hot_reload_dealloc(ptr, len)` frees a response buffer.
+///
+/// ```ignore
+/// #[cfg(target_arch = "wasm32")]
+/// hot_reload_interface::export_wasm_core!(CoreApp, AppState, Message);
+/// ```
+#[macro_export]
+macro_rules! export_wasm_core {
+    ($core:ty, $state:ty, $message:ty) => {
+        ::std::thread_local! {
+            static HOT_RELOAD_CORE: ::std::cell::RefCell<::std::option::Option<$core>> =
+                const { ::std::cell::RefCell::new(::std::option::Option::None) };
+        }
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn hot_reload_alloc(len: u32) -> u32 {
+            let buffer = ::std::vec![0u8; len as usize].into_boxed_slice();
+            ::std::boxed::Box::into_raw(buffer) as *mut u8 as u32
+        }
+
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_dealloc(ptr: u32, len: u32) {
+            drop(unsafe {
+                ::std::boxed::Box::from_raw(::std::ptr::slice_from_raw_parts_mut(
+                    ptr as *mut u8,
+                    len as usize,
+                ))
+            });
+        }
+
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_handle(ptr: u32, len: u32) -> u64 {
+            let request = unsafe {
+                ::std::boxed::Box::from_raw(::std::ptr::slice_from_raw_parts_mut(
+                    ptr as *mut u8,
+                    len as usize,
+                ))
+            };
+
+            let response = HOT_RELOAD_CORE.with_borrow_mut(|app| {
+                $crate::handle_request::<$core, $state, $message>(app, &request)
+            });
+
+            let len = response.len() as u64;
+            let ptr = ::std::boxed::Box::into_raw(response.into_boxed_slice()) as *mut u8 as u64;
+            (ptr << 32) | len
+        }
+    };
+}
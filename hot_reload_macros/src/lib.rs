@@ -0,0 +1,388 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, GenericArgument, Ident, ImplItem, ItemImpl, LitBool, LitInt, LitStr, Path,
+    PathArguments, Type,
+};
+
+/// Generates the FFI entry points of a hot-reloadable core.
+///
+/// Placed on the `AppInterface<S, M>` implementation of the core, it emits
+/// the functions loaded by the shell:
+///
+/// * `create_app` and `destroy_app`, unless renamed with the arguments
+///   below - create and destroy the core, exchanged as a `RawApp`.
+/// * `hot_reload_abi_version` - describes how the core was built, checked by
+///   the shell before creating the core.
+/// * `hot_reload_presentation` - forwards the title, theme and scale factor
+///   of the core.
+/// * `hot_reload_capabilities` - declares the optional parts of the contract
+///   the impl provides, so the shell doesn't call into the others.
+/// * `hot_reload_state_version` - the version of the state schema, see
+///   `state_version` below.
+/// * `hot_reload_set_logger` - makes the `log` records of the core go
+///   through the logger of the shell.
+/// * `hot_reload_set_stylesheet` - makes `stylesheet()` read the stylesheet
+///   file watched by the shell.
+/// * `hot_reload_set_catalog` - makes `tr()` translate with the catalogs
+///   watched by the shell.
+/// * `hot_reload_set_allocator` - together with the `SharedAllocator` it
+///   declares as the global allocator, makes the core allocate through the
+///   shell, so memory can be freed on either side.
+/// * `migrate_state` and `hot_reload_self_test` - only with the `migrate`
+///   and `self_test` arguments below.
+///
+/// The state is transferred serialized, so the generated functions require
+/// `S: Serialize` to save it, `S: DeserializeOwned` to restore it and
+/// `S: Default` for the state of a core started without one, the bounds of
+/// `serialize_state` and `deserialize_state`. The core is constructed from it
+/// with `From<S>`, and no other bound applies to `S`. Panics are caught before they can unwind across the FFI
+/// boundary. A panicking constructor or a state which can't be deserialized
+/// makes `create_app` return null and write why to the error buffer of the
+/// shell, which reports it as a failed load.
+///
+/// Optional arguments:
+///
+/// * `create = "name"` - the exported name of the creation function.
+/// * `destroy = "name"` - the exported name of the destruction function.
+/// * `init = path` - a function called before every construction, e.g. to
+///   set up resources of the core. Logging needs no setup.
+/// * `state_version = 1` - the version of the state schema, `0` by default.
+///   Bump it when the state changes in a way serde defaults can't absorb.
+/// * `migrate = path` - a function
+///   `fn(old_version: u32, state: serde_json::Value) -> Result<serde_json::Value, String>`
+///   which the shell calls when a core with a different state version was
+///   loaded before, e.g. to rename fields or convert units.
+/// * `self_test = path` - a function `fn() -> Result<(), String>` which the
+///   shell calls after loading a rebuilt core and before swapping it in,
+///   e.g. to check embedded assets parse. A failing test keeps the running
+///   core.
+/// * `shared_allocator = false` - doesn't declare the global allocator, for
+///   cores declaring their own. They then have to forward to the shell's
+///   themselves, e.g. by wrapping `SharedAllocator`.
+///
+/// ```ignore
+/// #[hot_reload_core(state_version = 1, migrate = migrate)]
+/// impl AppInterface<AppState, Message> for CoreApp {
+///     // ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn hot_reload_core(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut create_name = "create_app".to_string();
+    let mut destroy_name = "destroy_app".to_string();
+    let mut init: Option<Path> = None;
+    let mut state_version: u32 = 0;
+    let mut migrate: Option<Path> = None;
+    let mut self_test: Option<Path> = None;
+    let mut shared_allocator = true;
+
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("create") {
+            create_name = meta.value()?.parse::<LitStr>()?.value();
+            Ok(())
+        } else if meta.path.is_ident("destroy") {
+            destroy_name = meta.value()?.parse::<LitStr>()?.value();
+            Ok(())
+        } else if meta.path.is_ident("init") {
+            init = Some(meta.value()?.parse::<Path>()?);
+            Ok(())
+        } else if meta.path.is_ident("state_version") {
+            state_version = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            Ok(())
+        } else if meta.path.is_ident("migrate") {
+            migrate = Some(meta.value()?.parse::<Path>()?);
+            Ok(())
+        } else if meta.path.is_ident("self_test") {
+            self_test = Some(meta.value()?.parse::<Path>()?);
+            Ok(())
+        } else if meta.path.is_ident("shared_allocator") {
+            shared_allocator = meta.value()?.parse::<LitBool>()?.value();
+            Ok(())
+        } else {
+            Err(meta.error("unsupported hot_reload_core property"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+
+    let item_impl = parse_macro_input!(item as ItemImpl);
+
+    let (state, message) = match interface_types(&item_impl) {
+        Ok(types) => types,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let core = &item_impl.self_ty;
+    let create_fn = format_ident!("{}", create_name);
+    let destroy_fn = format_ident!("{}", destroy_name);
+    let init_call = init.map(|init| quote! { #init(); });
+    let capabilities = capabilities(&item_impl, migrate.is_some());
+    let migrate_fn = migrate.map(|migrate| {
+        quote! {
+            /// Migrates the state of an older schema version with the core's migration.
+            ///
+            /// # Safety
+            ///
+            /// `state` has to point to `len` readable bytes, `writer` and `error` have
+            /// to be valid.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn migrate_state(
+                old_version: u32,
+                state: *const u8,
+                len: usize,
+                writer: *mut ::hot_reload_interface::StateWriter,
+                error: *mut ::hot_reload_interface::ErrorMessage,
+            ) -> bool {
+                unsafe {
+                    ::hot_reload_interface::run_migration(
+                        old_version, state, len, writer, error, #migrate,
+                    )
+                }
+            }
+        }
+    });
+
+    let self_test_fn = self_test.map(|self_test| {
+        quote! {
+            /// Runs the self test of the core before the shell swaps it in.
+            ///
+            /// # Safety
+            ///
+            /// `error` has to be null or point to a valid `ErrorMessage`.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn hot_reload_self_test(
+                error: *mut ::hot_reload_interface::ErrorMessage,
+            ) -> bool {
+                unsafe { ::hot_reload_interface::run_self_test(error, #self_test) }
+            }
+        }
+    });
+
+    let global_allocator = shared_allocator.then(|| {
+        quote! {
+            /// Allocates through the shell once it installed its allocator.
+            #[global_allocator]
+            static HOT_RELOAD_ALLOCATOR: ::hot_reload_interface::SharedAllocator =
+                ::hot_reload_interface::SharedAllocator;
+        }
+    });
+
+    quote! {
+        #item_impl
+
+        #global_allocator
+
+        /// Makes the core allocate through the allocator of the shell.
+        ///
+        /// # Safety
+        ///
+        /// `host` has to be null or point to a valid `HostAllocator`, owned by a
+        /// shell outliving the core.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_set_allocator(
+            host: *const ::hot_reload_interface::HostAllocator,
+        ) {
+            unsafe { ::hot_reload_interface::install_host_allocator(host) }
+        }
+
+        /// Creates the core instance with the state transferred by the shell.
+        ///
+        /// # Safety
+        ///
+        /// `state` has to be null or point to `len` readable bytes, `error` has
+        /// to be null or point to a valid `ErrorMessage`.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn #create_fn(
+            state: *const u8,
+            len: usize,
+            error: *mut ::hot_reload_interface::ErrorMessage,
+        ) -> ::hot_reload_interface::RawApp<#message> {
+            let bytes: &[u8] = if state.is_null() || len == 0 {
+                &[]
+            } else {
+                unsafe { ::std::slice::from_raw_parts(state, len) }
+            };
+
+            let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(move || {
+                #init_call
+                // A state which can't be deserialized anymore fails the
+                // creation, so the shell keeps the running core and its state.
+                let state: #state = ::hot_reload_interface::deserialize_state(bytes)?;
+                ::std::result::Result::Ok(::hot_reload_interface::RawApp::new(
+                    <#core as ::std::convert::From<#state>>::from(state),
+                ))
+            }));
+
+            let message = match result {
+                ::std::result::Result::Ok(::std::result::Result::Ok(app)) => return app,
+                ::std::result::Result::Ok(::std::result::Result::Err(e)) => e,
+                ::std::result::Result::Err(payload) => {
+                    ::hot_reload_interface::panic_message(&*payload).to_string()
+                }
+            };
+            if let Some(error) = unsafe { error.as_mut() } {
+                error.set(&message);
+            }
+            ::hot_reload_interface::RawApp::null()
+        }
+
+        /// Describes how the core was built, checked by the shell before loading it.
+        #[unsafe(no_mangle)]
+        pub extern "C" fn hot_reload_abi_version() -> ::hot_reload_interface::AbiInfo {
+            ::hot_reload_interface::AbiInfo::current::<#message>()
+        }
+
+        /// Describes the window title, theme and scale factor requested by the core.
+        ///
+        /// # Safety
+        ///
+        /// `app` has to point to a non-null handle created by this core, and
+        /// `presentation` and `error` have to be valid for writes.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_presentation(
+            app: *const ::hot_reload_interface::RawApp<#message>,
+            presentation: *mut ::hot_reload_interface::Presentation,
+            error: *mut ::hot_reload_interface::ErrorMessage,
+        ) -> bool {
+            unsafe {
+                ::hot_reload_interface::write_presentation::<#core, #state, #message>(
+                    app, presentation, error,
+                )
+            }
+        }
+
+        /// Declares the optional parts of the contract the core implements.
+        #[unsafe(no_mangle)]
+        pub extern "C" fn hot_reload_capabilities() -> ::hot_reload_interface::Capabilities {
+            ::hot_reload_interface::Capabilities::NONE
+                #(.union(::hot_reload_interface::Capabilities::#capabilities))*
+        }
+
+        /// Makes the core log through the logger of the shell.
+        ///
+        /// # Safety
+        ///
+        /// `logger` has to be null or point to a valid `HostLogger`, owned by a
+        /// shell outliving the core.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_set_logger(logger: *const ::hot_reload_interface::HostLogger) {
+            unsafe { ::hot_reload_interface::install_host_logger(logger) }
+        }
+
+        /// Makes the core read the stylesheet of the shell.
+        ///
+        /// # Safety
+        ///
+        /// `host` has to be null or point to a valid `HostStylesheet`, owned by a
+        /// shell outliving the core.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_set_stylesheet(
+            host: *const ::hot_reload_interface::HostStylesheet,
+        ) {
+            unsafe { ::hot_reload_interface::install_host_stylesheet(host) }
+        }
+
+        /// Makes the core translate its strings with the catalog of the shell.
+        ///
+        /// # Safety
+        ///
+        /// `host` has to be null or point to a valid `HostCatalog`, owned by a
+        /// shell outliving the core.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn hot_reload_set_catalog(
+            host: *const ::hot_reload_interface::HostCatalog,
+        ) {
+            unsafe { ::hot_reload_interface::install_host_catalog(host) }
+        }
+
+        /// Returns the version of the state schema of the core.
+        #[unsafe(no_mangle)]
+        pub extern "C" fn hot_reload_state_version() -> u32 {
+            #state_version
+        }
+
+        #migrate_fn
+
+        #self_test_fn
+
+        /// Destroys the core instance created by the creation function
+        /// through the drop entry of its vtable.
+        ///
+        /// # Safety
+        ///
+        /// `app` has to be null or created by the creation function of this
+        /// core, and must not be used afterwards.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn #destroy_fn(app: ::hot_reload_interface::RawApp<#message>) {
+            unsafe { app.destroy() }
+        }
+    }
+    .into()
+}
+
+/// The names of the `Capabilities` of the core, derived from the optional
+/// methods of `AppInterface` the impl overrides.
+fn capabilities(item_impl: &ItemImpl, migrates: bool) -> Vec<Ident> {
+    let overrides = |names: &[&str]| {
+        item_impl.items.iter().any(|item| match item {
+            ImplItem::Fn(method) => names.iter().any(|name| method.sig.ident == name),
+            _ => false,
+        })
+    };
+
+    let mut capabilities = vec![format_ident!("SERIALIZED_STATE")];
+    if overrides(&["title", "theme", "scale_factor"]) {
+        capabilities.push(format_ident!("THEME"));
+    }
+    if overrides(&["subscriptions"]) {
+        capabilities.push(format_ident!("SUBSCRIPTIONS"));
+    }
+    if migrates {
+        capabilities.push(format_ident!("MIGRATIONS"));
+    }
+    if overrides(&["window_view"]) {
+        capabilities.push(format_ident!("WINDOWS"));
+    }
+    if overrides(&["command_result"]) {
+        capabilities.push(format_ident!("COMMAND_RESULTS"));
+    }
+    if overrides(&["on_event"]) {
+        capabilities.push(format_ident!("EVENTS"));
+    }
+    if overrides(&["on_before_unload", "on_after_reload"]) {
+        capabilities.push(format_ident!("LIFECYCLE"));
+    }
+
+    capabilities
+}
+
+/// Extracts `S` and `M` from `impl AppInterface<S, M> for ...`.
+fn interface_types(item_impl: &ItemImpl) -> syn::Result<(Type, Type)> {
+    let Some((_, trait_path, _)) = &item_impl.trait_ else {
+        return Err(syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "hot_reload_core must be placed on an `impl AppInterface<S, M> for ...` block",
+        ));
+    };
+
+    let segment = trait_path.segments.last().expect("trait path is not empty");
+    let types: Vec<Type> = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    match <[Type; 2]>::try_from(types) {
+        Ok([state, message]) => Ok((state, message)),
+        Err(_) => Err(syn::Error::new_spanned(
+            trait_path,
+            "expected `AppInterface<S, M>` with the state and message types",
+        )),
+    }
+}
@@ -0,0 +1,273 @@
+//! Integration tests for hot-reloadable cores.
+//!
+//! [`TestCore`] loads the built library of a core the way the shell does,
+//! without opening a window, and drives it with scripted messages. Every
+//! step panics with a readable message when the core fails, so tests read
+//! as a script of what the user does and what the state should be:
+//!
+//! ```ignore
+//! #[test]
+//! fn counts_clicks() {
+//!     let mut core = TestCore::<Message>::load("app_core");
+//!     core.send_all([Message::Increment, Message::Increment])
+//!         .reload()
+//!         .render();
+//!     assert_eq!(core.state::<AppState>().counter, 2);
+//! }
+//! ```
+//!
+//! The library has to be built before the tests run, e.g. with
+//! `cargo build -p app_core && cargo test`, since the tests load the
+//! artifact the shell would load rather than linking the core.
+//!
+//! The same steps can be written down as a [`Scenario`] file and executed
+//! headlessly or against a running shell through its control server.
+//!
+//! With the `proptest` feature, [`StateRoundTrip`] checks that random states
+//! survive reloads.
+
+#[cfg(feature = "proptest")]
+mod round_trip;
+mod scenario;
+
+#[cfg(feature = "proptest")]
+pub use proptest;
+#[cfg(feature = "proptest")]
+pub use round_trip::StateRoundTrip;
+pub use scenario::{Scenario, Step};
+
+use hot_reload_interface::{deserialize_state, serialize_state, CoreCommand};
+use iced_hot_reload::{HeadlessCore, Screenshot};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+/// Rewrites the snapshots checked by [`TestCore::assert_snapshot`] instead
+/// of comparing with them, if set.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "HOT_RELOAD_UPDATE_SNAPSHOTS";
+
+/// The size in pixels of the window the view is rasterized in by
+/// [`TestCore::assert_golden`] and [`TestCore::reload_looks_unchanged`].
+pub const SCREENSHOT_SIZE: (u32, u32) = (800, 600);
+
+/// A core loaded for a test, see the [crate documentation](crate).
+pub struct TestCore<M> {
+    core: HeadlessCore<M>,
+    commands: Vec<CoreCommand<M>>,
+}
+
+impl<M: Debug> TestCore<M> {
+    /// Loads the core `lib_name` from the cargo target directory, starting
+    /// from its default state.
+    #[track_caller]
+    pub fn load(lib_name: &str) -> Self {
+        Self::load_state(lib_name, Vec::new())
+    }
+
+    /// Loads the core `lib_name` starting from `state`.
+    #[track_caller]
+    pub fn load_with_state<S: Serialize>(lib_name: &str, state: &S) -> Self {
+        let state = serialize_state(state).unwrap_or_else(|e| panic!("{}", e));
+        Self::load_state(lib_name, state)
+    }
+
+    #[track_caller]
+    fn load_state(lib_name: &str, state: Vec<u8>) -> Self {
+        let core = HeadlessCore::load(lib_name, &state).unwrap_or_else(|e| {
+            panic!("Failed to load the core {}, was it built? {}", lib_name, e)
+        });
+
+        Self {
+            core,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Passes `message` to the core. The commands it requests are kept for
+    /// [`TestCore::take_commands`].
+    #[track_caller]
+    pub fn send(&mut self, message: M) -> &mut Self {
+        let description = format!("{:?}", message);
+        match self.core.update(message) {
+            Ok(commands) => self.commands.extend(commands),
+            Err(e) => panic!("Updating the core with {} failed: {}", description, e),
+        }
+        self
+    }
+
+    /// Passes `messages` to the core one after the other.
+    #[track_caller]
+    pub fn send_all(&mut self, messages: impl IntoIterator<Item = M>) -> &mut Self {
+        for message in messages {
+            self.send(message);
+        }
+        self
+    }
+
+    /// Reloads the library and carries the state over, as the shell does
+    /// when the library was rebuilt.
+    #[track_caller]
+    pub fn reload(&mut self) -> &mut Self {
+        match self.core.reload() {
+            Ok(commands) => self.commands.extend(commands),
+            Err(e) => panic!("Reloading the core failed: {}", e),
+        }
+        self
+    }
+
+    /// Builds the view of the core and drops it again, so a panic in `view`
+    /// fails the test.
+    #[track_caller]
+    pub fn render(&mut self) -> &mut Self {
+        if let Err(e) = self.core.view() {
+            panic!("Building the view of the core failed: {}", e);
+        }
+        self
+    }
+
+    /// Returns the commands requested by the core since the last call.
+    pub fn take_commands(&mut self) -> Vec<CoreCommand<M>> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// Returns the state of the core, as it would be carried over to the
+    /// next reload.
+    #[track_caller]
+    pub fn state<S: DeserializeOwned + Default>(&self) -> S {
+        deserialize_state(&self.saved_state()).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns the state of the core as JSON.
+    #[track_caller]
+    pub fn snapshot(&self) -> serde_json::Value {
+        let state = self.saved_state();
+        if state.is_empty() {
+            return serde_json::Value::Null;
+        }
+
+        serde_json::from_slice(&state)
+            .unwrap_or_else(|e| panic!("The state of the core isn't JSON: {}", e))
+    }
+
+    /// Asserts the state of the core equals `expected`.
+    #[track_caller]
+    pub fn assert_state_eq<S>(&self, expected: &S)
+    where
+        S: DeserializeOwned + Default + PartialEq + Debug,
+    {
+        assert_eq!(
+            &self.state::<S>(),
+            expected,
+            "The state of the core differs"
+        );
+    }
+
+    /// Asserts the state of the core equals the JSON snapshot at `path`.
+    ///
+    /// A missing snapshot is written and passes, so the first run records
+    /// it. Setting [`UPDATE_SNAPSHOTS_VAR`] rewrites snapshots which differ.
+    #[track_caller]
+    pub fn assert_snapshot(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let snapshot = self.snapshot();
+
+        let expected = match fs::read_to_string(path) {
+            Ok(expected) if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_none() => expected,
+            _ => {
+                let json = serde_json::to_string_pretty(&snapshot)
+                    .unwrap_or_else(|e| panic!("Failed to serialize the snapshot: {}", e));
+                fs::write(path, json + "\n").unwrap_or_else(|e| {
+                    panic!("Failed to write the snapshot {}: {}", path.display(), e)
+                });
+                return;
+            }
+        };
+
+        let expected: serde_json::Value = serde_json::from_str(&expected)
+            .unwrap_or_else(|e| panic!("The snapshot {} isn't JSON: {}", path.display(), e));
+        assert_eq!(
+            snapshot,
+            expected,
+            "The state of the core differs from the snapshot {}, set {} to update it",
+            path.display(),
+            UPDATE_SNAPSHOTS_VAR
+        );
+    }
+
+    /// Rasterizes the view of the core offscreen, in a window of
+    /// [`SCREENSHOT_SIZE`].
+    #[track_caller]
+    pub fn screenshot(&self) -> Screenshot {
+        let (width, height) = SCREENSHOT_SIZE;
+        self.core
+            .screenshot(width, height)
+            .unwrap_or_else(|e| panic!("Rasterizing the view of the core failed: {}", e))
+    }
+
+    /// Asserts the view of the core looks like the golden image at `path`,
+    /// with at most `threshold` of the pixels, from 0 to 1, differing.
+    ///
+    /// A missing image is written and passes, so the first run records it.
+    /// Setting [`UPDATE_SNAPSHOTS_VAR`] rewrites images which differ. A
+    /// differing view is written next to the image, e.g. `main.actual.png`
+    /// for `main.png`, to compare them.
+    #[track_caller]
+    pub fn assert_golden(&self, path: impl AsRef<Path>, threshold: f32) {
+        let path = path.as_ref();
+        let screenshot = self.screenshot();
+
+        let expected = match Screenshot::load_png(path) {
+            Ok(expected) if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_none() => expected,
+            _ => {
+                screenshot.save_png(path).unwrap_or_else(|e| {
+                    panic!("Failed to write the image {}: {}", path.display(), e)
+                });
+                return;
+            }
+        };
+
+        let diff = screenshot.diff(&expected);
+        if diff > threshold {
+            let actual = path.with_extension("actual.png");
+            let _ = screenshot.save_png(&actual);
+            panic!(
+                "The view of the core differs from the image {} in {:.2}% of the pixels, see {}; set {} to update it",
+                path.display(),
+                diff * 100.0,
+                actual.display(),
+                UPDATE_SNAPSHOTS_VAR
+            );
+        }
+    }
+
+    /// Reloads the library and asserts the view looks as it did before,
+    /// with at most `threshold` of the pixels, from 0 to 1, differing, to
+    /// catch visual changes made by a refactoring.
+    #[track_caller]
+    pub fn reload_looks_unchanged(&mut self, threshold: f32) -> &mut Self {
+        let before = self.screenshot();
+        self.reload();
+        let diff = self.screenshot().diff(&before);
+
+        assert!(
+            diff <= threshold,
+            "The view of the core changed in {:.2}% of the pixels after the reload",
+            diff * 100.0
+        );
+        self
+    }
+
+    /// The loaded core, for what the helpers don't cover.
+    pub fn core(&mut self) -> &mut HeadlessCore<M> {
+        &mut self.core
+    }
+
+    #[track_caller]
+    fn saved_state(&self) -> Vec<u8> {
+        self.core
+            .save_state()
+            .unwrap_or_else(|e| panic!("Saving the state of the core failed: {}", e))
+    }
+}
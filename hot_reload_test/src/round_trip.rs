@@ -0,0 +1,119 @@
+use hot_reload_interface::serialize_state;
+use iced_hot_reload::HeadlessCore;
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt::Debug;
+
+/// How many states are checked by default. Every state is carried over by
+/// a reload, which copies the library, so fewer than proptest's default.
+const DEFAULT_CASES: u32 = 64;
+
+/// Checks that random states survive being handed from one core instance
+/// to the next, as on every reload, catching fields the core drops or
+/// (de)serializes differently than the shell's copy of the state type.
+///
+/// Every generated state is serialized, given to an instance of the core,
+/// carried over by a reload, migrations included, and read back. Failing
+/// states are shrunk to a minimal one, as in any proptest:
+///
+/// ```ignore
+/// use hot_reload_test::proptest::prelude::*;
+///
+/// #[test]
+/// fn states_survive_reloads() {
+///     let states = any::<i32>().prop_map(|counter| AppState { counter });
+///     StateRoundTrip::<Message>::load("app_core").check(states);
+/// }
+/// ```
+pub struct StateRoundTrip<M> {
+    core: RefCell<HeadlessCore<M>>,
+    config: Config,
+}
+
+impl<M> StateRoundTrip<M> {
+    /// Loads the core `lib_name` from the cargo target directory.
+    #[track_caller]
+    pub fn load(lib_name: &str) -> Self {
+        let core = HeadlessCore::load(lib_name, &[]).unwrap_or_else(|e| {
+            panic!("Failed to load the core {}, was it built? {}", lib_name, e)
+        });
+
+        Self {
+            core: RefCell::new(core),
+            config: Config::with_cases(DEFAULT_CASES),
+        }
+    }
+
+    /// Checks `cases` states instead of 64.
+    pub fn cases(mut self, cases: u32) -> Self {
+        self.config.cases = cases;
+        self
+    }
+
+    /// Checks that every state generated by `strategy` comes back equal
+    /// after a reload.
+    #[track_caller]
+    pub fn check<S>(&self, strategy: impl Strategy<Value = S>)
+    where
+        S: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        self.check_with(strategy, |before, after| {
+            if before == after {
+                Ok(())
+            } else {
+                Err(TestCaseError::fail(format!(
+                    "The state changed in the reload: {:?}",
+                    after
+                )))
+            }
+        });
+    }
+
+    /// Checks `invariant` holds for every state generated by `strategy` and
+    /// the state it became after a reload, e.g. for states with caches the
+    /// core rebuilds.
+    #[track_caller]
+    pub fn check_with<S>(
+        &self,
+        strategy: impl Strategy<Value = S>,
+        invariant: impl Fn(&S, &S) -> Result<(), TestCaseError>,
+    ) where
+        S: Serialize + DeserializeOwned + Debug,
+    {
+        let mut runner = TestRunner::new(self.config.clone());
+        let result = runner.run(&strategy, |state| {
+            let after = self.round_trip(&state)?;
+            invariant(&state, &after)
+        });
+
+        if let Err(e) = result {
+            panic!("The state isn't carried over by reloads: {}", e);
+        }
+    }
+
+    /// Hands `state` to a new instance of the core, reloads it and reads
+    /// the state back.
+    fn round_trip<S>(&self, state: &S) -> Result<S, TestCaseError>
+    where
+        S: Serialize + DeserializeOwned,
+    {
+        let mut core = self.core.borrow_mut();
+        let state = serialize_state(state).map_err(TestCaseError::fail)?;
+
+        let state = core
+            .restore(&state)
+            .and_then(|()| {
+                core.reload()?;
+                core.save_state()
+            })
+            .map_err(|e| TestCaseError::fail(e.to_string()))?;
+
+        // Unlike when starting a core, an empty state isn't a default one
+        // here, the core lost the state it was given.
+        serde_json::from_slice(&state)
+            .map_err(|e| TestCaseError::fail(format!("Failed to deserialize the state: {}", e)))
+    }
+}
@@ -0,0 +1,320 @@
+use crate::TestCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Debug;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// How long a running shell may take to handle a message or a reload.
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A script of what the user does and what the state should be, read from
+/// a RON file, e.g. `scenarios/three_increments.ron`:
+///
+/// ```ron
+/// Scenario(
+///     core: "app_core",
+///     steps: [
+///         Send(message: Increment, times: 3),
+///         Build(features: ["fancy"]),
+///         Reload,
+///         Assert(path: "counter", equals: 3),
+///     ],
+/// )
+/// ```
+///
+/// [`Scenario::run`] executes it headlessly with a [`TestCore`] and
+/// [`Scenario::run_against`] against a running shell, through its control
+/// server. Either panics with the step which failed.
+#[derive(Debug, Deserialize)]
+#[serde(bound = "M: DeserializeOwned")]
+pub struct Scenario<M> {
+    /// The name of the core library, also the package built by
+    /// [`Step::Build`].
+    pub core: String,
+
+    /// The steps, executed in order.
+    pub steps: Vec<Step<M>>,
+}
+
+/// A step of a [`Scenario`].
+#[derive(Debug, Deserialize)]
+#[serde(bound = "M: DeserializeOwned")]
+pub enum Step<M> {
+    /// Passes `message` to the core `times` times, once by default.
+    Send {
+        message: M,
+        #[serde(default = "once")]
+        times: u32,
+    },
+
+    /// Builds the core with cargo, enabling `features`. The new build is
+    /// only loaded by a [`Step::Reload`].
+    Build {
+        #[serde(default)]
+        features: Vec<String>,
+    },
+
+    /// Reloads the library, carrying the state over.
+    Reload,
+
+    /// Builds the view of the core, to check it doesn't panic. A running
+    /// shell renders the view itself, so this does nothing there.
+    Render,
+
+    /// Asserts the value at `path` in the state, its fields separated by
+    /// dots, e.g. `todos.0.done`, equals `equals`.
+    Assert { path: String, equals: Value },
+}
+
+fn once() -> u32 {
+    1
+}
+
+impl<M> Scenario<M>
+where
+    M: DeserializeOwned + Serialize + Clone + Debug,
+{
+    /// Reads the scenario at `path`.
+    #[track_caller]
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let scenario = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read the scenario {}: {}", path.display(), e));
+
+        ron::from_str(&scenario)
+            .unwrap_or_else(|e| panic!("Invalid scenario {}: {}", path.display(), e))
+    }
+
+    /// Executes the scenario on the core loaded without a window.
+    #[track_caller]
+    pub fn run(&self) {
+        let mut core = TestCore::<M>::load(&self.core);
+
+        for (index, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Send { message, times } => {
+                    for _ in 0..*times {
+                        core.send(message.clone());
+                    }
+                }
+                Step::Build { features } => self.build(features, index),
+                Step::Reload => {
+                    core.reload();
+                }
+                Step::Render => {
+                    core.render();
+                }
+                Step::Assert { path, equals } => {
+                    assert_value(&core.snapshot(), path, equals, index);
+                }
+            }
+        }
+    }
+
+    /// Executes the scenario against the shell running the control server
+    /// at `addr`, started with `HotReloadBuilder::control_server` and
+    /// `HotReloadBuilder::message_console` to accept messages.
+    #[track_caller]
+    pub fn run_against(&self, addr: SocketAddr) {
+        for (index, step) in self.steps.iter().enumerate() {
+            let result = match step {
+                Step::Send { message, times } => {
+                    let message = serde_json::to_vec(message).unwrap_or_else(|e| {
+                        panic!(
+                            "Step {}: failed to serialize {:?}: {}",
+                            index + 1,
+                            message,
+                            e
+                        )
+                    });
+                    (0..*times).try_for_each(|_| send_message(addr, &message))
+                }
+                Step::Build { features } => {
+                    self.build(features, index);
+                    Ok(())
+                }
+                Step::Reload => reload(addr),
+                Step::Render => Ok(()),
+                Step::Assert { path, equals } => request(addr, "GET", "/state", b"")
+                    .and_then(|state| {
+                        serde_json::from_slice(&state)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                    })
+                    .map(|state| assert_value(&state, path, equals, index)),
+            };
+
+            if let Err(e) = result {
+                panic!("Step {} ({:?}) failed: {}", index + 1, step, e);
+            }
+        }
+    }
+
+    /// Builds the core package with `features`, as the shell would.
+    #[track_caller]
+    fn build(&self, features: &[String], index: usize) {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let mut command = Command::new(cargo);
+        command.args(["build", "-p", &self.core]);
+        if !features.is_empty() {
+            command.args(["--features", &features.join(",")]);
+        }
+        if !cfg!(debug_assertions) {
+            command.arg("--release");
+        }
+
+        match command.status() {
+            Ok(status) if status.success() => (),
+            Ok(status) => panic!(
+                "Step {}: building {} failed: {}",
+                index + 1,
+                self.core,
+                status
+            ),
+            Err(e) => panic!("Step {}: failed to run cargo: {}", index + 1, e),
+        }
+    }
+}
+
+/// Asserts the value at the dotted `path` in `state` equals `expected`.
+#[track_caller]
+fn assert_value(state: &Value, path: &str, expected: &Value, index: usize) {
+    let pointer: String = path.split('.').map(|field| format!("/{}", field)).collect();
+    let actual = state
+        .pointer(&pointer)
+        .unwrap_or_else(|| panic!("Step {}: the state has no {}: {}", index + 1, path, state));
+
+    assert_eq!(
+        actual,
+        expected,
+        "Step {}: the state differs at {}",
+        index + 1,
+        path
+    );
+}
+
+/// Passes a message to the core and waits until the shell handled it.
+fn send_message(addr: SocketAddr, message: &[u8]) -> io::Result<()> {
+    let mut events = Events::subscribe(addr)?;
+    request(addr, "POST", "/message", message)?;
+
+    let mut panic = None;
+    loop {
+        let event = events.next()?;
+        match event["event"].as_str() {
+            Some("panicked") => panic = Some(describe(&event["message"])),
+            Some("message_handled") => break,
+            Some("message_rejected") => return Err(other(describe(&event["error"]))),
+            _ => (),
+        }
+    }
+
+    match panic {
+        Some(panic) => Err(other(format!("The core panicked: {}", panic))),
+        None => Ok(()),
+    }
+}
+
+/// Reloads the library and waits until the new core was swapped in.
+fn reload(addr: SocketAddr) -> io::Result<()> {
+    let mut events = Events::subscribe(addr)?;
+    request(addr, "POST", "/reload", b"")?;
+
+    loop {
+        let event = events.next()?;
+        match event["event"].as_str() {
+            Some("reloaded") => return Ok(()),
+            Some("reload_failed") => return Err(other(describe(&event["error"]))),
+            Some("rolled_back") => {
+                return Err(other(format!(
+                    "The new core panicked: {}",
+                    describe(&event["panic"])
+                )));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Sends a request to the control server and returns the body of a
+/// successful response.
+fn request(addr: SocketAddr, method: &str, path: &str, body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(CONTROL_TIMEOUT))?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        method,
+        path,
+        addr,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head.lines().next().unwrap_or_default();
+
+    if status
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|code| code.starts_with('2'))
+    {
+        Ok(body.as_bytes().to_vec())
+    } else {
+        Err(other(format!("{} {} answered {}", method, path, status)))
+    }
+}
+
+/// The events of the shell, streamed from `GET /events`.
+struct Events {
+    reader: BufReader<TcpStream>,
+}
+
+impl Events {
+    /// Starts streaming the events, before the request they should follow
+    /// is sent so none is missed.
+    fn subscribe(addr: SocketAddr) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(CONTROL_TIMEOUT))?;
+        write!(stream, "GET /events HTTP/1.1\r\nHost: {}\r\n\r\n", addr)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Waits for the next event.
+    fn next(&mut self) -> io::Result<Value> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(other("The shell closed the event stream"));
+            }
+            if let Some(data) = line.trim_end().strip_prefix("data: ") {
+                return serde_json::from_str(data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+        }
+    }
+}
+
+/// The text of a string field of an event.
+fn describe(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+fn other(error: impl ToString) -> io::Error {
+    io::Error::other(error.to_string())
+}
@@ -0,0 +1,29 @@
+//! Run with `cargo build -p app_core && cargo test -p hot_reload_test`.
+
+use hot_reload_test::{Scenario, TestCore};
+use shared_types::Message;
+use std::path::Path;
+
+/// The share of the pixels allowed to differ from the golden image. The
+/// comparison already tolerates anti-aliasing, and a changed digit of the
+/// counter only touches a few pixels, so none may differ.
+const GOLDEN_THRESHOLD: f32 = 0.0;
+
+#[test]
+fn three_increments() {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../app_core/scenarios/three_increments.ron");
+
+    Scenario::<Message>::load(path).run();
+}
+
+#[test]
+fn counter_looks_like_golden() {
+    let mut core = TestCore::<Message>::load("app_core");
+    core.send_all([Message::Increment, Message::Increment]);
+
+    core.assert_golden(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/counter.png"),
+        GOLDEN_THRESHOLD,
+    );
+}
@@ -0,0 +1,15 @@
+//! Run with `cargo build -p app_core && cargo test -p hot_reload_test --features proptest`.
+
+use hot_reload_test::proptest::prelude::*;
+use hot_reload_test::StateRoundTrip;
+use shared_types::{AppState, Message};
+
+#[test]
+fn app_core_states_survive_reloads() {
+    let states = any::<i32>().prop_map(|counter| AppState { counter });
+
+    StateRoundTrip::<Message>::load("app_core").check_with(states, |before, after| {
+        prop_assert_eq!(before.counter, after.counter);
+        Ok(())
+    });
+}
@@ -0,0 +1,14 @@
+//! iced as a shared library.
+//!
+//! Linking iced and its dependencies into every build of a core dominates
+//! the link time of a change. Depending on this crate, which is compiled as
+//! a Rust `dylib` once, makes the shell and the core link against the same
+//! prebuilt `libiced_dylib` instead, so a rebuild of the core only links the
+//! core's own code.
+//!
+//! Nothing needs to be used from here: the crates keep depending on `iced`
+//! as usual, and cargo resolves both to the same build, whose code lives in
+//! this library. Enable it with the `dynamic-iced` feature of
+//! `iced_hot_reload` in the shell and of `hot_reload_interface` in the core.
+
+pub use iced;
@@ -0,0 +1,137 @@
+//! Measures the phases of a reload: copying the library, opening it,
+//! resolving its symbols, instantiating the core and the full swap.
+//!
+//! Every build of `app_core` found in the target directory is measured, so
+//! a debug and a release build compare a small library with a large one.
+//! Build them first, then save a baseline to compare later runs with:
+//!
+//! ```sh
+//! cargo build -p app_core && cargo build -p app_core --release
+//! cargo bench -p iced_hot_reload --bench reload -- --save-baseline main
+//! cargo bench -p iced_hot_reload --bench reload -- --baseline main
+//! ```
+//!
+//! `HOT_RELOAD_BENCH_LIBS` lists other libraries to measure instead,
+//! separated by commas, as long as their message type is the one of
+//! `app_core`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use hot_reload_interface::{ABI_VERSION_SYMBOL, CREATE_SYMBOL, DESTROY_SYMBOL};
+use iced_hot_reload::HeadlessCore;
+use shared_types::Message;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LIB_NAME: &str = "app_core";
+
+/// The libraries to measure, with their size in bytes.
+fn libraries() -> Vec<(PathBuf, u64)> {
+    let paths: Vec<PathBuf> = match std::env::var("HOT_RELOAD_BENCH_LIBS") {
+        Ok(libs) => libs.split(',').map(PathBuf::from).collect(),
+        Err(_) => {
+            let target_dir = std::env::var_os("CARGO_TARGET_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../target"));
+            let file_name = format!(
+                "{}{}{}",
+                std::env::consts::DLL_PREFIX,
+                LIB_NAME,
+                std::env::consts::DLL_SUFFIX
+            );
+
+            ["debug", "release"]
+                .iter()
+                .map(|profile| target_dir.join(profile).join(&file_name))
+                .collect()
+        }
+    };
+
+    let libraries: Vec<_> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let size = fs::metadata(&path).ok()?.len();
+            Some((path, size))
+        })
+        .collect();
+    assert!(
+        !libraries.is_empty(),
+        "No library to measure, build {} first",
+        LIB_NAME
+    );
+    libraries
+}
+
+/// Names a library by its parent directory, e.g. the profile, and size.
+fn label(path: &Path, size: u64) -> String {
+    let profile = path
+        .parent()
+        .and_then(Path::file_name)
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    format!("{} {} KiB", profile, size / 1024)
+}
+
+fn reload_phases(c: &mut Criterion) {
+    let copy_dir = std::env::temp_dir().join("iced_hot_reload_bench");
+    fs::create_dir_all(&copy_dir).expect("Failed to create the copy directory");
+
+    for (path, size) in libraries() {
+        let label = label(&path, size);
+        let file_name = path
+            .file_name()
+            .expect("The library has a file name")
+            .to_string_lossy()
+            .into_owned();
+        let copy_path = copy_dir.join(&file_name);
+
+        c.bench_with_input(BenchmarkId::new("copy", &label), &path, |b, path| {
+            b.iter(|| fs::copy(path, &copy_path).expect("Failed to copy the library"));
+        });
+
+        // Every iteration opens a copy of its own, as every reload does, so
+        // the loader doesn't hand out the library opened before.
+        c.bench_with_input(BenchmarkId::new("load", &label), &path, |b, path| {
+            let mut copies = 0;
+            b.iter_batched(
+                || {
+                    copies += 1;
+                    let copy = copy_dir.join(format!("{}-{}", copies, file_name));
+                    fs::copy(path, &copy).expect("Failed to copy the library");
+                    copy
+                },
+                |copy| unsafe { libloading::Library::new(copy) }.expect("Failed to open"),
+                BatchSize::PerIteration,
+            );
+        });
+
+        c.bench_with_input(BenchmarkId::new("symbols", &label), &path, |b, path| {
+            let library = unsafe { libloading::Library::new(path) }.expect("Failed to open");
+            b.iter(|| {
+                for symbol in [CREATE_SYMBOL, DESTROY_SYMBOL, ABI_VERSION_SYMBOL] {
+                    unsafe { library.get::<*const ()>(symbol.as_bytes()) }.expect("Missing symbol");
+                }
+            });
+        });
+
+        let mut core = HeadlessCore::<Message>::load_path(LIB_NAME, path.clone(), &[])
+            .expect("Failed to load the core");
+        let state = core.save_state().expect("Failed to save the state");
+
+        c.bench_with_input(
+            BenchmarkId::new("instantiate", &label),
+            &state,
+            |b, state| {
+                b.iter(|| core.restore(state).expect("Failed to instantiate"));
+            },
+        );
+
+        // Loads the library again and carries the state over, as a reload.
+        c.bench_with_input(BenchmarkId::new("swap", &label), &(), |b, ()| {
+            b.iter(|| core.reload().expect("Failed to reload"));
+        });
+    }
+
+    let _ = fs::remove_dir_all(&copy_dir);
+}
+
+criterion_group!(benches, reload_phases);
+criterion_main!(benches);
@@ -0,0 +1,85 @@
+use crate::message::ShellMessage;
+use iced::{font, Task};
+use log::{error, trace};
+use std::path::{Path, PathBuf};
+
+/// The extensions of the font files iced can load.
+const FONT_EXTENSIONS: [&str; 2] = ["ttf", "otf"];
+
+/// The font files in `dir` and its subdirectories.
+pub(crate) fn fonts_in(dir: &Path) -> Vec<PathBuf> {
+    files_in(dir, &FONT_EXTENSIONS)
+}
+
+/// The files with one of the `extensions` in `dir` and its subdirectories.
+pub(crate) fn files_in(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("Failed to read {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.is_dir() {
+                dirs.push(path);
+            } else if has_extension(&path, extensions) {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+/// Loads the fonts among `paths`, so the core can use them by name.
+///
+/// Fonts can't be unloaded, so a font which was changed is loaded again
+/// next to its previous version.
+pub(crate) fn load_fonts<M: Send + 'static>(paths: &[PathBuf]) -> Task<ShellMessage<M>> {
+    Task::batch(
+        paths
+            .iter()
+            .filter(|path| is_font(path) && path.is_file())
+            .filter_map(|path| match std::fs::read(path) {
+                Ok(bytes) => Some(load_font(path.clone(), bytes)),
+                Err(e) => {
+                    error!("Failed to read the font {}: {}", path.display(), e);
+                    None
+                }
+            }),
+    )
+}
+
+fn load_font<M: Send + 'static>(path: PathBuf, bytes: Vec<u8>) -> Task<ShellMessage<M>> {
+    font::load(bytes).then(move |result| {
+        match result {
+            Ok(()) => trace!("Loaded the font {}", path.display()),
+            Err(e) => error!("Failed to load the font {}: {:?}", path.display(), e),
+        }
+        Task::none()
+    })
+}
+
+fn is_font(path: &Path) -> bool {
+    has_extension(path, &FONT_EXTENSIONS)
+}
+
+/// Whether the extension of `path` is one of `extensions`, ignoring case.
+pub(crate) fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|expected| extension.eq_ignore_ascii_case(expected))
+        })
+}
@@ -0,0 +1,518 @@
+use crate::copies;
+use crate::debug_info;
+use crate::handle::CoreHandle;
+use crate::loader::{
+    instantiate, lib_file_name, load_library, migrate_state, self_test, validate_symbols, LibInfo,
+    LoadTimings, LoadedLibrary,
+};
+use hot_reload_interface::{
+    Capabilities, CommandResult, CoreCommand, CoreEvent, CoreSubscription, DestroyFn, ErrorMessage,
+    Presentation, PresentationFn, ReloadInfo,
+};
+use iced::Element;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Loads the current version of the core on a background thread, see
+/// [`LoaderBackend::background_load`].
+pub(crate) type BackgroundLoad<L> = Box<dyn FnOnce() -> io::Result<L> + Send>;
+
+/// Loads versions of the core and creates instances from them.
+///
+/// The shell drives reloads, rollbacks and unloading through this trait
+/// only, so the way the core is loaded can change without touching the
+/// shell. [`DylibBackend`] loads it from a dynamic library.
+pub(crate) trait LoaderBackend<M> {
+    /// A loaded version of the core, which may be loaded on a background
+    /// thread.
+    type Library: Send;
+
+    /// A core instance created from a [`Self::Library`], destroyed when it
+    /// is dropped.
+    type Instance: CoreInstance<M>;
+
+    /// Loads the current version of the core.
+    fn load(&mut self) -> io::Result<Self::Library>;
+
+    /// Returns a function doing what [`LoaderBackend::load`] does, which the
+    /// shell runs on a background thread to keep the UI responsive. `None`
+    /// if the backend only loads on the UI thread, the default.
+    fn background_load(&self) -> Option<BackgroundLoad<Self::Library>> {
+        None
+    }
+
+    /// Creates an instance of `lib` with the serialized `state`. An empty
+    /// state makes the core start from its default state.
+    fn instantiate(&mut self, lib: &Self::Library, state: &[u8]) -> io::Result<Self::Instance>;
+
+    /// Converts `state`, saved by an instance of `previous`, for `lib`.
+    ///
+    /// The state is passed on unchanged unless the backend knows better.
+    fn migrate(
+        &mut self,
+        _lib: &Self::Library,
+        _previous: &Self::Library,
+        state: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        Ok(state.to_vec())
+    }
+
+    /// Checks the symbols exported by `lib` match what it declares, before
+    /// any of them is called. Passes by default.
+    fn validate(&mut self, _lib: &Self::Library) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Checks `lib` is fit to replace the running core, before it is
+    /// instantiated. Passes by default.
+    fn self_test(&mut self, _lib: &Self::Library) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Unloads `lib`, whose instances have all been dropped.
+    fn unload(&mut self, lib: Self::Library);
+
+    /// Removes what the backend left on disk when the shell exits, before
+    /// the current library is unloaded. Does nothing by default.
+    fn clean_up(&mut self) {}
+
+    /// Whether a newer version of the core is available than `lib`.
+    fn changed_since(&self, lib: &Self::Library) -> bool;
+
+    /// When `lib` was built, if known.
+    fn built_at(&self, _lib: &Self::Library) -> Option<SystemTime> {
+        None
+    }
+
+    /// How long copying and opening `lib` took, if measured.
+    fn load_timings(&self, _lib: &Self::Library) -> Option<LoadTimings> {
+        None
+    }
+
+    /// The generation the allocations of the core in `lib` are attributed
+    /// to, see [`hot_reload_interface::live_allocations`], if they are.
+    fn generation(&self, _lib: &Self::Library) -> Option<usize> {
+        None
+    }
+
+    /// When the earlier versions which can be loaded again were built,
+    /// oldest first.
+    fn builds(&self) -> Vec<SystemTime> {
+        Vec::new()
+    }
+
+    /// Loads the earlier version built at `built_at`.
+    fn load_build(&mut self, _built_at: SystemTime) -> io::Result<Self::Library> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "Earlier builds can't be loaded by this backend",
+        ))
+    }
+}
+
+/// A core instance created by a [`LoaderBackend`].
+///
+/// Every call fails with the message of the panic raised by the core.
+pub(crate) trait CoreInstance<M> {
+    /// Passes `message` to the core and returns the commands it requested.
+    fn update(&mut self, message: M) -> Result<Vec<CoreCommand<M>>, String>;
+
+    /// Renders the view of the core.
+    fn view(&self) -> Result<Element<'static, M>, String>;
+
+    /// Serializes the state of the core.
+    fn save_state(&self) -> Result<Vec<u8>, String>;
+
+    /// Returns the subscriptions declared by the core.
+    fn subscriptions(&self) -> Result<Vec<CoreSubscription<M>>, String>;
+
+    /// Returns the title, theme and scale factor requested by the core.
+    fn presentation(&self) -> Result<Presentation, String>;
+
+    /// Renders the view of the window `window` opened by the core.
+    fn window_view(&self, window: &str) -> Result<Element<'static, M>, String>;
+
+    /// Asks the core for the message of `result`, the result of the command
+    /// named `request`.
+    fn command_result(&self, request: &str, result: &CommandResult) -> Result<Option<M>, String>;
+
+    /// Whether the core listens to the events of the window, so they are
+    /// worth forwarding.
+    fn handles_events(&self) -> bool;
+
+    /// Asks the core for the message of `event`.
+    fn on_event(&self, event: &CoreEvent) -> Result<Option<M>, String>;
+
+    /// Tells the core it was created by the `reload` and returns the
+    /// commands it requested.
+    fn after_reload(&mut self, reload: &ReloadInfo) -> Result<Vec<CoreCommand<M>>, String>;
+
+    /// Destroys the core ahead of the instance, e.g. before the shell exits.
+    /// The instance must not be called afterwards.
+    fn destroy(&mut self);
+}
+
+/// Loads the core from a dynamic library, the default backend.
+///
+/// Every version is loaded from its own copy of the library, so the library
+/// can be rebuilt while a version of it is loaded. Every build loaded is also
+/// archived in the session directory, so it can be loaded again later.
+pub(crate) struct DylibBackend<M> {
+    lib_info: LibInfo,
+
+    /// The archived builds with the time they were built, oldest first.
+    /// Shared with the background loads, which archive the builds they load.
+    builds: Arc<Mutex<Vec<(SystemTime, PathBuf)>>>,
+
+    _message: PhantomData<M>,
+}
+
+impl<M> DylibBackend<M> {
+    /// Creates the backend loading the library described by `lib_info`.
+    ///
+    /// The copies go to a directory of this session. Copies left behind by
+    /// sessions older than `copy_retention` are removed.
+    pub(crate) fn new(mut lib_info: LibInfo, copy_retention: Duration) -> Self {
+        lib_info.copy_dir = match copies::session_dir(&lib_info.name, copy_retention) {
+            Ok(dir) => Some(dir),
+            Err(e) => {
+                log::error!("Failed to create the directory for library copies: {}", e);
+                None
+            }
+        };
+
+        Self {
+            lib_info,
+            builds: Arc::default(),
+            _message: PhantomData,
+        }
+    }
+
+    /// The archived builds, oldest first.
+    fn archived(&self) -> std::sync::MutexGuard<'_, Vec<(SystemTime, PathBuf)>> {
+        self.builds
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Keeps a copy of the build `lib` was loaded from in `builds`, unless it
+/// was archived already.
+fn archive<M>(
+    lib_info: &LibInfo,
+    builds: &Mutex<Vec<(SystemTime, PathBuf)>>,
+    lib: &LoadedLibrary<M>,
+) {
+    let Some(copy_dir) = &lib_info.copy_dir else {
+        return;
+    };
+    let mut builds = builds
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if builds.iter().any(|(built_at, _)| *built_at == lib.modified) {
+        return;
+    }
+
+    let builds_dir = copy_dir.join("builds");
+    let path = builds_dir.join(lib_file_name(&format!(
+        "{}_{}",
+        lib_info.name,
+        builds.len()
+    )));
+
+    match std::fs::create_dir_all(&builds_dir).and_then(|()| copies::stage(lib.copy.path(), &path))
+    {
+        Ok(_) => {
+            // Dropped with the builds directory in `clean_up`.
+            debug_info::stage(lib.copy.path(), &path);
+            builds.push((lib.modified, path))
+        }
+        Err(e) => log::error!("Failed to archive the build {}: {}", path.display(), e),
+    }
+}
+
+// The ABI of the core is checked by `load_library` before any of its symbols
+// is called, which is what makes the calls below sound.
+impl<M> LoaderBackend<M> for DylibBackend<M> {
+    type Library = LoadedLibrary<M>;
+    type Instance = DylibInstance<M>;
+
+    fn load(&mut self) -> io::Result<LoadedLibrary<M>> {
+        let lib = unsafe { load_library(&self.lib_info) }?;
+        archive(&self.lib_info, &self.builds, &lib);
+        Ok(lib)
+    }
+
+    /// Copies, opens and archives the library off the UI thread. The core
+    /// is only instantiated on the UI thread.
+    fn background_load(&self) -> Option<BackgroundLoad<LoadedLibrary<M>>> {
+        let lib_info = self.lib_info.clone();
+        let builds = Arc::clone(&self.builds);
+
+        Some(Box::new(move || {
+            let lib = unsafe { load_library(&lib_info) }?;
+            archive(&lib_info, &builds, &lib);
+            Ok(lib)
+        }))
+    }
+
+    fn instantiate(
+        &mut self,
+        lib: &LoadedLibrary<M>,
+        state: &[u8],
+    ) -> io::Result<DylibInstance<M>> {
+        let app = unsafe { instantiate(lib, state) }?;
+
+        Ok(DylibInstance {
+            app: CoreHandle::new(app),
+            destroy_fn: lib.destroy_fn,
+            presentation_fn: lib.presentation_fn,
+            capabilities: lib.capabilities,
+        })
+    }
+
+    /// Migrates the state with the hook exported by `lib` if the state
+    /// schema version changed.
+    fn migrate(
+        &mut self,
+        lib: &LoadedLibrary<M>,
+        previous: &LoadedLibrary<M>,
+        state: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        if lib.state_version == previous.state_version {
+            return Ok(state.to_vec());
+        }
+
+        unsafe { migrate_state(lib, previous.state_version, state) }
+    }
+
+    fn validate(&mut self, lib: &LoadedLibrary<M>) -> io::Result<()> {
+        validate_symbols(lib)
+    }
+
+    /// Runs the self test exported by the core, if any.
+    fn self_test(&mut self, lib: &LoadedLibrary<M>) -> io::Result<()> {
+        unsafe { self_test(lib) }
+    }
+
+    fn unload(&mut self, lib: LoadedLibrary<M>) {
+        log::trace!("Unload a replaced library");
+        drop(lib);
+    }
+
+    /// Removes the archived builds. The copies of the loaded libraries
+    /// remove themselves when they are unloaded.
+    fn clean_up(&mut self) {
+        let Some(copy_dir) = &self.lib_info.copy_dir else {
+            return;
+        };
+        let mut builds = self.archived();
+        if builds.is_empty() {
+            return;
+        }
+
+        let builds_dir = copy_dir.join("builds");
+        log::trace!("Remove the archived builds in {}", builds_dir.display());
+        if let Err(e) = std::fs::remove_dir_all(&builds_dir) {
+            log::error!("Failed to remove {}: {}", builds_dir.display(), e);
+        }
+        builds.clear();
+    }
+
+    /// Builds loaded before don't count as newer, so loading an earlier
+    /// build sticks until the library is rebuilt.
+    fn changed_since(&self, lib: &LoadedLibrary<M>) -> bool {
+        std::fs::metadata(&self.lib_info.path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| {
+                modified > lib.modified
+                    && !self
+                        .archived()
+                        .iter()
+                        .any(|(built_at, _)| *built_at == modified)
+            })
+    }
+
+    fn built_at(&self, lib: &LoadedLibrary<M>) -> Option<SystemTime> {
+        Some(lib.modified)
+    }
+
+    fn load_timings(&self, lib: &LoadedLibrary<M>) -> Option<LoadTimings> {
+        Some(lib.timings)
+    }
+
+    fn generation(&self, lib: &LoadedLibrary<M>) -> Option<usize> {
+        Some(lib.generation)
+    }
+
+    fn builds(&self) -> Vec<SystemTime> {
+        self.archived()
+            .iter()
+            .map(|(built_at, _)| *built_at)
+            .collect()
+    }
+
+    fn load_build(&mut self, built_at: SystemTime) -> io::Result<LoadedLibrary<M>> {
+        let Some(path) = self
+            .archived()
+            .iter()
+            .find(|(time, _)| *time == built_at)
+            .map(|(_, path)| path.clone())
+        else {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                "The build was not archived",
+            ));
+        };
+
+        let lib_info = LibInfo {
+            path,
+            ..self.lib_info.clone()
+        };
+        let mut lib = unsafe { load_library(&lib_info) }?;
+
+        // The archive was written when the build was first loaded.
+        lib.modified = built_at;
+        Ok(lib)
+    }
+}
+
+/// A core instance created from a [`LoadedLibrary`], which has to stay
+/// loaded until the instance is dropped.
+pub(crate) struct DylibInstance<M> {
+    /// The FFI handle to the core instance, absent once it was destroyed.
+    app: CoreHandle<M>,
+
+    /// The function destroying `app`, from the library it was created by.
+    destroy_fn: DestroyFn<M>,
+
+    /// The optional function describing the title, theme and scale factor.
+    presentation_fn: Option<PresentationFn<M>>,
+
+    /// The optional parts of the contract the core implements; the others
+    /// are never called.
+    capabilities: Capabilities,
+}
+
+impl<M> CoreInstance<M> for DylibInstance<M> {
+    fn update(&mut self, message: M) -> Result<Vec<CoreCommand<M>>, String> {
+        self.app.call_mut(|app| unsafe { app.update(message) })
+    }
+
+    fn view(&self) -> Result<Element<'static, M>, String> {
+        self.app.call(|app| unsafe { app.view() })
+    }
+
+    /// A core without a serialized state starts over from its default
+    /// state, which an empty state stands for.
+    fn save_state(&self) -> Result<Vec<u8>, String> {
+        if !self.capabilities.contains(Capabilities::SERIALIZED_STATE) {
+            return Ok(Vec::new());
+        }
+
+        self.app.call_poisoned(|app| unsafe { app.save_state() })
+    }
+
+    fn subscriptions(&self) -> Result<Vec<CoreSubscription<M>>, String> {
+        if !self.capabilities.contains(Capabilities::SUBSCRIPTIONS) {
+            return Ok(Vec::new());
+        }
+
+        self.app.call(|app| unsafe { app.subscriptions() })
+    }
+
+    /// Without an exported presentation the shell's defaults apply.
+    fn presentation(&self) -> Result<Presentation, String> {
+        let mut presentation = Presentation::new();
+
+        let Some(presentation_fn) = self
+            .presentation_fn
+            .filter(|_| self.capabilities.contains(Capabilities::THEME))
+        else {
+            return Ok(presentation);
+        };
+
+        self.app.call(|app| {
+            let mut error = ErrorMessage::new();
+            if !unsafe { presentation_fn(app, &mut presentation, &mut error) } {
+                return Err(error.as_str().to_string());
+            }
+
+            Ok(presentation)
+        })
+    }
+
+    /// A core without windows of its own shows its main view in them.
+    fn window_view(&self, window: &str) -> Result<Element<'static, M>, String> {
+        if !self.capabilities.contains(Capabilities::WINDOWS) {
+            return self.view();
+        }
+
+        self.app.call(|app| unsafe { app.window_view(window) })
+    }
+
+    /// A core which doesn't handle results drops them.
+    fn command_result(&self, request: &str, result: &CommandResult) -> Result<Option<M>, String> {
+        if !self.capabilities.contains(Capabilities::COMMAND_RESULTS) {
+            return Ok(None);
+        }
+
+        self.app
+            .call(|app| unsafe { app.command_result(request, result) })
+    }
+
+    fn handles_events(&self) -> bool {
+        self.capabilities.contains(Capabilities::EVENTS)
+    }
+
+    /// A core which doesn't listen to events ignores them, so it never keeps
+    /// the window open.
+    fn on_event(&self, event: &CoreEvent) -> Result<Option<M>, String> {
+        if !self.handles_events() {
+            return Ok(None);
+        }
+
+        self.app.call(|app| unsafe { app.on_event(event) })
+    }
+
+    fn after_reload(&mut self, reload: &ReloadInfo) -> Result<Vec<CoreCommand<M>>, String> {
+        if !self.capabilities.contains(Capabilities::LIFECYCLE) {
+            return Ok(Vec::new());
+        }
+
+        self.app.call_mut(|app| unsafe { app.after_reload(reload) })
+    }
+
+    /// Runs the `on_before_unload` hook of the core first, so a panic in it
+    /// is only logged. Destroying the core again does nothing.
+    fn destroy(&mut self) {
+        if self.app.is_absent() {
+            return;
+        }
+
+        if self.capabilities.contains(Capabilities::LIFECYCLE)
+            && let Err(panic) = self
+                .app
+                .call_poisoned_mut(|app| unsafe { app.before_unload() })
+        {
+            log::error!("The core panicked before being unloaded: {}", panic);
+        }
+
+        log::trace!("Destroy the core");
+        if let Some(app) = self.app.take() {
+            unsafe {
+                (self.destroy_fn)(app);
+            }
+        }
+    }
+}
+
+impl<M> Drop for DylibInstance<M> {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
@@ -0,0 +1,533 @@
+use crate::message::ShellMessage;
+use crate::ready;
+use iced::futures::channel::mpsc::{unbounded, UnboundedSender};
+use iced::futures::{SinkExt, Stream, StreamExt};
+use iced::stream;
+use log::{error, trace};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// How long the sources have to stay untouched before a build is started.
+///
+/// Editors often save a file in several steps (backup, write, rename).
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A compiler message reported by a failed build of the core.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The severity of the message.
+    pub level: DiagnosticLevel,
+
+    /// The message itself, e.g. ``cannot find value `x` in this scope``.
+    pub message: String,
+
+    /// The file the message points at, relative to the workspace.
+    pub file: Option<String>,
+
+    /// The line in `file`, starting at 1.
+    pub line: usize,
+
+    /// The column in `file`, starting at 1.
+    pub column: usize,
+}
+
+impl Diagnostic {
+    /// Creates an error which doesn't point at a source file, e.g. because
+    /// cargo couldn't be run at all.
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            level: DiagnosticLevel::Error,
+            message: message.into(),
+            file: None,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    /// Parses the `message` field of a `compiler-message` emitted by cargo.
+    fn from_compiler_message(message: &serde_json::Value) -> Option<Self> {
+        let level = match message["level"].as_str()? {
+            "error" | "error: internal compiler error" => DiagnosticLevel::Error,
+            "warning" => DiagnosticLevel::Warning,
+            _ => DiagnosticLevel::Note,
+        };
+
+        let primary_span = message["spans"].as_array().and_then(|spans| {
+            spans
+                .iter()
+                .find(|span| span["is_primary"].as_bool() == Some(true))
+        });
+
+        let position = |key: &str| {
+            primary_span
+                .and_then(|span| span[key].as_u64())
+                .unwrap_or(0) as usize
+        };
+
+        Some(Self {
+            level,
+            message: message["message"].as_str()?.to_string(),
+            file: primary_span
+                .and_then(|span| span["file_name"].as_str())
+                .map(str::to_string),
+            line: position("line_start"),
+            column: position("column_start"),
+        })
+    }
+}
+
+/// The package rebuilt by the shell whenever its sources change.
+#[derive(Debug, Clone)]
+pub(crate) struct BuildConfig {
+    /// The cargo package of the core, passed to `cargo build -p`.
+    pub(crate) package: String,
+
+    /// The directory with the sources of the core, watched recursively.
+    pub(crate) src_dir: PathBuf,
+
+    /// The profile the core is built with, the profile of the shell if
+    /// `None`.
+    pub(crate) profile: Option<BuildProfile>,
+
+    /// The library the shell loads, which a build has to produce.
+    pub(crate) lib_path: Option<PathBuf>,
+}
+
+/// A cargo profile the core is rebuilt with, independent of the profile of
+/// the shell, see [`crate::HotReloadBuilder::build_profile`].
+///
+/// A profile created with [`Self::new`] doesn't have to be declared in
+/// `Cargo.toml`: it inherits from `dev` and its settings are passed to cargo
+/// with `--config`. Its artifacts go to `target/<name>`, where the shell then
+/// looks for the core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildProfile {
+    name: String,
+    inherits: Option<String>,
+    opt_level: Option<String>,
+    debug: Option<String>,
+    cranelift: bool,
+}
+
+impl BuildProfile {
+    /// A profile called `name`, inheriting every setting from `dev`.
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let inherits = (!is_builtin(&name)).then(|| "dev".to_string());
+        Self {
+            name,
+            inherits,
+            opt_level: None,
+            debug: None,
+            cranelift: false,
+        }
+    }
+
+    /// The profile `name` as declared in `Cargo.toml`, or one of cargo's own
+    /// like `dev`, passed to cargo as is.
+    pub fn declared(name: impl Into<String>) -> Self {
+        Self {
+            inherits: None,
+            ..Self::new(name)
+        }
+    }
+
+    /// The `hot-reload` profile, which builds without optimizations and only
+    /// with line tables as debug information, so the core links quickly while
+    /// panics still point at the source.
+    pub fn fast() -> Self {
+        Self::new("hot-reload")
+            .opt_level("0")
+            .debug("line-tables-only")
+    }
+
+    /// Sets `opt-level`, e.g. `0` or `1`.
+    pub fn opt_level(mut self, opt_level: impl Into<String>) -> Self {
+        self.opt_level = Some(opt_level.into());
+        self
+    }
+
+    /// Sets `debug`, e.g. `line-tables-only` or `none`.
+    pub fn debug(mut self, debug: impl Into<String>) -> Self {
+        self.debug = Some(debug.into());
+        self
+    }
+
+    /// Generates code with the cranelift backend instead of LLVM, which is
+    /// faster at the cost of slower code.
+    ///
+    /// Cranelift is only available on nightly, with the
+    /// `rustc-codegen-cranelift-preview` component installed, so cargo is
+    /// run with `+nightly`.
+    pub fn cranelift(mut self) -> Self {
+        self.cranelift = true;
+        self
+    }
+
+    /// The name of the profile.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The arguments selecting the profile and passing its settings, placed
+    /// after the cargo subcommand.
+    fn cargo_args(&self) -> Vec<String> {
+        let config = |key: &str, value: &str| {
+            [
+                "--config".to_string(),
+                format!("profile.{}.{}={}", self.name, key, value),
+            ]
+        };
+
+        let mut args = vec!["--profile".to_string(), self.name.clone()];
+        if let Some(inherits) = &self.inherits {
+            args.extend(config("inherits", &format!("\"{}\"", inherits)));
+        }
+        if let Some(opt_level) = &self.opt_level {
+            // `s` and `z` are strings, the levels are numbers.
+            let value = match opt_level.parse::<u8>() {
+                Ok(level) => level.to_string(),
+                Err(_) => format!("\"{}\"", opt_level),
+            };
+            args.extend(config("opt-level", &value));
+        }
+        if let Some(debug) = &self.debug {
+            args.extend(config("debug", &format!("\"{}\"", debug)));
+        }
+        if self.cranelift {
+            args.push("-Zcodegen-backend".to_string());
+            args.extend(config("codegen-backend", "\"cranelift\""));
+        }
+        args
+    }
+}
+
+/// Watches the sources described by `config`, rebuilds the core with cargo
+/// after every change and yields `ShellMessage::Building` when a build starts
+/// and `ShellMessage::BuildFinished` when it's done.
+///
+/// A build only counts as successful if cargo succeeded and produced the
+/// `cdylib` artifact of the package, so the shell never reloads a library
+/// left over from an earlier build. The artifact is marked ready afterwards.
+pub fn run<M: Send + 'static>(config: BuildConfig) -> impl Stream<Item = ShellMessage<M>> {
+    stream::channel(16, move |mut output| async move {
+        let (build_tx, mut build_rx) = unbounded();
+
+        let _watcher = match create_watcher(&config, build_tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Failed to watch {}: {}", config.src_dir.display(), e);
+                return;
+            }
+        };
+
+        while let Some(message) = build_rx.next().await {
+            if output.send(message).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Creates the native watcher and spawns the thread running the builds.
+fn create_watcher<M: Send + 'static>(
+    config: &BuildConfig,
+    output: UnboundedSender<ShellMessage<M>>,
+) -> notify::Result<notify::RecommendedWatcher> {
+    let (events_tx, events_rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events_tx)?;
+    watcher.watch(&config.src_dir, RecursiveMode::Recursive)?;
+
+    trace!("Watching {} for source changes", config.src_dir.display());
+
+    let config = config.clone();
+    thread::spawn(move || build_on_change(&config, events_rx, output));
+
+    Ok(watcher)
+}
+
+/// Runs a build after every burst of changes to the sources.
+///
+/// Changes made while a build is running are picked up by the next one.
+fn build_on_change<M>(
+    config: &BuildConfig,
+    events: Receiver<notify::Result<Event>>,
+    output: UnboundedSender<ShellMessage<M>>,
+) {
+    loop {
+        match events.recv() {
+            Ok(Ok(event)) if modifies(&event) => (),
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => {
+                error!("File watcher error: {}", e);
+                continue;
+            }
+            Err(_) => return,
+        }
+
+        // Wait until the editor stops writing.
+        loop {
+            match events.recv_timeout(DEBOUNCE) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        if output.unbounded_send(ShellMessage::Building).is_err() {
+            return;
+        }
+
+        let result = build(config);
+        if output
+            .unbounded_send(ShellMessage::BuildFinished(result))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Whether `event` changed the contents of the watched directory.
+fn modifies(event: &Event) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any
+    )
+}
+
+/// Builds the package of `config` with its profile, or the profile of the
+/// shell, and waits for cargo to finish, collecting the compiler messages of
+/// a failed build.
+///
+/// With the `dynamic-iced` feature, the core is built as a `dylib` linking
+/// the shared iced of the shell, since a `cdylib` can't link Rust dylibs.
+fn build(config: &BuildConfig) -> Result<(), Vec<Diagnostic>> {
+    let package = config.package.as_str();
+    trace!("Building {}", package);
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    if config
+        .profile
+        .as_ref()
+        .is_some_and(|profile| profile.cranelift)
+    {
+        command.arg("+nightly");
+    }
+    if cfg!(feature = "dynamic-iced") {
+        command.args([
+            "rustc",
+            "-p",
+            package,
+            "--lib",
+            "--crate-type",
+            "dylib",
+            "--features",
+            "hot_reload_interface/dynamic-iced",
+        ]);
+    } else {
+        command.args(["build", "-p", package]);
+    }
+    command.arg("--message-format=json").stdout(Stdio::piped());
+    match &config.profile {
+        Some(profile) => {
+            command.args(profile.cargo_args());
+        }
+        None if !cfg!(debug_assertions) => {
+            command.arg("--release");
+        }
+        None => (),
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| vec![Diagnostic::error(format!("Failed to run cargo: {}", e))])?;
+
+    let mut diagnostics = Vec::new();
+    let mut cdylibs = Vec::new();
+    let mut succeeded = false;
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+
+            match message["reason"].as_str() {
+                Some("compiler-artifact") if is_cdylib_of(&message, package) => {
+                    cdylibs.extend(dynamic_libraries(&message));
+                }
+                Some("compiler-message") => {
+                    diagnostics.extend(Diagnostic::from_compiler_message(&message["message"]));
+                }
+                Some("build-finished") => {
+                    succeeded = message["success"].as_bool().unwrap_or(false);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let status = child.wait().map_err(|e| {
+        vec![Diagnostic::error(format!(
+            "Failed to wait for cargo: {}",
+            e
+        ))]
+    })?;
+
+    if !status.success() || !succeeded {
+        if !diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.level == DiagnosticLevel::Error)
+        {
+            diagnostics.push(Diagnostic::error(format!(
+                "Building {} failed, see the terminal for details",
+                package
+            )));
+        }
+        return Err(diagnostics);
+    }
+
+    if cdylibs.is_empty() {
+        return Err(vec![Diagnostic::error(format!(
+            "Building {} produced no cdylib",
+            package
+        ))]);
+    }
+
+    // A build to another profile directory than the one the shell loads
+    // from would never be reloaded.
+    if let Some(lib_path) = &config.lib_path
+        && !cdylibs.iter().any(|cdylib| same_file(cdylib, lib_path))
+    {
+        return Err(vec![Diagnostic::error(format!(
+            "Building {} produced {}, but the shell loads {}",
+            package,
+            cdylibs[0].display(),
+            lib_path.display()
+        ))]);
+    }
+
+    for cdylib in &cdylibs {
+        if let Err(e) = ready::mark_ready(cdylib) {
+            error!("Failed to mark {} ready: {}", cdylib.display(), e);
+        }
+    }
+
+    trace!("Built {}", package);
+    Ok(())
+}
+
+/// Whether `name` is a profile cargo defines itself.
+fn is_builtin(name: &str) -> bool {
+    matches!(name, "dev" | "release" | "test" | "bench")
+}
+
+/// Whether `a` and `b` are the same file, comparing them as given if either
+/// doesn't exist.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Returns the dynamic libraries among the files of the `compiler-artifact`
+/// `message`, leaving out import libraries and debug information.
+fn dynamic_libraries(message: &serde_json::Value) -> Vec<PathBuf> {
+    message["filenames"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|filename| filename.as_str())
+        .map(PathBuf::from)
+        .filter(|path| {
+            path.extension().and_then(|extension| extension.to_str())
+                == Some(std::env::consts::DLL_EXTENSION)
+        })
+        .collect()
+}
+
+/// Whether the `compiler-artifact` `message` is the dynamic library of
+/// `package`.
+fn is_cdylib_of(message: &serde_json::Value, package: &str) -> bool {
+    let target = &message["target"];
+
+    // Cargo normalizes dashes in target names.
+    let name_matches = target["name"]
+        .as_str()
+        .is_some_and(|name| name == package.replace('-', "_") || name == package);
+    let is_cdylib = target["kind"]
+        .as_array()
+        .is_some_and(|kinds| kinds.iter().any(|kind| kind == "cdylib" || kind == "dylib"));
+
+    name_matches && is_cdylib
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compiler_messages_point_at_their_primary_span() {
+        let message = json!({
+            "level": "error",
+            "message": "cannot find value `x` in this scope",
+            "spans": [
+                {
+                    "file_name": "src/macros.rs",
+                    "line_start": 3,
+                    "column_start": 1,
+                    "is_primary": false
+                },
+                {
+                    "file_name": "src/lib.rs",
+                    "line_start": 12,
+                    "column_start": 5,
+                    "is_primary": true
+                }
+            ]
+        });
+
+        let diagnostic = Diagnostic::from_compiler_message(&message).unwrap();
+
+        assert_eq!(diagnostic.level, DiagnosticLevel::Error);
+        assert_eq!(diagnostic.message, "cannot find value `x` in this scope");
+        assert_eq!(diagnostic.file.as_deref(), Some("src/lib.rs"));
+        assert_eq!((diagnostic.line, diagnostic.column), (12, 5));
+    }
+
+    #[test]
+    fn compiler_messages_without_spans_have_no_position() {
+        let message = json!({
+            "level": "warning",
+            "message": "unused manifest key",
+            "spans": []
+        });
+
+        let diagnostic = Diagnostic::from_compiler_message(&message).unwrap();
+
+        assert_eq!(diagnostic.level, DiagnosticLevel::Warning);
+        assert_eq!(diagnostic.file, None);
+        assert_eq!((diagnostic.line, diagnostic.column), (0, 0));
+        assert!(Diagnostic::from_compiler_message(&json!({ "message": "no level" })).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::message::ShellMessage;
+use hot_reload_interface::{CommandResult, CoreCommand};
+use iced::{window, Size, Task};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// Turns the commands returned by the core into a task of the shell.
+pub(crate) fn into_task<M>(commands: Vec<CoreCommand<M>>) -> Task<ShellMessage<M>>
+where
+    M: Send + 'static,
+{
+    Task::batch(commands.into_iter().map(command_task))
+}
+
+fn command_task<M: Send + 'static>(command: CoreCommand<M>) -> Task<ShellMessage<M>> {
+    match command {
+        CoreCommand::Message { message } => Task::done(ShellMessage::App(message)),
+        CoreCommand::After { delay_ms, message } => Task::future(async move {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            ShellMessage::App(message)
+        }),
+        CoreCommand::CloseWindow => window::get_latest().and_then(window::close),
+        CoreCommand::OpenWindow {
+            name,
+            width,
+            height,
+        } => Task::done(ShellMessage::OpenWindow {
+            name: name.as_str().to_string(),
+            size: Size::new(width, height),
+        }),
+        CoreCommand::CloseNamedWindow { name } => {
+            Task::done(ShellMessage::CloseWindow(name.as_str().to_string()))
+        }
+        CoreCommand::CopyToClipboard { text } => iced::clipboard::write(text),
+        CoreCommand::OpenUrl { url } => {
+            open_url(url);
+            Task::none()
+        }
+        CoreCommand::PickFile {
+            request,
+            title,
+            extensions,
+        } => Task::perform(pick_file(title, extensions), move |result| {
+            ShellMessage::CommandResult {
+                request: request.as_str().to_string(),
+                result,
+            }
+        }),
+        CoreCommand::Exit => iced::exit(),
+    }
+}
+
+/// Opens `url` with the opener of the platform, without waiting for it.
+fn open_url(url: String) {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(&url);
+
+    thread::spawn(move || match command.status() {
+        Ok(status) if status.success() => log::trace!("Opened {}", url),
+        Ok(status) => log::error!("Failed to open {}: {}", url, status),
+        Err(e) => log::error!("Failed to open {}: {}", url, e),
+    });
+}
+
+/// Shows the file dialog of [`CoreCommand::PickFile`].
+async fn pick_file(title: String, extensions: String) -> CommandResult {
+    let extensions: Vec<&str> = extensions
+        .split(',')
+        .map(str::trim)
+        .filter(|extension| !extension.is_empty())
+        .collect();
+
+    let mut dialog = rfd::AsyncFileDialog::new().set_title(title);
+    if !extensions.is_empty() {
+        dialog = dialog.add_filter(extensions.join(", "), &extensions);
+    }
+
+    match dialog.pick_file().await {
+        Some(file) => CommandResult::FilePicked {
+            path: file.path().to_string_lossy().into_owned(),
+        },
+        None => CommandResult::Cancelled,
+    }
+}
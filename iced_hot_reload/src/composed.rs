@@ -0,0 +1,424 @@
+use crate::backend::{CoreInstance, LoaderBackend};
+use crate::commands;
+use crate::loader::LibInfo;
+use crate::message::ShellMessage;
+use crate::shell::{notice_banner, panic_view};
+use crate::subscriptions;
+use crate::watcher;
+use hot_reload_interface::ReloadInfo;
+use iced::time::{every, Duration};
+use iced::widget::{button, column, container, text, Column, Row};
+use iced::{Color, Element, Length, Subscription, Task};
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+/// A core of a [`ComposedShell`], see [`crate::HotReloadBuilder::core`].
+pub(crate) struct CoreConfig {
+    /// The name the core is shown and routed by.
+    pub(crate) name: String,
+
+    /// Metadata and symbol names used to identify and load the library.
+    pub(crate) lib_info: LibInfo,
+}
+
+/// How a [`ComposedShell`] arranges the views of its cores.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoreLayout {
+    /// Side by side, sharing the width of the window.
+    #[default]
+    Row,
+
+    /// Stacked, sharing the height of the window.
+    Column,
+
+    /// One core at a time, the one of the current route, chosen from a tab
+    /// bar listing the cores by name. The hidden cores stay loaded and keep
+    /// their state, but their subscriptions are paused.
+    Routed,
+}
+
+/// Messages handled by a [`ComposedShell`].
+#[derive(Debug, Clone)]
+pub enum ComposedMessage<M> {
+    /// A message for the core at the index, e.g. a message of its view or a
+    /// change of its library.
+    Core(usize, ShellMessage<M>),
+
+    /// Unloads the libraries replaced by the last reloads, once the UI was
+    /// rebuilt without them.
+    ReleaseRetired,
+
+    /// Shows the core with the name, in the [`CoreLayout::Routed`] layout.
+    Navigate(String),
+}
+
+/// A core loaded by a [`ComposedShell`], reloaded independently of the
+/// others.
+struct ComposedCore<M, B: LoaderBackend<M>> {
+    name: String,
+
+    /// The current core instance, declared before the library so it is
+    /// destroyed before the library is unloaded.
+    instance: B::Instance,
+
+    /// The currently loaded library.
+    lib: B::Library,
+
+    /// Loads the library of this core.
+    backend: B,
+
+    /// The library file watched for changes.
+    lib_path: PathBuf,
+
+    /// The message of the panic raised by the core, if any.
+    panic: RefCell<Option<String>>,
+
+    /// A notice about a failed reload.
+    notice: Option<String>,
+
+    /// How many times the core was reloaded, see [`ReloadInfo`].
+    reloads: u32,
+}
+
+/// A shell composing the views of several cores, each loaded from its own
+/// library, e.g. one per screen of a large application.
+///
+/// Every library is watched on its own and only the core whose library
+/// changed is reloaded, keeping the state of the others untouched. All cores
+/// share the message type `M`, messages of a core's view are routed back to
+/// that core.
+pub struct ComposedShell<M, B: LoaderBackend<M>> {
+    /// The window title.
+    title: String,
+
+    /// How the views of the cores are arranged.
+    layout: CoreLayout,
+
+    /// Polls the library files with this interval instead of watching them.
+    poll_interval: Option<Duration>,
+
+    /// The cores, in the order they were registered.
+    cores: Vec<ComposedCore<M, B>>,
+
+    /// The index of the core shown in the [`CoreLayout::Routed`] layout.
+    route: usize,
+
+    /// Libraries replaced by a reload, with the index of their core, kept
+    /// loaded until iced has rebuilt the UI without them.
+    retired: Vec<(usize, B::Library)>,
+}
+
+impl<M, B> ComposedShell<M, B>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+    B: LoaderBackend<M>,
+{
+    /// Loads every core with the backend created for it by `backend` and
+    /// instantiates it with its default state. Fails if any of them can't
+    /// be loaded or started.
+    pub(crate) fn new(
+        title: String,
+        layout: CoreLayout,
+        poll_interval: Option<Duration>,
+        cores: Vec<CoreConfig>,
+        backend: impl Fn(&LibInfo) -> B,
+    ) -> std::io::Result<Self> {
+        let cores = cores
+            .into_iter()
+            .map(|config| {
+                let mut backend = backend(&config.lib_info);
+
+                log::trace!("Initial load of the core {}", config.name);
+                let (lib, instance) = backend
+                    .load()
+                    .and_then(|lib| {
+                        backend
+                            .instantiate(&lib, &[])
+                            .map(|instance| (lib, instance))
+                    })
+                    .map_err(|e| {
+                        std::io::Error::new(
+                            e.kind(),
+                            format!("Failed to load the core {}: {}", config.name, e),
+                        )
+                    })?;
+
+                Ok(ComposedCore {
+                    name: config.name,
+                    instance,
+                    lib,
+                    backend,
+                    lib_path: config.lib_info.path,
+                    panic: RefCell::new(None),
+                    notice: None,
+                    reloads: 0,
+                })
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(Self {
+            title,
+            layout,
+            poll_interval,
+            cores,
+            route: 0,
+            retired: Vec::new(),
+        })
+    }
+
+    pub fn title(&self) -> String {
+        self.title.clone()
+    }
+
+    pub fn update(&mut self, message: ComposedMessage<M>) -> Task<ComposedMessage<M>> {
+        let (index, message) = match message {
+            ComposedMessage::ReleaseRetired => {
+                for (index, lib) in self.retired.drain(..) {
+                    self.cores[index].backend.unload(lib);
+                }
+                return Task::none();
+            }
+            ComposedMessage::Navigate(name) => {
+                match self.cores.iter().position(|core| core.name == name) {
+                    Some(index) => self.route = index,
+                    None => log::error!("No core for the route {}", name),
+                }
+                return Task::none();
+            }
+            ComposedMessage::Core(index, message) => (index, message),
+        };
+
+        let Some(core) = self.cores.get_mut(index) else {
+            return Task::none();
+        };
+
+        match message {
+            ShellMessage::Tick if core.backend.changed_since(&core.lib) => {
+                return self.reload(index);
+            }
+            ShellMessage::LibraryChanged | ShellMessage::Reload => {
+                return self.reload(index);
+            }
+            ShellMessage::KeyPressed(key) => {
+                if core.panic.borrow().is_some() {
+                    return Task::none();
+                }
+
+                match core.instance.subscriptions() {
+                    Ok(core_subscriptions) => {
+                        if let Some(message) = subscriptions::key_binding(&core_subscriptions, &key)
+                        {
+                            return Task::done(ComposedMessage::Core(
+                                index,
+                                ShellMessage::App(message),
+                            ));
+                        }
+                    }
+                    Err(panic) => core.set_panic(panic),
+                }
+            }
+            ShellMessage::App(message) => {
+                if core.panic.borrow().is_some() {
+                    return Task::none();
+                }
+
+                match core.instance.update(message) {
+                    Ok(core_commands) => {
+                        return commands::into_task(core_commands)
+                            .map(move |message| ComposedMessage::Core(index, message));
+                    }
+                    Err(panic) => core.set_panic(panic),
+                }
+            }
+            ShellMessage::CommandResult { request, result } => {
+                if core.panic.borrow().is_some() {
+                    return Task::none();
+                }
+
+                match core.instance.command_result(&request, &result) {
+                    Ok(Some(message)) => {
+                        return Task::done(ComposedMessage::Core(
+                            index,
+                            ShellMessage::App(message),
+                        ));
+                    }
+                    Ok(None) => (),
+                    Err(panic) => core.set_panic(panic),
+                }
+            }
+            _ => (),
+        }
+
+        Task::none()
+    }
+
+    /// Loads the changed library of the core at `index` and swaps it in,
+    /// keeping the current version if that fails.
+    fn reload(&mut self, index: usize) -> Task<ComposedMessage<M>> {
+        let core = &mut self.cores[index];
+        log::trace!("Reload the core {}", core.name);
+
+        let state = match core.instance.save_state() {
+            Ok(state) => state,
+            Err(e) => {
+                log::error!("{}", e);
+                Vec::new()
+            }
+        };
+
+        let lib = match core.backend.load() {
+            Ok(lib) => lib,
+            Err(e) => {
+                core.notice = Some(format!(
+                    "Reload failed: {}. Keeping the current version.",
+                    e
+                ));
+                return Task::none();
+            }
+        };
+
+        let instance = match core
+            .backend
+            .self_test(&lib)
+            .and_then(|()| core.backend.migrate(&lib, &core.lib, &state))
+            .and_then(|state| core.backend.instantiate(&lib, &state))
+        {
+            Ok(instance) => instance,
+            Err(e) => {
+                core.backend.unload(lib);
+                core.notice = Some(format!(
+                    "Reload failed: {}. Keeping the current version.",
+                    e
+                ));
+                return Task::none();
+            }
+        };
+
+        drop(std::mem::replace(&mut core.instance, instance));
+        let old_lib = std::mem::replace(&mut core.lib, lib);
+        *core.panic.borrow_mut() = None;
+        core.notice = None;
+        core.reloads += 1;
+        let reload = ReloadInfo::new(
+            core.reloads,
+            false,
+            core.backend.built_at(&core.lib),
+            core.backend.built_at(&old_lib),
+        );
+        let after_reload = match core.instance.after_reload(&reload) {
+            Ok(core_commands) => commands::into_task(core_commands)
+                .map(move |message| ComposedMessage::Core(index, message)),
+            Err(panic) => {
+                core.set_panic(panic);
+                Task::none()
+            }
+        };
+        self.retired.push((index, old_lib));
+
+        Task::batch([after_reload, Task::done(ComposedMessage::ReleaseRetired)])
+    }
+
+    pub fn view(&self) -> Element<'_, ComposedMessage<M>> {
+        let panes = self.cores.iter().enumerate().map(|(index, core)| {
+            core.view()
+                .map(move |message| ComposedMessage::Core(index, message))
+        });
+
+        match self.layout {
+            CoreLayout::Row => Row::with_children(panes).spacing(1).into(),
+            CoreLayout::Column => Column::with_children(panes).spacing(1).into(),
+            CoreLayout::Routed => self.routed_view(),
+        }
+    }
+
+    /// Renders the tab bar of the routes above the core of the current one.
+    fn routed_view(&self) -> Element<'_, ComposedMessage<M>> {
+        let tabs = self.cores.iter().enumerate().map(|(index, core)| {
+            button(text(core.name.clone()).size(12))
+                .on_press_maybe(
+                    (index != self.route).then(|| ComposedMessage::Navigate(core.name.clone())),
+                )
+                .into()
+        });
+
+        let route = self.route;
+        let content = match self.cores.get(route) {
+            Some(core) => core
+                .view()
+                .map(move |message| ComposedMessage::Core(route, message)),
+            None => text("No cores").into(),
+        };
+
+        column![Row::with_children(tabs).spacing(4).padding(4), content].into()
+    }
+
+    pub fn subscription(&self) -> Subscription<ComposedMessage<M>> {
+        Subscription::batch(self.cores.iter().enumerate().map(|(index, core)| {
+            let library_changes = match self.poll_interval {
+                Some(interval) => every(interval).map(|_| ShellMessage::Tick),
+                None => Subscription::run_with_id(
+                    core.lib_path.clone(),
+                    watcher::watch(core.lib_path.clone()),
+                ),
+            };
+
+            let hidden = self.layout == CoreLayout::Routed && index != self.route;
+            let core_subscriptions = if hidden || core.panic.borrow().is_some() {
+                Subscription::none()
+            } else {
+                match core.instance.subscriptions() {
+                    Ok(core_subscriptions) => subscriptions::materialize(&core_subscriptions),
+                    Err(panic) => {
+                        core.set_panic(panic);
+                        Subscription::none()
+                    }
+                }
+            };
+
+            Subscription::batch([library_changes, core_subscriptions])
+                .with(index)
+                .map(|(index, message)| ComposedMessage::Core(index, message))
+        }))
+    }
+}
+
+impl<M, B> ComposedCore<M, B>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+    B: LoaderBackend<M>,
+{
+    /// Renders the core under its name, or the panic it raised.
+    fn view(&self) -> Element<'_, ShellMessage<M>> {
+        let content = if let Some(panic) = self.panic.borrow().as_ref() {
+            panic_view(panic)
+        } else {
+            match self.instance.view() {
+                Ok(view) => view.map(ShellMessage::App),
+                Err(panic) => {
+                    let view = panic_view(&panic);
+                    self.set_panic(panic);
+                    view
+                }
+            }
+        };
+
+        let mut pane = column![text(self.name.clone())
+            .size(12)
+            .color(Color::from_rgb(0.4, 0.4, 0.4))]
+        .padding(4);
+        if let Some(notice) = &self.notice {
+            pane = pane.push(notice_banner(notice));
+        }
+
+        container(pane.push(container(content).height(Length::Fill)))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// Records that the core panicked with `panic`.
+    fn set_panic(&self, panic: String) {
+        log::error!("The core {} panicked: {}", self.name, panic);
+        *self.panic.borrow_mut() = Some(panic);
+    }
+}
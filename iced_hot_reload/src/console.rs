@@ -0,0 +1,113 @@
+use crate::message::ShellMessage;
+use crate::recording::DeserializeFn;
+use iced::widget::{button, column, row, text, text_input};
+use iced::{Color, Element};
+use serde_json::Value;
+
+/// A console in the dev overlay dispatching messages typed in by the user,
+/// so update paths without a UI trigger can be exercised.
+///
+/// A message is given by the name of its variant and, unless it is a unit
+/// variant, its payload as JSON, e.g. `SetCounter` and `42`.
+pub(crate) struct MessageConsole<M> {
+    /// The name of the variant.
+    name: String,
+
+    /// The JSON payload of the variant, empty for unit variants.
+    payload: String,
+
+    /// Why the last message couldn't be constructed.
+    error: Option<String>,
+
+    /// Converts the JSON representation to a message.
+    deserialize: DeserializeFn<M>,
+}
+
+impl<M> MessageConsole<M> {
+    pub(crate) fn new(deserialize: DeserializeFn<M>) -> Self {
+        Self {
+            name: String::new(),
+            payload: String::new(),
+            error: None,
+            deserialize,
+        }
+    }
+
+    pub(crate) fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub(crate) fn set_payload(&mut self, payload: String) {
+        self.payload = payload;
+    }
+
+    /// Constructs the message typed in, or records why it couldn't be.
+    pub(crate) fn message(&mut self) -> Option<M> {
+        match self.construct() {
+            Ok(message) => {
+                self.error = None;
+                Some(message)
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Deserializes the message from its externally tagged representation,
+    /// the default of serde for enums.
+    fn construct(&self) -> Result<M, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Enter the name of a message".to_string());
+        }
+
+        let message = match self.payload.trim() {
+            "" => Value::String(name.to_string()),
+            payload => {
+                let payload: Value =
+                    serde_json::from_str(payload).map_err(|e| format!("Invalid payload: {}", e))?;
+                Value::Object([(name.to_string(), payload)].into_iter().collect())
+            }
+        };
+
+        self.parse(message)
+    }
+
+    /// Deserializes a message given in its JSON representation, e.g. sent
+    /// to the control server.
+    pub(crate) fn parse(&self, message: Value) -> Result<M, String> {
+        (self.deserialize)(message).map_err(|e| format!("Invalid message: {}", e))
+    }
+
+    /// Renders the inputs of the console.
+    pub(crate) fn view(&self) -> Element<'_, ShellMessage<M>>
+    where
+        M: Clone + 'static,
+    {
+        let mut layout = column![row![
+            text_input("Message, e.g. Increment", &self.name)
+                .on_input(ShellMessage::ConsoleNameChanged)
+                .on_submit(ShellMessage::SendConsoleMessage)
+                .size(12),
+            text_input("JSON payload", &self.payload)
+                .on_input(ShellMessage::ConsolePayloadChanged)
+                .on_submit(ShellMessage::SendConsoleMessage)
+                .size(12),
+            button(text("Send").size(12)).on_press(ShellMessage::SendConsoleMessage),
+        ]
+        .spacing(4)]
+        .spacing(4);
+
+        if let Some(e) = &self.error {
+            layout = layout.push(
+                text(e.clone())
+                    .size(12)
+                    .color(Color::from_rgb(0.9, 0.4, 0.4)),
+            );
+        }
+
+        layout.into()
+    }
+}
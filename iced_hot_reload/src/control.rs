@@ -0,0 +1,314 @@
+use crate::logs;
+use crate::message::ShellMessage;
+use iced::futures::channel::mpsc::{unbounded, UnboundedSender};
+use iced::futures::{SinkExt, StreamExt};
+use iced::{stream, Subscription};
+use log::{error, trace, LevelFilter};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// An event reported to the clients of `GET /events` and written to the
+/// event log.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum ControlEvent {
+    /// A new version of the library was detected.
+    ChangeDetected,
+
+    /// Loading the new core started, `attempt` counting from 1.
+    ReloadStarted { attempt: u32 },
+
+    /// An attempt to load the new core failed. It is retried unless a
+    /// [`ControlEvent::ReloadFailed`] follows.
+    LoadFailed { attempt: u32, error: String },
+
+    /// The new core was swapped in, `duration_ms` after loading it started.
+    Reloaded { duration_ms: u64 },
+
+    /// Loading the new core failed, the current one is kept.
+    ReloadFailed { error: String },
+
+    /// The new core panicked and the previous one was restored.
+    RolledBack { panic: String },
+
+    /// The core panicked.
+    Panicked { message: String },
+
+    /// A build of the core started.
+    BuildStarted,
+
+    /// A build of the core failed.
+    BuildFailed { error: Option<String> },
+
+    /// Automatic reloading was paused or resumed.
+    AutoReload { enabled: bool },
+
+    /// A message sent to `POST /message` was handled by the core.
+    MessageHandled,
+
+    /// A message sent to `POST /message` couldn't be constructed.
+    MessageRejected { error: String },
+}
+
+/// The largest request body accepted, as a state sent to `PUT /state`.
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+
+/// What the shell shares with the connections of the control server.
+#[derive(Default)]
+struct Hub {
+    /// The last state published by the shell, serialized.
+    state: Vec<u8>,
+
+    /// The connections streaming events.
+    listeners: Vec<Sender<ControlEvent>>,
+}
+
+/// A local HTTP server letting editor plugins and scripts drive the shell.
+///
+/// * `POST /reload` reloads the library
+/// * `POST /pause` and `POST /resume` pause and resume automatic reloading
+/// * `GET /state` returns the state of the core as JSON
+/// * `PUT /state` replaces the state of the core with the JSON body
+/// * `POST /message` passes the message in the JSON body to the core
+/// * `GET /log-levels` returns the log level of each target prefix, e.g.
+///   `{"app_core": "TRACE", "*": "OFF"}`, `*` standing for all other targets
+/// * `PUT /log-levels` changes the log levels given in the same form
+/// * `GET /events` streams [`ControlEvent`]s as server-sent events
+pub(crate) struct ControlServer {
+    addr: SocketAddr,
+    hub: Arc<Mutex<Hub>>,
+}
+
+impl ControlServer {
+    pub(crate) fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            hub: Arc::default(),
+        }
+    }
+
+    /// Makes `state` the state returned by `GET /state`.
+    pub(crate) fn set_state(&self, state: Vec<u8>) {
+        if let Ok(mut hub) = self.hub.lock() {
+            hub.state = state;
+        }
+    }
+
+    /// Sends `event` to the clients of `GET /events`.
+    pub(crate) fn emit(&self, event: ControlEvent) {
+        if let Ok(mut hub) = self.hub.lock() {
+            hub.listeners
+                .retain(|listener| listener.send(event.clone()).is_ok());
+        }
+    }
+
+    /// Runs the server, yielding the messages requested by its clients.
+    pub(crate) fn subscription<M: Send + 'static>(&self) -> Subscription<ShellMessage<M>> {
+        let addr = self.addr;
+        let hub = self.hub.clone();
+
+        Subscription::run_with_id(
+            ("control", addr),
+            stream::channel(16, move |mut output| async move {
+                let (request_tx, mut request_rx) = unbounded();
+                thread::spawn(move || serve(addr, hub, request_tx));
+
+                while let Some(message) = request_rx.next().await {
+                    if output.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }),
+        )
+    }
+}
+
+/// Accepts clients, each handled by its own thread.
+fn serve<M: Send + 'static>(
+    addr: SocketAddr,
+    hub: Arc<Mutex<Hub>>,
+    output: UnboundedSender<ShellMessage<M>>,
+) {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start the control server on {}: {}", addr, e);
+            return;
+        }
+    };
+
+    trace!("Control server listening on http://{}", addr);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let hub = hub.clone();
+                let output = output.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream, &hub, &output) {
+                        trace!("Control connection closed: {}", e);
+                    }
+                });
+            }
+            Err(e) => error!("Failed to accept a control client: {}", e),
+        }
+    }
+}
+
+/// Answers a single HTTP request.
+fn handle<M>(
+    mut stream: TcpStream,
+    hub: &Mutex<Hub>,
+    output: &UnboundedSender<ShellMessage<M>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Only the length of the body matters to the endpoints.
+    let mut content_length = 0;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+        header.clear();
+    }
+
+    if content_length > MAX_BODY_LEN {
+        return respond(&mut stream, "413 Payload Too Large", b"{\"ok\":false}");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let message = match (method, path) {
+        ("POST", "/reload") => ShellMessage::Reload,
+        ("POST", "/pause") => ShellMessage::SetAutoReload(false),
+        ("POST", "/resume") => ShellMessage::SetAutoReload(true),
+        ("GET", "/state") => {
+            let state = hub.lock().map(|hub| hub.state.clone()).unwrap_or_default();
+            let body = if state.is_empty() {
+                b"null".to_vec()
+            } else {
+                state
+            };
+            return respond(&mut stream, "200 OK", &body);
+        }
+        ("PUT", "/state") => {
+            if serde_json::from_slice::<serde_json::Value>(&body).is_err() {
+                return respond(&mut stream, "400 Bad Request", b"{\"ok\":false}");
+            }
+            ShellMessage::ReplaceState(body)
+        }
+        ("POST", "/message") => match serde_json::from_slice(&body) {
+            Ok(message) => ShellMessage::ControlMessage(message),
+            Err(_) => return respond(&mut stream, "400 Bad Request", b"{\"ok\":false}"),
+        },
+        ("GET", "/log-levels") => {
+            let levels: serde_json::Map<String, serde_json::Value> = logs::target_levels()
+                .into_iter()
+                .map(|(prefix, level)| (prefix_key(prefix), level.to_string().into()))
+                .collect();
+            let body = serde_json::to_vec(&levels).unwrap_or_default();
+            return respond(&mut stream, "200 OK", &body);
+        }
+        ("PUT", "/log-levels") => {
+            let Some(levels) = parse_levels(&body) else {
+                return respond(&mut stream, "400 Bad Request", b"{\"ok\":false}");
+            };
+            for (prefix, level) in levels {
+                logs::set_target_level(prefix, level);
+            }
+            return respond(&mut stream, "200 OK", b"{\"ok\":true}");
+        }
+        ("GET", "/events") => return stream_events(stream, hub),
+        (
+            _,
+            "/reload" | "/pause" | "/resume" | "/state" | "/message" | "/log-levels" | "/events",
+        ) => {
+            return respond(&mut stream, "405 Method Not Allowed", b"{\"ok\":false}");
+        }
+        _ => return respond(&mut stream, "404 Not Found", b"{\"ok\":false}"),
+    };
+
+    if output.unbounded_send(message).is_err() {
+        return respond(&mut stream, "503 Service Unavailable", b"{\"ok\":false}");
+    }
+
+    respond(&mut stream, "202 Accepted", b"{\"ok\":true}")
+}
+
+/// Names the empty prefix, which applies to all other targets, `*`.
+fn prefix_key(prefix: String) -> String {
+    if prefix.is_empty() {
+        "*".to_string()
+    } else {
+        prefix
+    }
+}
+
+/// Parses a JSON object mapping target prefixes to levels, e.g.
+/// `{"app_core": "debug"}`, or returns `None` if any level is invalid.
+fn parse_levels(body: &[u8]) -> Option<Vec<(String, LevelFilter)>> {
+    let levels: HashMap<String, String> = serde_json::from_slice(body).ok()?;
+
+    levels
+        .into_iter()
+        .map(|(prefix, level)| {
+            let prefix = if prefix == "*" { String::new() } else { prefix };
+            level.parse().ok().map(|level| (prefix, level))
+        })
+        .collect()
+}
+
+/// Writes a JSON response and closes the connection.
+fn respond(stream: &mut TcpStream, status: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Streams the events emitted by the shell until the client disconnects.
+fn stream_events(mut stream: TcpStream, hub: &Mutex<Hub>) -> io::Result<()> {
+    let events = subscribe(hub);
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    stream.flush()?;
+
+    for event in events {
+        let data = serde_json::to_string(&event).map_err(io::Error::other)?;
+        write!(stream, "data: {}\n\n", data)?;
+        stream.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Registers a new listener for the events emitted by the shell.
+fn subscribe(hub: &Mutex<Hub>) -> Receiver<ControlEvent> {
+    let (events_tx, events_rx) = channel();
+    if let Ok(mut hub) = hub.lock() {
+        hub.listeners.push(events_tx);
+    }
+
+    events_rx
+}
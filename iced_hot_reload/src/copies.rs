@@ -0,0 +1,143 @@
+use crate::debug_info;
+use log::{error, trace};
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A copy of the core library made for loading, deleted when dropped
+/// together with the debug information staged for it.
+///
+/// It must be dropped after the `Library` opened from it, which Windows
+/// keeps locked while it is loaded.
+pub(crate) struct LibraryCopy {
+    path: PathBuf,
+    debug_info: Vec<PathBuf>,
+
+    /// Whether the copy is left on disk when dropped.
+    kept: bool,
+}
+
+impl LibraryCopy {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            debug_info: Vec::new(),
+            kept: false,
+        }
+    }
+
+    /// Leaves the copy and its debug information on disk when dropped, for
+    /// tools symbolizing the library after it was replaced.
+    pub(crate) fn keep(&mut self) {
+        self.kept = true;
+    }
+
+    /// Stages the debug information of `library`, which the copy was made
+    /// from, next to the copy, see [`debug_info::stage`].
+    pub(crate) fn stage_debug_info(&mut self, library: &Path) {
+        self.debug_info = debug_info::stage(library, &self.path);
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LibraryCopy {
+    fn drop(&mut self) {
+        if self.kept {
+            return;
+        }
+
+        trace!("Remove {}", self.path.display());
+        if let Err(e) = fs::remove_file(&self.path) {
+            error!("Failed to remove {}: {}", self.path.display(), e);
+        }
+
+        for path in &self.debug_info {
+            if let Err(e) = debug_info::remove(path) {
+                error!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Places a copy of the library at `source` at `destination`, which must not
+/// exist, as cheaply as the platform allows.
+///
+/// On Linux and the BSDs the copy is a hard link, since linkers and cargo
+/// replace the artifact rather than rewriting it, which leaves the linked
+/// file untouched. On Windows a loaded library locks its file, and on macOS
+/// the copy is code signed, so both would reach the original through a
+/// hard link; they get a real copy, which `fs::copy` makes a copy-on-write
+/// clone where the file system supports it, as it does on Linux when the
+/// hard link fails, e.g. because the copies are on another file system.
+pub(crate) fn stage(source: &Path, destination: &Path) -> Result<()> {
+    if cfg!(all(unix, not(target_os = "macos"))) {
+        match fs::hard_link(source, destination) {
+            Ok(()) => {
+                trace!("Linked {} to {}", destination.display(), source.display());
+                return Ok(());
+            }
+            Err(e) => trace!(
+                "Failed to link {} to {}, copying it: {}",
+                destination.display(),
+                source.display(),
+                e
+            ),
+        }
+    }
+
+    fs::copy(source, destination).map(drop)
+}
+
+/// Creates the directory the library copies of this session are placed in,
+/// `<temp>/iced_hot_reload/<lib_name>/<pid>_<start time>`.
+///
+/// Copies left behind by previous sessions, e.g. after a crash, are swept
+/// first if they are older than `retention`. Younger ones are kept, as they
+/// may belong to another shell which is still running.
+pub(crate) fn session_dir(lib_name: &str, retention: Duration) -> Result<PathBuf> {
+    let sessions_dir = std::env::temp_dir().join("iced_hot_reload").join(lib_name);
+    fs::create_dir_all(&sessions_dir)?;
+
+    sweep(&sessions_dir, retention);
+
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let session_dir = sessions_dir.join(format!("{}_{}", std::process::id(), started));
+    fs::create_dir_all(&session_dir)?;
+
+    trace!("Copying libraries to {}", session_dir.display());
+
+    Ok(session_dir)
+}
+
+/// Removes the session directories in `sessions_dir` which weren't modified
+/// for longer than `retention`.
+fn sweep(sessions_dir: &Path, retention: Duration) {
+    let entries = match fs::read_dir(sessions_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to read {}: {}", sessions_dir.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > retention);
+
+        if stale {
+            trace!("Remove stale copies in {}", entry.path().display());
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                error!("Failed to remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
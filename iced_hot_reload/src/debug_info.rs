@@ -0,0 +1,151 @@
+use log::{trace, warn};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+/// The signature of a CodeView record in the debug directory of a PE file,
+/// followed by the GUID, the age and the NUL terminated path of the PDB.
+const CODEVIEW_SIGNATURE: &[u8] = b"RSDS";
+
+/// Places the debug information of the library at `library` next to its
+/// copy at `copy`, so a debugger attached to the shell finds the symbols of
+/// the reloaded code, and returns the files placed.
+///
+/// On Windows the copy still names the PDB of the original library, which
+/// the next build overwrites, so the PDB is copied and the copy is patched
+/// to name it. On macOS the `.dSYM` bundle, and on Linux the `.dwp` package
+/// of split debug information, are found through the name of the library and
+/// copied alongside. Debug information embedded in the library needs nothing.
+///
+/// Missing debug information isn't an error, since release builds often
+/// come without it; failing to stage it is only logged, as the library works
+/// without it.
+pub(crate) fn stage(library: &Path, copy: &Path) -> Vec<PathBuf> {
+    let result = if cfg!(windows) {
+        stage_pdb(library, copy)
+    } else if cfg!(target_os = "macos") {
+        stage_sibling(library, copy, "dSYM")
+    } else {
+        stage_sibling(library, copy, "dwp")
+    };
+
+    match result {
+        Ok(staged) => staged.into_iter().collect(),
+        Err(e) => {
+            warn!(
+                "Failed to stage the debug information of {}, breakpoints in the reloaded code \
+                 may not resolve: {}",
+                library.display(),
+                e
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Removes debug information placed by [`stage`].
+pub(crate) fn remove(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Copies `app_core.pdb` next to the copy of `app_core.dll`, named after the
+/// copy, and points the copy at it.
+fn stage_pdb(library: &Path, copy: &Path) -> Result<Option<PathBuf>> {
+    let pdb = library.with_extension("pdb");
+    if !pdb.is_file() {
+        return Ok(None);
+    }
+
+    let staged_pdb = copy.with_extension("pdb");
+    fs::copy(&pdb, &staged_pdb)?;
+
+    // Only the file name is written, which the debugger looks up next to
+    // the library and which fits in place of the absolute path of the build.
+    let file_name = staged_pdb
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The PDB path isn't valid UTF-8"))?;
+    if let Err(e) = patch_pdb_path(copy, file_name) {
+        let _ = fs::remove_file(&staged_pdb);
+        return Err(e);
+    }
+
+    trace!("Staged {} as {}", pdb.display(), staged_pdb.display());
+    Ok(Some(staged_pdb))
+}
+
+/// Replaces the PDB path in the CodeView record of the PE file at `library`
+/// with `pdb_path`, padded with NULs to the length of the old path.
+fn patch_pdb_path(library: &Path, pdb_path: &str) -> Result<()> {
+    let mut bytes = fs::read(library)?;
+
+    // The GUID and the age precede the path.
+    let path_offset = CODEVIEW_SIGNATURE.len() + 16 + 4;
+    let record = bytes
+        .windows(CODEVIEW_SIGNATURE.len())
+        .enumerate()
+        .filter(|(_, window)| *window == CODEVIEW_SIGNATURE)
+        .map(|(start, _)| start + path_offset)
+        .find_map(|path_start| {
+            let length = bytes.get(path_start..)?.iter().position(|&b| b == 0)?;
+            let path = &bytes[path_start..path_start + length];
+            path.to_ascii_lowercase()
+                .ends_with(b".pdb")
+                .then_some((path_start, length))
+        })
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No CodeView record names a PDB"))?;
+
+    let (path_start, length) = record;
+    if pdb_path.len() > length {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is longer than the PDB path of the library", pdb_path),
+        ));
+    }
+
+    let path = &mut bytes[path_start..path_start + length];
+    path.fill(0);
+    path[..pdb_path.len()].copy_from_slice(pdb_path.as_bytes());
+
+    fs::write(library, bytes)
+}
+
+/// Copies `<library>.<extension>`, a file or a bundle, to
+/// `<copy>.<extension>` if it exists.
+fn stage_sibling(library: &Path, copy: &Path, extension: &str) -> Result<Option<PathBuf>> {
+    let with_extension = |path: &Path| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".");
+        name.push(extension);
+        PathBuf::from(name)
+    };
+
+    let source = with_extension(library);
+    if !source.exists() {
+        return Ok(None);
+    }
+
+    let staged = with_extension(copy);
+    copy_all(&source, &staged)?;
+
+    trace!("Staged {} as {}", source.display(), staged.display());
+    Ok(Some(staged))
+}
+
+/// Copies the file or directory `source` to `destination`.
+fn copy_all(source: &Path, destination: &Path) -> Result<()> {
+    if !source.is_dir() {
+        return fs::copy(source, destination).map(drop);
+    }
+
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        copy_all(&entry.path(), &destination.join(entry.file_name()))?;
+    }
+    Ok(())
+}
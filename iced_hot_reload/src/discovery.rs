@@ -0,0 +1,114 @@
+use crate::loader::{lib_file_name, profile_dir};
+use log::trace;
+use serde_json::Value;
+use std::io::{Error, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The crate the hot-reload entry points are exported with, see
+/// [`hot_reload_interface::hot_reload_core`].
+const INTERFACE_CRATE: &str = "hot_reload_interface";
+
+/// A hot-reloadable core found in the workspace by [`discover_cores`].
+#[derive(Debug, Clone)]
+pub struct DiscoveredCore {
+    /// The name of the package, e.g. `app_core`.
+    pub package: String,
+
+    /// The name of the library, without prefix or extension.
+    pub lib_name: String,
+
+    /// Where the library is built with the profile of the shell.
+    pub lib_path: PathBuf,
+
+    /// The directory of the sources of the library.
+    pub src_dir: PathBuf,
+}
+
+impl DiscoveredCore {
+    /// Whether the library was built already.
+    pub fn is_built(&self) -> bool {
+        self.lib_path.is_file()
+    }
+}
+
+/// Lists the packages of the current workspace building a `cdylib` and
+/// depending on `hot_reload_interface`, i.e. the cores the shell can host.
+///
+/// The workspace is described by `cargo metadata`, so the shell has to be
+/// started from within it. Whether a library actually exports the entry
+/// points is only known once it is loaded.
+pub fn discover_cores() -> Result<Vec<DiscoveredCore>> {
+    let metadata = cargo_metadata(true)?;
+    let profile_dir = profile_dir(&PathBuf::from(
+        metadata["target_directory"].as_str().unwrap_or("target"),
+    ));
+
+    let packages = metadata["packages"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+    let cores = packages
+        .iter()
+        .filter(|package| depends_on_interface(package))
+        .filter_map(|package| {
+            let target = package["targets"].as_array()?.iter().find(|target| {
+                target["crate_types"]
+                    .as_array()
+                    .is_some_and(|types| types.iter().any(|t| t == "cdylib"))
+            })?;
+
+            // Library names use underscores even if the package uses dashes.
+            let lib_name = target["name"].as_str()?.replace('-', "_");
+            let src_dir = PathBuf::from(target["src_path"].as_str()?)
+                .parent()?
+                .to_path_buf();
+
+            trace!("Found the core {} in {}", lib_name, src_dir.display());
+
+            Some(DiscoveredCore {
+                package: package["name"].as_str()?.to_string(),
+                lib_path: profile_dir.join(lib_file_name(&lib_name)),
+                lib_name,
+                src_dir,
+            })
+        })
+        .collect();
+
+    Ok(cores)
+}
+
+/// Describes the current workspace with `cargo metadata`, including the
+/// resolved dependency graph unless `no_deps` is set.
+pub(crate) fn cargo_metadata(no_deps: bool) -> Result<Value> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--format-version", "1"]);
+    if no_deps {
+        command.arg("--no-deps");
+    }
+    let output = command.output()?;
+
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(Error::other)
+}
+
+pub(crate) fn depends_on_interface(package: &Value) -> bool {
+    depends_on(package, INTERFACE_CRATE)
+}
+
+/// Whether `package` of the metadata depends on the crate `name`.
+pub(crate) fn depends_on(package: &Value, name: &str) -> bool {
+    package["dependencies"]
+        .as_array()
+        .is_some_and(|dependencies| {
+            dependencies
+                .iter()
+                .any(|dependency| dependency["name"] == name)
+        })
+}
@@ -0,0 +1,367 @@
+use crate::discovery::{cargo_metadata, depends_on, depends_on_interface};
+use crate::loader::{lib_file_name, open_library};
+use hot_reload_interface::{
+    AbiVersionFn, ABI_VERSION, ABI_VERSION_SYMBOL, CREATE_SYMBOL, DESTROY_SYMBOL,
+};
+use log::trace;
+use notify::{RecursiveMode, Watcher};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// The crate hosting cores, which shells depend on.
+const SHELL_CRATE: &str = "iced_hot_reload";
+
+/// How long to wait for the file watcher to report a change.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The outcome of one check of [`doctor`].
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// What was checked, e.g. `app_core is built as a cdylib`.
+    pub description: String,
+
+    /// How to fix the problem if the check failed, `None` if it passed.
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn passed(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            fix: None,
+        }
+    }
+
+    fn failed(description: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    /// Whether the check passed.
+    pub fn is_ok(&self) -> bool {
+        self.fix.is_none()
+    }
+}
+
+/// Checks the current workspace for the usual reasons a core fails to load
+/// the first time: the core isn't built as a `cdylib`, doesn't export the
+/// entry points or was built by another compiler, the shell and the core
+/// depend on different iced versions, the target directory isn't where it's
+/// expected, or the file system doesn't report changes.
+///
+/// `profile` is the cargo profile the shell runs with, e.g. `debug`.
+pub fn doctor(profile: &str) -> Vec<Check> {
+    let metadata = match cargo_metadata(false) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return vec![Check::failed(
+                "The workspace is described by cargo metadata",
+                format!(
+                    "Run the doctor from within the workspace of the shell ({})",
+                    e
+                ),
+            )];
+        }
+    };
+
+    let mut checks = Vec::new();
+
+    let target_dir = PathBuf::from(metadata["target_directory"].as_str().unwrap_or("target"));
+    let profile_dir = target_dir.join(profile);
+    checks.push(if profile_dir.is_dir() {
+        Check::passed(format!(
+            "The profile directory {} exists",
+            profile_dir.display()
+        ))
+    } else {
+        Check::failed(
+            format!("The profile directory {} exists", profile_dir.display()),
+            "Build the workspace with `cargo build`, and start the shell with the same \
+             CARGO_TARGET_DIR and build.target-dir configuration",
+        )
+    });
+
+    let packages = metadata["packages"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+    let members: Vec<&Value> = packages
+        .iter()
+        .filter(|package| is_member(&metadata, package))
+        .collect();
+    let cores: Vec<&Value> = members
+        .iter()
+        .copied()
+        .filter(|package| depends_on_interface(package) && package["name"] != SHELL_CRATE)
+        .collect();
+    let shells: Vec<&Value> = members
+        .iter()
+        .copied()
+        .filter(|package| depends_on(package, SHELL_CRATE))
+        .collect();
+
+    if cores.is_empty() {
+        checks.push(Check::failed(
+            "The workspace has a core",
+            "Cores are crates depending on hot_reload_interface, generate one with \
+             `cargo hot-reload init`",
+        ));
+    }
+
+    let rustc_version = rustc_version();
+    for core in &cores {
+        check_core(
+            core,
+            &profile_dir,
+            profile,
+            rustc_version.as_deref(),
+            &mut checks,
+        );
+
+        for shell in &shells {
+            checks.push(check_iced_versions(&metadata, core, shell));
+        }
+    }
+
+    checks.push(check_watcher(&profile_dir));
+
+    checks
+}
+
+/// Checks that `core` is built as a `cdylib` exporting the entry points,
+/// with the same contract and compiler as the shell. A `dylib` passes as
+/// well, which a core sharing iced through `iced_dylib` is built as.
+fn check_core(
+    core: &Value,
+    profile_dir: &Path,
+    profile: &str,
+    rustc_version: Option<&str>,
+    checks: &mut Vec<Check>,
+) {
+    let name = core["name"].as_str().unwrap_or_default();
+    let build = match profile {
+        "debug" => format!("cargo build -p {}", name),
+        "release" => format!("cargo build -p {} --release", name),
+        profile => format!("cargo build -p {} --profile {}", name, profile),
+    };
+
+    let Some(target) = core["targets"].as_array().and_then(|targets| {
+        targets.iter().find(|target| {
+            target["crate_types"]
+                .as_array()
+                .is_some_and(|types| types.iter().any(|t| t == "cdylib" || t == "dylib"))
+        })
+    }) else {
+        checks.push(Check::failed(
+            format!("{} is built as a cdylib", name),
+            format!(
+                "Add `[lib] crate-type = [\"cdylib\", \"rlib\"]` to {}",
+                core["manifest_path"].as_str().unwrap_or("its Cargo.toml")
+            ),
+        ));
+        return;
+    };
+    checks.push(Check::passed(format!("{} is built as a cdylib", name)));
+
+    let lib_name = target["name"].as_str().unwrap_or(name).replace('-', "_");
+    let lib_path = profile_dir.join(lib_file_name(&lib_name));
+    if !lib_path.is_file() {
+        checks.push(Check::failed(
+            format!("{} is built at {}", name, lib_path.display()),
+            format!("Build it with `{}`", build),
+        ));
+        return;
+    }
+    checks.push(Check::passed(format!(
+        "{} is built at {}",
+        name,
+        lib_path.display()
+    )));
+
+    let library = match open_library(&lib_path) {
+        Ok(library) => library,
+        Err(e) => {
+            checks.push(Check::failed(
+                format!("{} can be loaded", name),
+                format!("Rebuild it with `{}` ({})", build, e),
+            ));
+            return;
+        }
+    };
+
+    let missing: Vec<&str> = [ABI_VERSION_SYMBOL, CREATE_SYMBOL, DESTROY_SYMBOL]
+        .into_iter()
+        .filter(|symbol| unsafe { library.get::<*const ()>(symbol.as_bytes()) }.is_err())
+        .collect();
+    checks.push(if missing.is_empty() {
+        Check::passed(format!("{} exports the entry points", name))
+    } else {
+        Check::failed(
+            format!("{} exports the entry points", name),
+            format!(
+                "{} missing: annotate the AppInterface impl with #[hot_reload_core] and \
+                 rebuild it, or pass custom entry point names to the shell with \
+                 `create_symbol` and `destroy_symbol`",
+                missing.join(", ")
+            ),
+        )
+    });
+
+    let Ok(abi_version_fn) =
+        (unsafe { library.get::<AbiVersionFn>(ABI_VERSION_SYMBOL.as_bytes()) })
+    else {
+        return;
+    };
+    let abi = unsafe { abi_version_fn() };
+
+    checks.push(if abi.abi_version == ABI_VERSION {
+        Check::passed(format!(
+            "{} was built against ABI version {}",
+            name, ABI_VERSION
+        ))
+    } else {
+        Check::failed(
+            format!("{} was built against ABI version {}", name, ABI_VERSION),
+            format!(
+                "It was built against version {}: update hot_reload_interface and \
+                 iced_hot_reload to the same revision and rebuild with `{}`",
+                abi.abi_version, build
+            ),
+        )
+    });
+
+    if let Some(rustc_version) = rustc_version {
+        let core_version = unsafe { abi.rustc_version() };
+        checks.push(if core_version == rustc_version {
+            Check::passed(format!("{} was built with {}", name, rustc_version))
+        } else {
+            Check::failed(
+                format!("{} was built with {}", name, rustc_version),
+                format!(
+                    "It was built with {}: rebuild the shell and the core with the same \
+                     toolchain, e.g. pinned in rust-toolchain.toml",
+                    core_version
+                ),
+            )
+        });
+    }
+}
+
+/// Checks that `core` and `shell` resolve the same iced package, as their
+/// elements cross the library boundary.
+fn check_iced_versions(metadata: &Value, core: &Value, shell: &Value) -> Check {
+    let core_name = core["name"].as_str().unwrap_or_default();
+    let shell_name = shell["name"].as_str().unwrap_or_default();
+    let description = format!("{} and {} depend on the same iced", core_name, shell_name);
+
+    match (iced_of(metadata, core), iced_of(metadata, shell)) {
+        (Some(core_iced), Some(shell_iced)) if core_iced != shell_iced => Check::failed(
+            description,
+            format!(
+                "{} uses {} and {} uses {}: depend on the same iced version in both",
+                core_name,
+                version_of(metadata, core_iced),
+                shell_name,
+                version_of(metadata, shell_iced)
+            ),
+        ),
+        _ => Check::passed(description),
+    }
+}
+
+/// Checks that the file system watcher reports a file written to `dir`.
+fn check_watcher(dir: &Path) -> Check {
+    let description = "The file system watcher reports changes";
+    let fix = "Start the shell with a poll interval instead, e.g. `--poll-ms 500`, which is \
+               common on network drives, in containers and in virtual machines";
+
+    let dir = if dir.is_dir() {
+        dir.to_path_buf()
+    } else {
+        std::env::temp_dir()
+    };
+    let probe = dir.join(format!(".hot_reload_doctor_{}", std::process::id()));
+
+    let (events_tx, events_rx) = std::sync::mpsc::channel();
+    let watched = notify::recommended_watcher(events_tx).and_then(|mut watcher| {
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    let _watcher = match watched {
+        Ok(watcher) => watcher,
+        Err(e) => return Check::failed(description, format!("{} ({})", fix, e)),
+    };
+
+    trace!("Writing {} to test the watcher", probe.display());
+    if let Err(e) = fs::write(&probe, b"doctor") {
+        return Check::failed(
+            description,
+            format!("Failed to write {}: {}", probe.display(), e),
+        );
+    }
+
+    let reported = events_rx
+        .recv_timeout(WATCH_TIMEOUT)
+        .is_ok_and(|event| event.is_ok());
+    let _ = fs::remove_file(&probe);
+
+    if reported {
+        Check::passed(description)
+    } else {
+        Check::failed(description, fix)
+    }
+}
+
+/// Whether `package` is a member of the workspace, rather than a dependency.
+fn is_member(metadata: &Value, package: &Value) -> bool {
+    metadata["workspace_members"]
+        .as_array()
+        .is_some_and(|members| members.iter().any(|member| *member == package["id"]))
+}
+
+/// The id of the iced package `package` resolves to.
+fn iced_of<'a>(metadata: &'a Value, package: &Value) -> Option<&'a str> {
+    let node = metadata["resolve"]["nodes"]
+        .as_array()?
+        .iter()
+        .find(|node| node["id"] == package["id"])?;
+
+    node["deps"]
+        .as_array()?
+        .iter()
+        .find(|dependency| dependency["name"] == "iced")?["pkg"]
+        .as_str()
+}
+
+/// The version of the package with the id `id`.
+fn version_of<'a>(metadata: &'a Value, id: &str) -> &'a str {
+    metadata["packages"]
+        .as_array()
+        .and_then(|packages| packages.iter().find(|package| package["id"] == id))
+        .and_then(|package| package["version"].as_str())
+        .unwrap_or("an unknown version")
+}
+
+/// The version and commit hash of the toolchain of the workspace, in the
+/// format the core reports them in its `AbiInfo`.
+fn rustc_version() -> Option<String> {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc).arg("-vV").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let version = output.lines().next()?.trim();
+    let commit_hash = output
+        .lines()
+        .find_map(|line| line.strip_prefix("commit-hash: "))
+        .unwrap_or("unknown")
+        .trim();
+
+    Some(format!("{}, commit {}", version, commit_hash))
+}
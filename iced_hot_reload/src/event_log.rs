@@ -0,0 +1,98 @@
+use crate::control::ControlEvent;
+use log::error;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Error, LineWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A line of the event log.
+#[derive(Serialize)]
+struct LoggedEvent<'a> {
+    /// When the event happened, in milliseconds since the epoch.
+    at_ms: u64,
+
+    #[serde(flatten)]
+    event: &'a ControlEvent,
+}
+
+/// Appends the reload events of the shell to a file, one JSON object per
+/// line, e.g. `{"at_ms":1700000000000,"event":"reloaded","duration_ms":42}`,
+/// to graph reload latencies or notify about failures with external tools.
+pub(crate) struct EventLog {
+    path: PathBuf,
+
+    /// Events are emitted from the view as well, e.g. when the core panics,
+    /// which only has shared access to the shell.
+    file: RefCell<LineWriter<File>>,
+}
+
+impl EventLog {
+    /// Opens the event log at `path`, appending to previous sessions.
+    pub(crate) fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Self {
+            path,
+            file: RefCell::new(LineWriter::new(file)),
+        })
+    }
+
+    /// Appends `event` to the log.
+    pub(crate) fn write(&self, event: &ControlEvent) {
+        let at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_millis() as u64);
+
+        let mut file = self.file.borrow_mut();
+        let result = serde_json::to_writer(&mut *file, &LoggedEvent { at_ms, event })
+            .map_err(Error::other)
+            .and_then(|()| writeln!(file));
+
+        if let Err(e) = result {
+            error!(
+                "Failed to write the event log {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_appended_as_json_lines() {
+        let path = std::env::temp_dir().join(format!(
+            "iced_hot_reload_events_{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        EventLog::open(path.clone())
+            .unwrap()
+            .write(&ControlEvent::ChangeDetected);
+
+        // A later session appends to the log of the previous one.
+        let log = EventLog::open(path.clone()).unwrap();
+        log.write(&ControlEvent::Reloaded { duration_ms: 42 });
+        drop(log);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines = contents
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"], "change_detected");
+        assert_eq!(lines[1]["event"], "reloaded");
+        assert_eq!(lines[1]["duration_ms"], 42);
+        assert!(lines[1]["at_ms"].as_u64().unwrap() > 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::message::ShellMessage;
+use iced::futures::Stream;
+use iced::stream;
+
+/// Yields the reloads and shutdowns requested by external tooling, e.g. a
+/// build script signalling that the library is complete.
+///
+/// On Unix `SIGUSR1` requests a reload and `SIGTERM` a graceful shutdown. On
+/// Windows the lines `reload` and `shutdown` are read from the named pipe
+/// `\\.\pipe\iced_hot_reload_<lib_name>`.
+pub(crate) fn listen<M: Send + 'static>(lib_name: String) -> impl Stream<Item = ShellMessage<M>> {
+    stream::channel(16, move |output| async move {
+        #[cfg(unix)]
+        {
+            let _ = lib_name;
+            unix::listen(output).await;
+        }
+
+        #[cfg(windows)]
+        windows::listen(&format!(r"\\.\pipe\iced_hot_reload_{}", lib_name), output).await;
+    })
+}
+
+#[cfg(unix)]
+mod unix {
+    use crate::message::ShellMessage;
+    use iced::futures::channel::mpsc::{unbounded, Sender, UnboundedSender};
+    use iced::futures::{SinkExt, StreamExt};
+    use log::{error, trace};
+    use signal_hook::consts::{SIGTERM, SIGUSR1};
+    use signal_hook::iterator::Signals;
+    use std::thread;
+
+    pub(super) async fn listen<M: Send + 'static>(mut output: Sender<ShellMessage<M>>) {
+        let (signal_tx, mut signal_rx) = unbounded();
+        thread::spawn(move || wait_for_signals(signal_tx));
+
+        while let Some(message) = signal_rx.next().await {
+            if output.send(message).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Maps the received signals to messages until the receiver goes away.
+    fn wait_for_signals<M>(output: UnboundedSender<ShellMessage<M>>) {
+        let mut signals = match Signals::new([SIGUSR1, SIGTERM]) {
+            Ok(signals) => signals,
+            Err(e) => {
+                error!("Failed to register the signal handlers: {}", e);
+                return;
+            }
+        };
+
+        trace!(
+            "Send SIGUSR1 to reload or SIGTERM to shut down (pid {})",
+            std::process::id()
+        );
+
+        for signal in signals.forever() {
+            let message = match signal {
+                SIGUSR1 => ShellMessage::Reload,
+                _ => ShellMessage::Shutdown,
+            };
+
+            if output.unbounded_send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use crate::message::ShellMessage;
+    use iced::futures::channel::mpsc::Sender;
+    use iced::futures::SinkExt;
+    use log::{error, trace, warn};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    pub(super) async fn listen<M>(pipe_name: &str, mut output: Sender<ShellMessage<M>>) {
+        let mut server = match ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(pipe_name)
+        {
+            Ok(server) => server,
+            Err(e) => {
+                error!("Failed to create the pipe {}: {}", pipe_name, e);
+                return;
+            }
+        };
+
+        trace!("Reading commands from {}", pipe_name);
+
+        loop {
+            if let Err(e) = server.connect().await {
+                error!("Failed to accept a client on {}: {}", pipe_name, e);
+                return;
+            }
+
+            // The next client connects to a new instance of the pipe.
+            let client = server;
+            server = match ServerOptions::new().create(pipe_name) {
+                Ok(server) => server,
+                Err(e) => {
+                    error!("Failed to create the pipe {}: {}", pipe_name, e);
+                    return;
+                }
+            };
+
+            let mut lines = BufReader::new(client).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let message = match line.trim() {
+                    "reload" => ShellMessage::Reload,
+                    "shutdown" => ShellMessage::Shutdown,
+                    command => {
+                        warn!("Unknown command on {}: {}", pipe_name, command);
+                        continue;
+                    }
+                };
+
+                if output.send(message).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use hot_reload_interface::RawApp;
+use std::cell::RefCell;
+
+/// Whether a [`CoreHandle`] may still be called.
+enum Validity {
+    /// The core can be called.
+    Live,
+
+    /// The core panicked, with the message of the panic. It is only asked
+    /// for its state and told it is unloaded anymore.
+    Poisoned(String),
+
+    /// The core was never created or was destroyed.
+    Absent,
+}
+
+/// A [`RawApp`] which tracks whether it may be called.
+///
+/// Calls on a core which is absent or poisoned fail with a message, shown by
+/// the shell in place of the core's view, instead of dereferencing a null or
+/// dangling pointer. The raw handle is only reachable through [`Self::call`]
+/// and its variants, which never hand out an absent one.
+pub(crate) struct CoreHandle<M> {
+    app: RawApp<M>,
+    validity: RefCell<Validity>,
+}
+
+impl<M> CoreHandle<M> {
+    /// Wraps `app`, which is absent if it is null.
+    pub(crate) fn new(app: RawApp<M>) -> Self {
+        let validity = if app.is_null() {
+            Validity::Absent
+        } else {
+            Validity::Live
+        };
+
+        Self {
+            app,
+            validity: RefCell::new(validity),
+        }
+    }
+
+    /// Whether the core was never created or was destroyed.
+    pub(crate) fn is_absent(&self) -> bool {
+        matches!(*self.validity.borrow(), Validity::Absent)
+    }
+
+    /// Calls the live core with `call`. A panic reported by `call` poisons
+    /// the core.
+    pub(crate) fn call<T>(
+        &self,
+        call: impl FnOnce(&RawApp<M>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.check(false)?;
+        self.poison_on_panic(call(&self.app))
+    }
+
+    /// Calls the live core with `call`, which may mutate it. A panic
+    /// reported by `call` poisons the core.
+    pub(crate) fn call_mut<T>(
+        &mut self,
+        call: impl FnOnce(&mut RawApp<M>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.check(false)?;
+        let result = call(&mut self.app);
+        self.poison_on_panic(result)
+    }
+
+    /// Calls the core with `call` even if it is poisoned, to save its state
+    /// for the next build or tell it it is unloaded.
+    pub(crate) fn call_poisoned<T>(
+        &self,
+        call: impl FnOnce(&RawApp<M>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.check(true)?;
+        call(&self.app)
+    }
+
+    /// Like [`Self::call_poisoned`], for calls which may mutate the core.
+    pub(crate) fn call_poisoned_mut<T>(
+        &mut self,
+        call: impl FnOnce(&mut RawApp<M>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.check(true)?;
+        call(&mut self.app)
+    }
+
+    /// Takes the raw handle out to destroy the core, leaving the handle
+    /// absent. Returns `None` if the core is absent already.
+    pub(crate) fn take(&mut self) -> Option<RawApp<M>> {
+        let validity = std::mem::replace(self.validity.get_mut(), Validity::Absent);
+        if matches!(validity, Validity::Absent) {
+            return None;
+        }
+
+        Some(std::mem::replace(&mut self.app, RawApp::null()))
+    }
+
+    fn check(&self, allow_poisoned: bool) -> Result<(), String> {
+        match &*self.validity.borrow() {
+            Validity::Live => Ok(()),
+            Validity::Poisoned(_) if allow_poisoned => Ok(()),
+            Validity::Poisoned(panic) => Err(format!("The core panicked before: {}", panic)),
+            Validity::Absent => Err("The core is not loaded".to_string()),
+        }
+    }
+
+    fn poison_on_panic<T>(&self, result: Result<T, String>) -> Result<T, String> {
+        if let Err(panic) = &result {
+            *self.validity.borrow_mut() = Validity::Poisoned(panic.clone());
+        }
+        result
+    }
+}
@@ -0,0 +1,193 @@
+use crate::backend::{CoreInstance, DylibBackend, DylibInstance, LoaderBackend};
+use crate::loader::{make_lib_path, LibInfo, LoadedLibrary};
+use crate::offscreen::Screenshot;
+use hot_reload_interface::{
+    CoreCommand, CoreSubscription, ReloadInfo, CREATE_SYMBOL, DESTROY_SYMBOL,
+};
+use iced::{Element, Size};
+use std::io::{Error, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long library copies of earlier test runs are kept.
+const COPY_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// A core loaded from its dynamic library without a window, for tests.
+///
+/// The library is copied, checked and opened the way the shell does it,
+/// and the core is driven through the same calls, so integration tests see
+/// the core as the shell would. A panic of the core fails the call with its
+/// message instead of unwinding into the test.
+///
+/// ```ignore
+/// let mut core = HeadlessCore::<Message>::load("app_core", &[])?;
+/// core.update(Message::Increment)?;
+/// let state: State = hot_reload_interface::deserialize_state(&core.save_state()?)?;
+/// ```
+pub struct HeadlessCore<M> {
+    instance: Option<DylibInstance<M>>,
+    lib: Option<LoadedLibrary<M>>,
+    backend: DylibBackend<M>,
+    reloads: u32,
+}
+
+impl<M> HeadlessCore<M> {
+    /// Loads the core `lib_name` from the cargo target directory, where the
+    /// shell finds it, and instantiates it with the serialized `state`. An
+    /// empty state makes the core start from its default state.
+    pub fn load(lib_name: &str, state: &[u8]) -> Result<Self> {
+        Self::load_path(lib_name, make_lib_path(lib_name), state)
+    }
+
+    /// Loads the core `lib_name` from the library at `path` and
+    /// instantiates it with the serialized `state`.
+    pub fn load_path(lib_name: &str, path: impl Into<PathBuf>, state: &[u8]) -> Result<Self> {
+        let lib_info = LibInfo {
+            name: lib_name.to_string(),
+            path: path.into(),
+            create_fn_name: CREATE_SYMBOL.to_string(),
+            destroy_fn_name: DESTROY_SYMBOL.to_string(),
+            copy_dir: None,
+            keep_loaded: false,
+            keep_thread_locals: false,
+        };
+        let mut backend = DylibBackend::new(lib_info, COPY_RETENTION);
+
+        let lib = backend.load()?;
+        backend.self_test(&lib)?;
+        let instance = backend.instantiate(&lib, state)?;
+
+        Ok(Self {
+            instance: Some(instance),
+            lib: Some(lib),
+            backend,
+            reloads: 0,
+        })
+    }
+
+    /// Replaces the instance with a new one of the current library created
+    /// with the serialized `state`, as the shell does when the state is
+    /// replaced.
+    pub fn restore(&mut self, state: &[u8]) -> Result<()> {
+        let lib = self
+            .lib
+            .as_ref()
+            .ok_or_else(|| Error::other("The core was not loaded"))?;
+        let instance = self.backend.instantiate(lib, state)?;
+
+        drop(self.instance.replace(instance));
+        Ok(())
+    }
+
+    /// Passes `message` to the core and returns the commands it requested,
+    /// which aren't executed.
+    pub fn update(&mut self, message: M) -> Result<Vec<CoreCommand<M>>> {
+        self.instance_mut()?.update(message).map_err(panicked)
+    }
+
+    /// Serializes the state of the core, as the shell does before a reload.
+    pub fn save_state(&self) -> Result<Vec<u8>> {
+        self.instance()?.save_state().map_err(panicked)
+    }
+
+    /// Builds the view of the core, to check it doesn't panic. The element
+    /// must be dropped before the core is reloaded or dropped.
+    pub fn view(&self) -> Result<Element<'static, M>> {
+        self.instance()?.view().map_err(panicked)
+    }
+
+    /// Rasterizes the view of the core in a window of `width` by `height`
+    /// pixels with the software renderer, in the theme the core requests.
+    pub fn screenshot(&self, width: u32, height: u32) -> Result<Screenshot> {
+        let instance = self.instance()?;
+        let theme = instance
+            .presentation()
+            .map_err(panicked)?
+            .theme()
+            .unwrap_or_default();
+        let element = instance.view().map_err(panicked)?;
+        let size = Size::new(width, height);
+
+        Ok(Screenshot::render(&element, size, &theme))
+    }
+
+    /// Returns the subscriptions declared by the core.
+    pub fn subscriptions(&self) -> Result<Vec<CoreSubscription<M>>> {
+        self.instance()?.subscriptions().map_err(panicked)
+    }
+
+    /// Loads the library again and swaps in a new instance with the state of
+    /// the current one, migrated if its version changed, as a reload of the
+    /// shell does. Returns the commands requested by `on_after_reload`.
+    pub fn reload(&mut self) -> Result<Vec<CoreCommand<M>>> {
+        let state = self.save_state()?;
+        let previous = self
+            .lib
+            .take()
+            .ok_or_else(|| Error::other("The core was not loaded"))?;
+
+        let lib = match self.backend.load() {
+            Ok(lib) => lib,
+            Err(e) => {
+                self.lib = Some(previous);
+                return Err(e);
+            }
+        };
+        let instance = self
+            .backend
+            .migrate(&lib, &previous, &state)
+            .and_then(|state| {
+                self.backend.self_test(&lib)?;
+                self.backend.instantiate(&lib, &state)
+            });
+        let mut instance = match instance {
+            Ok(instance) => instance,
+            Err(e) => {
+                self.backend.unload(lib);
+                self.lib = Some(previous);
+                return Err(e);
+            }
+        };
+
+        self.reloads += 1;
+        let reload = ReloadInfo::new(
+            self.reloads,
+            false,
+            self.backend.built_at(&lib),
+            self.backend.built_at(&previous),
+        );
+        let commands = instance.after_reload(&reload).map_err(panicked);
+
+        drop(self.instance.replace(instance));
+        self.backend.unload(previous);
+        self.lib = Some(lib);
+        commands
+    }
+
+    fn instance(&self) -> Result<&DylibInstance<M>> {
+        self.instance
+            .as_ref()
+            .ok_or_else(|| Error::other("The core was not loaded"))
+    }
+
+    fn instance_mut(&mut self) -> Result<&mut DylibInstance<M>> {
+        self.instance
+            .as_mut()
+            .ok_or_else(|| Error::other("The core was not loaded"))
+    }
+}
+
+/// Destroys the core and unloads its library, whose copy removes itself.
+impl<M> Drop for HeadlessCore<M> {
+    fn drop(&mut self) {
+        drop(self.instance.take());
+        if let Some(lib) = self.lib.take() {
+            self.backend.unload(lib);
+        }
+        self.backend.clean_up();
+    }
+}
+
+fn panicked(panic: String) -> Error {
+    Error::other(format!("The core panicked: {}", panic))
+}
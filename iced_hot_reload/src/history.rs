@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+
+/// A bounded history of serialized state snapshots the user can step
+/// through, like the undo history of an editor.
+pub(crate) struct StateHistory {
+    /// The snapshots, oldest first.
+    snapshots: VecDeque<Vec<u8>>,
+
+    /// How many snapshots are kept.
+    capacity: usize,
+
+    /// Takes a snapshot after this many messages, only on demand if `None`.
+    every: Option<u32>,
+
+    /// How many messages were handled since the last snapshot.
+    messages: u32,
+
+    /// The snapshot the core was restored to, `None` while it runs ahead of
+    /// the history.
+    cursor: Option<usize>,
+}
+
+impl StateHistory {
+    pub(crate) fn new(every: Option<u32>, capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            capacity: capacity.max(1),
+            every,
+            messages: 0,
+            cursor: None,
+        }
+    }
+
+    /// Records that the core handled a message and returns whether a
+    /// snapshot is due.
+    ///
+    /// A message handled after stepping back discards the snapshots ahead of
+    /// the restored one.
+    pub(crate) fn message_handled(&mut self) -> bool {
+        self.discard_ahead();
+
+        let Some(every) = self.every else {
+            return false;
+        };
+
+        self.messages += 1;
+        if self.messages < every {
+            return false;
+        }
+
+        self.messages = 0;
+        true
+    }
+
+    /// Adds `state` as the latest snapshot, dropping the oldest one if the
+    /// history is full.
+    pub(crate) fn push(&mut self, state: Vec<u8>) {
+        self.discard_ahead();
+
+        if self.snapshots.back() == Some(&state) {
+            return;
+        }
+
+        self.snapshots.push_back(state);
+        if self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Steps back from `current`, the state of the running core, and returns
+    /// the snapshot to restore.
+    ///
+    /// Stepping back from the running core records its state first, so the
+    /// user can step forward to it again.
+    pub(crate) fn back(&mut self, current: Vec<u8>) -> Option<Vec<u8>> {
+        let cursor = match self.cursor {
+            Some(cursor) => cursor,
+            None => {
+                self.push(current);
+                self.snapshots.len().checked_sub(1)?
+            }
+        };
+
+        let previous = cursor.checked_sub(1)?;
+        self.cursor = Some(previous);
+        self.snapshots.get(previous).cloned()
+    }
+
+    /// Steps forward to the next snapshot and returns it.
+    pub(crate) fn forward(&mut self) -> Option<Vec<u8>> {
+        let next = self.cursor? + 1;
+        let snapshot = self.snapshots.get(next)?.clone();
+        self.cursor = Some(next);
+        Some(snapshot)
+    }
+
+    /// Describes the position in the history, e.g. `3 of 10`.
+    pub(crate) fn position(&self) -> String {
+        match self.cursor {
+            Some(cursor) => format!("{} of {}", cursor + 1, self.snapshots.len()),
+            None => format!("live, {} snapshots", self.snapshots.len()),
+        }
+    }
+
+    /// Forgets the snapshots ahead of the restored one, as the core moved on
+    /// from it.
+    fn discard_ahead(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.snapshots.truncate(cursor + 1);
+        }
+    }
+}
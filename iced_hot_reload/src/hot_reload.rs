@@ -0,0 +1,991 @@
+use crate::backend::DylibBackend;
+use crate::build_runner::{BuildConfig, BuildProfile};
+use crate::composed::{ComposedShell, CoreConfig, CoreLayout};
+use crate::discovery::DiscoveredCore;
+use crate::loader::{lib_file_name, make_lib_path, make_profile_lib_path, LibInfo};
+use crate::message::ShellMessage;
+use crate::mock::{MockBackend, MockShell};
+use crate::observer::ReloadObserver;
+use crate::picker::CorePicker;
+use crate::pipeline::ReloadPipeline;
+use crate::push::PushConfig;
+use crate::recording::{DeserializeFn, RecordConfig, ReplayConfig};
+use crate::remote::{ProcessCore, RemoteShell};
+use crate::shell::{DylibShell, ShellApp, ShellSettings};
+use crate::soak::{self, SoakConfig};
+use crate::static_app;
+use crate::strategy::{FocusCheck, ReloadStrategy};
+use crate::unload::UnloadPolicy;
+use crate::view_file::{self, ParseViewFn};
+use crate::window_geometry;
+use hot_reload_interface::{serialize_state, FnApp, CREATE_SYMBOL, DESTROY_SYMBOL};
+use iced::{window, Element, Task};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long library copies of previous sessions are kept by default.
+const DEFAULT_COPY_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// How many state snapshots are kept by default.
+const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+/// How many times loading a changed library is attempted by default.
+const DEFAULT_RELOAD_ATTEMPTS: u32 = 5;
+
+/// A configured hot-reloading shell, ready to be run.
+///
+/// `HotReload` hosts a core compiled as a dynamic library, renders its view
+/// and swaps it for a freshly built one whenever the library file changes.
+///
+/// `M` is the message type of the application. The application state is
+/// defined by the core alone and handed over from one core instance to the
+/// next in serialized form, so the shell doesn't depend on it.
+///
+/// ```no_run
+/// # #[derive(Debug, Clone)]
+/// # enum MyMessage {}
+/// fn main() -> iced::Result {
+///     iced_hot_reload::HotReload::<MyMessage>::builder()
+///         .lib_name("my_core")
+///         .create_symbol("create_app")
+///         .run()
+/// }
+/// ```
+pub struct HotReload<M> {
+    /// The configuration handed over to the shell.
+    settings: ShellSettings<M>,
+}
+
+impl<M> HotReload<M>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    /// Creates a builder with the defaults used by the `app_core` example.
+    pub fn builder() -> HotReloadBuilder<M> {
+        HotReloadBuilder::default()
+    }
+
+    /// Opens the shell window and blocks until it is closed. The windows
+    /// opened by the core close along with it.
+    ///
+    /// The window opens with the geometry it had when the shell last ran,
+    /// unless [`HotReloadBuilder::remember_window`] turned that off. The core
+    /// is loaded before, so a core failing to load or start fails with
+    /// [`iced::Error::WindowCreationFailed`] carrying why. A failed soak test
+    /// exits the process with status 1 once the shell shut down.
+    pub fn run(self) -> iced::Result {
+        let geometry = self
+            .settings
+            .window_geometry
+            .as_deref()
+            .and_then(window_geometry::load)
+            .unwrap_or_default();
+
+        let settings = window::Settings {
+            size: geometry.size(),
+            position: geometry.position().unwrap_or_default(),
+            // The core may keep the window open, see `CoreEvent::CloseRequested`.
+            exit_on_close_request: false,
+            ..window::Settings::default()
+        };
+
+        let (mut shell, tasks) = self.into_shell().map_err(|e| {
+            log::error!("{}", e);
+            iced::Error::WindowCreationFailed(Box::new(e))
+        })?;
+
+        // A daemon, so the core can open windows of its own.
+        iced::daemon(
+            DylibShell::<M>::window_title,
+            DylibShell::<M>::update,
+            DylibShell::<M>::window_view,
+        )
+        .subscription(DylibShell::<M>::subscription)
+        .theme(DylibShell::<M>::window_theme)
+        .scale_factor(DylibShell::<M>::window_scale_factor)
+        .run_with(move || {
+            let open = shell.open_main_window(settings);
+            (shell, open.chain(tasks))
+        })?;
+
+        // The shell is dropped by now, so the copies are removed and the
+        // libraries unloaded.
+        if soak::failed() {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Creates the shell without opening a window, running the core of
+    /// `backend` instead of loading the library, to test how the shell
+    /// handles reloads and failures. Fails like [`HotReload::run`] if the
+    /// first load of the core fails.
+    pub fn mock(self, backend: MockBackend<M>) -> std::io::Result<MockShell<M>> {
+        ShellApp::new(self.settings, backend).map(MockShell::new)
+    }
+
+    /// Loads the core and creates the shell, along with the task replaying
+    /// the recorded messages, if any.
+    pub(crate) fn into_shell(self) -> std::io::Result<(DylibShell<M>, Task<ShellMessage<M>>)> {
+        let HotReload { settings } = self;
+
+        let backend = DylibBackend::new(settings.lib_info.clone(), settings.copy_retention);
+        let shell = ShellApp::new(settings, backend)?;
+        let tasks = Task::batch([shell.load_assets(), shell.replay()]);
+        Ok((shell, tasks))
+    }
+}
+
+/// Builds a [`HotReload`] shell.
+///
+/// Every setting has a default matching the layout of this workspace, so
+/// only the values that differ have to be provided.
+pub struct HotReloadBuilder<M> {
+    title: String,
+    lib_name: String,
+    lib_path: Option<PathBuf>,
+    lib_dir: Option<PathBuf>,
+    create_symbol: String,
+    destroy_symbol: String,
+    poll_interval: Option<Duration>,
+    focus_check: Option<FocusCheck>,
+    initial_state: Vec<u8>,
+    persist_path: Option<PathBuf>,
+    fresh: bool,
+    build: Option<BuildConfig>,
+    build_profile: Option<BuildProfile>,
+    core_profile: Option<String>,
+    push_addr: Option<SocketAddr>,
+    staging_dir: Option<PathBuf>,
+    control_addr: Option<SocketAddr>,
+    event_log: Option<PathBuf>,
+    external_commands: bool,
+    require_ready_marker: bool,
+    reload_attempts: u32,
+    snapshot_every: Option<u32>,
+    history_capacity: usize,
+    record: Option<RecordConfig<M>>,
+    replay: Option<ReplayConfig<M>>,
+    console: Option<DeserializeFn<M>>,
+    view_file: Option<(PathBuf, ParseViewFn<M>)>,
+    assets: Option<PathBuf>,
+    stylesheet: Option<PathBuf>,
+    localization: Option<(PathBuf, String)>,
+    remember_window: bool,
+    copy_retention: Duration,
+    unload_policy: UnloadPolicy,
+    keep_libraries: usize,
+    track_leaks: bool,
+    soak_reloads: Option<u32>,
+    soak_messages: Option<ReplayConfig<M>>,
+    reload_strategy: ReloadStrategy,
+    reload_pipeline: ReloadPipeline,
+    observers: Vec<Box<dyn ReloadObserver>>,
+    cores: Vec<(String, String)>,
+}
+
+impl<M> Default for HotReloadBuilder<M> {
+    fn default() -> Self {
+        Self {
+            title: "Application".to_string(),
+            lib_name: "app_core".to_string(),
+            lib_path: None,
+            lib_dir: None,
+            create_symbol: CREATE_SYMBOL.to_string(),
+            destroy_symbol: DESTROY_SYMBOL.to_string(),
+            poll_interval: None,
+            focus_check: None,
+            initial_state: Vec::new(),
+            persist_path: None,
+            fresh: false,
+            build: None,
+            build_profile: None,
+            core_profile: None,
+            push_addr: None,
+            staging_dir: None,
+            control_addr: None,
+            event_log: None,
+            external_commands: false,
+            require_ready_marker: false,
+            reload_attempts: DEFAULT_RELOAD_ATTEMPTS,
+            snapshot_every: None,
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            record: None,
+            replay: None,
+            console: None,
+            view_file: None,
+            assets: None,
+            stylesheet: None,
+            localization: None,
+            remember_window: true,
+            copy_retention: DEFAULT_COPY_RETENTION,
+            unload_policy: UnloadPolicy::default(),
+            keep_libraries: 0,
+            track_leaks: false,
+            soak_reloads: None,
+            soak_messages: None,
+            reload_strategy: ReloadStrategy::default(),
+            reload_pipeline: ReloadPipeline::default(),
+            observers: Vec::new(),
+            cores: Vec::new(),
+        }
+    }
+}
+
+impl<M> HotReloadBuilder<M>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    /// Sets the window title, used unless the core provides its own.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the logical name of the core library, without prefix or extension.
+    pub fn lib_name(mut self, lib_name: impl Into<String>) -> Self {
+        self.lib_name = lib_name.into();
+        self
+    }
+
+    /// Overrides the path of the core library resolved from its name.
+    pub fn lib_path(mut self, lib_path: impl Into<PathBuf>) -> Self {
+        self.lib_path = Some(lib_path.into());
+        self
+    }
+
+    /// Looks for the core library in `lib_dir` instead of the `target`
+    /// directory of the current profile.
+    pub fn lib_dir(mut self, lib_dir: impl Into<PathBuf>) -> Self {
+        self.lib_dir = Some(lib_dir.into());
+        self
+    }
+
+    /// Sets the name of the exported function creating the core instance.
+    pub fn create_symbol(mut self, create_symbol: impl Into<String>) -> Self {
+        self.create_symbol = create_symbol.into();
+        self
+    }
+
+    /// Sets the name of the exported function destroying the core instance.
+    pub fn destroy_symbol(mut self, destroy_symbol: impl Into<String>) -> Self {
+        self.destroy_symbol = destroy_symbol.into();
+        self
+    }
+
+    /// Polls the library for changes with `interval` instead of using the
+    /// native file watcher.
+    ///
+    /// While the window is unfocused for longer than a minute without the
+    /// library changing, it is polled every 5 seconds at most.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Checks the library for changes when the window gains the focus, in
+    /// addition to watching or polling it, or instead of it with
+    /// [`FocusCheck::Only`].
+    ///
+    /// Builds pushed, built by the shell or requested by external tooling
+    /// are reloaded as usual.
+    pub fn check_on_focus(mut self, check: FocusCheck) -> Self {
+        self.focus_check = Some(check);
+        self
+    }
+
+    /// Sets the state the first core instance is created with.
+    ///
+    /// By default the core starts from the `Default` of its state. Fails if
+    /// the state can't be serialized.
+    pub fn initial_state<S: Serialize>(mut self, initial_state: &S) -> std::io::Result<Self> {
+        self.initial_state = serialize_state(initial_state).map_err(std::io::Error::other)?;
+        Ok(self)
+    }
+
+    /// Persists the state to the file at `path`, so it survives restarts of
+    /// the shell.
+    ///
+    /// The state is written after every message handled by the core and when
+    /// the shell exits, and restored on the next start in place of the
+    /// initial state.
+    pub fn persist_state(mut self, path: impl Into<PathBuf>) -> Self {
+        self.persist_path = Some(path.into());
+        self
+    }
+
+    /// Ignores the persisted state on startup if `fresh` is `true`, e.g. to
+    /// honour a `--fresh` flag. The state is still persisted afterwards.
+    pub fn fresh(mut self, fresh: bool) -> Self {
+        self.fresh = fresh;
+        self
+    }
+
+    /// Keeps the size and position of the window in the config directory
+    /// and opens the window with them on the next start if `remember` is
+    /// `true`, the default. Reloads never change the window either way.
+    pub fn remember_window(mut self, remember: bool) -> Self {
+        self.remember_window = remember;
+        self
+    }
+
+    /// Rebuilds the core with `cargo build -p <package>` whenever a file in
+    /// `src_dir` changes.
+    ///
+    /// The library is then reloaded after every successful build instead of
+    /// whenever the library file changes, so `cargo build` no longer has to
+    /// be run by hand.
+    pub fn rebuild_on_change(
+        mut self,
+        package: impl Into<String>,
+        src_dir: impl Into<PathBuf>,
+    ) -> Self {
+        self.build = Some(BuildConfig {
+            package: package.into(),
+            src_dir: src_dir.into(),
+            profile: None,
+            lib_path: None,
+        });
+        self
+    }
+
+    /// Rebuilds the core with `profile` instead of the profile of the shell,
+    /// e.g. [`BuildProfile::fast`], so a change links sooner while the shell
+    /// stays optimized.
+    ///
+    /// Only applies together with [`Self::rebuild_on_change`]. Unless a path
+    /// or directory is given, the core is then loaded from the directory of
+    /// `profile`.
+    pub fn build_profile(mut self, profile: BuildProfile) -> Self {
+        self.build_profile = Some(profile);
+        self
+    }
+
+    /// Loads the core built with the cargo profile `profile`, e.g. `dev` or
+    /// a custom `dev-fast`, from `target/<profile>` instead of the directory
+    /// of the shell's profile, so a release shell can host a debug core.
+    ///
+    /// With [`Self::rebuild_on_change`], the core is rebuilt with this
+    /// profile as declared in `Cargo.toml`, unless [`Self::build_profile`]
+    /// is given as well.
+    pub fn core_profile(mut self, profile: impl Into<String>) -> Self {
+        self.core_profile = Some(profile.into());
+        self
+    }
+
+    /// Receives libraries pushed with [`crate::push_library`] on `addr`,
+    /// e.g. to develop on a device while building on another machine.
+    ///
+    /// A pushed library is written to the staging directory, verified
+    /// against its checksum and then replaces the library of the shell,
+    /// which is reloaded. The library file is neither watched nor rebuilt in
+    /// this mode.
+    ///
+    /// Anyone able to connect to `addr` can run code in the shell, so only
+    /// listen on trusted networks.
+    pub fn receive_pushes(mut self, addr: SocketAddr) -> Self {
+        self.push_addr = Some(addr);
+        self
+    }
+
+    /// Sets the directory pushed libraries are received in, `staging` next
+    /// to the library by default. Keeping it on the same file system as the
+    /// library lets it be replaced atomically.
+    pub fn staging_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.staging_dir = Some(dir.into());
+        self
+    }
+
+    /// Starts a local HTTP server on `addr`, e.g. `127.0.0.1:7878`, so
+    /// editor plugins and scripts can drive the shell.
+    ///
+    /// `POST /reload` reloads the library, `POST /pause` and `POST /resume`
+    /// pause and resume automatic reloading, `GET /state` returns the state
+    /// of the core as JSON and `GET /events` streams reloads, failed builds
+    /// and panics as server-sent events. With
+    /// [`HotReloadBuilder::message_console`], `POST /message` passes the
+    /// message in the JSON body to the core.
+    pub fn control_server(mut self, addr: SocketAddr) -> Self {
+        self.control_addr = Some(addr);
+        self
+    }
+
+    /// Appends the reload events to the file at `path`, one JSON object per
+    /// line with a millisecond timestamp: detected changes, started and
+    /// failed load attempts, completed swaps with their duration, builds,
+    /// rollbacks and panics.
+    ///
+    /// The same events are streamed by the control server, see
+    /// [`HotReloadBuilder::control_server`].
+    pub fn event_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.event_log = Some(path.into());
+        self
+    }
+
+    /// Leaves reloading to external tooling, e.g. a build script, which
+    /// knows exactly when the library is complete, instead of watching the
+    /// library file.
+    ///
+    /// On Unix the shell reloads on `SIGUSR1` and persists the state and
+    /// exits on `SIGTERM`. On Windows it reads the commands `reload` and
+    /// `shutdown`, one per line, from the named pipe
+    /// `\\.\pipe\iced_hot_reload_<lib_name>`. Rebuilding on changes and
+    /// receiving pushes still reload the library themselves.
+    pub fn external_commands(mut self, enabled: bool) -> Self {
+        self.external_commands = enabled;
+        self
+    }
+
+    /// Only reloads the library once it was marked ready with
+    /// [`crate::mark_ready`], so a library the linker is still writing is
+    /// never loaded.
+    ///
+    /// The integrated build runner and the push receiver write the marker
+    /// themselves. Libraries built by hand can be built with the `build_core`
+    /// wrapper of the `app_shell` example, or marked by the build script.
+    pub fn require_ready_marker(mut self, enabled: bool) -> Self {
+        self.require_ready_marker = enabled;
+        self
+    }
+
+    /// Sets how many times loading a changed library is attempted before
+    /// the shell gives up and shows the error, 5 by default.
+    ///
+    /// Failed attempts are retried after 100 ms, doubling the delay every
+    /// time, which covers libraries still locked by the linker on Windows.
+    pub fn reload_attempts(mut self, attempts: u32) -> Self {
+        self.reload_attempts = attempts.max(1);
+        self
+    }
+
+    /// Takes a snapshot of the state after every `messages` messages handled
+    /// by the core.
+    ///
+    /// The snapshots can be stepped through in the dev overlay (F12), which
+    /// restores them into the running core, so a bug can be reproduced
+    /// against freshly reloaded code. Without this, snapshots are only taken
+    /// on demand from the overlay.
+    pub fn snapshot_every(mut self, messages: u32) -> Self {
+        self.snapshot_every = Some(messages.max(1));
+        self
+    }
+
+    /// Sets how many state snapshots are kept, 100 by default.
+    pub fn history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Records every message handled by the core to the file at `path`,
+    /// one JSON object per line with the time it was handled, preceded by
+    /// the state the recording started from.
+    pub fn record_messages(mut self, path: impl Into<PathBuf>) -> Self
+    where
+        M: Serialize,
+    {
+        self.record = Some(RecordConfig {
+            path: path.into(),
+            serialize: |message| serde_json::to_value(message),
+        });
+        self
+    }
+
+    /// Replays the messages recorded with [`HotReloadBuilder::record_messages`]
+    /// to the file at `path`.
+    ///
+    /// Every core, the first one as well as each reloaded one, starts from
+    /// the recorded state and is fed the recorded messages right away, so
+    /// changes to `update` or `view` can be checked against the same
+    /// interaction. Messages handled afterwards are not recorded.
+    pub fn replay_messages(mut self, path: impl Into<PathBuf>) -> Self
+    where
+        M: DeserializeOwned,
+    {
+        self.replay = Some(ReplayConfig {
+            path: path.into(),
+            deserialize: serde_json::from_value,
+        });
+        self
+    }
+
+    /// Adds a console to the dev overlay (F12) which sends messages typed in
+    /// by the user to the core, e.g. `Increment`, or `SetCounter` with the
+    /// payload `42`.
+    ///
+    /// This exercises update paths which have no UI triggering them yet.
+    pub fn message_console(mut self) -> Self
+    where
+        M: DeserializeOwned,
+    {
+        self.console = Some(serde_json::from_value);
+        self
+    }
+
+    /// Describes the view in the RON file at `path` instead of building it
+    /// with the `view` of the core, and redraws it whenever the file is
+    /// saved, so layout, spacing and texts change without a rebuild.
+    ///
+    /// The file contains a [`hot_reload_interface::ViewNode`], whose buttons
+    /// send messages to the core as usual. Placeholders like `{counter}` or
+    /// `{player.name}` in texts are filled in from the state of the core. If
+    /// the file is invalid, the view of the core is shown with the error.
+    ///
+    /// ```ron
+    /// Center(content: Column(spacing: 10.0, centered: true, children: [
+    ///     Button(content: Text(content: "+"), on_press: Some(Increment)),
+    ///     Text(content: "Counter: {counter}", size: Some(24.0)),
+    /// ]))
+    /// ```
+    pub fn view_file(mut self, path: impl Into<PathBuf>) -> Self
+    where
+        M: DeserializeOwned,
+    {
+        self.view_file = Some((path.into(), view_file::parse_ron));
+        self
+    }
+
+    /// Watches the directory of the images, fonts and icons of the
+    /// application, so changing them doesn't require restarting the shell.
+    ///
+    /// The fonts in the directory are loaded when the shell starts and
+    /// loaded again when they change. Images shown with
+    /// `hot_reload_interface::image_handle`, available with the `image`
+    /// feature of the interface, are read again on the next redraw. Cores
+    /// which cache data read from the assets declare
+    /// [`hot_reload_interface::CoreSubscription::assets_changed`] to be told
+    /// when to drop it.
+    pub fn watch_assets(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.assets = Some(dir.into());
+        self
+    }
+
+    /// Loads the widget styling from the TOML or JSON file at `path` and
+    /// hands it to the core, which reads it with
+    /// [`hot_reload_interface::stylesheet`], so paddings, radii and colors
+    /// change without recompiling.
+    ///
+    /// The view is rebuilt with the new stylesheet when the file is saved.
+    /// The palette also becomes the theme of the window, unless the core
+    /// provides its own.
+    ///
+    /// ```toml
+    /// padding = 8.0
+    /// spacing = 12.0
+    ///
+    /// [palette]
+    /// primary = "#3b82f6"
+    /// ```
+    pub fn stylesheet(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stylesheet = Some(path.into());
+        self
+    }
+
+    /// Translates the strings of the core, looked up with
+    /// [`hot_reload_interface::tr`], with the Fluent (`.ftl`) and gettext
+    /// (`.po`) catalogs of `locale` in `dir`, and re-renders the view when
+    /// they change, so strings can be edited live.
+    ///
+    /// The catalogs of a locale are the files in its directory, e.g.
+    /// `locales/en-US/app.ftl`, and the files named after it, e.g.
+    /// `locales/en-US.po`. Strings missing from the catalogs show their key.
+    pub fn localization(mut self, dir: impl Into<PathBuf>, locale: impl Into<String>) -> Self {
+        self.localization = Some((dir.into(), locale.into()));
+        self
+    }
+
+    /// Sets how old the library copies left behind by previous sessions, e.g.
+    /// after a crash, have to be before they are removed on startup.
+    ///
+    /// The copies of the running session are removed as soon as they are
+    /// unloaded. Copies younger than `retention` are kept, as they may belong
+    /// to another shell which is still running. One hour by default.
+    pub fn copy_retention(mut self, retention: Duration) -> Self {
+        self.copy_retention = retention;
+        self
+    }
+
+    /// Sets when libraries replaced by a reload are unloaded.
+    ///
+    /// By default they are unloaded as soon as the shell no longer needs
+    /// them. Cores leaking closures or using thread-local storage may need a
+    /// grace period, [`UnloadPolicy::KeepAlive`] for libraries with
+    /// thread-locals, or [`UnloadPolicy::Never`]. [`UnloadPolicy::Leak`]
+    /// keeps every library mapped for debugging crashes after a reload.
+    pub fn unload_policy(mut self, policy: UnloadPolicy) -> Self {
+        self.unload_policy = policy;
+        self
+    }
+
+    /// Keeps the `count` most recently replaced libraries loaded regardless
+    /// of the unload policy.
+    pub fn keep_libraries(mut self, count: usize) -> Self {
+        self.keep_libraries = count;
+        self
+    }
+
+    /// Reports the allocations each unloaded library left behind in the log
+    /// and the dev overlay if `track` is `true`, to find out why memory grows
+    /// over a session with many reloads.
+    ///
+    /// The allocations are counted by [`crate::CheckedAllocator`], which has
+    /// to be the global allocator of the shell.
+    pub fn track_leaks(mut self, track: bool) -> Self {
+        self.track_leaks = track;
+        self
+    }
+
+    /// Runs a soak test: reloads the core `reloads` times in a row, with
+    /// random messages from [`HotReloadBuilder::soak_messages`] dispatched
+    /// in between, then exits.
+    ///
+    /// The shell exits with an error as soon as a reload fails, the core
+    /// panics or, on Linux, the resident memory grows by more than 128 MiB
+    /// past the first reload, so the test can run unattended, e.g. in CI.
+    /// The library is reloaded whether it changed or not.
+    pub fn soak_test(mut self, reloads: u32) -> Self {
+        self.soak_reloads = Some(reloads);
+        self
+    }
+
+    /// Picks the messages dispatched by the soak test from the recording at
+    /// `path`, made with [`HotReloadBuilder::record_messages`].
+    pub fn soak_messages(mut self, path: impl Into<PathBuf>) -> Self
+    where
+        M: DeserializeOwned,
+    {
+        self.soak_messages = Some(ReplayConfig {
+            path: path.into(),
+            deserialize: serde_json::from_value,
+        });
+        self
+    }
+
+    /// Sets when a detected change of the library is applied, immediately by
+    /// default. [`ReloadStrategy::OnIdle`] and [`ReloadStrategy::Manual`]
+    /// keep a reload from changing the UI in the middle of an interaction.
+    pub fn reload_strategy(mut self, strategy: ReloadStrategy) -> Self {
+        self.reload_strategy = strategy;
+        self
+    }
+
+    /// Sets the checks a rebuilt core has to pass before it replaces the
+    /// running one, see [`ReloadPipeline`]. The outcome of every stage of
+    /// the last reload is shown in the dev overlay.
+    pub fn reload_pipeline(mut self, pipeline: ReloadPipeline) -> Self {
+        self.reload_pipeline = pipeline;
+        self
+    }
+
+    /// Registers `observer` to receive the lifecycle events of the reloads,
+    /// e.g. to collect metrics. Observers are called in the order they were
+    /// registered.
+    pub fn observer(mut self, observer: impl ReloadObserver + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Adds a core named `name`, loaded from the library `lib_name`, to the
+    /// shell started by [`HotReloadBuilder::run_cores`].
+    ///
+    /// The library is looked up like the one of [`HotReloadBuilder::lib_name`]
+    /// and has to export the same create and destroy symbols.
+    pub fn core(mut self, name: impl Into<String>, lib_name: impl Into<String>) -> Self {
+        self.cores.push((name.into(), lib_name.into()));
+        self
+    }
+
+    /// Finishes the configuration.
+    pub fn build(self) -> HotReload<M> {
+        let profile = self
+            .build_profile
+            .filter(|_| self.build.is_some())
+            .or_else(|| self.core_profile.map(BuildProfile::declared));
+        let path = match (self.lib_path, &self.lib_dir, &profile) {
+            (Some(path), _, _) => path,
+            (None, Some(dir), _) => dir.join(lib_file_name(&self.lib_name)),
+            (None, None, Some(profile)) => make_profile_lib_path(&self.lib_name, profile.name()),
+            (None, None, None) => make_lib_path(&self.lib_name),
+        };
+
+        let build = self.build.map(|build| BuildConfig {
+            profile,
+            lib_path: Some(path.clone()),
+            ..build
+        });
+
+        let push = self.push_addr.map(|addr| PushConfig {
+            addr,
+            staging_dir: self
+                .staging_dir
+                .unwrap_or_else(|| path.with_file_name("staging")),
+        });
+
+        let window_geometry = self
+            .remember_window
+            .then(|| window_geometry::default_path(&self.lib_name))
+            .flatten();
+
+        HotReload {
+            settings: ShellSettings {
+                title: self.title,
+                lib_info: LibInfo {
+                    name: self.lib_name,
+                    path,
+                    create_fn_name: self.create_symbol,
+                    destroy_fn_name: self.destroy_symbol,
+                    copy_dir: None,
+                    keep_loaded: self.unload_policy == UnloadPolicy::Leak,
+                    keep_thread_locals: matches!(self.unload_policy, UnloadPolicy::KeepAlive(_)),
+                },
+                poll_interval: self.poll_interval,
+                focus_check: self.focus_check,
+                initial_state: self.initial_state,
+                persist_path: self.persist_path,
+                fresh: self.fresh,
+                build,
+                push,
+                control_addr: self.control_addr,
+                event_log: self.event_log,
+                external_commands: self.external_commands,
+                require_ready_marker: self.require_ready_marker,
+                reload_attempts: self.reload_attempts,
+                snapshot_every: self.snapshot_every,
+                history_capacity: self.history_capacity,
+                record: self.record,
+                replay: self.replay,
+                console: self.console,
+                view_file: self.view_file,
+                assets: self.assets,
+                stylesheet: self.stylesheet,
+                localization: self.localization,
+                window_geometry,
+                copy_retention: self.copy_retention,
+                unload_policy: self.unload_policy,
+                keep_libraries: self.keep_libraries,
+                track_leaks: self.track_leaks,
+                soak: self.soak_reloads.map(|reloads| SoakConfig {
+                    reloads,
+                    messages: self.soak_messages,
+                }),
+                reload_strategy: self.reload_strategy,
+                reload_pipeline: self.reload_pipeline,
+                observers: self.observers,
+            },
+        }
+    }
+
+    /// Finishes the configuration and runs the shell.
+    pub fn run(self) -> iced::Result {
+        self.build().run()
+    }
+
+    /// Hot reloads a plain iced application, given by its `update` and `view`
+    /// functions, without restructuring it into a shell and a core.
+    ///
+    /// The functions live in a library crate built as a `cdylib` and an
+    /// `rlib` which exports them with [`hot_reload_interface::export_app`]
+    /// and is named by [`HotReloadBuilder::lib_name`]. If the library was
+    /// built, it is loaded and reloaded like any core. Otherwise the
+    /// functions linked into the binary run as a regular iced application,
+    /// starting from the default state.
+    ///
+    /// ```ignore
+    /// // Before: iced::application("My app", my_app::update, my_app::view).run()
+    /// HotReload::<my_app::Message>::builder()
+    ///     .title("My app")
+    ///     .lib_name("my_app")
+    ///     .run_app(my_app::update, my_app::view)
+    /// ```
+    pub fn run_app<S: Default + 'static>(
+        self,
+        update: fn(&mut S, M),
+        view: fn(&S) -> Element<'static, M>,
+    ) -> iced::Result {
+        let hot_reload = self.build();
+        let lib_path = &hot_reload.settings.lib_info.path;
+        if lib_path.is_file() {
+            return hot_reload.run();
+        }
+
+        log::warn!(
+            "{} wasn't built, running the linked application without hot reloading",
+            lib_path.display()
+        );
+        let title = hot_reload.settings.title.clone();
+        static_app::run_app(title, FnApp::new(S::default(), update, view))
+    }
+
+    /// Runs a shell composing the views of the cores added with
+    /// [`HotReloadBuilder::core`], arranged according to `layout`.
+    ///
+    /// Each library is watched on its own and a rebuild only reloads the core
+    /// it belongs to, so the others keep their state. Only the title, the
+    /// library directory or core profile, the poll interval and the copy
+    /// retention of this builder apply. The cores are loaded before the
+    /// window opens, so a core failing to load or start fails with
+    /// [`iced::Error::WindowCreationFailed`] carrying why.
+    pub fn run_cores(self, layout: CoreLayout) -> iced::Result {
+        let cores = self
+            .cores
+            .into_iter()
+            .map(|(name, lib_name)| CoreConfig {
+                name,
+                lib_info: LibInfo {
+                    path: match (&self.lib_dir, &self.core_profile) {
+                        (Some(dir), _) => dir.join(lib_file_name(&lib_name)),
+                        (None, Some(profile)) => make_profile_lib_path(&lib_name, profile),
+                        (None, None) => make_lib_path(&lib_name),
+                    },
+                    name: lib_name,
+                    create_fn_name: self.create_symbol.clone(),
+                    destroy_fn_name: self.destroy_symbol.clone(),
+                    copy_dir: None,
+                    keep_loaded: false,
+                    keep_thread_locals: false,
+                },
+            })
+            .collect();
+        let copy_retention = self.copy_retention;
+
+        let shell = ComposedShell::new(self.title, layout, self.poll_interval, cores, |lib_info| {
+            DylibBackend::new(lib_info.clone(), copy_retention)
+        })
+        .map_err(|e| {
+            log::error!("{}", e);
+            iced::Error::WindowCreationFailed(Box::new(e))
+        })?;
+
+        iced::application(
+            ComposedShell::<M, DylibBackend<M>>::title,
+            ComposedShell::<M, DylibBackend<M>>::update,
+            ComposedShell::<M, DylibBackend<M>>::view,
+        )
+        .subscription(ComposedShell::<M, DylibBackend<M>>::subscription)
+        .run_with(move || (shell, Task::none()))
+    }
+
+    /// Shows a list of `cores`, e.g. found with [`crate::discover_cores`],
+    /// and runs the shell with the one picked, so one shell serves many
+    /// cores without flags. If only one of them was built, it is started
+    /// right away.
+    ///
+    /// The picked core replaces the library of this builder and is rebuilt
+    /// whenever its sources change. All other settings apply.
+    pub fn run_picker(self, cores: Vec<DiscoveredCore>) -> iced::Result {
+        iced::application(CorePicker::title, CorePicker::update, CorePicker::view)
+            .subscription(CorePicker::subscription)
+            .theme(CorePicker::theme)
+            .scale_factor(CorePicker::scale_factor)
+            .run_with(move || CorePicker::new(self, cores))?;
+
+        if soak::failed() {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Runs the shell with the core hosted in a separate process instead of
+    /// a dynamic library.
+    ///
+    /// `executable` is a binary calling [`hot_reload_interface::serve`]. It
+    /// is restarted with the current state whenever it is rebuilt, and a
+    /// crash of the core, even a segfault, doesn't take the shell down.
+    /// Messages have to be serializable and the view is described with
+    /// [`hot_reload_interface::ViewNode`]s. Only the title and the initial
+    /// state of this builder apply.
+    pub fn run_subprocess(self, executable: impl Into<PathBuf>) -> iced::Result
+    where
+        M: Serialize + DeserializeOwned,
+    {
+        let title = self.title;
+        let executable = executable.into();
+        let initial_state = self.initial_state;
+
+        iced::application(
+            RemoteShell::<M>::title,
+            RemoteShell::<M>::update,
+            RemoteShell::<M>::view,
+        )
+        .subscription(RemoteShell::<M>::subscription)
+        .run_with(move || {
+            (
+                RemoteShell::new(title, executable, ProcessCore::spawn, initial_state),
+                Task::none(),
+            )
+        })
+    }
+
+    /// Runs the shell with the core compiled to `wasm32-unknown-unknown` and
+    /// executed by wasmtime instead of loaded as a native library.
+    ///
+    /// `module` is the `.wasm` file of a core exporting its entry points with
+    /// [`hot_reload_interface::export_wasm_core`]. The module runs sandboxed
+    /// and is instantiated again with the current state whenever it is
+    /// rebuilt. Like with [`HotReloadBuilder::run_subprocess`], messages have
+    /// to be serializable and the view is described with
+    /// [`hot_reload_interface::ViewNode`]s.
+    #[cfg(feature = "wasm")]
+    pub fn run_wasm(self, module: impl Into<PathBuf>) -> iced::Result
+    where
+        M: Serialize + DeserializeOwned,
+    {
+        let title = self.title;
+        let module = module.into();
+        let initial_state = self.initial_state;
+
+        iced::application(
+            RemoteShell::<M>::title,
+            RemoteShell::<M>::update,
+            RemoteShell::<M>::view,
+        )
+        .subscription(RemoteShell::<M>::subscription)
+        .run_with(move || {
+            (
+                RemoteShell::new(title, module, crate::wasm::WasmCore::spawn, initial_state),
+                Task::none(),
+            )
+        })
+    }
+
+    /// Runs the shell with the core written as a Rhai script, interpreted
+    /// instead of compiled.
+    ///
+    /// `script` defines `update(state, message)`, returning the new state,
+    /// and `view(state)`, built with `column`, `row`, `text`, `button`,
+    /// `space` and `center`, and optionally `init()`. The script is read
+    /// again with the current state whenever it is saved, without running
+    /// the compiler or the linker. Like with
+    /// [`HotReloadBuilder::run_subprocess`], messages have to be
+    /// serializable and the view is described with
+    /// [`hot_reload_interface::ViewNode`]s.
+    #[cfg(feature = "script")]
+    pub fn run_script(self, script: impl Into<PathBuf>) -> iced::Result
+    where
+        M: Serialize + DeserializeOwned,
+    {
+        let title = self.title;
+        let script = script.into();
+        let initial_state = self.initial_state;
+
+        iced::application(
+            RemoteShell::<M>::title,
+            RemoteShell::<M>::update,
+            RemoteShell::<M>::view,
+        )
+        .subscription(RemoteShell::<M>::subscription)
+        .run_with(move || {
+            (
+                RemoteShell::new(
+                    title,
+                    script,
+                    crate::script::ScriptCore::spawn,
+                    initial_state,
+                ),
+                Task::none(),
+            )
+        })
+    }
+}
@@ -0,0 +1,200 @@
+use crate::message::ShellMessage;
+use iced::widget::{button, column, row, scrollable, text, text_editor, Column};
+use iced::{padding, Color, Element};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// The state of the core as a collapsible JSON tree, shown in the dev
+/// overlay.
+///
+/// Nodes are identified by their JSON pointer, e.g. `/todos/0`, so nodes stay
+/// expanded while the state changes around them. The state can also be
+/// edited as JSON text and replaced in the running core.
+pub(crate) struct StateInspector {
+    /// The last state of the core, or the error describing why it couldn't
+    /// be parsed.
+    state: Result<Value, String>,
+
+    /// The pointers of the expanded objects and arrays. The root is expanded
+    /// initially.
+    expanded: HashSet<String>,
+
+    /// The JSON text of the state being edited, if any.
+    editor: Option<text_editor::Content>,
+
+    /// Why the edited state couldn't be applied.
+    edit_error: Option<String>,
+}
+
+impl StateInspector {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Ok(Value::Null),
+            expanded: HashSet::from([String::new()]),
+            editor: None,
+            edit_error: None,
+        }
+    }
+
+    /// Shows the serialized `state`.
+    pub(crate) fn refresh(&mut self, state: &[u8]) {
+        self.state = if state.is_empty() {
+            Ok(Value::Null)
+        } else {
+            serde_json::from_slice(state).map_err(|e| format!("The state isn't JSON: {}", e))
+        };
+    }
+
+    /// Expands the node at `pointer` if it is collapsed, collapses it
+    /// otherwise.
+    pub(crate) fn toggle(&mut self, pointer: String) {
+        if !self.expanded.remove(&pointer) {
+            self.expanded.insert(pointer);
+        }
+    }
+
+    /// Starts editing the current state as pretty-printed JSON.
+    pub(crate) fn start_editing(&mut self) {
+        let json = match &self.state {
+            Ok(state) => serde_json::to_string_pretty(state).unwrap_or_default(),
+            Err(_) => String::new(),
+        };
+
+        self.editor = Some(text_editor::Content::with_text(&json));
+        self.edit_error = None;
+    }
+
+    /// Applies `action` of the user to the edited state.
+    pub(crate) fn perform(&mut self, action: text_editor::Action) {
+        if let Some(editor) = self.editor.as_mut() {
+            editor.perform(action);
+        }
+    }
+
+    /// Discards the edited state.
+    pub(crate) fn stop_editing(&mut self) {
+        self.editor = None;
+        self.edit_error = None;
+    }
+
+    /// Returns the serialized edited state and stops editing, unless the
+    /// edited text isn't valid JSON.
+    pub(crate) fn edited_state(&mut self) -> Option<Vec<u8>> {
+        let editor = self.editor.as_ref()?;
+
+        match serde_json::from_str::<Value>(&editor.text()) {
+            Ok(state) => {
+                self.stop_editing();
+                serde_json::to_vec(&state).ok()
+            }
+            Err(e) => {
+                self.edit_error = Some(format!("Invalid JSON: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Renders the state tree, or the editor while the state is edited.
+    pub(crate) fn view<M: Clone + 'static>(&self) -> Element<'_, ShellMessage<M>> {
+        let error_color = Color::from_rgb(0.9, 0.4, 0.4);
+
+        if let Some(editor) = &self.editor {
+            let mut layout = column![text_editor(editor)
+                .on_action(ShellMessage::StateEditorAction)
+                .size(12)
+                .height(240)]
+            .spacing(4);
+            if let Some(e) = &self.edit_error {
+                layout = layout.push(text(e.clone()).size(12).color(error_color));
+            }
+
+            return layout
+                .push(
+                    row![
+                        button(text("Apply").size(12)).on_press(ShellMessage::ApplyStateEdit),
+                        button(text("Cancel").size(12)).on_press(ShellMessage::CancelStateEdit),
+                    ]
+                    .spacing(4),
+                )
+                .into();
+        }
+
+        let tree: Element<_> = match &self.state {
+            Ok(state) => self
+                .push_node(Column::new().spacing(2), "state", state, String::new(), 0)
+                .into(),
+            Err(e) => text(e.clone()).size(12).color(error_color).into(),
+        };
+
+        column![
+            button(text("Edit").size(12)).on_press(ShellMessage::EditState),
+            scrollable(tree),
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    /// Adds the line of `value` named `label` to `lines`, followed by its
+    /// children if it is expanded.
+    fn push_node<'a, M: Clone + 'static>(
+        &'a self,
+        lines: Column<'a, ShellMessage<M>>,
+        label: &str,
+        value: &'a Value,
+        pointer: String,
+        depth: u16,
+    ) -> Column<'a, ShellMessage<M>> {
+        let indent = f32::from(depth * 12);
+        let children: Vec<(String, &Value)> = match value {
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| (key.clone(), value))
+                .collect(),
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (index.to_string(), value))
+                .collect(),
+            leaf => {
+                let line = row![
+                    text(format!("{}:", label))
+                        .size(12)
+                        .color(Color::from_rgb(0.7, 0.7, 0.7)),
+                    text(leaf.to_string()).size(12).color(Color::WHITE),
+                ]
+                .spacing(6)
+                .padding(padding::left(indent));
+                return lines.push(line);
+            }
+        };
+
+        let expanded = self.expanded.contains(&pointer);
+        let summary = match value {
+            Value::Object(_) => format!("{{{}}}", children.len()),
+            _ => format!("[{}]", children.len()),
+        };
+        let marker = if expanded { "▾" } else { "▸" };
+
+        let toggle = button(text(format!("{} {} {}", marker, label, summary)).size(12))
+            .padding([0, 4])
+            .style(button::text)
+            .on_press(ShellMessage::ToggleStateNode(pointer.clone()));
+        let mut lines = lines.push(row![toggle].padding(padding::left(indent)));
+
+        if !expanded {
+            return lines;
+        }
+
+        for (key, child) in children {
+            let child_pointer = format!("{}/{}", pointer, escape(&key));
+            lines = self.push_node(lines, &key, child, child_pointer, depth + 1);
+        }
+
+        lines
+    }
+}
+
+/// Escapes `key` for use in a JSON pointer.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
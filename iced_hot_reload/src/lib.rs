@@ -0,0 +1,142 @@
+//! Hot reloading for [iced](https://github.com/iced-rs/iced) applications.
+//!
+//! The application logic is compiled as a dynamic library (the core) and
+//! hosted by a shell created with [`HotReload`]. Whenever the library is
+//! rebuilt, the shell loads the new version and hands it the current state.
+//!
+//! The hot reloading lives behind the default `hot-reload` feature. Without
+//! it, only [`run_static`] is left, which runs a core linked into the binary
+//! as a plain iced application, e.g. for release builds.
+
+#[cfg(feature = "hot-reload")]
+mod assets;
+#[cfg(feature = "hot-reload")]
+mod backend;
+#[cfg(feature = "hot-reload")]
+mod build_runner;
+mod commands;
+#[cfg(feature = "hot-reload")]
+mod composed;
+#[cfg(feature = "hot-reload")]
+mod console;
+#[cfg(feature = "hot-reload")]
+mod control;
+#[cfg(feature = "hot-reload")]
+mod copies;
+#[cfg(feature = "hot-reload")]
+mod debug_info;
+#[cfg(feature = "hot-reload")]
+mod discovery;
+#[cfg(feature = "hot-reload")]
+mod doctor;
+#[cfg(feature = "hot-reload")]
+mod event_log;
+#[cfg(feature = "hot-reload")]
+mod external;
+#[cfg(feature = "hot-reload")]
+mod handle;
+#[cfg(feature = "hot-reload")]
+mod headless;
+#[cfg(feature = "hot-reload")]
+mod history;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+#[cfg(feature = "hot-reload")]
+mod inspector;
+#[cfg(feature = "hot-reload")]
+mod loader;
+#[cfg(feature = "hot-reload")]
+mod localization;
+#[cfg(feature = "hot-reload")]
+mod logs;
+mod message;
+#[cfg(feature = "hot-reload")]
+mod mock;
+#[cfg(feature = "hot-reload")]
+mod observer;
+#[cfg(feature = "hot-reload")]
+mod offscreen;
+#[cfg(feature = "hot-reload")]
+mod persistence;
+#[cfg(feature = "hot-reload")]
+mod picker;
+#[cfg(feature = "hot-reload")]
+mod pipeline;
+#[cfg(feature = "hot-reload")]
+mod profiler;
+#[cfg(feature = "hot-reload")]
+mod push;
+#[cfg(feature = "hot-reload")]
+mod ready;
+#[cfg(feature = "hot-reload")]
+mod recording;
+#[cfg(feature = "hot-reload")]
+mod remote;
+#[cfg(feature = "script")]
+mod script;
+#[cfg(feature = "hot-reload")]
+mod shell;
+#[cfg(feature = "hot-reload")]
+mod soak;
+mod static_app;
+#[cfg(feature = "hot-reload")]
+mod strategy;
+#[cfg(feature = "hot-reload")]
+mod stylesheet;
+mod subscriptions;
+#[cfg(feature = "hot-reload")]
+mod tls;
+#[cfg(feature = "hot-reload")]
+mod unload;
+#[cfg(feature = "hot-reload")]
+mod view_file;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "hot-reload")]
+mod watcher;
+#[cfg(feature = "hot-reload")]
+mod widget_state;
+#[cfg(feature = "hot-reload")]
+mod widget_tree;
+#[cfg(feature = "hot-reload")]
+mod window_geometry;
+
+#[cfg(feature = "hot-reload")]
+pub use build_runner::{BuildProfile, Diagnostic, DiagnosticLevel};
+#[cfg(feature = "hot-reload")]
+pub use composed::{ComposedMessage, CoreLayout};
+#[cfg(feature = "hot-reload")]
+pub use discovery::{discover_cores, DiscoveredCore};
+#[cfg(feature = "hot-reload")]
+pub use doctor::{doctor, Check};
+#[cfg(feature = "hot-reload")]
+pub use headless::HeadlessCore;
+#[cfg(feature = "hot-reload")]
+pub use hot_reload::{HotReload, HotReloadBuilder};
+#[cfg(feature = "hot-reload")]
+pub use logs::LogCapture;
+pub use message::ShellMessage;
+#[cfg(feature = "hot-reload")]
+pub use mock::{MockBackend, MockFailure, MockShell};
+#[cfg(feature = "hot-reload")]
+pub use observer::ReloadObserver;
+#[cfg(feature = "hot-reload")]
+pub use offscreen::Screenshot;
+#[cfg(feature = "hot-reload")]
+pub use picker::{CorePicker, PickerMessage};
+#[cfg(feature = "hot-reload")]
+pub use pipeline::{ReloadPipeline, ReloadStage};
+#[cfg(feature = "hot-reload")]
+pub use push::push_library;
+#[cfg(feature = "hot-reload")]
+pub use ready::mark_ready;
+pub use static_app::{run_static, StaticApp};
+#[cfg(feature = "hot-reload")]
+pub use strategy::{FocusCheck, ReloadStrategy};
+#[cfg(feature = "hot-reload")]
+pub use unload::UnloadPolicy;
+
+/// For shells checking that memory crossing the library boundary is freed by
+/// the allocator which allocated it.
+#[cfg(feature = "hot-reload")]
+pub use hot_reload_interface::CheckedAllocator;
@@ -0,0 +1,799 @@
+use crate::copies::{self, LibraryCopy};
+use crate::tls;
+use hot_reload_interface::{
+    checked_allocator_installed, AbiInfo, AbiVersionFn, Capabilities, CapabilitiesFn, CreateFn,
+    DestroyFn, ErrorMessage, HostAllocator, HostCatalog, HostLogger, HostStylesheet, MigrateFn,
+    PresentationFn, RawApp, SelfTestFn, SetAllocatorFn, SetCatalogFn, SetLoggerFn, SetStylesheetFn,
+    StateVersionFn, StateWriter, ABI_VERSION_SYMBOL, CAPABILITIES_SYMBOL, MIGRATE_SYMBOL,
+    PRESENTATION_SYMBOL, SELF_TEST_SYMBOL, SET_ALLOCATOR_SYMBOL, SET_CATALOG_SYMBOL,
+    SET_LOGGER_SYMBOL, SET_STYLESHEET_SYMBOL, STATE_VERSION_SYMBOL,
+};
+use libloading::{Library, Symbol};
+use log::{error, info, trace};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use time::{macros::format_description, OffsetDateTime};
+
+/// Constructs a platform-specific path to a dynamic library file.
+///
+/// This function builds the full `PathBuf` to a compiled dynamic library
+/// (e.g., `.dll`, `.so`, or `.dylib`) in the profile directory of the cargo
+/// target directory, based on the provided logical library name. On
+/// Unix-like platforms cargo prefixes `cdylib` artifacts with `lib`, so the
+/// prefix is added as well.
+///
+/// # Arguments
+///
+/// * `lib_name` - The base name of the dynamic library without extension.
+///
+/// # Returns
+///
+/// A `PathBuf` pointing to the platform-appropriate dynamic library file.
+pub(crate) fn make_lib_path(lib_name: &str) -> PathBuf {
+    let target_dir = target_dir();
+    let profile_dir = profile_dir(&target_dir);
+
+    trace!("Looking for the core in {}", profile_dir.display());
+
+    profile_dir.join(lib_file_name(lib_name))
+}
+
+/// Like [`make_lib_path`], for a core built with the cargo profile
+/// `profile` rather than the profile of the shell, e.g. a debug core loaded
+/// by a release shell.
+pub(crate) fn make_profile_lib_path(lib_name: &str, profile: &str) -> PathBuf {
+    let profile_dir = target_dir().join(profile_dir_name(profile));
+
+    trace!("Looking for the core in {}", profile_dir.display());
+
+    profile_dir.join(lib_file_name(lib_name))
+}
+
+/// Resolves the cargo target directory.
+///
+/// `CARGO_TARGET_DIR` wins if it is set, otherwise `cargo metadata` is asked,
+/// which also accounts for `build.target-dir` in the cargo configuration and
+/// for the shell being started from a subdirectory of the workspace. If cargo
+/// isn't available, `target` in the current directory is assumed.
+fn target_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("CARGO_TARGET_DIR") {
+        return PathBuf::from(dir);
+    }
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = std::process::Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .output();
+
+    let target_directory = match output {
+        Ok(output) if output.status.success() => {
+            serde_json::from_slice::<serde_json::Value>(&output.stdout)
+                .ok()
+                .and_then(|metadata| metadata["target_directory"].as_str().map(PathBuf::from))
+        }
+        Ok(output) => {
+            error!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            error!("Failed to run cargo metadata: {}", e);
+            None
+        }
+    };
+
+    target_directory.unwrap_or_else(|| PathBuf::from("target"))
+}
+
+/// Returns the directory of the current profile in `target_dir`.
+///
+/// When the shell itself runs from `target_dir`, e.g. with `cargo run`, the
+/// core is expected next to it, which picks up custom profiles as well.
+/// Otherwise the profile the shell was built with is used.
+pub(crate) fn profile_dir(target_dir: &Path) -> PathBuf {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+    if let (Some(exe_dir), Ok(target_dir)) = (exe_dir, target_dir.canonicalize())
+        && exe_dir.parent() == Some(target_dir.as_path())
+    {
+        return exe_dir;
+    }
+
+    let profile = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+
+    target_dir.join(profile)
+}
+
+/// Returns the directory in `target` cargo writes the artifacts of the
+/// profile `profile` to: `debug` for `dev` and `test`, `release` for
+/// `bench`, and the name of the profile otherwise.
+pub(crate) fn profile_dir_name(profile: &str) -> &str {
+    match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        profile => profile,
+    }
+}
+
+/// Returns the platform-specific file name of the dynamic library `lib_name`,
+/// e.g. `libapp_core.so` on Linux or `app_core.dll` on Windows.
+pub(crate) fn lib_file_name(lib_name: &str) -> String {
+    let prefix = if cfg!(windows) { "" } else { "lib" };
+
+    let extension = if cfg!(windows) {
+        "dll"
+    } else if cfg!(target_os = "macos") {
+        "dylib"
+    } else {
+        "so"
+    };
+
+    format!("{}{}.{}", prefix, lib_name, extension)
+}
+
+/// Opens the dynamic library located at `path`.
+///
+/// On Linux and macOS the library is opened with `RTLD_NOW | RTLD_LOCAL`: all
+/// symbols are resolved eagerly, so a broken build fails here instead of on
+/// the first call, and the symbols of one core generation never leak into the
+/// global namespace where they could shadow the symbols of the next one.
+///
+/// Note that `dlopen` (and dyld, which caches images by path) returns the
+/// already loaded handle when called twice with the same path, which is why
+/// every generation is loaded from its own copy.
+pub(crate) fn open_library(path: &Path) -> std::result::Result<Library, libloading::Error> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        use libloading::os::unix::{Library as UnixLibrary, RTLD_LOCAL, RTLD_NOW};
+        unsafe { UnixLibrary::open(Some(path), RTLD_NOW | RTLD_LOCAL) }.map(Library::from)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        unsafe { Library::new(path) }
+    }
+}
+
+/// Keeps the library opened from `copy` mapped and its copy on disk for the
+/// rest of the process.
+///
+/// The library is opened a second time and that handle is never closed, so
+/// the loader's reference count never drops to zero, whichever way the
+/// library is dropped.
+fn pin(copy: &mut LibraryCopy) {
+    match open_library(copy.path()) {
+        Ok(library) => std::mem::forget(library),
+        Err(e) => error!("Failed to keep {} loaded: {}", copy.path().display(), e),
+    }
+    copy.keep();
+}
+
+/// Pins the library opened from `copy`, see [`pin`], and logs where it is
+/// mapped.
+fn keep_loaded(copy: &mut LibraryCopy, create_fn: *const ()) {
+    pin(copy);
+
+    let ranges = mapped_ranges(copy.path());
+    if ranges.is_empty() {
+        info!(
+            "Keeping {} loaded, its create function is at {:p}",
+            copy.path().display(),
+            create_fn
+        );
+    } else {
+        info!(
+            "Keeping {} loaded, mapped at {}",
+            copy.path().display(),
+            ranges.join(", ")
+        );
+    }
+}
+
+/// Returns the address ranges `path` is mapped at, e.g. `7f1c2a000000-7f1c2a1b4000`,
+/// read from `/proc/self/maps`. Empty where that doesn't exist, i.e. outside
+/// of Linux.
+fn mapped_ranges(path: &Path) -> Vec<String> {
+    let Ok(maps) = fs::read_to_string("/proc/self/maps") else {
+        return Vec::new();
+    };
+
+    let path = path.to_string_lossy();
+    maps.lines()
+        .filter(|line| line.ends_with(path.as_ref()))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Re-signs the copied library with an ad-hoc signature.
+///
+/// On Apple Silicon every loaded image must carry a valid code signature.
+/// The linker signs the original artifact, but copying it may leave the
+/// kernel with a stale signature cached for the destination vnode, after which
+/// dyld kills the process on load. Forcing a fresh ad-hoc signature on the copy
+/// avoids that. Failures are only logged: on Intel Macs signing is optional.
+#[cfg(target_os = "macos")]
+fn codesign_library(path: &Path) {
+    match std::process::Command::new("codesign")
+        .args(["--force", "--sign", "-"])
+        .arg(path)
+        .output()
+    {
+        Ok(output) if output.status.success() => trace!("Signed {}", path.display()),
+        Ok(output) => log::warn!(
+            "Failed to sign {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => log::warn!("Failed to run codesign: {}", e),
+    }
+}
+
+/// Numbers the libraries loaded by this process, see [`next_generation`].
+/// The number names the copy of a library and attributes its allocations.
+static GENERATION_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+/// Returns the generation of the next library loaded. Generation `0` stands
+/// for the shell itself.
+fn next_generation() -> usize {
+    GENERATION_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Makes the core in `library` allocate through the shell, before anything
+/// else in it is called, attributing its allocations to `generation`.
+///
+/// Cores built before the allocator was shared keep their own, which is the
+/// system allocator like the shell's unless either declares another. A shell
+/// checking for memory freed by the wrong allocator refuses them instead,
+/// since it would abort on the first element of their view.
+unsafe fn share_allocator(library: &Library, generation: usize) -> Result<()> {
+    match unsafe { library.get::<SetAllocatorFn>(SET_ALLOCATOR_SYMBOL.as_bytes()) } {
+        Ok(set_allocator_fn) => {
+            unsafe { set_allocator_fn(&HostAllocator::for_generation(generation)) };
+            Ok(())
+        }
+        Err(_) if checked_allocator_installed() => {
+            let message = format!(
+                "The core doesn't export {}, rebuild it with #[hot_reload_core]",
+                SET_ALLOCATOR_SYMBOL
+            );
+            error!("{}", message);
+            Err(Error::new(ErrorKind::InvalidData, message))
+        }
+        Err(_) => Ok(()),
+    }
+}
+
+/// Verifies that the core in `library` was built with the same contract,
+/// compiler, iced and message type as the shell.
+///
+/// Calling into a core built differently would silently corrupt memory, so
+/// any mismatch is reported as an error instead.
+unsafe fn check_abi<M>(library: &Library) -> Result<()> {
+    let abi_version_fn: Symbol<AbiVersionFn> =
+        match unsafe { library.get(ABI_VERSION_SYMBOL.as_bytes()) } {
+            Ok(s) => s,
+            Err(e) => {
+                error!(
+                    "The core doesn't export {}, rebuild it with #[hot_reload_core]",
+                    ABI_VERSION_SYMBOL
+                );
+                return Err(Error::other(e));
+            }
+        };
+
+    let core = unsafe { abi_version_fn() };
+    let shell = AbiInfo::current::<M>();
+
+    let mismatch = if core.abi_version != shell.abi_version {
+        Some(format!(
+            "The core was built against ABI version {}, the shell expects version {}",
+            core.abi_version, shell.abi_version
+        ))
+    } else if unsafe { core.rustc_version() != shell.rustc_version() } {
+        // Trait objects and std types may be laid out differently by
+        // another compiler, without any error until memory is corrupted.
+        Some(format!(
+            "The core was built with '{}', the shell with '{}'; build both with the same \
+             toolchain, e.g. pinned in rust-toolchain.toml",
+            unsafe { core.rustc_version() },
+            unsafe { shell.rustc_version() }
+        ))
+    } else if unsafe { core.iced_build() != shell.iced_build() } {
+        // Elements of different iced builds differ in layout, passing them
+        // from the core to the shell would be undefined behaviour.
+        Some(format!(
+            "The core was built against {}, the shell against {}; depend on the same iced \
+             version with the same features in both",
+            unsafe { core.iced_build() },
+            unsafe { shell.iced_build() }
+        ))
+    } else if core.schema_hash != shell.schema_hash {
+        Some("The message type of the core differs from the shell's, rebuild the shell".to_string())
+    } else {
+        None
+    };
+
+    match mismatch {
+        Some(message) => {
+            error!("Refusing to load the core: {}", message);
+            Err(Error::new(ErrorKind::InvalidData, message))
+        }
+        None => Ok(()),
+    }
+}
+
+/// A copy of the core library opened by the shell, together with its
+/// resolved entry points.
+///
+/// The function pointers stay valid as long as `library` is loaded, which is
+/// why they are kept together.
+pub(crate) struct LoadedLibrary<M> {
+    /// The function creating a core instance.
+    pub(crate) create_fn: CreateFn<M>,
+
+    /// The function destroying a core instance.
+    pub(crate) destroy_fn: DestroyFn<M>,
+
+    /// The version of the state schema of the core.
+    pub(crate) state_version: u32,
+
+    /// The optional function migrating the state of older schema versions.
+    pub(crate) migrate_fn: Option<MigrateFn>,
+
+    /// The optional function describing the title, theme and scale factor.
+    pub(crate) presentation_fn: Option<PresentationFn<M>>,
+
+    /// The optional self test run before the core is swapped in.
+    pub(crate) self_test_fn: Option<SelfTestFn>,
+
+    /// The optional parts of the contract the core implements.
+    pub(crate) capabilities: Capabilities,
+
+    /// The modification time of the library file this copy was made from.
+    pub(crate) modified: SystemTime,
+
+    /// How long copying and opening the library took.
+    pub(crate) timings: LoadTimings,
+
+    /// The number of this library among those loaded by the process, which
+    /// the allocations of its core are attributed to.
+    pub(crate) generation: usize,
+
+    /// The opened library copy, only held to keep the entry points above
+    /// valid until it is dropped.
+    _library: Library,
+
+    /// The file `_library` was opened from, deleted after `_library` is
+    /// closed since fields are dropped in declaration order.
+    pub(crate) copy: LibraryCopy,
+}
+
+/// How long the steps of loading a library took.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LoadTimings {
+    /// Copying the library to the file it is opened from.
+    pub(crate) copy: Duration,
+
+    /// Opening the copy and resolving its symbols.
+    pub(crate) open: Duration,
+}
+
+/// Loads the library and extracts symbols from it.
+pub(crate) unsafe fn load_library<M>(lib_info: &LibInfo) -> Result<LoadedLibrary<M>> {
+    let started = Instant::now();
+
+    if !(cfg!(windows) || cfg!(target_os = "linux") || cfg!(target_os = "macos")) {
+        error!("The dynamic library loading is implemented only for windows, linux and macos.");
+        return Err(Error::other(
+            "The dynamic library loading is implemented only for windows, linux and macos.",
+        ));
+    }
+
+    let metadata = match std::fs::metadata(&lib_info.path) {
+        Ok(m) => m,
+        Err(e) => {
+            error!("Failed to read metadata: {}", e);
+            return Err(e);
+        }
+    };
+
+    let timestamp = match metadata.modified() {
+        Ok(ts) => ts,
+        Err(e) => {
+            error!("Failed to get modified timestamp: {}", e);
+            return Err(e);
+        }
+    };
+
+    let generation = next_generation();
+    let mut copy = copy_library(lib_info, timestamp, generation)?;
+    let load_lib_path = copy.path();
+
+    #[cfg(target_os = "macos")]
+    codesign_library(load_lib_path);
+
+    let copied = Instant::now();
+    let library = match open_library(load_lib_path) {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Failed to load library: {}", e);
+            return Err(Error::other(e));
+        }
+    };
+
+    unsafe { check_abi::<M>(&library) }?;
+    unsafe { share_allocator(&library, generation) }?;
+
+    let create_fn: Symbol<CreateFn<M>> =
+        match unsafe { library.get(lib_info.create_fn_name.as_bytes()) } {
+            Ok(s) => s,
+            Err(e) => {
+                error!(
+                    "Failed to load symbol {} from library {}",
+                    lib_info.create_fn_name,
+                    load_lib_path.display()
+                );
+                return Err(Error::other(e));
+            }
+        };
+
+    let destroy_fn: Symbol<DestroyFn<M>> =
+        match unsafe { library.get(lib_info.destroy_fn_name.as_bytes()) } {
+            Ok(s) => s,
+            Err(e) => {
+                error!(
+                    "Failed to load symbol {} from library {}",
+                    lib_info.destroy_fn_name,
+                    load_lib_path.display()
+                );
+                return Err(Error::other(e));
+            }
+        };
+
+    let create_fn_raw: CreateFn<M> = unsafe { *create_fn.into_raw() };
+    let destroy_fn_raw: DestroyFn<M> = unsafe { *destroy_fn.into_raw() };
+
+    // Cores built without a state version are treated as version 0.
+    let state_version =
+        match unsafe { library.get::<StateVersionFn>(STATE_VERSION_SYMBOL.as_bytes()) } {
+            Ok(state_version_fn) => unsafe { state_version_fn() },
+            Err(_) => 0,
+        };
+
+    // Cores built before the logger was handed over log on their own.
+    if let Ok(set_logger_fn) = unsafe { library.get::<SetLoggerFn>(SET_LOGGER_SYMBOL.as_bytes()) } {
+        unsafe { set_logger_fn(&HostLogger::new()) };
+    }
+
+    // Cores built before the stylesheet was handed over see the default one.
+    if let Ok(set_stylesheet_fn) =
+        unsafe { library.get::<SetStylesheetFn>(SET_STYLESHEET_SYMBOL.as_bytes()) }
+    {
+        unsafe { set_stylesheet_fn(&HostStylesheet::new()) };
+    }
+
+    // Cores built before the catalog was handed over show the keys.
+    if let Ok(set_catalog_fn) =
+        unsafe { library.get::<SetCatalogFn>(SET_CATALOG_SYMBOL.as_bytes()) }
+    {
+        unsafe { set_catalog_fn(&HostCatalog::new()) };
+    }
+
+    let migrate_fn = unsafe { library.get::<MigrateFn>(MIGRATE_SYMBOL.as_bytes()) }
+        .ok()
+        .map(|migrate_fn| unsafe { *migrate_fn.into_raw() });
+
+    // Cores built before the presentation was introduced leave it to the shell.
+    let presentation_fn =
+        unsafe { library.get::<PresentationFn<M>>(PRESENTATION_SYMBOL.as_bytes()) }
+            .ok()
+            .map(|presentation_fn| unsafe { *presentation_fn.into_raw() });
+
+    let self_test_fn = unsafe { library.get::<SelfTestFn>(SELF_TEST_SYMBOL.as_bytes()) }
+        .ok()
+        .map(|self_test_fn| unsafe { *self_test_fn.into_raw() });
+
+    let capabilities =
+        match unsafe { library.get::<CapabilitiesFn>(CAPABILITIES_SYMBOL.as_bytes()) } {
+            Ok(capabilities_fn) => unsafe { capabilities_fn() },
+            // Cores built before the capabilities were introduced implement
+            // whatever they export, and all of the vtable.
+            Err(_) => {
+                let mut capabilities = Capabilities::SUBSCRIPTIONS | Capabilities::SERIALIZED_STATE;
+                if presentation_fn.is_some() {
+                    capabilities = capabilities | Capabilities::THEME;
+                }
+                if migrate_fn.is_some() {
+                    capabilities = capabilities | Capabilities::MIGRATIONS;
+                }
+                capabilities
+            }
+        };
+    trace!("The core implements {:?}", capabilities.names());
+
+    if lib_info.keep_loaded {
+        keep_loaded(&mut copy, create_fn_raw as *const ());
+    } else if lib_info.keep_thread_locals {
+        match tls::has_thread_locals(copy.path()) {
+            Ok(true) => {
+                trace!(
+                    "{} has thread-local storage, keeping it loaded",
+                    copy.path().display()
+                );
+                pin(&mut copy);
+            }
+            Ok(false) => (),
+            Err(e) => error!(
+                "Failed to read the headers of {}: {}",
+                copy.path().display(),
+                e
+            ),
+        }
+    }
+
+    Ok(LoadedLibrary {
+        create_fn: create_fn_raw,
+        destroy_fn: destroy_fn_raw,
+        state_version,
+        migrate_fn,
+        presentation_fn,
+        self_test_fn,
+        capabilities,
+        modified: timestamp,
+        timings: LoadTimings {
+            copy: copied - started,
+            open: copied.elapsed(),
+        },
+        generation,
+        _library: library,
+        copy,
+    })
+}
+
+/// Copies the library of `lib_info`, last modified at `modified`, to the file
+/// it is loaded from as `generation`.
+fn copy_library(
+    lib_info: &LibInfo,
+    modified: SystemTime,
+    generation: usize,
+) -> Result<LibraryCopy> {
+    let timestamp_dt: OffsetDateTime = modified.into();
+    let suffix = timestamp_dt
+        .format(format_description!(
+            "[year]-[month]-[day]_[hour]-[minute]-[second]"
+        ))
+        .unwrap();
+
+    // The copy goes to the session directory, or next to the library if
+    // there is none. The timestamp only has a resolution of a second, so the
+    // generation keeps a copy of a quick rebuild off the loaded one.
+    let copy_name = lib_file_name(&format!("{}_{}_{}", lib_info.name, suffix, generation));
+    let load_lib_path = match &lib_info.copy_dir {
+        Some(dir) => dir.join(copy_name),
+        None => lib_info.path.with_file_name(copy_name),
+    };
+
+    trace!(
+        "Copy from {} to {}",
+        lib_info.path.display(),
+        load_lib_path.to_str().unwrap()
+    );
+
+    // Overwriting a shared object that is currently mapped by `dlopen` crashes
+    // the process, so the stale copy is unlinked first. The loaded generation
+    // keeps its inode alive until it is closed.
+    if cfg!(unix)
+        && load_lib_path.exists()
+        && let Err(e) = fs::remove_file(&load_lib_path)
+    {
+        error!("Failed to remove stale library copy: {}", e);
+        return Err(e);
+    }
+
+    if let Err(e) = copies::stage(&lib_info.path, &load_lib_path) {
+        error!("Failed to copy library: {}", e);
+        return Err(e);
+    }
+
+    // Removes the copy again if it can't be loaded.
+    let mut copy = LibraryCopy::new(load_lib_path);
+    copy.stage_debug_info(&lib_info.path);
+    Ok(copy)
+}
+
+/// Checks the optional symbols exported by `lib` match the capabilities it
+/// declares, before a reload relies on them.
+pub(crate) fn validate_symbols<M>(lib: &LoadedLibrary<M>) -> Result<()> {
+    let missing = [
+        (
+            Capabilities::MIGRATIONS,
+            lib.migrate_fn.is_some(),
+            MIGRATE_SYMBOL,
+        ),
+        (
+            Capabilities::THEME,
+            lib.presentation_fn.is_some(),
+            PRESENTATION_SYMBOL,
+        ),
+    ]
+    .into_iter()
+    .filter(|(capability, exported, _)| lib.capabilities.contains(*capability) && !exported)
+    .map(|(_, _, symbol)| symbol)
+    .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "The core declares capabilities without exporting {}",
+        missing.join(", ")
+    );
+    error!("{}", message);
+    Err(Error::new(ErrorKind::InvalidData, message))
+}
+
+/// Creates a core instance from `lib` with the serialized initial state
+/// `app_state`. An empty state makes the core start from its default state.
+pub(crate) unsafe fn instantiate<M>(lib: &LoadedLibrary<M>, app_state: &[u8]) -> Result<RawApp<M>> {
+    let mut message = ErrorMessage::new();
+    let app_i = unsafe { (lib.create_fn)(app_state.as_ptr(), app_state.len(), &mut message) };
+
+    if app_i.is_null() {
+        // Cores built with custom entry points may return null without
+        // giving a reason.
+        let message = match message.as_str() {
+            "" => "Failed to initialize the core app".to_string(),
+            reason => format!("Failed to initialize the core app: {}", reason),
+        };
+        error!("{}", message);
+        return Err(Error::other(message));
+    }
+
+    Ok(app_i)
+}
+
+/// Migrates the serialized `app_state` of a core with state schema version
+/// `old_version` for the core in `lib`.
+///
+/// Without an exported migration the state is passed on unchanged, leaving
+/// it to serde defaults to fill in the gaps.
+pub(crate) unsafe fn migrate_state<M>(
+    lib: &LoadedLibrary<M>,
+    old_version: u32,
+    app_state: &[u8],
+) -> Result<Vec<u8>> {
+    let Some(migrate_fn) = lib.migrate_fn else {
+        log::warn!(
+            "The state version changed from {} to {}, but the core exports no {}",
+            old_version,
+            lib.state_version,
+            MIGRATE_SYMBOL
+        );
+        return Ok(app_state.to_vec());
+    };
+
+    trace!(
+        "Migrate the state from version {} to {}",
+        old_version,
+        lib.state_version
+    );
+
+    let mut migrated = Vec::new();
+    let mut writer = StateWriter::new(&mut migrated);
+    let mut message = ErrorMessage::new();
+    let migrated_ok = unsafe {
+        migrate_fn(
+            old_version,
+            app_state.as_ptr(),
+            app_state.len(),
+            &mut writer,
+            &mut message,
+        )
+    };
+
+    if !migrated_ok {
+        error!("Failed to migrate the state: {}", message.as_str());
+        return Err(Error::new(ErrorKind::InvalidData, message.as_str()));
+    }
+
+    Ok(migrated)
+}
+
+/// Contains metadata and symbol names for a dynamically loaded library.
+///
+/// `LibInfo` holds all the information needed to load and interface with a
+/// dynamic application core, including its name, file path, and the names
+/// of its FFI-exported creation and destruction functions.
+#[derive(Clone)]
+pub(crate) struct LibInfo {
+    /// The logical name of the library (e.g., "app_core")
+    pub(crate) name: String,
+
+    /// The filesystem path to the dynamic library (.dll, .so, .dylib)
+    pub(crate) path: PathBuf,
+
+    /// The exported symbol name for the function creating the core instance
+    pub(crate) create_fn_name: String,
+
+    /// The exported symbol name for the function destroying the core instance
+    pub(crate) destroy_fn_name: String,
+
+    /// The directory the library is copied to before it is loaded, next to
+    /// the library itself if `None`
+    pub(crate) copy_dir: Option<PathBuf>,
+
+    /// Whether loaded libraries and their copies are kept for the rest of
+    /// the process, see [`crate::UnloadPolicy::Leak`]
+    pub(crate) keep_loaded: bool,
+
+    /// Whether libraries with thread-local storage are kept loaded for the
+    /// rest of the process, see [`crate::UnloadPolicy::KeepAlive`]
+    pub(crate) keep_thread_locals: bool,
+}
+
+/// Runs the self test exported by the core in `lib`, if any.
+pub(crate) unsafe fn self_test<M>(lib: &LoadedLibrary<M>) -> Result<()> {
+    let Some(self_test_fn) = lib.self_test_fn else {
+        return Ok(());
+    };
+
+    trace!("Run the self test of the core");
+    let mut message = ErrorMessage::new();
+    if !unsafe { self_test_fn(&mut message) } {
+        error!("The self test of the core failed: {}", message.as_str());
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("The self test of the core failed: {}", message.as_str()),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rapid_rebuilds_get_copies_of_their_own() {
+        let dir =
+            std::env::temp_dir().join(format!("iced_hot_reload_copies_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(lib_file_name("core"));
+        fs::write(&path, b"library").unwrap();
+        let lib_info = LibInfo {
+            name: "core".to_string(),
+            path,
+            create_fn_name: "create_app".to_string(),
+            destroy_fn_name: "destroy_app".to_string(),
+            copy_dir: None,
+            keep_loaded: false,
+            keep_thread_locals: false,
+        };
+
+        // Both builds were written within the same second.
+        let modified = SystemTime::now();
+        let first = copy_library(&lib_info, modified, next_generation()).unwrap();
+        let second = copy_library(&lib_info, modified, next_generation()).unwrap();
+
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().exists());
+        assert!(second.path().exists());
+
+        drop((first, second));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
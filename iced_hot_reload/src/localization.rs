@@ -0,0 +1,250 @@
+use crate::assets;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use hot_reload_interface::set_host_catalog;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use unic_langid::LanguageIdentifier;
+
+/// The extensions of the catalog files, Fluent and gettext.
+const CATALOG_EXTENSIONS: [&str; 2] = ["ftl", "po"];
+
+/// The translation catalogs watched by the shell, see
+/// [`crate::HotReloadBuilder::localization`].
+///
+/// The catalogs of the locale are the `.ftl` and `.po` files in the
+/// directory of the locale, e.g. `locales/en-US/app.ftl`, and the files
+/// named after it, e.g. `locales/en-US.po`. Every time they change, they
+/// are read again and handed to the cores through [`set_host_catalog`].
+pub(crate) struct Localization {
+    /// The directory of the catalogs of all locales.
+    dir: PathBuf,
+
+    /// The locale translated to, e.g. `en-US`.
+    locale: String,
+
+    /// What was wrong with the catalogs the last time they were read.
+    errors: Vec<String>,
+}
+
+impl Localization {
+    pub(crate) fn new(dir: PathBuf, locale: String) -> Self {
+        let mut localization = Self {
+            dir,
+            locale,
+            errors: Vec::new(),
+        };
+        localization.reload();
+        localization
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// What was wrong with the catalogs the last time they were read, if
+    /// anything. The messages which could be read are used anyway.
+    pub(crate) fn error(&self) -> Option<String> {
+        (!self.errors.is_empty()).then(|| self.errors.join("\n"))
+    }
+
+    /// Reads the catalogs of the locale again.
+    pub(crate) fn reload(&mut self) {
+        let mut files = assets::files_in(&self.dir.join(&self.locale), &CATALOG_EXTENSIONS);
+        files.extend(
+            CATALOG_EXTENSIONS
+                .iter()
+                .map(|extension| self.dir.join(format!("{}.{}", self.locale, extension)))
+                .filter(|path| path.is_file()),
+        );
+        files.sort();
+
+        let (catalog, errors) = Catalog::read(&self.locale, &files);
+        for e in &errors {
+            log::error!("{}", e);
+        }
+        log::trace!(
+            "Read {} catalogs for {} from {}",
+            files.len(),
+            self.locale,
+            self.dir.display()
+        );
+
+        self.errors = errors;
+        set_host_catalog(Box::new(move |key, args| catalog.lookup(key, args)));
+    }
+}
+
+/// The translations of one locale.
+struct Catalog {
+    /// The messages of the Fluent files.
+    fluent: FluentBundle<FluentResource>,
+
+    /// The messages of the gettext files by their `msgid`.
+    gettext: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Reads the catalog of `locale` from `files`, along with what was wrong
+    /// with them.
+    fn read(locale: &str, files: &[PathBuf]) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+
+        let language = locale.parse::<LanguageIdentifier>().unwrap_or_else(|e| {
+            errors.push(format!("Invalid locale {}: {}", locale, e));
+            LanguageIdentifier::default()
+        });
+        let mut fluent = FluentBundle::new_concurrent(vec![language]);
+        // The isolation marks would show up as boxes in iced.
+        fluent.set_use_isolating(false);
+
+        let mut gettext = HashMap::new();
+
+        for path in files {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    errors.push(format!("Failed to read {}: {}", path.display(), e));
+                    continue;
+                }
+            };
+
+            if assets::has_extension(path, &["po"]) {
+                gettext.extend(parse_po(&contents));
+                continue;
+            }
+
+            let resource = FluentResource::try_new(contents).unwrap_or_else(|(resource, e)| {
+                errors.push(format!("Invalid catalog {}: {:?}", path.display(), e));
+                resource
+            });
+            fluent.add_resource_overriding(resource);
+        }
+
+        (Self { fluent, gettext }, errors)
+    }
+
+    /// The translation of `key` with the named `args`.
+    ///
+    /// Fluent messages are formatted by Fluent, so numeric arguments select
+    /// plural forms. gettext messages get `{name}` replaced with the
+    /// argument `name`.
+    fn lookup(&self, key: &str, args: &[(&str, &str)]) -> Option<String> {
+        if let Some(pattern) = self
+            .fluent
+            .get_message(key)
+            .and_then(|message| message.value())
+        {
+            let mut fluent_args = FluentArgs::new();
+            for (name, value) in args {
+                match value.parse::<f64>() {
+                    Ok(number) => fluent_args.set(*name, number),
+                    Err(_) => fluent_args.set(*name, *value),
+                }
+            }
+
+            let mut errors = Vec::new();
+            let translation = self
+                .fluent
+                .format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                log::error!("Failed to format {}: {:?}", key, errors);
+            }
+            return Some(translation.into_owned());
+        }
+
+        self.gettext.get(key).map(|translation| {
+            args.iter()
+                .fold(translation.clone(), |translation, (name, value)| {
+                    translation.replace(&format!("{{{}}}", name), value)
+                })
+        })
+    }
+}
+
+/// Which string of a gettext entry is being read.
+enum PoField {
+    Id,
+    Translation,
+    Other,
+}
+
+/// Reads the translated entries of a gettext `.po` file. Plural forms are
+/// translated with their first form.
+fn parse_po(contents: &str) -> HashMap<String, String> {
+    let mut entries = HashMap::new();
+    let mut id = String::new();
+    let mut translation = String::new();
+    let mut field = PoField::Other;
+
+    let mut finish_entry = |id: &mut String, translation: &mut String| {
+        // The entry with the empty id is the header.
+        if !id.is_empty() && !translation.is_empty() {
+            entries.insert(std::mem::take(id), std::mem::take(translation));
+        }
+        id.clear();
+        translation.clear();
+    };
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('"') {
+            match field {
+                PoField::Id => id.push_str(&unquote(line)),
+                PoField::Translation => translation.push_str(&unquote(line)),
+                PoField::Other => (),
+            }
+            continue;
+        }
+
+        let (keyword, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match keyword {
+            "msgctxt" => {
+                finish_entry(&mut id, &mut translation);
+                field = PoField::Other;
+            }
+            "msgid" => {
+                finish_entry(&mut id, &mut translation);
+                id = unquote(value.trim());
+                field = PoField::Id;
+            }
+            "msgstr" | "msgstr[0]" => {
+                translation = unquote(value.trim());
+                field = PoField::Translation;
+            }
+            _ => field = PoField::Other,
+        }
+    }
+    finish_entry(&mut id, &mut translation);
+
+    entries
+}
+
+/// The contents of a quoted gettext string, with the escapes resolved.
+fn unquote(quoted: &str) -> String {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .unwrap_or(quoted);
+
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some(escaped) => unquoted.push(escaped),
+            None => (),
+        }
+    }
+
+    unquoted
+}
@@ -0,0 +1,375 @@
+use crate::message::ShellMessage;
+use iced::widget::{button, column, pick_list, row, scrollable, text, text_input, Column};
+use iced::{Color, Element};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::collections::VecDeque;
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+use time::{macros::format_description, OffsetDateTime};
+
+/// How many records are kept by default.
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// How many of the matching records the log pane shows at most, the most
+/// recent ones.
+const MAX_SHOWN: usize = 200;
+
+/// The levels offered by the level filter of the log pane.
+const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+/// The levels offered for the targets of the log pane.
+const TARGET_LEVELS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+/// The maximum level of each target prefix, changed at runtime from the dev
+/// overlay or the control server. The empty prefix applies to all other
+/// targets.
+static TARGET_LEVELS_BY_PREFIX: RwLock<Vec<(String, LevelFilter)>> = RwLock::new(Vec::new());
+
+/// The records captured by the installed [`LogCapture`], shared with the
+/// log pane of the dev overlay.
+static BUFFER: Mutex<LogBuffer> = Mutex::new(LogBuffer {
+    entries: VecDeque::new(),
+    capacity: 0,
+    generation: 0,
+});
+
+/// A bounded buffer of log records, dropping the oldest ones once full.
+struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+
+    /// How many records are kept, 0 until a [`LogCapture`] is installed.
+    capacity: usize,
+
+    /// Incremented with every record, so the log pane knows when to refresh.
+    generation: u64,
+}
+
+/// A captured log record.
+#[derive(Clone)]
+struct LogEntry {
+    level: Level,
+    target: String,
+    message: String,
+    at: SystemTime,
+}
+
+/// A logger keeping the recent records in memory, so the dev overlay (F12)
+/// can show them in its log pane, and passing them on to another logger.
+///
+/// The level of each target is set with [`LogCapture::level`] and can be
+/// changed while the shell runs, from the dev overlay or with
+/// `PUT /log-levels` of the control server. The records of cores go through
+/// the same levels, see [`hot_reload_interface::HostLogger`].
+///
+/// ```ignore
+/// LogCapture::new(SimpleLogger::new(LevelFilter::Trace, Config::default()))
+///     .default_level(LevelFilter::Warn)
+///     .level("app_core", LevelFilter::Trace)
+///     .install()
+///     .expect("Failed to install the logger");
+/// ```
+pub struct LogCapture {
+    inner: Box<dyn Log>,
+    capacity: usize,
+    targets: Vec<String>,
+    levels: Vec<(String, LevelFilter)>,
+}
+
+impl LogCapture {
+    /// Captures the records logged, passing them on to `inner`.
+    pub fn new(inner: Box<dyn Log>) -> Self {
+        Self {
+            inner,
+            capacity: DEFAULT_CAPACITY,
+            targets: Vec::new(),
+            levels: vec![(String::new(), LevelFilter::Trace)],
+        }
+    }
+
+    /// Sets the maximum level of the records whose target starts with
+    /// `prefix`, e.g. `iced_hot_reload::loader`. The longest matching prefix
+    /// applies.
+    pub fn level(mut self, prefix: impl Into<String>, level: LevelFilter) -> Self {
+        set_level(&mut self.levels, prefix.into(), level);
+        self
+    }
+
+    /// Sets the maximum level of the records of targets without a level of
+    /// their own, `Trace` by default.
+    pub fn default_level(self, level: LevelFilter) -> Self {
+        self.level(String::new(), level)
+    }
+
+    /// Sets how many records are kept, 1000 by default.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Only captures records whose target starts with `prefix`, e.g. a crate
+    /// name. All records are captured if no target is allowed, which may let
+    /// the records of iced and its renderer push out the interesting ones.
+    ///
+    /// Records are passed on to the inner logger regardless.
+    pub fn allow_target(mut self, prefix: impl Into<String>) -> Self {
+        self.targets.push(prefix.into());
+        self
+    }
+
+    /// Installs the capture as the global logger.
+    pub fn install(mut self) -> Result<(), SetLoggerError> {
+        if let Ok(mut buffer) = BUFFER.lock() {
+            buffer.capacity = self.capacity;
+        }
+        if let Ok(mut levels) = TARGET_LEVELS_BY_PREFIX.write() {
+            *levels = std::mem::take(&mut self.levels);
+        }
+
+        log::set_boxed_logger(Box::new(self))?;
+        update_max_level();
+        Ok(())
+    }
+
+    fn captures(&self, target: &str) -> bool {
+        self.targets.is_empty() || self.targets.iter().any(|prefix| target.starts_with(prefix))
+    }
+}
+
+impl Log for LogCapture {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= target_level(metadata.target())
+            && (self.inner.enabled(metadata) || self.captures(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() > target_level(record.target()) {
+            return;
+        }
+
+        self.inner.log(record);
+
+        if !self.captures(record.target()) {
+            return;
+        }
+
+        let entry = LogEntry {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            at: SystemTime::now(),
+        };
+
+        if let Ok(mut buffer) = BUFFER.lock() {
+            buffer.entries.push_back(entry);
+            while buffer.entries.len() > buffer.capacity {
+                buffer.entries.pop_front();
+            }
+            buffer.generation += 1;
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Returns the maximum level of `target`, from the longest matching prefix.
+fn target_level(target: &str) -> LevelFilter {
+    let Ok(levels) = TARGET_LEVELS_BY_PREFIX.read() else {
+        return LevelFilter::Trace;
+    };
+
+    levels
+        .iter()
+        .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(LevelFilter::Trace, |(_, level)| *level)
+}
+
+/// Replaces the level of `prefix` in `levels`, or adds it.
+fn set_level(levels: &mut Vec<(String, LevelFilter)>, prefix: String, level: LevelFilter) {
+    match levels.iter_mut().find(|(existing, _)| *existing == prefix) {
+        Some((_, existing)) => *existing = level,
+        None => levels.push((prefix, level)),
+    }
+}
+
+/// Lets `log` skip the records no target prefix wants.
+fn update_max_level() {
+    let max_level = TARGET_LEVELS_BY_PREFIX
+        .read()
+        .ok()
+        .and_then(|levels| levels.iter().map(|(_, level)| *level).max())
+        .unwrap_or(LevelFilter::Trace);
+    log::set_max_level(max_level);
+}
+
+/// Returns the level of each target prefix, the empty prefix standing for
+/// all other targets.
+pub(crate) fn target_levels() -> Vec<(String, LevelFilter)> {
+    TARGET_LEVELS_BY_PREFIX
+        .read()
+        .map(|levels| levels.clone())
+        .unwrap_or_default()
+}
+
+/// Changes the maximum level of the records whose target starts with
+/// `prefix`, while the shell runs.
+pub(crate) fn set_target_level(prefix: String, level: LevelFilter) {
+    if let Ok(mut levels) = TARGET_LEVELS_BY_PREFIX.write() {
+        set_level(&mut levels, prefix, level);
+    }
+    update_max_level();
+}
+
+/// Whether a [`LogCapture`] was installed.
+pub(crate) fn is_capturing() -> bool {
+    BUFFER.lock().is_ok_and(|buffer| buffer.capacity > 0)
+}
+
+/// The log pane of the dev overlay, filtering the captured records by level
+/// and text.
+pub(crate) struct LogViewer {
+    /// The least severe level shown.
+    level: LevelFilter,
+
+    /// Only records containing this text are shown, ignoring case.
+    search: String,
+
+    /// Whether the shown records are frozen, to read them while the core
+    /// keeps logging.
+    paused: bool,
+
+    /// The records shown, copied from the buffer.
+    entries: Vec<LogEntry>,
+
+    /// The generation of the buffer the records were copied at.
+    generation: u64,
+}
+
+impl LogViewer {
+    pub(crate) fn new() -> Self {
+        Self {
+            level: LevelFilter::Debug,
+            search: String::new(),
+            paused: false,
+            entries: Vec::new(),
+            generation: 0,
+        }
+    }
+
+    /// Copies the records captured since the last refresh, unless paused.
+    pub(crate) fn refresh(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        if let Ok(buffer) = BUFFER.lock()
+            && buffer.generation != self.generation
+        {
+            self.entries = buffer.entries.iter().cloned().collect();
+            self.generation = buffer.generation;
+        }
+    }
+
+    pub(crate) fn set_level(&mut self, level: LevelFilter) {
+        self.level = level;
+    }
+
+    pub(crate) fn set_search(&mut self, search: String) {
+        self.search = search;
+    }
+
+    /// Freezes the shown records, or resumes showing new ones.
+    pub(crate) fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.refresh();
+    }
+
+    /// Renders the filters and the most recent matching records.
+    pub(crate) fn view<M: Clone + 'static>(&self) -> Element<'_, ShellMessage<M>> {
+        let search = self.search.to_lowercase();
+        let mut matching: Vec<&LogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.level <= self.level)
+            .filter(|entry| {
+                search.is_empty()
+                    || entry.message.to_lowercase().contains(&search)
+                    || entry.target.to_lowercase().contains(&search)
+            })
+            .take(MAX_SHOWN)
+            .collect();
+        matching.reverse();
+
+        let lines = matching.into_iter().map(|entry| {
+            let color = match entry.level {
+                Level::Error => Color::from_rgb(0.9, 0.4, 0.4),
+                Level::Warn => Color::from_rgb(0.9, 0.7, 0.3),
+                Level::Info => Color::WHITE,
+                Level::Debug | Level::Trace => Color::from_rgb(0.7, 0.7, 0.7),
+            };
+            let at = OffsetDateTime::from(entry.at)
+                .format(format_description!("[hour]:[minute]:[second]"))
+                .unwrap_or_default();
+
+            text(format!(
+                "{} {:<5} {}: {}",
+                at, entry.level, entry.target, entry.message
+            ))
+            .size(11)
+            .color(color)
+            .into()
+        });
+
+        let target_levels = target_levels().into_iter().map(|(prefix, level)| {
+            let label = if prefix.is_empty() {
+                "(other targets)".to_string()
+            } else {
+                prefix.clone()
+            };
+
+            row![
+                text(label).size(12).width(180),
+                pick_list(TARGET_LEVELS, Some(level), move |level| {
+                    ShellMessage::SetTargetLevel(prefix.clone(), level)
+                })
+                .text_size(12),
+            ]
+            .spacing(4)
+            .into()
+        });
+
+        column![
+            Column::with_children(target_levels).spacing(2),
+            row![
+                pick_list(LEVELS, Some(self.level), ShellMessage::LogLevelSelected).text_size(12),
+                text_input("Search", &self.search)
+                    .on_input(ShellMessage::LogSearchChanged)
+                    .size(12),
+                button(text(if self.paused { "Resume" } else { "Pause" }).size(12))
+                    .on_press(ShellMessage::ToggleLogPause),
+            ]
+            .spacing(4),
+            scrollable(Column::with_children(lines).spacing(2))
+                .anchor_bottom()
+                .height(200),
+        ]
+        .spacing(4)
+        .into()
+    }
+}
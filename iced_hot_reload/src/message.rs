@@ -0,0 +1,204 @@
+#[cfg(feature = "hot-reload")]
+use crate::build_runner::Diagnostic;
+#[cfg(feature = "hot-reload")]
+use crate::widget_state::WidgetState;
+use hot_reload_interface::{CommandResult, CoreEvent};
+use iced::widget::text_editor;
+use iced::{window, Point, Size};
+use std::path::PathBuf;
+
+/// Messages handled by the shell.
+///
+/// The shell reserves the control variants for itself and forwards
+/// everything wrapped in [`ShellMessage::App`] to the loaded core, so
+/// applications are free to define their own message type `M`.
+#[derive(Debug, Clone)]
+pub enum ShellMessage<M> {
+    /// Checks whether the library changed and reloads it if so.
+    Tick,
+
+    /// The library was rebuilt. Reloads it unless automatic reloading is
+    /// paused.
+    LibraryChanged,
+
+    /// Reloads the library unconditionally.
+    Reload,
+
+    /// The library being loaded on a background thread is ready, or failed
+    /// to load.
+    LibraryLoaded,
+
+    /// The scroll offsets and focus of the view were captured before a
+    /// reload, which follows.
+    #[cfg(feature = "hot-reload")]
+    WidgetStateCaptured(WidgetState),
+
+    /// Adds the current state to the state history.
+    TakeSnapshot,
+
+    /// Restores the previous snapshot of the state history.
+    HistoryBack,
+
+    /// Restores the next snapshot of the state history.
+    HistoryForward,
+
+    /// Retries a reload which failed, unless another reload succeeded since.
+    RetryReload,
+
+    /// Persists the state and exits.
+    Shutdown,
+
+    /// Pauses or resumes automatic reloading.
+    ToggleAutoReload,
+
+    /// Resumes automatic reloading if `true`, pauses it otherwise.
+    SetAutoReload(bool),
+
+    /// Shows or hides the dev overlay.
+    ToggleOverlay,
+
+    /// Starts keeping the version of the core replaced by a reload next to
+    /// the new one, or stops comparing them.
+    ToggleCompare,
+
+    /// The file describing the view was rewritten.
+    ViewFileChanged,
+
+    /// The window was resized to the size.
+    WindowResized(window::Id, Size),
+
+    /// The window was moved to the position.
+    WindowMoved(window::Id, Point),
+
+    /// Opens the window of the core with the name and size, see
+    /// `CoreCommand::OpenWindow`.
+    OpenWindow { name: String, size: Size },
+
+    /// Closes the window of the core with the name.
+    CloseWindow(String),
+
+    /// The window was opened.
+    WindowOpened(window::Id),
+
+    /// The window was closed by the user or by the shell.
+    WindowClosed(window::Id),
+
+    /// The user asked to close the window, see `CoreEvent::CloseRequested`.
+    CloseRequested(window::Id),
+
+    /// An event of the window, forwarded to the core.
+    CoreEvent(window::Id, CoreEvent),
+
+    /// A command of the core named `request` completed, see
+    /// `CoreCommand::PickFile`.
+    CommandResult {
+        request: String,
+        result: CommandResult,
+    },
+
+    /// The stylesheet file was rewritten.
+    StylesheetChanged,
+
+    /// The translation catalogs were written, created or removed.
+    CatalogsChanged,
+
+    /// The files in the assets directory were written, created or removed.
+    AssetsChanged(Vec<PathBuf>),
+
+    /// Expands or collapses the node of the state inspector at the JSON
+    /// pointer.
+    ToggleStateNode(String),
+
+    /// Starts editing the state in the dev overlay.
+    EditState,
+
+    /// The user edited the state in the dev overlay.
+    StateEditorAction(text_editor::Action),
+
+    /// Replaces the state of the core with the edited state, if it is valid
+    /// JSON.
+    ApplyStateEdit,
+
+    /// Discards the edited state.
+    CancelStateEdit,
+
+    /// Expands or collapses the node of the widget tree inspector at the
+    /// path of child indices.
+    ToggleWidgetNode(String),
+
+    /// Lays out the view of the core again for the widget tree inspector.
+    RefreshWidgetTree,
+
+    /// The name of the message typed into the console changed.
+    ConsoleNameChanged(String),
+
+    /// The payload of the message typed into the console changed.
+    ConsolePayloadChanged(String),
+
+    /// Sends the message typed into the console to the core.
+    SendConsoleMessage,
+
+    /// Reloads the core again or finishes the soak test, once the previous
+    /// reload is done.
+    SoakStep,
+
+    /// Copies the captured log records to the log pane.
+    RefreshLogs,
+
+    /// Shows only log records of the level or more severe ones.
+    LogLevelSelected(log::LevelFilter),
+
+    /// Shows only log records containing the text.
+    LogSearchChanged(String),
+
+    /// Changes the maximum level of the log records whose target starts with
+    /// the prefix.
+    SetTargetLevel(String, log::LevelFilter),
+
+    /// Freezes or unfreezes the records shown in the log pane.
+    ToggleLogPause,
+
+    /// Replaces the running core with an instance created with the
+    /// serialized state.
+    ReplaceState(Vec<u8>),
+
+    /// A message for the core in its JSON representation, sent to the
+    /// control server.
+    ControlMessage(serde_json::Value),
+
+    /// Replaces the running core with an instance of the earlier build of
+    /// the library made at the time, handing over the current state.
+    LoadBuild(std::time::SystemTime),
+
+    /// The integrated build runner started to rebuild the core.
+    Building,
+
+    /// The integrated build runner finished, with the compiler messages if
+    /// it failed.
+    #[cfg(feature = "hot-reload")]
+    BuildFinished(Result<(), Vec<Diagnostic>>),
+
+    /// Replaces a panicked core with the previous version of the library.
+    Rollback,
+
+    /// Unloads the libraries replaced by the last reload or rollback, once
+    /// the UI was rebuilt without them.
+    ReleaseRetired,
+
+    /// A key was pressed, forwarded to the core if it subscribed to the key.
+    KeyPressed(String),
+
+    /// The window gained (`true`) or lost (`false`) the focus.
+    FocusChanged(window::Id, bool),
+
+    /// The user used the keyboard, mouse or a touch screen, delaying a
+    /// reload waiting for the UI to be idle.
+    UserInput,
+
+    /// A message of the application, handled by the core.
+    App(M),
+
+    /// A message of the application, handled by the previous version of the
+    /// core shown for comparison.
+    ComparedApp(M),
+}
@@ -0,0 +1,370 @@
+use crate::backend::{CoreInstance, LoaderBackend};
+use crate::message::ShellMessage;
+use crate::shell::ShellApp;
+use crate::widget_state::WidgetState;
+use hot_reload_interface::{
+    deserialize_state, panic_message, serialize_state, AppInterface, CommandResult, CoreCommand,
+    CoreEvent, CoreSubscription, FnApp, Presentation, ReloadInfo, ABI_VERSION,
+};
+use iced::Element;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{Error, ErrorKind, Result};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+/// Creates a core from its serialized state.
+type CreateFn<M> =
+    Arc<dyn Fn(&[u8]) -> std::result::Result<Box<dyn MockCore<M>>, String> + Send + Sync>;
+
+/// A failure [`MockBackend`] simulates when loading a library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockFailure {
+    /// Loading fails as if the library didn't export the symbol.
+    MissingSymbol(String),
+
+    /// The create function of the library returns null, so creating an
+    /// instance fails.
+    NullCreate,
+
+    /// Loading takes the duration, then succeeds.
+    SlowLoad(Duration),
+
+    /// Loading fails as if the core was built against the ABI version.
+    BadAbi(u32),
+
+    /// The instances of the library panic with the message when passed a
+    /// message, so they pass the checks of the reload and the shell rolls
+    /// back to the previous version once it is used.
+    Panic(String),
+}
+
+/// What the backend shares with the clones kept by the test.
+struct Script {
+    /// The failures of the next loads, first to last.
+    failures: VecDeque<MockFailure>,
+
+    /// The number of the current build, raised by [`MockBackend::rebuild`].
+    generation: usize,
+
+    /// How many times a library was loaded.
+    loads: u32,
+}
+
+/// A version of the core loaded by [`MockBackend`].
+pub(crate) struct MockLibrary {
+    generation: usize,
+    built_at: SystemTime,
+    null_create: bool,
+    panic: Option<String>,
+}
+
+/// A loader backend running a core linked into the test instead of loading
+/// it from a library, with scripted failures, so the reload, rollback and
+/// error paths of the shell can be tested without building libraries. See
+/// [`HotReload::mock`](crate::HotReload::mock).
+///
+/// The backend is cloned to keep scripting it after handing it to the
+/// shell:
+///
+/// ```ignore
+/// let backend = MockBackend::new(update, view);
+/// let mut shell = HotReload::<Message>::builder()
+///     .reload_attempts(1)
+///     .build()
+///     .mock(backend.clone())?;
+/// backend.fail_next_load(MockFailure::MissingSymbol("create_app".into()));
+/// shell.reload();
+/// assert!(shell.notice().is_some());
+/// ```
+pub struct MockBackend<M> {
+    create: CreateFn<M>,
+    script: Arc<Mutex<Script>>,
+}
+
+impl<M> Clone for MockBackend<M> {
+    fn clone(&self) -> Self {
+        Self {
+            create: self.create.clone(),
+            script: self.script.clone(),
+        }
+    }
+}
+
+impl<M: 'static> MockBackend<M> {
+    /// Creates the backend running the core given by its `update` and `view`
+    /// functions, as [`hot_reload_interface::export_app`] would export it.
+    pub fn new<S>(update: fn(&mut S, M), view: fn(&S) -> Element<'static, M>) -> Self
+    where
+        S: Serialize + DeserializeOwned + Default + 'static,
+    {
+        let create: CreateFn<M> = Arc::new(move |state| {
+            let state = deserialize_state::<S>(state)?;
+            Ok(Box::new(FnApp::new(state, update, view)) as Box<dyn MockCore<M>>)
+        });
+
+        Self {
+            create,
+            script: Arc::new(Mutex::new(Script {
+                failures: VecDeque::new(),
+                generation: 0,
+                loads: 0,
+            })),
+        }
+    }
+}
+
+impl<M> MockBackend<M> {
+    /// Makes the next load of a library fail with `failure`. Failures are
+    /// used up in the order they were added, one per load.
+    pub fn fail_next_load(&self, failure: MockFailure) {
+        self.script().failures.push_back(failure);
+    }
+
+    /// Simulates a new build of the library, which the shell reloads if it
+    /// reloads automatically.
+    pub fn rebuild(&self) {
+        self.script().generation += 1;
+    }
+
+    /// How many times a library was loaded, including failed loads.
+    pub fn loads(&self) -> u32 {
+        self.script().loads
+    }
+
+    fn script(&self) -> MutexGuard<'_, Script> {
+        self.script.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<M> LoaderBackend<M> for MockBackend<M> {
+    type Library = MockLibrary;
+    type Instance = MockInstance<M>;
+
+    fn load(&mut self) -> Result<MockLibrary> {
+        let (failure, generation) = {
+            let mut script = self.script();
+            script.loads += 1;
+            (script.failures.pop_front(), script.generation)
+        };
+
+        let mut lib = MockLibrary {
+            generation,
+            built_at: SystemTime::now(),
+            null_create: false,
+            panic: None,
+        };
+        match failure {
+            None => (),
+            Some(MockFailure::MissingSymbol(symbol)) => {
+                return Err(Error::other(format!("The core doesn't export {}", symbol)));
+            }
+            Some(MockFailure::NullCreate) => lib.null_create = true,
+            Some(MockFailure::SlowLoad(duration)) => std::thread::sleep(duration),
+            Some(MockFailure::BadAbi(version)) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "The core was built against ABI version {}, the shell expects version {}",
+                        version, ABI_VERSION
+                    ),
+                ));
+            }
+            Some(MockFailure::Panic(panic)) => lib.panic = Some(panic),
+        }
+
+        Ok(lib)
+    }
+
+    fn instantiate(&mut self, lib: &MockLibrary, state: &[u8]) -> Result<MockInstance<M>> {
+        if lib.null_create {
+            return Err(Error::other("Failed to initialize the core app"));
+        }
+
+        let core = guard(|| (self.create)(state))
+            .and_then(|core| core)
+            .map_err(Error::other)?;
+
+        Ok(MockInstance {
+            core,
+            panic: lib.panic.clone(),
+        })
+    }
+
+    fn unload(&mut self, _lib: MockLibrary) {}
+
+    fn changed_since(&self, lib: &MockLibrary) -> bool {
+        self.script().generation != lib.generation
+    }
+
+    fn built_at(&self, lib: &MockLibrary) -> Option<SystemTime> {
+        Some(lib.built_at)
+    }
+}
+
+/// The part of the contract a mocked core implements.
+trait MockCore<M> {
+    fn update(&mut self, message: M) -> Vec<CoreCommand<M>>;
+    fn view(&self) -> Element<'static, M>;
+    fn save_state(&self) -> std::result::Result<Vec<u8>, String>;
+}
+
+impl<S: Serialize, M> MockCore<M> for FnApp<S, M> {
+    fn update(&mut self, message: M) -> Vec<CoreCommand<M>> {
+        AppInterface::update(self, message)
+    }
+
+    fn view(&self) -> Element<'static, M> {
+        AppInterface::view(self)
+    }
+
+    fn save_state(&self) -> std::result::Result<Vec<u8>, String> {
+        serialize_state(self.state())
+    }
+}
+
+/// An instance of a core created by [`MockBackend`].
+pub(crate) struct MockInstance<M> {
+    core: Box<dyn MockCore<M>>,
+
+    /// The panic every update fails with, simulating a broken build.
+    panic: Option<String>,
+}
+
+impl<M> CoreInstance<M> for MockInstance<M> {
+    fn update(&mut self, message: M) -> std::result::Result<Vec<CoreCommand<M>>, String> {
+        if let Some(panic) = &self.panic {
+            return Err(panic.clone());
+        }
+
+        guard(|| self.core.update(message))
+    }
+
+    fn view(&self) -> std::result::Result<Element<'static, M>, String> {
+        guard(|| self.core.view())
+    }
+
+    fn save_state(&self) -> std::result::Result<Vec<u8>, String> {
+        guard(|| self.core.save_state()).and_then(|state| state)
+    }
+
+    fn subscriptions(&self) -> std::result::Result<Vec<CoreSubscription<M>>, String> {
+        guard(Vec::new)
+    }
+
+    fn presentation(&self) -> std::result::Result<Presentation, String> {
+        guard(Presentation::new)
+    }
+
+    fn window_view(&self, _window: &str) -> std::result::Result<Element<'static, M>, String> {
+        guard(|| self.core.view())
+    }
+
+    fn command_result(
+        &self,
+        _request: &str,
+        _result: &CommandResult,
+    ) -> std::result::Result<Option<M>, String> {
+        guard(|| None)
+    }
+
+    fn handles_events(&self) -> bool {
+        false
+    }
+
+    fn on_event(&self, _event: &CoreEvent) -> std::result::Result<Option<M>, String> {
+        guard(|| None)
+    }
+
+    fn after_reload(
+        &mut self,
+        _reload: &ReloadInfo,
+    ) -> std::result::Result<Vec<CoreCommand<M>>, String> {
+        guard(Vec::new)
+    }
+
+    fn destroy(&mut self) {}
+}
+
+/// Runs `f`, turning a panic into its message.
+fn guard<T>(f: impl FnOnce() -> T) -> std::result::Result<T, String> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(payload.as_ref()).to_string())
+}
+
+/// A shell running a core from a [`MockBackend`], without a window.
+///
+/// The tasks returned by the shell, e.g. the commands of the core or the
+/// delayed retries of a failed reload, aren't run; the methods drive the
+/// shell through the steps the tasks would have taken. Register a
+/// [`ReloadObserver`](crate::ReloadObserver) with the builder to follow the
+/// reloads and failures.
+pub struct MockShell<M> {
+    shell: ShellApp<M, MockBackend<M>>,
+}
+
+impl<M> MockShell<M>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    pub(crate) fn new(shell: ShellApp<M, MockBackend<M>>) -> Self {
+        Self { shell }
+    }
+
+    /// Passes `message` to the shell, dropping the task it returns.
+    pub fn update(&mut self, message: ShellMessage<M>) -> &mut Self {
+        let _ = self.shell.update(message);
+        self
+    }
+
+    /// Passes `message` to the core.
+    pub fn send(&mut self, message: M) -> &mut Self {
+        self.update(ShellMessage::App(message))
+    }
+
+    /// Reloads the library, retrying right away as many times as the shell
+    /// retries a failed load, until the new core is swapped in or the shell
+    /// gives up.
+    pub fn reload(&mut self) -> &mut Self {
+        self.update(ShellMessage::Reload);
+        loop {
+            self.update(ShellMessage::WidgetStateCaptured(WidgetState::default()));
+            if !self.shell.is_retrying() {
+                return self;
+            }
+            self.update(ShellMessage::RetryReload);
+        }
+    }
+
+    /// Builds the view of the shell, which calls the `view` of the core.
+    pub fn render(&mut self) -> &mut Self {
+        drop(self.shell.view());
+        self
+    }
+
+    /// Runs the periodic checks of the shell, which rolls a panicked core
+    /// back to the previous version.
+    pub fn tick(&mut self) -> &mut Self {
+        self.update(ShellMessage::Tick);
+        if self.shell.panic().is_some() {
+            self.update(ShellMessage::Rollback);
+        }
+        self
+    }
+
+    /// The serialized state of the core.
+    pub fn save_state(&self) -> Result<Vec<u8>> {
+        self.shell.save_state().map_err(Error::other)
+    }
+
+    /// The message of the last panic of the core, unless it was rolled back.
+    pub fn panic(&self) -> Option<String> {
+        self.shell.panic()
+    }
+
+    /// The notice shown to the user, e.g. why a reload failed.
+    pub fn notice(&self) -> Option<&str> {
+        self.shell.notice()
+    }
+}
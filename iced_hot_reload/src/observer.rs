@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+/// Receives the lifecycle events of the reloads, e.g. to collect metrics or
+/// to trigger custom behaviour like a notification.
+///
+/// Register observers with [`crate::HotReloadBuilder::observer`]. All
+/// methods do nothing by default. They are called on the UI thread, so they
+/// should return quickly.
+pub trait ReloadObserver {
+    /// A new version of the library was detected, before it is loaded.
+    fn on_change_detected(&mut self) {}
+
+    /// Loading the new library started. `attempt` counts from 1 and grows
+    /// while failed loads are retried.
+    fn on_load_start(&mut self, _attempt: u32) {}
+
+    /// The new core replaced the previous one, `duration` after loading
+    /// started.
+    fn on_swap_complete(&mut self, _duration: Duration) {}
+
+    /// Loading or building the new core failed, or the core panicked.
+    fn on_failure(&mut self, _error: &str) {}
+}
+
+/// The built-in observer collecting the statistics shown in the dev overlay.
+#[derive(Default)]
+pub(crate) struct ReloadMetrics {
+    /// How many times the library was reloaded successfully.
+    pub(crate) count: u32,
+
+    /// How many loads and builds failed and how many times the core
+    /// panicked.
+    pub(crate) failures: u32,
+
+    /// How long the last successful reload took.
+    pub(crate) last_duration: Option<Duration>,
+
+    /// How long all successful reloads took together.
+    pub(crate) total_duration: Duration,
+
+    /// The last error raised by a build, a reload or the core.
+    pub(crate) last_error: Option<String>,
+}
+
+/// How long the phases of a reload took, shown in the dev overlay. Phases
+/// which didn't run or weren't measured are `None`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ReloadTimings {
+    /// From the library being written to the shell noticing it, including
+    /// waiting for the linker to finish.
+    pub(crate) detection: Option<Duration>,
+
+    /// Copying the library to the file it is opened from.
+    pub(crate) copy: Option<Duration>,
+
+    /// Opening the copy and resolving its symbols.
+    pub(crate) open: Option<Duration>,
+
+    /// Creating the new instance from the state.
+    pub(crate) instantiate: Option<Duration>,
+
+    /// From the swap to the first view built by the new instance.
+    pub(crate) first_frame: Option<Duration>,
+}
+
+impl ReloadMetrics {
+    /// How long a successful reload took on average.
+    pub(crate) fn average_duration(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total_duration / self.count)
+    }
+}
+
+impl ReloadObserver for ReloadMetrics {
+    fn on_swap_complete(&mut self, duration: Duration) {
+        self.count += 1;
+        self.last_duration = Some(duration);
+        self.total_duration += duration;
+    }
+
+    fn on_failure(&mut self, error: &str) {
+        self.failures += 1;
+        self.last_error = Some(error.to_string());
+    }
+}
@@ -0,0 +1,164 @@
+use iced::advanced::graphics::Viewport;
+use iced::advanced::layout::{Layout, Limits};
+use iced::advanced::mouse::Cursor;
+use iced::advanced::renderer::Style;
+use iced::advanced::widget::Tree;
+use iced::{Element, Font, Pixels, Rectangle, Size, Theme};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Result};
+use std::path::Path;
+
+/// How much a channel of a pixel may differ before the pixel counts as
+/// changed, absorbing differences in anti-aliasing.
+const CHANNEL_TOLERANCE: u8 = 8;
+
+/// A view rasterized offscreen, as RGBA pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Lays out and draws `element` in a window of `size` with the software
+    /// renderer, without opening a window.
+    ///
+    /// Only system fonts are available, so text renders differently on
+    /// machines with other fonts installed.
+    pub(crate) fn render<M>(element: &Element<'_, M>, size: Size<u32>, theme: &Theme) -> Self {
+        let bounds = Size::new(size.width as f32, size.height as f32);
+        let mut renderer = software_renderer();
+
+        let widget = element.as_widget();
+        let mut tree = Tree::new(widget);
+        let node = widget.layout(&mut tree, &renderer, &Limits::new(Size::ZERO, bounds));
+        let style = Style {
+            text_color: theme.palette().text,
+        };
+        widget.draw(
+            &tree,
+            &mut renderer,
+            theme,
+            &style,
+            Layout::new(&node),
+            Cursor::Unavailable,
+            &Rectangle::with_size(bounds),
+        );
+
+        let mut bgra = vec![0; size.width as usize * size.height as usize * 4];
+        if let (iced::Renderer::Secondary(renderer), Some(mut pixels), Some(mut mask)) = (
+            &mut renderer,
+            tiny_skia::PixmapMut::from_bytes(&mut bgra, size.width, size.height),
+            tiny_skia::Mask::new(size.width, size.height),
+        ) {
+            renderer.draw::<&str>(
+                &mut pixels,
+                &mut mask,
+                &Viewport::with_physical_size(size, 1.0),
+                &[Rectangle::with_size(bounds)],
+                theme.palette().background,
+                &[],
+            );
+        }
+
+        // The renderer draws for a softbuffer, with red and blue swapped.
+        let rgba = bgra
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect();
+
+        Self {
+            width: size.width,
+            height: size.height,
+            rgba,
+        }
+    }
+
+    /// The width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels, row by row, four bytes each.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Returns the share of pixels, from 0 to 1, which differ from `other`
+    /// by more than anti-aliasing would explain. Screenshots of different
+    /// sizes differ entirely.
+    pub fn diff(&self, other: &Screenshot) -> f32 {
+        if self.width != other.width || self.height != other.height {
+            return 1.0;
+        }
+
+        let pixels = self.rgba.len() / 4;
+        if pixels == 0 {
+            return 0.0;
+        }
+
+        let changed = self
+            .rgba
+            .chunks_exact(4)
+            .zip(other.rgba.chunks_exact(4))
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+            })
+            .count();
+
+        changed as f32 / pixels as f32
+    }
+
+    /// Writes the screenshot as a PNG file.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.rgba))
+            .map_err(Error::other)
+    }
+
+    /// Reads a screenshot written by [`Screenshot::save_png`].
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        let mut reader = png::Decoder::new(file)
+            .read_info()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        let mut rgba = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut rgba)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Only 8-bit RGBA images are supported",
+            ));
+        }
+        rgba.truncate(info.buffer_size());
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            rgba,
+        })
+    }
+}
+
+/// The software renderer drawing offscreen, with the default font and text
+/// size of the shell.
+pub(crate) fn software_renderer() -> iced::Renderer {
+    iced::Renderer::Secondary(iced_tiny_skia::Renderer::new(Font::default(), Pixels(16.0)))
+}
@@ -0,0 +1,76 @@
+use log::{error, trace};
+use std::fs;
+use std::io::Result;
+use std::path::PathBuf;
+
+/// Keeps a snapshot of the serialized application state on disk, so the
+/// state survives restarts of the shell.
+pub(crate) struct StatePersistence {
+    /// The file the state is written to.
+    path: PathBuf,
+
+    /// The last state written, to skip writes when nothing changed.
+    last_saved: Vec<u8>,
+}
+
+impl StatePersistence {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            last_saved: Vec::new(),
+        }
+    }
+
+    /// Reads the persisted state, if there is one.
+    pub(crate) fn load(&mut self) -> Option<Vec<u8>> {
+        match fs::read(&self.path) {
+            Ok(state) => {
+                trace!("Restored the state from {}", self.path.display());
+                self.last_saved = state.clone();
+                Some(state)
+            }
+            Err(e) => {
+                trace!("No state restored from {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// The state last read or written, if any.
+    pub(crate) fn last_saved(&self) -> Option<&[u8]> {
+        (!self.last_saved.is_empty()).then_some(self.last_saved.as_slice())
+    }
+
+    /// Writes `state` to disk unless it is unchanged since the last write.
+    ///
+    /// The state is written to a temporary file first and then renamed, so a
+    /// crash in the middle of the write never leaves a truncated snapshot.
+    pub(crate) fn save(&mut self, state: &[u8]) {
+        if state == self.last_saved.as_slice() {
+            return;
+        }
+
+        if let Err(e) = self.write(state) {
+            error!(
+                "Failed to persist the state to {}: {}",
+                self.path.display(),
+                e
+            );
+            return;
+        }
+
+        self.last_saved = state.to_vec();
+    }
+
+    fn write(&self, state: &[u8]) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        fs::write(&tmp_path, state)?;
+        fs::rename(&tmp_path, &self.path)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::discovery::DiscoveredCore;
+use crate::hot_reload::HotReloadBuilder;
+use crate::message::ShellMessage;
+use crate::shell::DylibShell;
+use iced::widget::{button, column, container, text, Column};
+use iced::{Color, Element, Length, Subscription, Task, Theme};
+
+/// Messages handled by a [`CorePicker`].
+#[derive(Debug, Clone)]
+pub enum PickerMessage<M> {
+    /// Starts the shell with the core at the index of the list.
+    Pick(usize),
+
+    /// A message of the running shell.
+    Shell(ShellMessage<M>),
+}
+
+/// Lists the cores found in the workspace and runs the shell with the one
+/// picked, see [`HotReloadBuilder::run_picker`].
+pub struct CorePicker<M> {
+    /// The configuration the shell is started with, until a core is picked.
+    builder: Option<HotReloadBuilder<M>>,
+
+    /// The cores to pick from.
+    cores: Vec<DiscoveredCore>,
+
+    /// The shell running the picked core.
+    shell: Option<DylibShell<M>>,
+
+    /// Why the picked core failed to start, shown instead of the list.
+    error: Option<String>,
+}
+
+impl<M> CorePicker<M>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    /// Creates the picker, or starts the shell right away if there is only
+    /// one core built.
+    pub(crate) fn new(
+        builder: HotReloadBuilder<M>,
+        cores: Vec<DiscoveredCore>,
+    ) -> (Self, Task<PickerMessage<M>>) {
+        let mut picker = Self {
+            builder: Some(builder),
+            cores,
+            shell: None,
+            error: None,
+        };
+
+        let built: Vec<usize> = (0..picker.cores.len())
+            .filter(|&index| picker.cores[index].is_built())
+            .collect();
+        let task = match built[..] {
+            [index] => picker.update(PickerMessage::Pick(index)),
+            _ => Task::none(),
+        };
+
+        (picker, task)
+    }
+
+    pub fn title(&self) -> String {
+        match &self.shell {
+            Some(shell) => shell.title(),
+            None => "Pick a core".to_string(),
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.shell
+            .as_ref()
+            .map(DylibShell::theme)
+            .unwrap_or_default()
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.shell.as_ref().map_or(1.0, DylibShell::scale_factor)
+    }
+
+    pub fn update(&mut self, message: PickerMessage<M>) -> Task<PickerMessage<M>> {
+        match message {
+            PickerMessage::Pick(index) => {
+                let (Some(builder), Some(core)) = (self.builder.take(), self.cores.get(index))
+                else {
+                    return Task::none();
+                };
+
+                // The path is resolved again from the name, so a core profile
+                // given to the builder applies.
+                log::trace!("Picked the core {}", core.package);
+                let started = builder
+                    .lib_name(&core.lib_name)
+                    .rebuild_on_change(&core.package, &core.src_dir)
+                    .build()
+                    .into_shell();
+
+                match started {
+                    Ok((shell, replay)) => {
+                        self.shell = Some(shell);
+                        replay.map(PickerMessage::Shell)
+                    }
+                    Err(e) => {
+                        log::error!("{}", e);
+                        self.error = Some(e.to_string());
+                        Task::none()
+                    }
+                }
+            }
+            PickerMessage::Shell(message) => match self.shell.as_mut() {
+                Some(shell) => shell.update(message).map(PickerMessage::Shell),
+                None => Task::none(),
+            },
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, PickerMessage<M>> {
+        if let Some(shell) = &self.shell {
+            return shell.view().map(PickerMessage::Shell);
+        }
+
+        if let Some(e) = &self.error {
+            return container(text(e.clone()).color(Color::from_rgb(0.9, 0.3, 0.3)))
+                .padding(20)
+                .center(Length::Fill)
+                .into();
+        }
+
+        if self.cores.is_empty() {
+            return container(text(
+                "No hot-reloadable cores found. Cores are cdylib crates of the \
+                 workspace depending on hot_reload_interface.",
+            ))
+            .padding(20)
+            .center(Length::Fill)
+            .into();
+        }
+
+        let entries = self.cores.iter().enumerate().map(|(index, core)| {
+            let label = if core.is_built() {
+                text(core.package.clone())
+            } else {
+                text(format!("{} (not built)", core.package)).color(Color::from_rgb(0.5, 0.5, 0.5))
+            };
+
+            button(label)
+                .on_press_maybe(core.is_built().then_some(PickerMessage::Pick(index)))
+                .width(Length::Fill)
+                .into()
+        });
+
+        container(
+            column![
+                text("Pick a core").size(24),
+                Column::with_children(entries).spacing(4),
+            ]
+            .spacing(10)
+            .max_width(400),
+        )
+        .padding(20)
+        .center(Length::Fill)
+        .into()
+    }
+
+    pub fn subscription(&self) -> Subscription<PickerMessage<M>> {
+        match &self.shell {
+            Some(shell) => shell.subscription().map(PickerMessage::Shell),
+            None => Subscription::none(),
+        }
+    }
+}
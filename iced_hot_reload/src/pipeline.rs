@@ -0,0 +1,228 @@
+use crate::offscreen::Screenshot;
+use iced::{Element, Size, Theme};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+/// How long a stage may take by default before it fails the reload.
+const DEFAULT_STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The size of the viewport views are rendered in offscreen.
+const OFFSCREEN_VIEWPORT: Size<u32> = Size::new(1024, 768);
+
+/// A stage a rebuilt core passes before it replaces the running one, after
+/// its library was loaded and its ABI checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadStage {
+    /// Checks the optional symbols exported by the core match the
+    /// capabilities it declares.
+    SymbolValidation,
+
+    /// Runs the self test exported by the core.
+    SelfTest,
+
+    /// Migrates the state to the state schema of the new core.
+    Migrate,
+
+    /// Creates the new instance from the state.
+    Instantiate,
+
+    /// Replays the latest messages on a scratch instance created from the
+    /// same state, which is dropped afterwards.
+    SmokeUpdates,
+
+    /// Lays out and draws the view of the new instance with a software
+    /// renderer, without a window.
+    OffscreenRender,
+
+    /// Checks the new instance can hand its state on at the next reload.
+    SaveState,
+}
+
+impl fmt::Display for ReloadStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::SymbolValidation => "Symbol validation",
+            Self::SelfTest => "Self test",
+            Self::Migrate => "Migrate",
+            Self::Instantiate => "Instantiate",
+            Self::SmokeUpdates => "Smoke updates",
+            Self::OffscreenRender => "Offscreen render",
+            Self::SaveState => "Save state",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The checks a rebuilt core has to pass before it replaces the running
+/// one, see [`crate::HotReloadBuilder::reload_pipeline`].
+///
+/// Every stage has a timeout. The stages run the code of the core on the UI
+/// thread, which the shell can't interrupt, so a stage overrunning its
+/// timeout fails the reload once it returns.
+///
+/// ```ignore
+/// ReloadPipeline::default()
+///     .smoke_updates(10)
+///     .timeout(ReloadStage::Instantiate, Duration::from_millis(500))
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReloadPipeline {
+    /// How many of the latest messages are replayed in
+    /// [`ReloadStage::SmokeUpdates`].
+    pub(crate) smoke_updates: usize,
+
+    /// Whether [`ReloadStage::OffscreenRender`] runs.
+    pub(crate) render_view: bool,
+
+    /// The timeouts differing from the default one.
+    timeouts: Vec<(ReloadStage, Duration)>,
+
+    /// The timeout of the other stages.
+    default_timeout: Duration,
+}
+
+impl Default for ReloadPipeline {
+    /// Runs every stage but the smoke updates, with five seconds each.
+    fn default() -> Self {
+        Self {
+            smoke_updates: 0,
+            render_view: true,
+            timeouts: Vec::new(),
+            default_timeout: DEFAULT_STAGE_TIMEOUT,
+        }
+    }
+}
+
+impl ReloadPipeline {
+    /// Replays the latest `count` messages handled by the running core on a
+    /// scratch instance of the new one, 0 by default.
+    pub fn smoke_updates(mut self, count: usize) -> Self {
+        self.smoke_updates = count;
+        self
+    }
+
+    /// Renders the view of the new instance offscreen before it is swapped
+    /// in, on by default.
+    pub fn render_view(mut self, render_view: bool) -> Self {
+        self.render_view = render_view;
+        self
+    }
+
+    /// Sets the timeout of `stage`.
+    pub fn timeout(mut self, stage: ReloadStage, timeout: Duration) -> Self {
+        self.timeouts.retain(|(timed, _)| *timed != stage);
+        self.timeouts.push((stage, timeout));
+        self
+    }
+
+    /// Sets the timeout of the stages without one of their own.
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    fn timeout_of(&self, stage: ReloadStage) -> Duration {
+        self.timeouts
+            .iter()
+            .find(|(timed, _)| *timed == stage)
+            .map_or(self.default_timeout, |(_, timeout)| *timeout)
+    }
+}
+
+/// How a stage of the last reload went.
+#[derive(Debug, Clone)]
+pub(crate) struct StageOutcome {
+    pub(crate) stage: ReloadStage,
+    pub(crate) duration: Duration,
+    pub(crate) error: Option<String>,
+}
+
+/// The outcomes of the stages of the last reload, shown in the dev overlay.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PipelineReport {
+    pub(crate) stages: Vec<StageOutcome>,
+}
+
+impl PipelineReport {
+    /// Runs `stage` of `pipeline` and records how it went. A stage which
+    /// took longer than its timeout fails, dropping what it returned.
+    pub(crate) fn run<T>(
+        &mut self,
+        pipeline: &ReloadPipeline,
+        stage: ReloadStage,
+        run: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = run();
+        let duration = started.elapsed();
+
+        let timeout = pipeline.timeout_of(stage);
+        let result = match result {
+            Ok(_) if duration > timeout => Err(Error::new(
+                ErrorKind::TimedOut,
+                format!(
+                    "{} took {} ms, longer than its timeout of {} ms",
+                    stage,
+                    duration.as_millis(),
+                    timeout.as_millis()
+                ),
+            )),
+            result => result,
+        };
+
+        if let Err(e) = &result {
+            log::error!("The reload stage {} failed: {}", stage, e);
+        }
+        self.stages.push(StageOutcome {
+            stage,
+            duration,
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+
+        result
+    }
+}
+
+/// Lays out and draws `element` with the software renderer, without
+/// showing it, and drops the pixels. A view panicking while it is laid out or drawn fails the
+/// stage instead of the shell.
+pub(crate) fn render_offscreen<M>(element: Element<'_, M>, theme: &Theme) -> Result<()> {
+    catch_unwind(AssertUnwindSafe(|| {
+        Screenshot::render(&element, OFFSCREEN_VIEWPORT, theme);
+    }))
+    .map_err(|_| Error::other("The view panicked while it was rendered offscreen"))
+}
+
+/// The latest messages handled by the running core, replayed in
+/// [`ReloadStage::SmokeUpdates`].
+pub(crate) struct RecentMessages<M> {
+    capacity: usize,
+    messages: VecDeque<M>,
+}
+
+impl<M: Clone> RecentMessages<M> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(crate) fn push(&mut self, message: &M) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message.clone());
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &M> {
+        self.messages.iter()
+    }
+}
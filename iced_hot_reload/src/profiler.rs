@@ -0,0 +1,250 @@
+use crate::message::ShellMessage;
+use iced::widget::{row, text, Column};
+use iced::{Color, Element};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many of the latest calls of each kind the averages cover.
+const SAMPLES: usize = 120;
+
+/// How many spikes are kept, the oldest are dropped first.
+const SPIKES: usize = 8;
+
+/// How many times the average a call takes to count as a spike.
+const SPIKE_FACTOR: u32 = 3;
+
+/// How long a call takes at least to count as a spike, so calls which are
+/// fast anyway don't show up whenever the average is close to zero.
+const MIN_SPIKE: Duration = Duration::from_millis(2);
+
+/// How many calls are measured before spikes are looked for, so the
+/// average they are compared to settled.
+const MIN_SAMPLES: usize = 10;
+
+/// A kind of call into the core the profiler measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Call {
+    Update,
+    View,
+
+    /// From a message reaching the core to the view being rebuilt for it,
+    /// the time the core adds to a frame.
+    Frame,
+}
+
+impl Call {
+    const ALL: [Call; 3] = [Call::Update, Call::View, Call::Frame];
+
+    fn name(self) -> &'static str {
+        match self {
+            Call::Update => "update",
+            Call::View => "view",
+            Call::Frame => "frame",
+        }
+    }
+}
+
+/// The durations of the latest calls of a kind.
+#[derive(Default)]
+struct Samples {
+    durations: VecDeque<Duration>,
+}
+
+impl Samples {
+    fn push(&mut self, duration: Duration) {
+        if self.durations.len() == SAMPLES {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+    }
+
+    fn average(&self) -> Option<Duration> {
+        let count = u32::try_from(self.durations.len()).ok()?;
+        (count > 0).then(|| self.durations.iter().sum::<Duration>() / count)
+    }
+
+    fn max(&self) -> Option<Duration> {
+        self.durations.iter().max().copied()
+    }
+
+    /// Whether `duration` stands out from the calls measured before.
+    fn is_spike(&self, duration: Duration) -> bool {
+        if self.durations.len() < MIN_SAMPLES || duration < MIN_SPIKE {
+            return false;
+        }
+
+        self.average()
+            .is_some_and(|average| duration > average * SPIKE_FACTOR)
+    }
+}
+
+/// A call which took much longer than the calls before it.
+struct Spike {
+    call: Call,
+    duration: Duration,
+    generation: usize,
+}
+
+/// Measures how long the `update` and `view` calls into the core take, shown
+/// in the dev overlay to notice a change making the core slow right away.
+///
+/// The measurements are tagged with the generation of the library the core
+/// was loaded from. A new generation starts the averages over, keeping the
+/// ones of the previous generation to compare with.
+pub(crate) struct Profiler {
+    /// The generation of the library measured.
+    generation: usize,
+
+    /// The latest calls of each kind, in the order of [`Call::ALL`].
+    samples: [Samples; 3],
+
+    /// The generation measured before and its averages, in the order of
+    /// [`Call::ALL`].
+    previous: Option<(usize, [Option<Duration>; 3])>,
+
+    /// The latest spikes of any generation, oldest first.
+    spikes: VecDeque<Spike>,
+
+    /// When the first message since the last view reached the core.
+    frame_started: Option<Instant>,
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Self {
+        Self {
+            generation: 0,
+            samples: Default::default(),
+            previous: None,
+            spikes: VecDeque::new(),
+            frame_started: None,
+        }
+    }
+
+    /// Notes a message reaching the core, which starts a frame unless one
+    /// was started already.
+    pub(crate) fn message_received(&mut self) {
+        self.frame_started.get_or_insert_with(Instant::now);
+    }
+
+    /// Records a call of the core from `generation` which took `duration`.
+    /// A view ends the frame started by the messages before it.
+    pub(crate) fn record(&mut self, generation: usize, call: Call, duration: Duration) {
+        if generation != self.generation {
+            self.start_generation(generation);
+        }
+
+        self.push(call, duration);
+        if call == Call::View
+            && let Some(started) = self.frame_started.take()
+        {
+            self.push(Call::Frame, started.elapsed());
+        }
+    }
+
+    fn push(&mut self, call: Call, duration: Duration) {
+        let samples = &mut self.samples[call as usize];
+        if samples.is_spike(duration) {
+            log::debug!(
+                "The {} of library #{} took {}",
+                call.name(),
+                self.generation,
+                format_duration(duration)
+            );
+            if self.spikes.len() == SPIKES {
+                self.spikes.pop_front();
+            }
+            self.spikes.push_back(Spike {
+                call,
+                duration,
+                generation: self.generation,
+            });
+        }
+        samples.push(duration);
+    }
+
+    /// Starts measuring `generation`, keeping the averages of the current
+    /// one to compare with.
+    fn start_generation(&mut self, generation: usize) {
+        if self
+            .samples
+            .iter()
+            .any(|samples| !samples.durations.is_empty())
+        {
+            self.previous = Some((
+                self.generation,
+                Call::ALL.map(|call| self.samples[call as usize].average()),
+            ));
+        }
+        self.generation = generation;
+        self.samples = Default::default();
+        self.frame_started = None;
+    }
+
+    /// Renders the averages, the slowest calls and the latest spikes. The
+    /// element owns its text, so it outlives the borrow of the profiler.
+    pub(crate) fn view<'a, M: 'a>(&self) -> Element<'a, ShellMessage<M>> {
+        let label = |label: String| {
+            text(label)
+                .size(12)
+                .width(140)
+                .color(Color::from_rgb(0.7, 0.7, 0.7))
+        };
+
+        let mut lines = Column::new().spacing(2);
+        for call in Call::ALL {
+            let samples = &self.samples[call as usize];
+            let mut value = match (samples.average(), samples.max()) {
+                (Some(average), Some(max)) => format!(
+                    "avg {}, max {}",
+                    format_duration(average),
+                    format_duration(max)
+                ),
+                _ => "-".to_string(),
+            };
+            if let Some(average) = self
+                .previous
+                .and_then(|(_, averages)| averages[call as usize])
+            {
+                value.push_str(&format!(" (before {})", format_duration(average)));
+            }
+
+            lines = lines.push(row![
+                label(format!("{} #{}", call.name(), self.generation)),
+                text(value).size(12).color(Color::WHITE),
+            ]);
+        }
+
+        if let Some((generation, _)) = self.previous {
+            lines = lines.push(
+                text(format!("before: library #{}", generation))
+                    .size(12)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7)),
+            );
+        }
+
+        for spike in self.spikes.iter().rev() {
+            lines = lines.push(row![
+                label(format!("spike #{}", spike.generation)),
+                text(format!(
+                    "{} took {}",
+                    spike.call.name(),
+                    format_duration(spike.duration)
+                ))
+                .size(12)
+                .color(Color::from_rgb(1.0, 0.6, 0.3)),
+            ]);
+        }
+
+        lines.into()
+    }
+}
+
+/// Formats `duration` in microseconds below a millisecond, in milliseconds
+/// otherwise.
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_millis(1) {
+        format!("{} µs", duration.as_micros())
+    } else {
+        format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+    }
+}
@@ -0,0 +1,228 @@
+use crate::message::ShellMessage;
+use crate::ready;
+use iced::futures::channel::mpsc::{unbounded, UnboundedSender};
+use iced::futures::{SinkExt, Stream, StreamExt};
+use iced::stream;
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// The largest library accepted from a client.
+const MAX_LIBRARY_SIZE: u64 = 1 << 30;
+
+/// Where the shell receives libraries pushed with [`push_library`].
+#[derive(Debug, Clone)]
+pub(crate) struct PushConfig {
+    /// The address the shell listens on.
+    pub(crate) addr: SocketAddr,
+
+    /// The directory pushed libraries are written to and verified in before
+    /// they replace the library of the shell.
+    pub(crate) staging_dir: PathBuf,
+}
+
+/// Sent by the client as a line of JSON, followed by the library itself.
+#[derive(Serialize, Deserialize)]
+struct PushHeader {
+    /// The size of the library in bytes.
+    len: u64,
+
+    /// The SHA-256 checksum of the library, in lowercase hex.
+    sha256: String,
+}
+
+/// Listens on the address of `config` for pushed libraries, installs them at
+/// `lib_path` and yields `ShellMessage::LibraryChanged` for each one.
+///
+/// Every connection pushes one library. The client is answered with a line
+/// reading `ok`, or `error: ` followed by the reason the library was
+/// rejected.
+pub(crate) fn listen<M: Send + 'static>(
+    config: PushConfig,
+    lib_path: PathBuf,
+) -> impl Stream<Item = ShellMessage<M>> {
+    stream::channel(16, move |mut output| async move {
+        let (push_tx, mut push_rx) = unbounded();
+        thread::spawn(move || serve(&config, &lib_path, push_tx));
+
+        while let Some(message) = push_rx.next().await {
+            if output.send(message).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Accepts the clients one after the other until the receiver goes away.
+fn serve<M>(config: &PushConfig, lib_path: &Path, output: UnboundedSender<ShellMessage<M>>) {
+    let listener = match TcpListener::bind(config.addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "Failed to listen for pushed libraries on {}: {}",
+                config.addr, e
+            );
+            return;
+        }
+    };
+
+    trace!("Listening for pushed libraries on {}", config.addr);
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to accept a client: {}", e);
+                continue;
+            }
+        };
+
+        let peer = stream
+            .peer_addr()
+            .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+
+        let reply = match receive(&stream, &config.staging_dir, lib_path) {
+            Ok(()) => {
+                trace!("Installed the library pushed by {}", peer);
+                "ok".to_string()
+            }
+            Err(e) => {
+                error!("Rejected the library pushed by {}: {}", peer, e);
+                format!("error: {}", e)
+            }
+        };
+
+        let _ = writeln!(stream, "{}", reply);
+
+        if reply == "ok" && output.unbounded_send(ShellMessage::LibraryChanged).is_err() {
+            return;
+        }
+    }
+}
+
+/// Receives a library from `stream` into `staging_dir`, verifies its
+/// checksum and moves it to `lib_path`.
+fn receive(stream: &TcpStream, staging_dir: &Path, lib_path: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let header: PushHeader =
+        serde_json::from_str(&line).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    if header.len > MAX_LIBRARY_SIZE {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("The library is larger than {} bytes", MAX_LIBRARY_SIZE),
+        ));
+    }
+
+    let file_name = lib_path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The library path has no file name"))?;
+
+    fs::create_dir_all(staging_dir)?;
+    let staged = staging_dir.join(file_name);
+    let partial = staging_dir.join(format!("{}.partial", file_name.to_string_lossy()));
+
+    let mut file = File::create(&partial)?;
+    let mut hasher = Sha256::new();
+    let mut body = reader.take(header.len);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut received = 0;
+
+    loop {
+        let read = body.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..read])?;
+        hasher.update(&buffer[..read]);
+        received += read as u64;
+    }
+
+    file.sync_all()?;
+    drop(file);
+
+    if received != header.len {
+        let _ = fs::remove_file(&partial);
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            format!("Received {} of {} bytes", received, header.len),
+        ));
+    }
+
+    let checksum = to_hex(&hasher.finalize());
+    if !checksum.eq_ignore_ascii_case(&header.sha256) {
+        let _ = fs::remove_file(&partial);
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Checksum mismatch: expected {}, got {}",
+                header.sha256, checksum
+            ),
+        ));
+    }
+
+    fs::rename(&partial, &staged)?;
+    install(&staged, lib_path)
+}
+
+/// Moves the verified library from `staged` to `lib_path` and marks it
+/// ready.
+///
+/// Renaming replaces the library atomically. If the staging directory is on
+/// another file system the library is copied instead, which is fine since
+/// nothing but this receiver reloads the library in this mode.
+fn install(staged: &Path, lib_path: &Path) -> io::Result<()> {
+    if fs::rename(staged, lib_path).is_err() {
+        fs::copy(staged, lib_path)?;
+        fs::remove_file(staged)?;
+    }
+
+    ready::mark_ready(lib_path)
+}
+
+/// Pushes the library at `lib_path` to a shell listening on `addr`.
+///
+/// Returns once the shell verified and installed the library, or with the
+/// reason it was rejected.
+pub fn push_library(addr: impl ToSocketAddrs, lib_path: impl AsRef<Path>) -> io::Result<()> {
+    let library = fs::read(lib_path)?;
+    let header = PushHeader {
+        len: library.len() as u64,
+        sha256: to_hex(&Sha256::digest(&library)),
+    };
+
+    let mut stream = TcpStream::connect(addr)?;
+    let header = serde_json::to_string(&header).map_err(Error::other)?;
+    writeln!(stream, "{}", header)?;
+    stream.write_all(&library)?;
+    stream.flush()?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        "" => Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "The shell closed the connection without answering",
+        )),
+        reply => Err(Error::other(
+            reply.strip_prefix("error: ").unwrap_or(reply).to_string(),
+        )),
+    }
+}
+
+/// Formats `bytes` as lowercase hex.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The contents of a ready marker, describing the library it was written for.
+///
+/// A marker only vouches for the library with the same size and modification
+/// time, so a marker left over from an earlier build never lets the shell
+/// load a library the linker is still writing.
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+struct ReadyMarker {
+    /// The size of the library in bytes.
+    len: u64,
+
+    /// The modification time of the library, in nanoseconds since the epoch.
+    modified: u64,
+}
+
+impl ReadyMarker {
+    /// Describes the library currently at `lib_path`.
+    fn of(lib_path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(lib_path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+            .as_nanos() as u64;
+
+        Ok(Self {
+            len: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// Returns the path of the ready marker of the library at `lib_path`, e.g.
+/// `libapp_core.so.ready`.
+pub(crate) fn marker_path(lib_path: &Path) -> PathBuf {
+    let mut marker = lib_path.as_os_str().to_owned();
+    marker.push(".ready");
+    PathBuf::from(marker)
+}
+
+/// Marks the library at `lib_path` as complete, so a shell requiring ready
+/// markers reloads it.
+///
+/// Call this once the library was fully written, e.g. after `cargo build`
+/// returned. The marker is written to a temporary file first and renamed, so
+/// the shell never reads a partial marker.
+pub fn mark_ready(lib_path: impl AsRef<Path>) -> io::Result<()> {
+    let lib_path = lib_path.as_ref();
+    let marker = ReadyMarker::of(lib_path)?;
+    let contents = serde_json::to_vec(&marker).map_err(Error::other)?;
+
+    let marker_path = marker_path(lib_path);
+    let mut partial = marker_path.as_os_str().to_owned();
+    partial.push(".partial");
+
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, &marker_path)
+}
+
+/// Whether the ready marker of the library at `lib_path` exists and was
+/// written for the library currently there.
+pub(crate) fn is_ready(lib_path: &Path) -> bool {
+    let Ok(contents) = fs::read(marker_path(lib_path)) else {
+        return false;
+    };
+
+    match (
+        serde_json::from_slice::<ReadyMarker>(&contents),
+        ReadyMarker::of(lib_path),
+    ) {
+        (Ok(marker), Ok(current)) => marker == current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_only_vouch_for_the_library_they_were_written_for() {
+        let dir =
+            std::env::temp_dir().join(format!("iced_hot_reload_ready_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let lib_path = dir.join("libcore.so");
+        fs::write(&lib_path, b"library").unwrap();
+
+        assert!(!is_ready(&lib_path));
+
+        mark_ready(&lib_path).unwrap();
+        assert!(is_ready(&lib_path));
+
+        // The linker started writing the next build.
+        fs::write(&lib_path, b"partially written library").unwrap();
+        assert!(!is_ready(&lib_path));
+
+        fs::write(marker_path(&lib_path), b"{").unwrap();
+        assert!(!is_ready(&lib_path));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, LineWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Converts a message to JSON, only available for serializable messages.
+pub(crate) type SerializeFn<M> = fn(&M) -> serde_json::Result<serde_json::Value>;
+
+/// Converts JSON back to a message.
+pub(crate) type DeserializeFn<M> = fn(serde_json::Value) -> serde_json::Result<M>;
+
+/// The first line of a recording.
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    /// The state of the core when the recording started.
+    initial_state: serde_json::Value,
+}
+
+/// A line of a recording following the header.
+#[derive(Serialize, Deserialize)]
+struct RecordedMessage {
+    /// When the message was handled, in milliseconds since the recording
+    /// started.
+    at_ms: u64,
+
+    /// The message itself.
+    message: serde_json::Value,
+}
+
+/// Where to record the messages of the application to.
+pub(crate) struct RecordConfig<M> {
+    pub(crate) path: PathBuf,
+    pub(crate) serialize: SerializeFn<M>,
+}
+
+/// Where to replay the messages of the application from.
+pub(crate) struct ReplayConfig<M> {
+    pub(crate) path: PathBuf,
+    pub(crate) deserialize: DeserializeFn<M>,
+}
+
+/// Writes every message handled by the core to a file, one JSON object per
+/// line, preceded by the state the recording started from.
+pub(crate) struct MessageRecorder<M> {
+    file: LineWriter<File>,
+    started: Instant,
+    serialize: SerializeFn<M>,
+}
+
+impl<M> MessageRecorder<M> {
+    /// Starts a recording at the path of `config`, replacing any previous
+    /// one, from the serialized `initial_state`.
+    pub(crate) fn create(config: RecordConfig<M>, initial_state: &[u8]) -> io::Result<Self> {
+        let mut file = LineWriter::new(File::create(&config.path)?);
+
+        let header = RecordingHeader {
+            initial_state: serde_json::from_slice(initial_state).unwrap_or(serde_json::Value::Null),
+        };
+        write_line(&mut file, &header)?;
+
+        Ok(Self {
+            file,
+            started: Instant::now(),
+            serialize: config.serialize,
+        })
+    }
+
+    /// Appends `message` to the recording.
+    pub(crate) fn record(&mut self, message: &M) {
+        let result = (self.serialize)(message)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            .and_then(|message| {
+                let recorded = RecordedMessage {
+                    at_ms: self.started.elapsed().as_millis() as u64,
+                    message,
+                };
+                write_line(&mut self.file, &recorded)
+            });
+
+        if let Err(e) = result {
+            error!("Failed to record a message: {}", e);
+        }
+    }
+}
+
+/// Writes `value` as a line of JSON.
+fn write_line(file: &mut LineWriter<File>, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *file, value).map_err(Error::other)?;
+    writeln!(file)
+}
+
+/// A recording loaded for replay.
+pub(crate) struct Replay<M> {
+    /// The serialized state the recording started from.
+    pub(crate) initial_state: Vec<u8>,
+
+    /// The recorded messages, in the order they were handled.
+    pub(crate) messages: Vec<M>,
+}
+
+impl<M> Replay<M> {
+    /// Loads the recording at the path of `config`.
+    pub(crate) fn load(config: &ReplayConfig<M>) -> io::Result<Self> {
+        let mut lines = BufReader::new(File::open(&config.path)?).lines();
+        let invalid = |e: serde_json::Error| Error::new(ErrorKind::InvalidData, e);
+
+        let header = lines
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "The recording is empty"))??;
+        let header: RecordingHeader = serde_json::from_str(&header).map_err(invalid)?;
+
+        // A core started from an empty state uses its default state.
+        let initial_state = match header.initial_state {
+            serde_json::Value::Null => Vec::new(),
+            state => serde_json::to_vec(&state).map_err(invalid)?,
+        };
+
+        let mut messages = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let recorded: RecordedMessage = serde_json::from_str(&line).map_err(invalid)?;
+            messages.push((config.deserialize)(recorded.message).map_err(invalid)?);
+        }
+
+        Ok(Self {
+            initial_state,
+            messages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_replay_the_recorded_messages() {
+        let path = std::env::temp_dir().join(format!(
+            "iced_hot_reload_recording_{}.jsonl",
+            std::process::id()
+        ));
+        let initial_state = br#"{"value":3}"#;
+
+        let mut recorder = MessageRecorder::create(
+            RecordConfig {
+                path: path.clone(),
+                serialize: |message: &i32| serde_json::to_value(message),
+            },
+            initial_state,
+        )
+        .unwrap();
+        recorder.record(&1);
+        recorder.record(&-2);
+        drop(recorder);
+
+        let replay = Replay::load(&ReplayConfig {
+            path: path.clone(),
+            deserialize: serde_json::from_value::<i32>,
+        })
+        .unwrap();
+
+        assert_eq!(replay.initial_state, initial_state);
+        assert_eq!(replay.messages, [1, -2]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn recordings_of_a_default_state_replay_from_an_empty_state() {
+        let path = std::env::temp_dir().join(format!(
+            "iced_hot_reload_empty_recording_{}.jsonl",
+            std::process::id()
+        ));
+        let config = ReplayConfig {
+            path: path.clone(),
+            deserialize: serde_json::from_value::<i32>,
+        };
+
+        std::fs::write(&path, "").unwrap();
+        assert!(Replay::load(&config).is_err());
+
+        MessageRecorder::create(
+            RecordConfig {
+                path: path.clone(),
+                serialize: |message: &i32| serde_json::to_value(message),
+            },
+            &[],
+        )
+        .unwrap();
+        let replay = Replay::load(&config).unwrap();
+
+        assert!(replay.initial_state.is_empty());
+        assert!(replay.messages.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use crate::message::ShellMessage;
+use crate::shell::panic_view;
+use crate::watcher;
+use hot_reload_interface::{Request, Response, ViewNode};
+use iced::{Element, Subscription, Task};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Result, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// A connection to a core hosted outside of the shell's address space,
+/// exchanging [`Request`]s and [`Response`]s with it.
+pub(crate) trait Transport<M> {
+    /// Sends `request` and waits for the answer.
+    fn request(&mut self, request: &Request<M>) -> Result<Response<M>>;
+
+    fn init(&mut self, state: &[u8]) -> Result<()> {
+        match self.request(&Request::Init(state.to_vec()))? {
+            Response::Done => Ok(()),
+            Response::Error(e) => Err(Error::other(e)),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn update(&mut self, message: M) -> Result<()> {
+        match self.request(&Request::Update(message))? {
+            Response::Done => Ok(()),
+            Response::Error(e) => Err(Error::other(e)),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn view(&mut self) -> Result<ViewNode<M>> {
+        match self.request(&Request::View)? {
+            Response::View(view) => Ok(view),
+            Response::Error(e) => Err(Error::other(e)),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn save_state(&mut self) -> Result<Vec<u8>> {
+        match self.request(&Request::SaveState)? {
+            Response::State(state) => Ok(state),
+            Response::Error(e) => Err(Error::other(e)),
+            _ => Err(unexpected_response()),
+        }
+    }
+}
+
+/// Starts the core found at the path and creates it from the serialized state.
+pub(crate) type SpawnFn<M> = fn(&Path, &[u8]) -> Result<Box<dyn Transport<M>>>;
+
+/// A core running in a child process, driven over its stdin and stdout.
+pub(crate) struct ProcessCore<M> {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    _message: PhantomData<M>,
+}
+
+impl<M: Serialize + DeserializeOwned + 'static> ProcessCore<M> {
+    /// Starts the core executable at `path` and creates the core from `state`.
+    pub(crate) fn spawn(path: &Path, state: &[u8]) -> Result<Box<dyn Transport<M>>> {
+        log::trace!("Start {}", path.display());
+
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::other("The core process has no pipes"));
+        };
+
+        let mut core = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            _message: PhantomData,
+        };
+        core.init(state)?;
+
+        Ok(Box::new(core))
+    }
+}
+
+impl<M: Serialize + DeserializeOwned> Transport<M> for ProcessCore<M> {
+    fn request(&mut self, request: &Request<M>) -> Result<Response<M>> {
+        serde_json::to_writer(&mut self.stdin, request)?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()?;
+
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            let status = self.child.wait()?;
+            return Err(Error::new(
+                ErrorKind::BrokenPipe,
+                format!("The core process exited ({})", status),
+            ));
+        }
+
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+impl<M> Drop for ProcessCore<M> {
+    fn drop(&mut self) {
+        // Ask politely first, then make sure the process is gone.
+        let _ = self.stdin.write_all(b"\"Shutdown\"\n");
+        let _ = self.stdin.flush();
+        if !matches!(self.child.try_wait(), Ok(Some(_))) {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+pub(crate) fn unexpected_response() -> Error {
+    Error::new(ErrorKind::InvalidData, "Unexpected response from the core")
+}
+
+/// The shell of the backends hosting the core outside of the shell's
+/// address space, in a child process or a wasm sandbox, or interpreting it
+/// from a script.
+///
+/// A crash in the core, even a segfault, only ends the core. The shell
+/// keeps the last known state and starts the core again with it when the
+/// core is rebuilt.
+pub struct RemoteShell<M> {
+    /// The window title.
+    title: String,
+
+    /// The executable or module of the core.
+    path: PathBuf,
+
+    /// Starts the core found at `path`.
+    spawn: SpawnFn<M>,
+
+    /// The running core, if it didn't crash.
+    core: Option<Box<dyn Transport<M>>>,
+
+    /// The last view of the core.
+    view: Option<ViewNode<M>>,
+
+    /// The last known state of the core, used to restart it.
+    state: Vec<u8>,
+
+    /// Why the core isn't running, if it isn't.
+    error: Option<String>,
+}
+
+impl<M> RemoteShell<M>
+where
+    M: Serialize + DeserializeOwned + std::fmt::Debug + Clone + Send + 'static,
+{
+    pub(crate) fn new(
+        title: String,
+        path: PathBuf,
+        spawn: SpawnFn<M>,
+        initial_state: Vec<u8>,
+    ) -> Self {
+        let mut shell = Self {
+            title,
+            path,
+            spawn,
+            core: None,
+            view: None,
+            state: initial_state,
+            error: None,
+        };
+        shell.restart();
+        shell
+    }
+
+    pub fn title(&self) -> String {
+        self.title.clone()
+    }
+
+    pub fn update(&mut self, message: ShellMessage<M>) -> Task<ShellMessage<M>> {
+        match message {
+            ShellMessage::LibraryChanged | ShellMessage::Reload => {
+                if let Some(state) = self.core.as_mut().and_then(|core| core.save_state().ok()) {
+                    self.state = state;
+                }
+                self.core = None;
+                self.restart();
+            }
+            ShellMessage::App(message) => {
+                let result = match self.core.as_mut() {
+                    Some(core) => core.update(message).and_then(|()| {
+                        self.state = core.save_state()?;
+                        self.view = Some(core.view()?);
+                        Ok(())
+                    }),
+                    None => Ok(()),
+                };
+
+                if let Err(e) = result {
+                    self.crashed(e);
+                }
+            }
+            _ => (),
+        }
+
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, ShellMessage<M>> {
+        match (&self.error, &self.view) {
+            (Some(error), _) => panic_view(error),
+            (None, Some(view)) => view.clone().into_element().map(ShellMessage::App),
+            (None, None) => panic_view("The core has no view"),
+        }
+    }
+
+    pub fn subscription(&self) -> Subscription<ShellMessage<M>> {
+        Subscription::run_with_id(self.path.clone(), watcher::watch(self.path.clone()))
+    }
+
+    /// Starts the core with the last known state.
+    fn restart(&mut self) {
+        let result = (self.spawn)(&self.path, &self.state).and_then(|mut core| {
+            self.view = Some(core.view()?);
+            Ok(core)
+        });
+
+        match result {
+            Ok(core) => {
+                self.core = Some(core);
+                self.error = None;
+            }
+            Err(e) => self.crashed(e),
+        }
+    }
+
+    /// Records that the core process failed and stops talking to it.
+    fn crashed(&mut self, error: Error) {
+        log::error!("The core process failed: {}", error);
+        self.core = None;
+        self.error = Some(error.to_string());
+    }
+}
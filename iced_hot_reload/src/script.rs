@@ -0,0 +1,191 @@
+use crate::remote::Transport;
+use hot_reload_interface::{Request, Response};
+use rhai::{Array, Dynamic, Engine, ImmutableString, Map, Scope, AST, FLOAT, INT};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{Error, Result};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// A core written as a [Rhai](https://rhai.rs) script and interpreted by the
+/// shell.
+///
+/// The script defines `update(state, message)`, returning the new state,
+/// and `view(state)`, returning the view built with `column`, `row`, `text`,
+/// `button`, `space` and `center`. An optional `init()` returns the state to
+/// start from. States and messages are passed in their serde form, e.g. a
+/// map for a struct and `"Increment"` for a unit variant.
+///
+/// Nothing is compiled or linked: saving the script reloads it right away.
+pub(crate) struct ScriptCore<M> {
+    engine: Engine,
+    ast: AST,
+    state: Dynamic,
+    _message: PhantomData<M>,
+}
+
+impl<M: Serialize + DeserializeOwned + 'static> ScriptCore<M> {
+    /// Compiles the script at `path` and creates the core from `state`.
+    pub(crate) fn spawn(path: &Path, state: &[u8]) -> Result<Box<dyn Transport<M>>> {
+        log::trace!("Compile {}", path.display());
+
+        let engine = engine();
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(script_error)?;
+
+        let mut core = Self {
+            engine,
+            ast,
+            state: Dynamic::UNIT,
+            _message: PhantomData,
+        };
+        core.init(state)?;
+
+        Ok(Box::new(core))
+    }
+
+    /// Calls the function `name` of the script.
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic> {
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args)
+            .map_err(script_error)
+    }
+
+    fn handle(&mut self, request: &Request<M>) -> Result<Response<M>> {
+        let response = match request {
+            Request::Init(state) if state.is_empty() => {
+                let has_init = self.ast.iter_functions().any(|f| f.name == "init");
+                self.state = if has_init {
+                    self.call("init", ())?
+                } else {
+                    Map::new().into()
+                };
+                Response::Done
+            }
+            Request::Init(state) => {
+                let state: serde_json::Value = serde_json::from_slice(state)?;
+                self.state = rhai::serde::to_dynamic(state).map_err(script_error)?;
+                Response::Done
+            }
+            Request::Update(message) => {
+                let message = rhai::serde::to_dynamic(message).map_err(script_error)?;
+                self.state = self.call("update", (self.state.clone(), message))?;
+                Response::Done
+            }
+            Request::View => {
+                let view = self.call("view", (self.state.clone(),))?;
+                Response::View(rhai::serde::from_dynamic(&view).map_err(script_error)?)
+            }
+            Request::SaveState => {
+                let state: serde_json::Value =
+                    rhai::serde::from_dynamic(&self.state).map_err(script_error)?;
+                Response::State(serde_json::to_vec(&state)?)
+            }
+            Request::Shutdown => Response::Done,
+        };
+
+        Ok(response)
+    }
+}
+
+impl<M: Serialize + DeserializeOwned + 'static> Transport<M> for ScriptCore<M> {
+    /// Errors of the script are errors of the core, which the shell shows
+    /// until the script is saved again.
+    fn request(&mut self, request: &Request<M>) -> Result<Response<M>> {
+        Ok(self
+            .handle(request)
+            .unwrap_or_else(|e| Response::Error(e.to_string())))
+    }
+}
+
+/// Creates the engine with the functions describing the view, which build
+/// the serde form of [`hot_reload_interface::ViewNode`]s.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .register_fn("column", |children: Array| list("Column", children, 0.0))
+        .register_fn("column", |children: Array, spacing: FLOAT| {
+            list("Column", children, spacing)
+        })
+        .register_fn("row", |children: Array| list("Row", children, 0.0))
+        .register_fn("row", |children: Array, spacing: FLOAT| {
+            list("Row", children, spacing)
+        })
+        .register_fn("text", |content: ImmutableString| {
+            text(content, Dynamic::UNIT)
+        })
+        .register_fn("text", |content: ImmutableString, size: INT| {
+            text(content, Dynamic::from(size as f32))
+        })
+        .register_fn("text", |content: ImmutableString, size: FLOAT| {
+            text(content, Dynamic::from(size as f32))
+        })
+        .register_fn("button", |label: ImmutableString| {
+            button(text(label, Dynamic::UNIT), Dynamic::UNIT)
+        })
+        .register_fn("button", |label: ImmutableString, on_press: Dynamic| {
+            button(text(label, Dynamic::UNIT), on_press)
+        })
+        .register_fn("button", |content: Map, on_press: Dynamic| {
+            button(content.into(), on_press)
+        })
+        .register_fn("space", |width: INT, height: INT| {
+            space(width as f32, height as f32)
+        })
+        .register_fn("space", |width: FLOAT, height: FLOAT| {
+            space(width as f32, height as f32)
+        })
+        .register_fn("center", |content: Map| {
+            node("Center", [("content", content.into())])
+        });
+
+    engine
+}
+
+fn list(variant: &str, children: Array, spacing: FLOAT) -> Dynamic {
+    node(
+        variant,
+        [
+            ("children", children.into()),
+            ("spacing", Dynamic::from(spacing as f32)),
+            ("centered", true.into()),
+        ],
+    )
+}
+
+fn text(content: ImmutableString, size: Dynamic) -> Dynamic {
+    node("Text", [("content", content.into()), ("size", size)])
+}
+
+fn button(content: Dynamic, on_press: Dynamic) -> Dynamic {
+    node("Button", [("content", content), ("on_press", on_press)])
+}
+
+fn space(width: f32, height: f32) -> Dynamic {
+    node(
+        "Space",
+        [
+            ("width", Dynamic::from(width)),
+            ("height", Dynamic::from(height)),
+        ],
+    )
+}
+
+/// The serde form of the enum variant `variant` with the struct `fields`.
+fn node<const N: usize>(variant: &str, fields: [(&str, Dynamic); N]) -> Dynamic {
+    let fields: Map = fields
+        .into_iter()
+        .map(|(name, value)| (name.into(), value))
+        .collect();
+
+    let mut node = Map::new();
+    node.insert(variant.into(), fields.into());
+    node.into()
+}
+
+/// Converts an error of the script, e.g. a syntax error or a missing function.
+fn script_error(error: impl std::fmt::Display) -> Error {
+    Error::other(format!("script: {}", error))
+}
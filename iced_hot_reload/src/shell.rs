@@ -0,0 +1,2602 @@
+use crate::assets;
+use crate::backend::{CoreInstance, DylibBackend, LoaderBackend};
+use crate::build_runner::{self, BuildConfig, Diagnostic, DiagnosticLevel};
+use crate::commands;
+use crate::console::MessageConsole;
+use crate::control::{ControlEvent, ControlServer};
+use crate::event_log::EventLog;
+use crate::external;
+use crate::history::StateHistory;
+use crate::inspector::StateInspector;
+use crate::loader::LibInfo;
+use crate::localization::Localization;
+use crate::logs::{self, LogViewer};
+use crate::message::ShellMessage;
+use crate::observer::{ReloadMetrics, ReloadObserver, ReloadTimings};
+use crate::persistence::StatePersistence;
+use crate::pipeline::{
+    render_offscreen, PipelineReport, RecentMessages, ReloadPipeline, ReloadStage,
+};
+use crate::profiler::{Call, Profiler};
+use crate::push::{self, PushConfig};
+use crate::ready;
+use crate::recording::{DeserializeFn, MessageRecorder, RecordConfig, Replay, ReplayConfig};
+use crate::soak::{self, SoakConfig, SoakStep, SoakTest, SOAK_STEP_INTERVAL};
+use crate::strategy::{FocusCheck, ReloadStrategy};
+use crate::stylesheet::StylesheetFile;
+use crate::subscriptions;
+use crate::unload::{LingeringLibraries, UnloadPolicy};
+use crate::view_file::{ParseViewFn, ViewFile};
+use crate::watcher;
+use crate::widget_state::{self, WidgetState};
+use crate::widget_tree::WidgetTreeInspector;
+use crate::window_geometry::WindowGeometryStore;
+use hot_reload_interface::{
+    checked_allocator_installed, live_allocations, CoreCommand, CoreEvent, LiveAllocations,
+    Presentation, ReloadInfo,
+};
+use iced::futures::channel::oneshot;
+use iced::keyboard::{self, key::Named, Key, Modifiers};
+use iced::time::{every, Duration};
+use iced::widget::{
+    button, column, container, pick_list, row, scrollable, stack, text, Column, Container,
+};
+use iced::{window, Alignment, Color, Element, Length, Size, Subscription, Task, Theme};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Instant, SystemTime};
+use time::{macros::format_description, OffsetDateTime};
+
+/// How many unloaded libraries with leaks the dev overlay lists.
+const LEAK_REPORTS: usize = 5;
+
+/// How long the previous library stays loaded after a swap, so the shell can
+/// roll back to it if the new core panics.
+const ROLLBACK_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How long the shell waits before retrying a failed reload for the first
+/// time. The delay doubles with every further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// How many times the retry delay doubles at most, capping it at 6.4 seconds.
+const RETRY_MAX_DOUBLINGS: u32 = 6;
+
+/// How often the library is polled at most while the main window is
+/// unfocused and no change is expected.
+const UNFOCUSED_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long polling stays fast after the main window lost the focus or the
+/// library changed, while a rebuild is likely.
+const FAST_POLL_PERIOD: Duration = Duration::from_secs(60);
+
+/// How often the log pane of the dev overlay picks up new log records.
+const LOG_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The configuration of the shell, assembled by [`crate::HotReloadBuilder`].
+pub(crate) struct ShellSettings<M> {
+    /// The window title, unless the core provides its own.
+    pub(crate) title: String,
+
+    /// Metadata and symbol names used to identify and load the dynamic core.
+    pub(crate) lib_info: LibInfo,
+
+    /// Polls the library file with this interval instead of watching it.
+    pub(crate) poll_interval: Option<Duration>,
+
+    /// Whether the library is checked for changes when the main window
+    /// gains the focus.
+    pub(crate) focus_check: Option<FocusCheck>,
+
+    /// The serialized state the first core instance is created with.
+    pub(crate) initial_state: Vec<u8>,
+
+    /// The file the state is persisted to across restarts, if enabled.
+    pub(crate) persist_path: Option<PathBuf>,
+
+    /// Whether to ignore the persisted state on startup.
+    pub(crate) fresh: bool,
+
+    /// Rebuilds the core with cargo when its sources change, if enabled.
+    pub(crate) build: Option<BuildConfig>,
+
+    /// Receives libraries pushed over TCP, if enabled.
+    pub(crate) push: Option<PushConfig>,
+
+    /// The address of the control server, if enabled.
+    pub(crate) control_addr: Option<SocketAddr>,
+
+    /// The file the reload events are appended to, if enabled.
+    pub(crate) event_log: Option<PathBuf>,
+
+    /// Whether external tooling reloads the library with a signal or a pipe
+    /// command instead of the shell watching it.
+    pub(crate) external_commands: bool,
+
+    /// Whether changes of the library are only picked up once it was marked
+    /// ready.
+    pub(crate) require_ready_marker: bool,
+
+    /// How many times loading a changed library is attempted.
+    pub(crate) reload_attempts: u32,
+
+    /// Takes a state snapshot after this many messages, if enabled.
+    pub(crate) snapshot_every: Option<u32>,
+
+    /// How many state snapshots are kept.
+    pub(crate) history_capacity: usize,
+
+    /// Records the messages of the application, if enabled.
+    pub(crate) record: Option<RecordConfig<M>>,
+
+    /// Replays recorded messages into every core, if enabled.
+    pub(crate) replay: Option<ReplayConfig<M>>,
+
+    /// Deserializes the messages typed into the console of the dev overlay,
+    /// if enabled.
+    pub(crate) console: Option<DeserializeFn<M>>,
+
+    /// The file describing the view instead of the core, with its parser.
+    pub(crate) view_file: Option<(PathBuf, ParseViewFn<M>)>,
+
+    /// The directory of the images and fonts of the application, watched if
+    /// set.
+    pub(crate) assets: Option<PathBuf>,
+
+    /// The file of the stylesheet handed to the core, watched if set.
+    pub(crate) stylesheet: Option<PathBuf>,
+
+    /// The directory of the translation catalogs with the locale to
+    /// translate to, watched if set.
+    pub(crate) localization: Option<(PathBuf, String)>,
+
+    /// The file the geometry of the window is kept in, if it is remembered.
+    pub(crate) window_geometry: Option<PathBuf>,
+
+    /// How long library copies of previous sessions are kept by the dylib
+    /// backend.
+    pub(crate) copy_retention: Duration,
+
+    /// When replaced libraries are unloaded.
+    pub(crate) unload_policy: UnloadPolicy,
+
+    /// How many replaced libraries stay loaded regardless of the policy.
+    pub(crate) keep_libraries: usize,
+
+    /// Whether allocations left behind by unloaded libraries are reported.
+    pub(crate) track_leaks: bool,
+
+    /// The soak test to run instead of waiting for changes, if any.
+    pub(crate) soak: Option<SoakConfig<M>>,
+
+    /// When a detected change of the library is applied.
+    pub(crate) reload_strategy: ReloadStrategy,
+
+    /// The checks a rebuilt core passes before it is swapped in.
+    pub(crate) reload_pipeline: ReloadPipeline,
+
+    /// Receive the lifecycle events of the reloads.
+    pub(crate) observers: Vec<Box<dyn ReloadObserver>>,
+}
+
+/// The library and state the shell rolls back to if a freshly swapped core
+/// turns out to be broken.
+struct PreviousCore<L> {
+    /// The library of the replaced core, still loaded.
+    lib: L,
+
+    /// The serialized state the new core was created with.
+    state: Vec<u8>,
+
+    /// When the previous core was replaced.
+    swapped_at: Instant,
+}
+
+/// An earlier build of the library offered in the dev overlay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Build(SystemTime);
+
+impl fmt::Display for Build {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_built_at(self.0))
+    }
+}
+
+/// The version of the core replaced by the last reload, shown next to the
+/// current one while comparing.
+struct ComparedCore<I, L> {
+    /// The replaced instance, declared before its library so it is destroyed
+    /// before the library is unloaded.
+    instance: I,
+
+    /// The library of the replaced instance, kept loaded while it is shown.
+    lib: L,
+
+    /// The message of the panic raised by the replaced instance, if any.
+    panic: RefCell<Option<String>>,
+}
+
+/// Manages the main application shell responsible for loading, rendering,
+/// and reloading the dynamically linked core application logic.
+///
+/// `ShellApp` handles the lifecycle of the dynamic library, keeps track of
+/// the core's exported functions, and orchestrates hot-reload transitions.
+///
+/// `M` is the message type of the application. The application state is only
+/// handled in its serialized form, so it may change between reloads. `B` is
+/// the [`LoaderBackend`] loading the core.
+pub struct ShellApp<M, B: LoaderBackend<M>> {
+    /// The window title, unless the core provides its own.
+    title: String,
+
+    /// The current core instance, declared before the libraries so it is
+    /// destroyed before they are unloaded.
+    instance: B::Instance,
+
+    /// The currently loaded library, kept alive for symbol safety.
+    lib: B::Library,
+
+    /// The previous library, kept for a grace period after a swap.
+    previous: Option<PreviousCore<B::Library>>,
+
+    /// Loads the core and creates its instances.
+    backend: B,
+
+    /// The library file watched for changes.
+    lib_path: PathBuf,
+
+    /// Polls the library file with this interval instead of watching it.
+    /// The interval grows to [`UNFOCUSED_POLL_INTERVAL`] while the main
+    /// window is unfocused and no change is expected.
+    poll_interval: Option<Duration>,
+
+    /// Whether the library is checked for changes when the main window
+    /// gains the focus.
+    focus_check: Option<FocusCheck>,
+
+    /// Whether the main window has the focus.
+    focused: bool,
+
+    /// Until when the library is polled with the full rate although the main
+    /// window is unfocused.
+    fast_poll_until: Instant,
+
+    /// Libraries replaced by a reload or rollback, kept loaded until iced
+    /// has rebuilt the UI and dropped the elements and widget states built
+    /// by their code.
+    retired: Vec<B::Library>,
+
+    /// Libraries no longer used by the shell, unloaded according to the
+    /// [`UnloadPolicy`]. Libraries still waiting when the shell exits are
+    /// dropped without going through the backend.
+    lingering: LingeringLibraries<B::Library>,
+
+    /// The message of the panic raised by the core, if any. A panicked core
+    /// is not called anymore until it is replaced by the next reload.
+    panic: RefCell<Option<String>>,
+
+    /// A notice about a failed reload or a rollback, shown above the core's view.
+    notice: Option<String>,
+
+    /// Writes the state to disk after every change, if enabled.
+    persistence: Option<StatePersistence>,
+
+    /// Rebuilds the core when its sources change. The library is then only
+    /// reloaded after a successful build instead of whenever it changes.
+    build: Option<BuildConfig>,
+
+    /// Receives libraries pushed over TCP. The library is then only reloaded
+    /// after a push, neither watched nor rebuilt.
+    push: Option<PushConfig>,
+
+    /// Lets editor plugins and scripts drive the shell over HTTP, if enabled.
+    control: Option<ControlServer>,
+
+    /// Records the reload events for external tooling, if enabled.
+    event_log: Option<EventLog>,
+
+    /// The name of the library if external tooling requests the reloads,
+    /// which names the command pipe on Windows.
+    external_commands: Option<String>,
+
+    /// Whether changes of the library are ignored until its ready marker was
+    /// written for it, see [`crate::mark_ready`]. Reloading with F5 doesn't
+    /// wait for the marker.
+    require_ready_marker: bool,
+
+    /// The compiler messages of the last failed build, cleared by the next
+    /// successful one.
+    diagnostics: Vec<Diagnostic>,
+
+    /// Whether the library is reloaded as soon as it changes. Toggled with F6.
+    auto_reload: bool,
+
+    /// Whether the library changed while automatic reloading was paused, or
+    /// the change waits for the [`ReloadStrategy`].
+    reload_pending: bool,
+
+    /// When a detected change of the library is applied.
+    reload_strategy: ReloadStrategy,
+
+    /// When the user last used the keyboard, mouse or a touch screen, for
+    /// [`ReloadStrategy::OnIdle`].
+    last_input: Instant,
+
+    /// How many times loading a changed library is attempted before the
+    /// shell gives up and keeps the current version.
+    reload_attempts: u32,
+
+    /// How many attempts of the pending reload failed so far, 0 if no
+    /// reload is being retried.
+    failed_attempts: u32,
+
+    /// How many times a new instance was swapped in, handed to the core
+    /// after every reload, see [`ReloadInfo`].
+    reloads: u32,
+
+    /// The checks a rebuilt core passes before it is swapped in.
+    reload_pipeline: ReloadPipeline,
+
+    /// How the stages of the last reload went, if there was one.
+    pipeline_report: Option<PipelineReport>,
+
+    /// The latest messages handled by the core, replayed on a scratch
+    /// instance of the next one.
+    recent_messages: RecentMessages<M>,
+
+    /// Whether the shell was torn down, on request of the core or by being
+    /// dropped, see [`ShellApp::teardown`].
+    torn_down: bool,
+
+    /// Whether the dev overlay is shown on top of the core's view. Toggled
+    /// with F12.
+    show_overlay: bool,
+
+    /// Whether the version replaced by a reload is kept next to the new one
+    /// to compare them. Toggled with F7.
+    compare: bool,
+
+    /// The version replaced by the last reload while comparing. It runs on a
+    /// copy of the state, so interacting with it leaves the current core
+    /// untouched.
+    compared: Option<ComparedCore<B::Instance, B::Library>>,
+
+    /// The reload statistics shown in the dev overlay. Like the observers,
+    /// they are updated from the view as well when the core panics.
+    metrics: RefCell<ReloadMetrics>,
+
+    /// How long the calls into the core take, shown in the dev overlay.
+    /// Measured from the view as well.
+    profiler: RefCell<Profiler>,
+
+    /// Receive the lifecycle events of the reloads.
+    observers: RefCell<Vec<Box<dyn ReloadObserver>>>,
+
+    /// The state snapshots the user can step through in the dev overlay.
+    history: StateHistory,
+
+    /// The state of the core shown in the dev overlay, refreshed while the
+    /// overlay is shown.
+    inspector: StateInspector,
+
+    /// The structure of the view of the core shown in the dev overlay,
+    /// refreshed when the overlay is shown and after every reload.
+    widget_tree: WidgetTreeInspector,
+
+    /// The size of the main window, which the view of the core is laid out
+    /// in for the widget tree inspector.
+    window_size: Size,
+
+    /// The scroll offsets and focus captured from the view of the core
+    /// being replaced, applied to the view of the next one.
+    widget_state: WidgetState,
+
+    /// Receives the library being loaded on a background thread.
+    loading: Option<mpsc::Receiver<std::io::Result<B::Library>>>,
+
+    /// The library loaded on a background thread, swapped in once the
+    /// widget state was captured.
+    loaded: Option<std::io::Result<B::Library>>,
+
+    /// Whether a reload was requested while a library was being loaded,
+    /// started once the current one is done.
+    reload_queued: bool,
+
+    /// When the current reload started, for its duration.
+    reload_started: Instant,
+
+    /// How long the phases of the current reload took so far.
+    reload_timings: ReloadTimings,
+
+    /// How long the phases of the last successful reload took.
+    timings: RefCell<ReloadTimings>,
+
+    /// When the last reload swapped the new instance in, until it built its
+    /// first view.
+    swapped_at: Cell<Option<Instant>>,
+
+    /// Whether allocations left behind by unloaded libraries are reported.
+    track_leaks: bool,
+
+    /// The generations of the most recently unloaded libraries which left
+    /// allocations behind, with those allocations as counted right after the
+    /// unload, newest last.
+    leaks: VecDeque<(usize, LiveAllocations)>,
+
+    /// Reloads the core over and over, if enabled.
+    soak: Option<SoakTest<M>>,
+
+    /// Dispatches messages typed in by the user, if enabled.
+    console: Option<MessageConsole<M>>,
+
+    /// The view described in a file instead of built by the core, if enabled.
+    view_file: Option<ViewFile<M>>,
+
+    /// The watched directory of the images and fonts, if any.
+    assets: Option<PathBuf>,
+
+    /// The watched stylesheet handed to the core, if any.
+    stylesheet: Option<StylesheetFile>,
+
+    /// The watched translation catalogs handed to the core, if any.
+    localization: Option<Localization>,
+
+    /// Keeps the geometry of the window for the next start, if enabled.
+    window_geometry: Option<WindowGeometryStore>,
+
+    /// The main window, if the shell runs as a daemon opening the windows
+    /// itself, see [`ShellApp::open_main_window`].
+    main_window: Option<window::Id>,
+
+    /// The open windows of the core by their names. They belong to the
+    /// shell, so they stay open across reloads.
+    windows: Vec<(window::Id, String)>,
+
+    /// Shows the log records captured by [`crate::LogCapture`], if it was
+    /// installed.
+    log_viewer: Option<LogViewer>,
+
+    /// Records the messages handled by the core, if enabled.
+    recorder: Option<MessageRecorder<M>>,
+
+    /// The recording fed into every core, if enabled. Every core then starts
+    /// from its initial state instead of the current state.
+    replay: Option<Replay<M>>,
+}
+
+/// The shell loading the core from its library.
+pub(crate) type DylibShell<M> = ShellApp<M, DylibBackend<M>>;
+
+/// Tears the shell down in order: the state is persisted, the core destroyed
+/// and the libraries it no longer needs unloaded, before the fields unload
+/// the current library, whose copy removes itself.
+///
+/// iced drops the user interfaces before the shell, so no element built by
+/// the code of a retired library is left by now. Every step copes with what
+/// an earlier exit or a partially initialized shell left behind.
+impl<M, B: LoaderBackend<M>> Drop for ShellApp<M, B> {
+    fn drop(&mut self) {
+        self.teardown();
+
+        for lib in std::mem::take(&mut self.retired) {
+            self.backend.unload(lib);
+        }
+        self.backend.clean_up();
+    }
+}
+
+impl<M, B: LoaderBackend<M>> ShellApp<M, B> {
+    /// Persists the state, destroys the core and unloads the replaced
+    /// libraries before the shell exits, instead of leaving it to iced to
+    /// drop the shell, which it may skip. The current library and the ones
+    /// retired by the last reload, whose code the UI may still hold, are
+    /// unloaded along with the shell.
+    ///
+    /// The destroyed core counts as panicked, so the views rendered until
+    /// the shell is gone don't call it. Tearing down again does nothing.
+    fn teardown(&mut self) {
+        if std::mem::replace(&mut self.torn_down, true) {
+            return;
+        }
+
+        // A core which panicked may not be able to save its state anymore,
+        // the persisted one is the last good state then.
+        if let Some(mut persistence) = self.persistence.take()
+            && self.panic.borrow().is_none()
+        {
+            match self.instance.save_state() {
+                Ok(state) => persistence.save(&state),
+                Err(e) => log::error!("Failed to save the state on exit: {}", e),
+            }
+        }
+
+        *self.panic.borrow_mut() = Some("The shell exited".to_string());
+        if let Some(compared) = self.compared.take() {
+            self.retire_compared(compared);
+        }
+        self.instance.destroy();
+
+        let previous = self.previous.take().map(|previous| previous.lib);
+        let libraries: Vec<_> = previous.into_iter().chain(self.lingering.drain()).collect();
+        for lib in libraries {
+            self.backend.unload(lib);
+        }
+    }
+
+    /// Destroys the compared instance and retires its library.
+    fn retire_compared(&mut self, compared: ComparedCore<B::Instance, B::Library>) {
+        let ComparedCore { instance, lib, .. } = compared;
+        drop(instance);
+        self.retired.push(lib);
+    }
+}
+
+impl<M, B> ShellApp<M, B>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+    B: LoaderBackend<M>,
+    B::Library: 'static,
+{
+    /// Loads the core with `backend` and instantiates it with the persisted
+    /// state, or the initial state of `settings` if there is none. Fails if
+    /// the core can't be loaded or created, since there is no running core
+    /// to fall back to yet.
+    pub(crate) fn new(settings: ShellSettings<M>, mut backend: B) -> std::io::Result<Self> {
+        let ShellSettings {
+            title,
+            lib_info,
+            poll_interval,
+            focus_check,
+            initial_state,
+            persist_path,
+            fresh,
+            build,
+            push,
+            control_addr,
+            event_log,
+            external_commands,
+            require_ready_marker,
+            reload_attempts,
+            snapshot_every,
+            history_capacity,
+            record,
+            replay,
+            console,
+            view_file,
+            assets,
+            stylesheet,
+            localization,
+            window_geometry,
+            unload_policy,
+            keep_libraries,
+            track_leaks,
+            soak,
+            reload_strategy,
+            reload_pipeline,
+            observers,
+            ..
+        } = settings;
+
+        if track_leaks && !checked_allocator_installed() {
+            log::warn!(
+                "Tracking leaks requires CheckedAllocator as the global allocator of the shell"
+            );
+        }
+
+        let replay = replay.and_then(|config| match Replay::load(&config) {
+            Ok(replay) => {
+                log::trace!(
+                    "Replaying {} messages from {}",
+                    replay.messages.len(),
+                    config.path.display()
+                );
+                Some(replay)
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to load the recording {}: {}",
+                    config.path.display(),
+                    e
+                );
+                None
+            }
+        });
+
+        let mut persistence = persist_path.map(StatePersistence::new);
+        let initial_state = match (&replay, persistence.as_mut()) {
+            (Some(replay), _) => replay.initial_state.clone(),
+            (None, Some(persistence)) if !fresh => persistence.load().unwrap_or(initial_state),
+            _ => initial_state,
+        };
+
+        // Replayed messages aren't recorded again.
+        let recorder = match (record, &replay) {
+            (Some(config), None) => {
+                let path = config.path.clone();
+                match MessageRecorder::create(config, &initial_state) {
+                    Ok(recorder) => Some(recorder),
+                    Err(e) => {
+                        log::error!("Failed to record to {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let event_log = event_log.and_then(|path| match EventLog::open(path.clone()) {
+            Ok(event_log) => Some(event_log),
+            Err(e) => {
+                log::error!("Failed to open the event log {}: {}", path.display(), e);
+                None
+            }
+        });
+
+        log::trace!("Initial library load");
+        let (lib, instance) = backend
+            .load()
+            .and_then(|lib| {
+                backend
+                    .instantiate(&lib, &initial_state)
+                    .map(|instance| (lib, instance))
+            })
+            .map_err(|e| {
+                std::io::Error::new(e.kind(), format!("Failed to load the core: {}", e))
+            })?;
+
+        log::trace!("Library loaded");
+
+        let mut shell = Self {
+            title,
+            instance,
+            lib,
+            previous: None,
+            backend,
+            lib_path: lib_info.path,
+            poll_interval,
+            focus_check,
+            focused: true,
+            fast_poll_until: Instant::now(),
+            retired: Vec::new(),
+            lingering: LingeringLibraries::new(unload_policy, keep_libraries),
+            panic: RefCell::new(None),
+            notice: None,
+            persistence,
+            build,
+            push,
+            control: control_addr.map(ControlServer::new),
+            event_log,
+            external_commands: external_commands.then(|| lib_info.name.clone()),
+            require_ready_marker,
+            diagnostics: Vec::new(),
+            auto_reload: true,
+            reload_pending: false,
+            reload_strategy,
+            last_input: Instant::now(),
+            reload_attempts,
+            failed_attempts: 0,
+            reloads: 0,
+            recent_messages: RecentMessages::new(reload_pipeline.smoke_updates),
+            reload_pipeline,
+            pipeline_report: None,
+            torn_down: false,
+            show_overlay: false,
+            compare: false,
+            compared: None,
+            metrics: RefCell::default(),
+            profiler: RefCell::new(Profiler::new()),
+            observers: RefCell::new(observers),
+            history: StateHistory::new(snapshot_every, history_capacity),
+            inspector: StateInspector::new(),
+            widget_tree: WidgetTreeInspector::new(),
+            window_size: window::Settings::default().size,
+            widget_state: WidgetState::default(),
+            loading: None,
+            loaded: None,
+            reload_queued: false,
+            reload_started: Instant::now(),
+            reload_timings: ReloadTimings::default(),
+            timings: RefCell::default(),
+            swapped_at: Cell::new(None),
+            track_leaks,
+            leaks: VecDeque::new(),
+            soak: soak.map(SoakTest::new),
+            console: console.map(MessageConsole::new),
+            view_file: view_file.map(|(path, parse)| ViewFile::new(path, parse)),
+            assets,
+            stylesheet: stylesheet.map(StylesheetFile::new),
+            localization: localization.map(|(dir, locale)| Localization::new(dir, locale)),
+            window_geometry: window_geometry.map(WindowGeometryStore::new),
+            main_window: None,
+            windows: Vec::new(),
+            log_viewer: logs::is_capturing().then(LogViewer::new),
+            recorder,
+            replay,
+        };
+
+        if shell.control.is_some() || shell.view_file.is_some() {
+            shell.publish_state();
+        }
+
+        Ok(shell)
+    }
+
+    pub fn update(&mut self, message: ShellMessage<M>) -> Task<ShellMessage<M>> {
+        match message {
+            ShellMessage::Reload => {
+                if self.loading.is_some() {
+                    self.reload_queued = true;
+                    return Task::none();
+                }
+
+                self.failed_attempts = 0;
+                return self.start_load();
+            }
+            ShellMessage::LibraryLoaded => {
+                let Some(loading) = self.loading.take() else {
+                    return Task::none();
+                };
+
+                self.loaded = Some(loading.try_recv().unwrap_or_else(|_| {
+                    Err(std::io::Error::other("Loading the library panicked"))
+                }));
+                // The widget tree is rebuilt by the new core, so the scroll
+                // offsets and focus are read first and applied afterwards.
+                return widget_state::capture().map(ShellMessage::WidgetStateCaptured);
+            }
+            ShellMessage::WidgetStateCaptured(widget_state) => {
+                self.widget_state = widget_state;
+                let reloaded = self.reload();
+
+                if std::mem::take(&mut self.reload_queued) {
+                    return Task::batch([reloaded, Task::done(ShellMessage::Reload)]);
+                }
+                return reloaded;
+            }
+            ShellMessage::RetryReload => {
+                // A reload started in the meantime may have succeeded.
+                if self.failed_attempts > 0 && self.loading.is_none() {
+                    return self.start_load();
+                }
+            }
+            ShellMessage::TakeSnapshot => {
+                self.take_snapshot();
+            }
+            ShellMessage::HistoryBack => match self.instance.save_state() {
+                Ok(current) => {
+                    if let Some(snapshot) = self.history.back(current) {
+                        self.restore_state(&snapshot);
+                    }
+                }
+                Err(e) => self.set_panic(e),
+            },
+            ShellMessage::HistoryForward => {
+                if let Some(snapshot) = self.history.forward() {
+                    self.restore_state(&snapshot);
+                }
+            }
+            ShellMessage::Shutdown => {
+                log::trace!("Shutting down");
+                self.publish_state();
+                self.teardown();
+                return iced::exit();
+            }
+            ShellMessage::LibraryChanged => {
+                if self.require_ready_marker && !ready::is_ready(&self.lib_path) {
+                    log::trace!("The library changed, waiting for its ready marker");
+                    return Task::none();
+                }
+
+                self.emit(ControlEvent::ChangeDetected);
+                self.fast_poll_until = Instant::now() + FAST_POLL_PERIOD;
+                self.reload_timings.detection = std::fs::metadata(&self.lib_path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok());
+
+                self.reload_pending = true;
+                return self.reload_if_due();
+            }
+            ShellMessage::ToggleAutoReload => {
+                return self.set_auto_reload(!self.auto_reload);
+            }
+            ShellMessage::SetAutoReload(enabled) => {
+                return self.set_auto_reload(enabled);
+            }
+            ShellMessage::ToggleOverlay => {
+                self.show_overlay = !self.show_overlay;
+                if self.show_overlay {
+                    self.publish_state();
+                    self.refresh_widget_tree();
+                    if let Some(log_viewer) = self.log_viewer.as_mut() {
+                        log_viewer.refresh();
+                    }
+                }
+            }
+            ShellMessage::ToggleCompare => {
+                self.compare = !self.compare;
+                log::trace!(
+                    "Comparing versions {}",
+                    if self.compare { "started" } else { "stopped" }
+                );
+
+                if let Some(compared) = self.compared.take() {
+                    self.retire_compared(compared);
+                    return Task::done(ShellMessage::ReleaseRetired);
+                }
+            }
+            ShellMessage::ToggleStateNode(pointer) => {
+                self.inspector.toggle(pointer);
+            }
+            ShellMessage::EditState => {
+                self.inspector.start_editing();
+            }
+            ShellMessage::StateEditorAction(action) => {
+                self.inspector.perform(action);
+            }
+            ShellMessage::ApplyStateEdit => {
+                if let Some(state) = self.inspector.edited_state() {
+                    return Task::done(ShellMessage::ReplaceState(state));
+                }
+            }
+            ShellMessage::CancelStateEdit => {
+                self.inspector.stop_editing();
+            }
+            ShellMessage::ToggleWidgetNode(path) => {
+                self.widget_tree.toggle(path);
+            }
+            ShellMessage::RefreshWidgetTree => {
+                self.refresh_widget_tree();
+            }
+            ShellMessage::ConsoleNameChanged(name) => {
+                if let Some(console) = self.console.as_mut() {
+                    console.set_name(name);
+                }
+            }
+            ShellMessage::ConsolePayloadChanged(payload) => {
+                if let Some(console) = self.console.as_mut() {
+                    console.set_payload(payload);
+                }
+            }
+            ShellMessage::SendConsoleMessage => {
+                if let Some(message) = self.console.as_mut().and_then(MessageConsole::message) {
+                    return Task::done(ShellMessage::App(message));
+                }
+            }
+            ShellMessage::SoakStep => {
+                let reloads = self.reloads;
+                let loading =
+                    self.loading.is_some() || self.reload_queued || self.failed_attempts > 0;
+                let Some(soak) = self.soak.as_mut() else {
+                    return Task::none();
+                };
+                if self.panic.borrow().is_some() {
+                    soak.fail("The core panicked");
+                }
+
+                match soak.step(reloads, loading) {
+                    SoakStep::Wait => {}
+                    SoakStep::Reload(messages) => {
+                        return Task::batch(
+                            messages
+                                .into_iter()
+                                .map(|message| Task::done(ShellMessage::App(message))),
+                        )
+                        .chain(Task::done(ShellMessage::Reload));
+                    }
+                    SoakStep::Passed => {
+                        log::info!("Soak test passed: {}", soak.summary());
+                        return Task::done(ShellMessage::Shutdown);
+                    }
+                    SoakStep::Failed(reason) => {
+                        log::error!("Soak test failed: {}", reason);
+                        soak::record_failure();
+                        return Task::done(ShellMessage::Shutdown);
+                    }
+                }
+            }
+            ShellMessage::RefreshLogs => {
+                if let Some(log_viewer) = self.log_viewer.as_mut() {
+                    log_viewer.refresh();
+                }
+            }
+            ShellMessage::LogLevelSelected(level) => {
+                if let Some(log_viewer) = self.log_viewer.as_mut() {
+                    log_viewer.set_level(level);
+                }
+            }
+            ShellMessage::LogSearchChanged(search) => {
+                if let Some(log_viewer) = self.log_viewer.as_mut() {
+                    log_viewer.set_search(search);
+                }
+            }
+            ShellMessage::SetTargetLevel(prefix, level) => {
+                logs::set_target_level(prefix, level);
+            }
+            ShellMessage::ToggleLogPause => {
+                if let Some(log_viewer) = self.log_viewer.as_mut() {
+                    log_viewer.toggle_pause();
+                }
+            }
+            ShellMessage::ControlMessage(message) => {
+                let message = match &self.console {
+                    Some(console) => console.parse(message),
+                    None => Err("Sending messages requires the message console".to_string()),
+                };
+
+                match message {
+                    Ok(message) => {
+                        let task = self.update(ShellMessage::App(message));
+                        self.emit(ControlEvent::MessageHandled);
+                        return task;
+                    }
+                    Err(error) => {
+                        log::error!("{}", error);
+                        self.emit(ControlEvent::MessageRejected { error });
+                    }
+                }
+            }
+            ShellMessage::ReplaceState(state) => {
+                // Stepping back in the history undoes the replacement.
+                self.take_snapshot();
+                self.restore_state(&state);
+            }
+            ShellMessage::LoadBuild(built_at) => {
+                return self.load_build(built_at);
+            }
+            ShellMessage::Rollback => {
+                let after_reload = self.rollback();
+                return Task::batch([after_reload, Task::done(ShellMessage::ReleaseRetired)]);
+            }
+            ShellMessage::ReleaseRetired => {
+                for lib in self.retired.drain(..) {
+                    self.lingering.push(lib);
+                }
+                self.unload_expired();
+            }
+            ShellMessage::Tick => {
+                if self.panic.borrow().is_some() && self.previous.is_some() {
+                    return Task::done(ShellMessage::Rollback);
+                }
+
+                // The view file isn't watched if the library isn't either.
+                if let Some(view_file) = self.view_file.as_mut() {
+                    view_file.refresh();
+                }
+                if let Some(stylesheet) = self.stylesheet.as_mut() {
+                    stylesheet.refresh();
+                }
+
+                self.expire_previous();
+
+                // The change was detected already, polling again would
+                // report it on every tick.
+                if self.reload_pending {
+                    return self.reload_if_due();
+                }
+
+                if self.build.is_some() || self.push.is_some() || self.external_commands.is_some() {
+                    return Task::none();
+                }
+
+                if self.backend.changed_since(&self.lib) {
+                    return Task::done(ShellMessage::LibraryChanged);
+                }
+            }
+            ShellMessage::ViewFileChanged => {
+                if let Some(view_file) = self.view_file.as_mut() {
+                    view_file.reload();
+                }
+            }
+            ShellMessage::WindowResized(window, size) => {
+                if self
+                    .main_window
+                    .is_none_or(|main_window| main_window == window)
+                {
+                    self.window_size = size;
+                }
+                if let Some(window_geometry) = self.main_window_geometry(window) {
+                    window_geometry.resized(size);
+                }
+            }
+            ShellMessage::WindowMoved(window, position) => {
+                if let Some(window_geometry) = self.main_window_geometry(window) {
+                    window_geometry.moved(position);
+                }
+            }
+            ShellMessage::OpenWindow { name, size } => {
+                if self.main_window.is_none() {
+                    log::error!(
+                        "The core can't open the window {} outside of HotReload::run",
+                        name
+                    );
+                    return Task::none();
+                }
+
+                if let Some((window, _)) = self.windows.iter().find(|(_, open)| *open == name) {
+                    return window::gain_focus(*window);
+                }
+
+                let (window, open) = window::open(window::Settings {
+                    size,
+                    ..window::Settings::default()
+                });
+                log::trace!("Opening the window {}", name);
+                self.windows.push((window, name));
+                return open.map(ShellMessage::WindowOpened);
+            }
+            ShellMessage::CloseWindow(name) => {
+                if let Some((window, _)) = self.windows.iter().find(|(_, open)| *open == name) {
+                    return window::close(*window);
+                }
+            }
+            ShellMessage::CoreEvent(window, event) => {
+                if self
+                    .main_window
+                    .is_some_and(|main_window| main_window != window)
+                    || self.panic.borrow().is_some()
+                {
+                    return Task::none();
+                }
+
+                match self.instance.on_event(&event) {
+                    Ok(Some(message)) => return Task::done(ShellMessage::App(message)),
+                    Ok(None) => (),
+                    Err(panic) => self.set_panic(panic),
+                }
+            }
+            ShellMessage::CloseRequested(window) => {
+                if self.main_window != Some(window) {
+                    return window::close(window);
+                }
+
+                if self.panic.borrow().is_none() {
+                    match self.instance.on_event(&CoreEvent::CloseRequested) {
+                        Ok(Some(message)) => {
+                            log::trace!("The core keeps the window open");
+                            return Task::done(ShellMessage::App(message));
+                        }
+                        Ok(None) => (),
+                        Err(panic) => self.set_panic(panic),
+                    }
+                }
+
+                return window::close(window);
+            }
+            ShellMessage::CommandResult { request, result } => {
+                if self.panic.borrow().is_some() {
+                    log::trace!("Dropping the result of {}, the core panicked", request);
+                    return Task::none();
+                }
+
+                match self.instance.command_result(&request, &result) {
+                    Ok(Some(message)) => return Task::done(ShellMessage::App(message)),
+                    Ok(None) => log::trace!("The core ignored the result of {}", request),
+                    Err(panic) => self.set_panic(panic),
+                }
+            }
+            ShellMessage::WindowOpened(window) => {
+                log::trace!("Opened the window {:?}", window);
+            }
+            ShellMessage::WindowClosed(window) => {
+                if self.main_window == Some(window) {
+                    return Task::done(ShellMessage::Shutdown);
+                }
+
+                self.windows.retain(|(open, _)| *open != window);
+            }
+            ShellMessage::StylesheetChanged => {
+                if let Some(stylesheet) = self.stylesheet.as_mut() {
+                    stylesheet.reload();
+                }
+            }
+            ShellMessage::CatalogsChanged => {
+                if let Some(localization) = self.localization.as_mut() {
+                    localization.reload();
+                }
+            }
+            ShellMessage::AssetsChanged(paths) => {
+                // Images read with `image_handle` are replaced on the redraw
+                // following this message, fonts have to be loaded again.
+                let fonts = assets::load_fonts(&paths);
+                if self.panic.borrow().is_some() {
+                    return fonts;
+                }
+
+                match self.instance.subscriptions() {
+                    Ok(core_subscriptions) => {
+                        let notifications = subscriptions::assets_changed(&core_subscriptions)
+                            .into_iter()
+                            .map(|message| Task::done(ShellMessage::App(message)));
+                        return fonts.chain(Task::batch(notifications));
+                    }
+                    Err(panic) => self.set_panic(panic),
+                }
+
+                return fonts;
+            }
+            ShellMessage::Building => {
+                self.notice = Some("Building the core...".to_string());
+                self.emit(ControlEvent::BuildStarted);
+            }
+            ShellMessage::BuildFinished(Ok(())) => {
+                self.diagnostics.clear();
+                return Task::done(ShellMessage::LibraryChanged);
+            }
+            ShellMessage::BuildFinished(Err(diagnostics)) => {
+                log::error!("Building the core failed");
+                let error = diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.level == DiagnosticLevel::Error)
+                    .map(|diagnostic| diagnostic.message.clone());
+                self.notice = Some("Build failed. Keeping the current version.".to_string());
+                self.diagnostics = diagnostics;
+                self.emit(ControlEvent::BuildFailed { error });
+            }
+            ShellMessage::FocusChanged(window, focused) => {
+                if self.main_window == Some(window) {
+                    self.focused = focused;
+                    // Switching to the editor usually means a change is
+                    // about to be made.
+                    if !focused {
+                        self.fast_poll_until = Instant::now() + FAST_POLL_PERIOD;
+                    }
+
+                    let checks = focused
+                        && self.focus_check.is_some()
+                        && self.build.is_none()
+                        && self.push.is_none()
+                        && self.external_commands.is_none();
+                    if checks && self.backend.changed_since(&self.lib) {
+                        log::trace!("The library changed while the window was unfocused");
+                        return Task::done(ShellMessage::LibraryChanged);
+                    }
+                }
+            }
+            ShellMessage::UserInput => {
+                self.last_input = Instant::now();
+            }
+            ShellMessage::KeyPressed(key) => {
+                if self.panic.borrow().is_some() {
+                    return Task::none();
+                }
+
+                match self.instance.subscriptions() {
+                    Ok(core_subscriptions) => {
+                        if let Some(message) = subscriptions::key_binding(&core_subscriptions, &key)
+                        {
+                            return Task::done(ShellMessage::App(message));
+                        }
+                    }
+                    Err(panic) => self.set_panic(panic),
+                }
+            }
+            ShellMessage::App(message) => {
+                if self.panic.borrow().is_some() {
+                    return Task::none();
+                }
+
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record(&message);
+                }
+                self.recent_messages.push(&message);
+
+                let generation = self.library_generation();
+                self.profiler.get_mut().message_received();
+                let started = Instant::now();
+                let result = self.instance.update(message);
+                self.profiler
+                    .get_mut()
+                    .record(generation, Call::Update, started.elapsed());
+
+                match result {
+                    Ok(core_commands) => {
+                        self.publish_state();
+                        if self.history.message_handled() {
+                            self.take_snapshot();
+                        }
+                        return self.execute(core_commands);
+                    }
+                    Err(panic) => {
+                        self.set_panic(panic);
+
+                        if self.previous.is_some() {
+                            return Task::done(ShellMessage::Rollback);
+                        }
+                    }
+                }
+            }
+            ShellMessage::ComparedApp(message) => {
+                let Some(compared) = self.compared.as_mut() else {
+                    return Task::none();
+                };
+                if compared.panic.borrow().is_some() {
+                    return Task::none();
+                }
+
+                // Commands would reach the current core, so they are dropped.
+                match compared.instance.update(message) {
+                    Ok(core_commands) if !core_commands.is_empty() => {
+                        log::trace!("Ignoring the commands of the compared core");
+                    }
+                    Ok(_) => (),
+                    Err(panic) => {
+                        log::error!("The compared core panicked: {}", panic);
+                        *compared.panic.borrow_mut() = Some(panic);
+                    }
+                }
+            }
+        }
+
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, ShellMessage<M>> {
+        let (file_view, file_error) = match self.view_file.as_ref().map(ViewFile::view) {
+            Some(Ok(view)) => (Some(view), None),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, None),
+        };
+
+        let content = if let Some(panic) = self.panic.borrow().as_ref() {
+            panic_view(panic)
+        } else if let Some(view) = file_view {
+            view.map(ShellMessage::App)
+        } else {
+            let started = Instant::now();
+            let view = self.instance.view();
+            self.profiler.borrow_mut().record(
+                self.library_generation(),
+                Call::View,
+                started.elapsed(),
+            );
+
+            match view {
+                Ok(view) => {
+                    if let Some(swapped_at) = self.swapped_at.take() {
+                        self.timings.borrow_mut().first_frame = Some(swapped_at.elapsed());
+                    }
+                    view.map(ShellMessage::App)
+                }
+                Err(panic) => {
+                    let view = panic_view(&panic);
+                    self.set_panic(panic);
+                    view
+                }
+            }
+        };
+
+        let content = if self.compare {
+            row![
+                compare_pane("Previous", self.compared_view()),
+                compare_pane("Current", content),
+            ]
+            .spacing(1)
+            .into()
+        } else {
+            content
+        };
+
+        let mut layout = Column::new();
+        if let Some(notice) = &self.notice {
+            layout = layout.push(notice_banner(notice));
+        }
+        // The view of the core stands in for an invalid view file.
+        if let Some(e) = &file_error {
+            layout = layout.push(notice_banner(&format!("Invalid view file: {}", e)));
+        }
+        // The previous stylesheet stays in place while the file is invalid.
+        if let Some(e) = self.stylesheet.as_ref().and_then(StylesheetFile::error) {
+            layout = layout.push(notice_banner(&format!("Invalid stylesheet: {}", e)));
+        }
+        if let Some(e) = self.localization.as_ref().and_then(Localization::error) {
+            layout = layout.push(notice_banner(&e));
+        }
+        if !self.diagnostics.is_empty() {
+            layout = layout.push(diagnostics_panel(&self.diagnostics));
+        }
+        let content: Element<_> = if self.show_overlay {
+            stack![content, self.dev_overlay()].into()
+        } else {
+            content
+        };
+
+        layout
+            .push(container(content).height(Length::Fill))
+            .push(status_bar(
+                self.auto_reload,
+                self.reload_strategy,
+                self.reload_pending,
+                self.failed_attempts,
+                self.reload_attempts,
+            ))
+            .into()
+    }
+
+    /// Renders the version of the core replaced by the last reload.
+    fn compared_view(&self) -> Element<'_, ShellMessage<M>> {
+        let Some(compared) = &self.compared else {
+            return Container::new(text("The next reload keeps the current version here."))
+                .center(Length::Fill)
+                .into();
+        };
+
+        if let Some(panic) = compared.panic.borrow().as_ref() {
+            return panic_view(panic);
+        }
+
+        match compared.instance.view() {
+            Ok(view) => view.map(ShellMessage::ComparedApp),
+            Err(panic) => {
+                let view = panic_view(&panic);
+                log::error!("The compared core panicked: {}", panic);
+                *compared.panic.borrow_mut() = Some(panic);
+                view
+            }
+        }
+    }
+
+    /// Starts a reload. If the backend supports it, the library is copied
+    /// and opened on a background thread, so the UI stays responsive, and
+    /// [`ShellMessage::LibraryLoaded`] reports it is ready. Otherwise it is
+    /// loaded on the UI thread once the widget state was captured.
+    fn start_load(&mut self) -> Task<ShellMessage<M>> {
+        log::trace!("Reload library");
+        self.emit(ControlEvent::ReloadStarted {
+            attempt: self.failed_attempts + 1,
+        });
+        self.reload_started = Instant::now();
+
+        let Some(load) = self.backend.background_load() else {
+            return widget_state::capture().map(ShellMessage::WidgetStateCaptured);
+        };
+
+        let (lib_tx, lib_rx) = mpsc::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+        thread::spawn(move || {
+            let _ = lib_tx.send(load());
+            let _ = done_tx.send(());
+        });
+        self.loading = Some(lib_rx);
+
+        Task::perform(done_rx, |_| ShellMessage::LibraryLoaded)
+    }
+
+    /// Swaps in the library loaded in the background, or loads the changed
+    /// one now, retrying with growing delays if loading fails, e.g. because
+    /// the linker still locks it.
+    fn reload(&mut self) -> Task<ShellMessage<M>> {
+        self.reload_pending = false;
+
+        // While the library was loaded in the background, the running core
+        // kept handling the input. Its state is saved only now and the core
+        // swapped within this update, so no message is lost or arrives in
+        // between, and nothing has to be queued for the new core.
+        let started = self.reload_started;
+        let (current_state, state_error) = match &self.replay {
+            Some(replay) => (replay.initial_state.clone(), None),
+            None if self.panic.borrow().is_some() => self.panicked_state(),
+            None => match self.instance.save_state() {
+                Ok(state) => (state, None),
+                Err(e) => {
+                    log::error!("{}", e);
+                    (Vec::new(), Some(e))
+                }
+            },
+        };
+
+        let lib = match self.loaded.take() {
+            Some(lib) => lib,
+            None => self.backend.load(),
+        };
+        if let Some(load_timings) = lib
+            .as_ref()
+            .ok()
+            .and_then(|lib| self.backend.load_timings(lib))
+        {
+            self.reload_timings.copy = Some(load_timings.copy);
+            self.reload_timings.open = Some(load_timings.open);
+        }
+
+        match lib.and_then(|lib| self.instantiate_next(lib, &current_state)) {
+            Ok((lib, instance)) => {
+                log::trace!("Library reloaded");
+                self.failed_attempts = 0;
+                self.reload_timings.instantiate =
+                    self.pipeline_report.as_ref().and_then(|report| {
+                        report
+                            .stages
+                            .iter()
+                            .find(|outcome| outcome.stage == ReloadStage::Instantiate)
+                            .map(|outcome| outcome.duration)
+                    });
+                *self.timings.borrow_mut() = std::mem::take(&mut self.reload_timings);
+                self.swapped_at.set(Some(Instant::now()));
+                let after_reload = self.swap(lib, instance, current_state);
+                self.emit(ControlEvent::Reloaded {
+                    duration_ms: started.elapsed().as_millis() as u64,
+                });
+                self.publish_state();
+                if self.show_overlay {
+                    self.refresh_widget_tree();
+                }
+
+                if let Some(e) = state_error {
+                    self.metrics.borrow_mut().last_error = Some(e.clone());
+                    self.notice = Some(format!(
+                        "The state couldn't be transferred ({}). The core was reset.",
+                        e
+                    ));
+                }
+
+                let widget_state = widget_state::restore(std::mem::take(&mut self.widget_state));
+                Task::batch([
+                    after_reload,
+                    widget_state,
+                    Task::done(ShellMessage::ReleaseRetired).chain(self.replay()),
+                ])
+            }
+            Err(e) => {
+                self.failed_attempts += 1;
+                self.emit(ControlEvent::LoadFailed {
+                    attempt: self.failed_attempts,
+                    error: e.to_string(),
+                });
+
+                if self.failed_attempts < self.reload_attempts {
+                    let delay = RETRY_BASE_DELAY
+                        * 2u32.pow((self.failed_attempts - 1).min(RETRY_MAX_DOUBLINGS));
+                    log::warn!(
+                        "Reload attempt {} of {} failed, retrying in {} ms",
+                        self.failed_attempts,
+                        self.reload_attempts,
+                        delay.as_millis()
+                    );
+                    // The timer is created by the task, in the runtime.
+                    return Task::perform(async move { tokio::time::sleep(delay).await }, |()| {
+                        ShellMessage::RetryReload
+                    });
+                }
+
+                self.emit(ControlEvent::ReloadFailed {
+                    error: e.to_string(),
+                });
+                self.reload_timings = ReloadTimings::default();
+                self.notice = Some(format!(
+                    "Reload failed after {} attempts: {}. Keeping the current version.",
+                    self.failed_attempts, e
+                ));
+                self.failed_attempts = 0;
+                if let Some(soak) = self.soak.as_mut() {
+                    soak.fail(format!("A reload failed: {}", e));
+                }
+
+                Task::none()
+            }
+        }
+    }
+
+    /// Renders the dev overlay with the state of the library and the reloads.
+    fn dev_overlay(&self) -> Element<'_, ShellMessage<M>> {
+        let current_build = self.backend.built_at(&self.lib);
+        let built_at = current_build.map_or_else(|| "-".to_string(), format_built_at);
+        let metrics = self.metrics.borrow();
+        let milliseconds = |duration: Option<Duration>| match duration {
+            Some(duration) => format!("{} ms", duration.as_millis()),
+            None => "-".to_string(),
+        };
+        let last_error = self
+            .panic
+            .borrow()
+            .clone()
+            .or_else(|| metrics.last_error.clone())
+            .unwrap_or_else(|| "-".to_string());
+        let auto_reload = if self.auto_reload { "on" } else { "paused" };
+
+        let line = |label: &str, value: String| {
+            row![
+                text(label.to_string())
+                    .width(140)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7)),
+                text(value).color(Color::WHITE),
+            ]
+        };
+
+        let mut details = column![
+            line("Library built", built_at),
+            row![
+                text("Load build")
+                    .width(140)
+                    .color(Color::from_rgb(0.7, 0.7, 0.7)),
+                pick_list(
+                    self.backend
+                        .builds()
+                        .into_iter()
+                        .rev()
+                        .map(Build)
+                        .collect::<Vec<_>>(),
+                    current_build.map(Build),
+                    |build| ShellMessage::LoadBuild(build.0),
+                )
+                .text_size(12),
+            ],
+            line("Reloads", metrics.count.to_string()),
+            line("Failures", metrics.failures.to_string()),
+            line("Last reload", milliseconds(metrics.last_duration)),
+            line("Average reload", milliseconds(metrics.average_duration())),
+            line("Last error", last_error),
+            line("Auto-reload", auto_reload.to_string()),
+            line("State history", self.history.position()),
+            row![
+                button(text("Back").size(12)).on_press(ShellMessage::HistoryBack),
+                button(text("Snapshot").size(12)).on_press(ShellMessage::TakeSnapshot),
+                button(text("Forward").size(12)).on_press(ShellMessage::HistoryForward),
+            ]
+            .spacing(4),
+        ]
+        .spacing(4);
+
+        let timings = *self.timings.borrow();
+        details = details
+            .push(text("Last reload phases").color(Color::from_rgb(0.7, 0.7, 0.7)))
+            .push(line("Detection", milliseconds(timings.detection)))
+            .push(line("Copy", milliseconds(timings.copy)))
+            .push(line("Open", milliseconds(timings.open)))
+            .push(line("Instantiate", milliseconds(timings.instantiate)))
+            .push(line("First frame", milliseconds(timings.first_frame)));
+
+        if self.track_leaks {
+            details =
+                details.push(text("Leaks after unload").color(Color::from_rgb(0.7, 0.7, 0.7)));
+            if self.leaks.is_empty() {
+                details = details.push(line("Unloaded libraries", "none leaked".to_string()));
+            }
+            for (generation, live) in self.leaks.iter().rev() {
+                details = details.push(line(
+                    &format!("Library #{}", generation),
+                    format!("{} allocations, {} bytes", live.count, live.bytes),
+                ));
+            }
+        }
+
+        if let Some(report) = &self.pipeline_report {
+            details =
+                details.push(text("Last reload stages").color(Color::from_rgb(0.7, 0.7, 0.7)));
+            for outcome in &report.stages {
+                let result = match &outcome.error {
+                    Some(e) => format!("failed after {} ms: {}", outcome.duration.as_millis(), e),
+                    None => format!("passed in {} ms", outcome.duration.as_millis()),
+                };
+                details = details.push(line(&outcome.stage.to_string(), result));
+            }
+        }
+
+        details = details
+            .push(text("Profiler").color(Color::from_rgb(0.7, 0.7, 0.7)))
+            .push(self.profiler.borrow().view());
+
+        if let Some(console) = &self.console {
+            details = details
+                .push(text("Console").color(Color::from_rgb(0.7, 0.7, 0.7)))
+                .push(console.view());
+        }
+
+        if let Some(log_viewer) = &self.log_viewer {
+            details = details
+                .push(text("Logs").color(Color::from_rgb(0.7, 0.7, 0.7)))
+                .push(log_viewer.view());
+        }
+
+        let panel = Container::new(
+            details
+                .push(text("State").color(Color::from_rgb(0.7, 0.7, 0.7)))
+                .push(self.inspector.view())
+                .push(text("Widgets").color(Color::from_rgb(0.7, 0.7, 0.7)))
+                .push(self.widget_tree.view()),
+        )
+        .padding(10)
+        .max_height(600)
+        .max_width(480)
+        .style(|_| container::Style {
+            background: Some(Color::from_rgba(0.1, 0.1, 0.1, 0.85).into()),
+            ..container::Style::default()
+        });
+
+        Container::new(panel)
+            .padding(10)
+            .width(Length::Fill)
+            .align_x(Alignment::End)
+            .into()
+    }
+
+    /// Opens the main window with `settings` and shows the view of the core
+    /// in it. Used when the shell runs as a daemon, so the core can open
+    /// windows of its own.
+    pub(crate) fn open_main_window(&mut self, settings: window::Settings) -> Task<ShellMessage<M>> {
+        self.window_size = settings.size;
+        let (window, open) = window::open(settings);
+        self.main_window = Some(window);
+        open.map(ShellMessage::WindowOpened)
+    }
+
+    /// The view of `window`: the shell around the view of the core in the
+    /// main window, the view the core provides for its other windows.
+    pub fn window_view(&self, window: window::Id) -> Element<'_, ShellMessage<M>> {
+        let Some(name) = self.window_name(window) else {
+            return self.view();
+        };
+
+        if let Some(panic) = self.panic.borrow().as_ref() {
+            return panic_view(panic);
+        }
+
+        match self.instance.window_view(name) {
+            Ok(view) => view.map(ShellMessage::App),
+            Err(panic) => {
+                let view = panic_view(&panic);
+                self.set_panic(panic);
+                view
+            }
+        }
+    }
+
+    /// The title of `window`, the title of the core followed by the name of
+    /// the window for the windows of the core.
+    pub fn window_title(&self, window: window::Id) -> String {
+        match self.window_name(window) {
+            Some(name) => format!("{} - {}", self.title(), name),
+            None => self.title(),
+        }
+    }
+
+    /// The theme of `window`, the same for all windows.
+    pub fn window_theme(&self, _window: window::Id) -> Theme {
+        self.theme()
+    }
+
+    /// The scale factor of `window`, the same for all windows.
+    pub fn window_scale_factor(&self, _window: window::Id) -> f64 {
+        self.scale_factor()
+    }
+
+    /// The name of `window` if it is a window of the core.
+    fn window_name(&self, window: window::Id) -> Option<&str> {
+        self.windows
+            .iter()
+            .find(|(open, _)| *open == window)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The geometry store, if `window` is the one it keeps the geometry of.
+    fn main_window_geometry(&mut self, window: window::Id) -> Option<&mut WindowGeometryStore> {
+        if self
+            .main_window
+            .is_some_and(|main_window| main_window != window)
+        {
+            return None;
+        }
+
+        self.window_geometry.as_mut()
+    }
+
+    /// Runs the commands of the core. [`CoreCommand::CloseWindow`] closes
+    /// the main window, even if a window of the core was focused last, and
+    /// [`CoreCommand::Exit`] shuts the shell down like closing the window
+    /// does.
+    fn execute(&self, core_commands: Vec<CoreCommand<M>>) -> Task<ShellMessage<M>> {
+        Task::batch(
+            core_commands
+                .into_iter()
+                .map(|command| match (command, self.main_window) {
+                    (CoreCommand::CloseWindow, Some(main_window)) => window::close(main_window),
+                    (CoreCommand::Exit, _) => Task::done(ShellMessage::Shutdown),
+                    (command, _) => commands::into_task(vec![command]),
+                }),
+        )
+    }
+
+    /// The title of the window, as requested by the core.
+    pub fn title(&self) -> String {
+        self.presentation()
+            .title()
+            .map_or_else(|| self.title.clone(), str::to_string)
+    }
+
+    /// The theme of the window, as requested by the core or described by
+    /// the palette of the stylesheet.
+    pub fn theme(&self) -> Theme {
+        self.presentation()
+            .theme()
+            .or_else(|| self.stylesheet.as_ref().and_then(StylesheetFile::theme))
+            .unwrap_or_default()
+    }
+
+    /// The scale factor of the window, as requested by the core.
+    pub fn scale_factor(&self) -> f64 {
+        self.presentation().scale_factor().unwrap_or(1.0)
+    }
+
+    /// Asks the core for its title, theme and scale factor.
+    fn presentation(&self) -> Presentation {
+        if self.panic.borrow().is_some() {
+            return Presentation::new();
+        }
+
+        match self.instance.presentation() {
+            Ok(presentation) => presentation,
+            Err(panic) => {
+                self.set_panic(panic);
+                Presentation::new()
+            }
+        }
+    }
+
+    /// Picks the state to transfer from a panicked core, which is not called
+    /// anymore: the last persisted state, or else the state the core was
+    /// created with.
+    fn panicked_state(&self) -> (Vec<u8>, Option<String>) {
+        let persisted = self
+            .persistence
+            .as_ref()
+            .and_then(StatePersistence::last_saved);
+        if let Some(state) = persisted {
+            log::warn!("The core panicked, reloading with the last persisted state");
+            return (state.to_vec(), None);
+        }
+
+        if let Some(previous) = self.previous.as_ref() {
+            log::warn!("The core panicked, reloading with the state it was created with");
+            return (previous.state.clone(), None);
+        }
+
+        log::warn!("The core panicked and no earlier state is known, reloading with a fresh one");
+        (
+            Vec::new(),
+            Some("The core panicked before its state could be saved".to_string()),
+        )
+    }
+
+    /// The number the profiler tags its measurements with: the generation
+    /// of the current library, or the number of reloads for backends which
+    /// don't number their libraries.
+    fn library_generation(&self) -> usize {
+        self.backend
+            .generation(&self.lib)
+            .unwrap_or(self.reloads as usize)
+    }
+
+    /// Lays out the view of the core in the size of the main window for the
+    /// widget tree inspector.
+    fn refresh_widget_tree(&mut self) {
+        if self.panic.borrow().is_some() {
+            return;
+        }
+
+        match self.instance.view() {
+            Ok(view) => self.widget_tree.refresh(&view, self.window_size),
+            Err(panic) => self.set_panic(panic),
+        }
+    }
+
+    /// Writes the current state to disk if persistence is enabled, and
+    /// hands it to the control server and the state inspector if they are
+    /// in use.
+    fn publish_state(&mut self) {
+        if self.persistence.is_none()
+            && self.control.is_none()
+            && !self.show_overlay
+            && self.view_file.is_none()
+        {
+            return;
+        }
+
+        match self.instance.save_state() {
+            Ok(state) => {
+                if let Some(persistence) = self.persistence.as_mut() {
+                    persistence.save(&state);
+                }
+                if self.show_overlay {
+                    self.inspector.refresh(&state);
+                }
+                if let Some(view_file) = self.view_file.as_mut() {
+                    view_file.set_state(&state);
+                }
+                if let Some(control) = &self.control {
+                    control.set_state(state);
+                }
+            }
+            Err(e) => log::error!("{}", e),
+        }
+    }
+
+    /// Feeds the recorded messages to the core in the order they were
+    /// recorded, if replaying is enabled.
+    pub(crate) fn replay(&self) -> Task<ShellMessage<M>> {
+        let Some(replay) = &self.replay else {
+            return Task::none();
+        };
+
+        replay
+            .messages
+            .iter()
+            .cloned()
+            .map(|message| Task::done(ShellMessage::App(message)))
+            .fold(Task::none(), Task::chain)
+    }
+
+    /// Loads the fonts in the assets directory, if one is watched.
+    pub(crate) fn load_assets(&self) -> Task<ShellMessage<M>> {
+        match &self.assets {
+            Some(dir) => assets::load_fonts(&assets::fonts_in(dir)),
+            None => Task::none(),
+        }
+    }
+
+    /// Serializes the state of the current core.
+    pub(crate) fn save_state(&self) -> Result<Vec<u8>, String> {
+        self.instance.save_state()
+    }
+
+    /// The message of the panic of the current core, if it panicked.
+    pub(crate) fn panic(&self) -> Option<String> {
+        self.panic.borrow().clone()
+    }
+
+    /// The notice shown above the view, if any.
+    pub(crate) fn notice(&self) -> Option<&str> {
+        self.notice.as_deref()
+    }
+
+    /// Whether loading the library failed and is retried.
+    pub(crate) fn is_retrying(&self) -> bool {
+        self.failed_attempts > 0
+    }
+
+    /// Adds the current state to the history.
+    fn take_snapshot(&mut self) {
+        match self.instance.save_state() {
+            Ok(state) => self.history.push(state),
+            Err(e) => log::error!("{}", e),
+        }
+    }
+
+    /// Replaces the running core with a new instance of the current library
+    /// created with `state`.
+    fn restore_state(&mut self, state: &[u8]) {
+        match self.backend.instantiate(&self.lib, state) {
+            Ok(instance) => {
+                drop(std::mem::replace(&mut self.instance, instance));
+                *self.panic.borrow_mut() = None;
+                self.publish_state();
+            }
+            Err(e) => {
+                self.notice = Some(format!("Restoring the state failed: {}", e));
+            }
+        }
+    }
+
+    /// Reports `event` to the observers, to the clients of the control
+    /// server and to the event log, if enabled.
+    fn emit(&self, event: ControlEvent) {
+        self.notify(&event);
+
+        if let Some(event_log) = &self.event_log {
+            event_log.write(&event);
+        }
+        if let Some(control) = &self.control {
+            control.emit(event);
+        }
+    }
+
+    /// Pauses or resumes automatic reloading, reloading right away if the
+    /// library changed while it was paused.
+    fn set_auto_reload(&mut self, enabled: bool) -> Task<ShellMessage<M>> {
+        self.auto_reload = enabled;
+        log::trace!(
+            "Automatic reloading {}",
+            if enabled { "resumed" } else { "paused" }
+        );
+        self.emit(ControlEvent::AutoReload { enabled });
+
+        self.reload_if_due()
+    }
+
+    /// Starts the pending reload if automatic reloading isn't paused and the
+    /// [`ReloadStrategy`] allows it now.
+    fn reload_if_due(&mut self) -> Task<ShellMessage<M>> {
+        if self.reload_pending && self.auto_reload && self.reload_strategy.is_due(self.last_input) {
+            self.reload_pending = false;
+            return Task::done(ShellMessage::Reload);
+        }
+
+        Task::none()
+    }
+
+    /// Calls the observers, the built-in metrics first, for `event`.
+    fn notify(&self, event: &ControlEvent) {
+        notify_observer(&mut *self.metrics.borrow_mut(), event);
+        for observer in self.observers.borrow_mut().iter_mut() {
+            notify_observer(observer.as_mut(), event);
+        }
+    }
+
+    /// Records that the core panicked with `panic`.
+    fn set_panic(&self, panic: String) {
+        log::error!("The core panicked: {}", panic);
+        self.emit(ControlEvent::Panicked {
+            message: panic.clone(),
+        });
+        *self.panic.borrow_mut() = Some(panic);
+    }
+
+    /// Swaps in the earlier build made at `built_at`, with the current
+    /// state. The next change of the library is loaded as usual.
+    fn load_build(&mut self, built_at: SystemTime) -> Task<ShellMessage<M>> {
+        log::trace!("Load the build of {}", format_built_at(built_at));
+
+        let state = match self.instance.save_state() {
+            Ok(state) => state,
+            Err(e) => {
+                self.notice = Some(format!("Loading the build failed: {}", e));
+                return Task::none();
+            }
+        };
+
+        match self
+            .backend
+            .load_build(built_at)
+            .and_then(|lib| self.instantiate_next(lib, &state))
+        {
+            Ok((lib, instance)) => {
+                let after_reload = self.swap(lib, instance, state);
+                self.publish_state();
+                Task::batch([after_reload, Task::done(ShellMessage::ReleaseRetired)])
+            }
+            Err(e) => {
+                self.notice = Some(format!("Loading the build failed: {}", e));
+                Task::none()
+            }
+        }
+    }
+
+    /// Validates `lib` with the stages of the [`ReloadPipeline`] before it
+    /// replaces the running core, and instantiates it with `state`. `lib` is
+    /// unloaded again if any stage fails, so a broken build never replaces
+    /// the running core.
+    fn instantiate_next(
+        &mut self,
+        lib: B::Library,
+        state: &[u8],
+    ) -> std::io::Result<(B::Library, B::Instance)> {
+        let mut report = PipelineReport::default();
+        let result = self.run_pipeline(&lib, state, &mut report);
+        self.pipeline_report = Some(report);
+
+        match result {
+            Ok(instance) => Ok((lib, instance)),
+            Err(e) => {
+                self.unload(lib);
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs the stages of the [`ReloadPipeline`] on `lib`, recording how
+    /// they went in `report`, and returns the new instance.
+    fn run_pipeline(
+        &mut self,
+        lib: &B::Library,
+        state: &[u8],
+        report: &mut PipelineReport,
+    ) -> std::io::Result<B::Instance> {
+        let pipeline = self.reload_pipeline.clone();
+        let core_error = |e: String| std::io::Error::other(e);
+
+        report.run(&pipeline, ReloadStage::SymbolValidation, || {
+            self.backend.validate(lib)
+        })?;
+        report.run(&pipeline, ReloadStage::SelfTest, || {
+            self.backend.self_test(lib)
+        })?;
+        let state = report.run(&pipeline, ReloadStage::Migrate, || {
+            self.backend.migrate(lib, &self.lib, state)
+        })?;
+        let instance = report.run(&pipeline, ReloadStage::Instantiate, || {
+            self.backend.instantiate(lib, &state)
+        })?;
+
+        if pipeline.smoke_updates > 0 {
+            report.run(&pipeline, ReloadStage::SmokeUpdates, || {
+                let mut scratch = self.backend.instantiate(lib, &state)?;
+                for message in self.recent_messages.iter() {
+                    scratch.update(message.clone()).map_err(core_error)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        if pipeline.render_view {
+            let theme = instance
+                .presentation()
+                .ok()
+                .and_then(|presentation| presentation.theme())
+                .unwrap_or_else(|| self.theme());
+            report.run(&pipeline, ReloadStage::OffscreenRender, || {
+                let view = instance.view().map_err(core_error)?;
+                render_offscreen(view, &theme)
+            })?;
+        }
+
+        report.run(&pipeline, ReloadStage::SaveState, || {
+            instance
+                .save_state()
+                .map(drop)
+                .map_err(|e| core_error(format!("The new core can't save its state: {}", e)))
+        })?;
+
+        Ok(instance)
+    }
+
+    /// Replaces the current core with `instance` created from `lib`, keeping
+    /// the old library and `state` around for a rollback. Returns the
+    /// commands the new core requested after the reload.
+    fn swap(
+        &mut self,
+        lib: B::Library,
+        instance: B::Instance,
+        state: Vec<u8>,
+    ) -> Task<ShellMessage<M>> {
+        let old_instance = std::mem::replace(&mut self.instance, instance);
+        let old_lib = std::mem::replace(&mut self.lib, lib);
+        *self.panic.borrow_mut() = None;
+        self.notice = None;
+        let after_reload = self.after_reload(&old_lib, false);
+
+        // The replaced version is shown next to the new one instead of being
+        // kept for a rollback.
+        if self.compare {
+            if let Some(replaced) = self.compared.replace(ComparedCore {
+                instance: old_instance,
+                lib: old_lib,
+                panic: RefCell::new(None),
+            }) {
+                self.retire_compared(replaced);
+            }
+            return after_reload;
+        }
+        drop(old_instance);
+
+        // The UI still holds elements built by the old core until the next
+        // view, which is fine as its library stays loaded as `previous`. The
+        // library replaced as `previous` may have been swapped in just now,
+        // so it is retired rather than unloaded.
+        if let Some(replaced) = self.previous.replace(PreviousCore {
+            lib: old_lib,
+            state,
+            swapped_at: Instant::now(),
+        }) {
+            self.retired.push(replaced.lib);
+        }
+
+        after_reload
+    }
+
+    /// Runs the `on_after_reload` hook of the core swapped in just now,
+    /// which replaced a core created from `previous_lib`.
+    fn after_reload(
+        &mut self,
+        previous_lib: &B::Library,
+        rolled_back: bool,
+    ) -> Task<ShellMessage<M>> {
+        self.reloads += 1;
+        let reload = ReloadInfo::new(
+            self.reloads,
+            rolled_back,
+            self.backend.built_at(&self.lib),
+            self.backend.built_at(previous_lib),
+        );
+
+        match self.instance.after_reload(&reload) {
+            Ok(core_commands) => self.execute(core_commands),
+            Err(panic) => {
+                self.set_panic(panic);
+                Task::none()
+            }
+        }
+    }
+
+    /// Replaces the panicked core with a new instance of the previous library.
+    /// Returns the commands the restored core requested after the rollback.
+    fn rollback(&mut self) -> Task<ShellMessage<M>> {
+        let Some(previous) = self.previous.take() else {
+            return Task::none();
+        };
+
+        let panic = self.panic.borrow().clone().unwrap_or_default();
+        log::warn!("Rolling back to the previous library");
+
+        match self.backend.instantiate(&previous.lib, &previous.state) {
+            Ok(instance) => {
+                drop(std::mem::replace(&mut self.instance, instance));
+                let broken_lib = std::mem::replace(&mut self.lib, previous.lib);
+
+                *self.panic.borrow_mut() = None;
+                let after_reload = self.after_reload(&broken_lib, true);
+                self.retired.push(broken_lib);
+                self.emit(ControlEvent::RolledBack {
+                    panic: panic.clone(),
+                });
+                self.notice = Some(format!(
+                    "The new core panicked ({}). Rolled back to the previous version.",
+                    panic
+                ));
+                self.publish_state();
+                after_reload
+            }
+            Err(e) => {
+                self.notice = Some(format!(
+                    "Rolling back to the previous version failed: {}",
+                    e
+                ));
+                self.lingering.push(previous.lib);
+                Task::none()
+            }
+        }
+    }
+
+    /// Hands the previous library over to the unload policy once the
+    /// rollback grace period is over, and unloads the expired libraries.
+    fn expire_previous(&mut self) {
+        if let Some(previous) = self
+            .previous
+            .take_if(|previous| previous.swapped_at.elapsed() > ROLLBACK_GRACE_PERIOD)
+        {
+            log::trace!("The rollback grace period is over");
+            self.lingering.push(previous.lib);
+        }
+
+        self.unload_expired();
+    }
+
+    /// Unloads the libraries the unload policy no longer keeps.
+    fn unload_expired(&mut self) {
+        for lib in self.lingering.expire() {
+            self.unload(lib);
+        }
+    }
+
+    /// Unloads `lib` and, if leaks are tracked, records the allocations its
+    /// core left behind.
+    fn unload(&mut self, lib: B::Library) {
+        let generation = self
+            .track_leaks
+            .then(|| self.backend.generation(&lib))
+            .flatten();
+        self.backend.unload(lib);
+
+        let Some(generation) = generation else {
+            return;
+        };
+        let live = live_allocations(generation);
+        if live.count == 0 {
+            return;
+        }
+
+        log::warn!(
+            "Library #{} left {} allocations ({} bytes) behind",
+            generation,
+            live.count,
+            live.bytes
+        );
+        if self.leaks.len() == LEAK_REPORTS {
+            self.leaks.pop_front();
+        }
+        self.leaks.push_back((generation, live));
+    }
+
+    pub fn subscription(&self) -> Subscription<ShellMessage<M>> {
+        let library_changes = match (&self.push, &self.build, self.poll_interval) {
+            (Some(push), _, _) => Subscription::run_with_id(
+                push.addr,
+                push::listen(push.clone(), self.lib_path.clone()),
+            ),
+            (None, Some(build), _) => {
+                Subscription::run_with_id(build.src_dir.clone(), build_runner::run(build.clone()))
+            }
+            // External tooling requests the reloads itself.
+            (None, None, _) if self.external_commands.is_some() => Subscription::none(),
+            (None, None, _) if self.focus_check == Some(FocusCheck::Only) => Subscription::none(),
+            (None, None, Some(interval)) => {
+                every(self.adaptive_poll_interval(interval)).map(|_| ShellMessage::Tick)
+            }
+            // Writing the marker is the last step of a build.
+            (None, None, None) if self.require_ready_marker => {
+                let marker_path = ready::marker_path(&self.lib_path);
+                Subscription::run_with_id(marker_path.clone(), watcher::watch(marker_path))
+            }
+            (None, None, None) => Subscription::run_with_id(
+                self.lib_path.clone(),
+                watcher::watch(self.lib_path.clone()),
+            ),
+        };
+
+        // While a rollback is possible, tick to detect panics in the view and
+        // to unload the previous library when the grace period is over. Tick
+        // as well while replaced libraries wait to be unloaded.
+        let grace_period = if self.previous.is_some() || self.lingering.is_waiting() {
+            every(Duration::from_secs(1)).map(|_| ShellMessage::Tick)
+        } else {
+            Subscription::none()
+        };
+
+        // Waiting for the UI to be idle needs the input, and a tick to reload
+        // once it stayed idle for long enough.
+        let idle = match self.reload_strategy {
+            ReloadStrategy::OnIdle(_) if self.reload_pending => Subscription::batch([
+                iced::event::listen_with(user_input),
+                every(Duration::from_millis(250)).map(|_| ShellMessage::Tick),
+            ]),
+            ReloadStrategy::OnIdle(_) => iced::event::listen_with(user_input),
+            _ => Subscription::none(),
+        };
+
+        let control = match &self.control {
+            Some(control) => control.subscription(),
+            None => Subscription::none(),
+        };
+
+        let external_commands = match &self.external_commands {
+            Some(lib_name) => {
+                Subscription::run_with_id("external_commands", external::listen(lib_name.clone()))
+            }
+            None => Subscription::none(),
+        };
+
+        // New log records are only picked up while the log pane is shown.
+        let logs = if self.show_overlay && self.log_viewer.is_some() {
+            every(LOG_REFRESH_INTERVAL).map(|_| ShellMessage::RefreshLogs)
+        } else {
+            Subscription::none()
+        };
+
+        let view_file = match &self.view_file {
+            Some(view_file) => Subscription::run_with_id(
+                view_file.path().to_path_buf(),
+                watcher::watch_file(view_file.path().to_path_buf(), || {
+                    ShellMessage::ViewFileChanged
+                }),
+            ),
+            None => Subscription::none(),
+        };
+
+        let assets = match &self.assets {
+            Some(dir) => Subscription::run_with_id(
+                dir.clone(),
+                watcher::watch_dir(dir.clone(), ShellMessage::AssetsChanged),
+            ),
+            None => Subscription::none(),
+        };
+
+        let stylesheet = match &self.stylesheet {
+            Some(stylesheet) => Subscription::run_with_id(
+                stylesheet.path().to_path_buf(),
+                watcher::watch_file(stylesheet.path().to_path_buf(), || {
+                    ShellMessage::StylesheetChanged
+                }),
+            ),
+            None => Subscription::none(),
+        };
+
+        let localization = match &self.localization {
+            Some(localization) => Subscription::run_with_id(
+                localization.dir().to_path_buf(),
+                watcher::watch_dir(localization.dir().to_path_buf(), |_| {
+                    ShellMessage::CatalogsChanged
+                }),
+            ),
+            None => Subscription::none(),
+        };
+
+        let focus = if self.poll_interval.is_some() || self.focus_check.is_some() {
+            iced::event::listen_with(focus_changed)
+        } else {
+            Subscription::none()
+        };
+
+        // The size of the window is followed while the overlay is shown, for
+        // the widget tree inspector.
+        let window_geometry = if self.window_geometry.is_some() || self.show_overlay {
+            iced::event::listen_with(window_geometry_changed)
+        } else {
+            Subscription::none()
+        };
+
+        // Daemons keep running when the windows are closed. The main window
+        // only closes once the core agreed to.
+        let window_closed = if self.main_window.is_some() {
+            Subscription::batch([
+                window::close_events().map(ShellMessage::WindowClosed),
+                window::close_requests().map(ShellMessage::CloseRequested),
+            ])
+        } else {
+            Subscription::none()
+        };
+
+        let soak = match &self.soak {
+            Some(_) => every(SOAK_STEP_INTERVAL).map(|_| ShellMessage::SoakStep),
+            None => Subscription::none(),
+        };
+
+        let core_events = if self.instance.handles_events() && self.panic.borrow().is_none() {
+            subscriptions::core_events()
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([
+            library_changes,
+            window_geometry,
+            focus,
+            window_closed,
+            core_events,
+            view_file,
+            assets,
+            stylesheet,
+            localization,
+            control,
+            external_commands,
+            soak,
+            grace_period,
+            idle,
+            logs,
+            keyboard::on_key_press(hotkey),
+            self.core_subscriptions(),
+        ])
+    }
+
+    /// Polls with `interval` while the main window is focused or a change is
+    /// likely, and backs off to [`UNFOCUSED_POLL_INTERVAL`] otherwise, so an
+    /// idle shell doesn't keep waking the CPU up.
+    fn adaptive_poll_interval(&self, interval: Duration) -> Duration {
+        if self.focused || Instant::now() < self.fast_poll_until {
+            interval
+        } else {
+            interval.max(UNFOCUSED_POLL_INTERVAL)
+        }
+    }
+
+    /// Sets up the subscriptions declared by the current core.
+    fn core_subscriptions(&self) -> Subscription<ShellMessage<M>> {
+        if self.panic.borrow().is_some() {
+            return Subscription::none();
+        }
+
+        match self.instance.subscriptions() {
+            Ok(core_subscriptions) => subscriptions::materialize(&core_subscriptions),
+            Err(panic) => {
+                self.set_panic(panic);
+                Subscription::none()
+            }
+        }
+    }
+}
+
+/// Calls the method of `observer` corresponding to `event`, if any.
+fn notify_observer(observer: &mut dyn ReloadObserver, event: &ControlEvent) {
+    match event {
+        ControlEvent::ChangeDetected => observer.on_change_detected(),
+        ControlEvent::ReloadStarted { attempt } => observer.on_load_start(*attempt),
+        ControlEvent::Reloaded { duration_ms } => {
+            observer.on_swap_complete(Duration::from_millis(*duration_ms))
+        }
+        ControlEvent::LoadFailed { error, .. } => observer.on_failure(error),
+        ControlEvent::BuildFailed { error } => {
+            observer.on_failure(error.as_deref().unwrap_or("The build failed"))
+        }
+        ControlEvent::Panicked { message } => observer.on_failure(message),
+        _ => (),
+    }
+}
+
+/// Formats the time a library was built at.
+fn format_built_at(built_at: SystemTime) -> String {
+    OffsetDateTime::from(built_at)
+        .format(format_description!(
+            "[year]-[month]-[day] [hour]:[minute]:[second]"
+        ))
+        .unwrap_or_default()
+}
+
+/// Maps the shell's hotkeys: F5 reloads the library, F6 pauses or resumes
+/// automatic reloading, F7 toggles comparing versions and F12 toggles the
+/// dev overlay.
+fn hotkey<M>(key: Key, _modifiers: Modifiers) -> Option<ShellMessage<M>> {
+    match key {
+        Key::Named(Named::F5) => Some(ShellMessage::Reload),
+        Key::Named(Named::F6) => Some(ShellMessage::ToggleAutoReload),
+        Key::Named(Named::F7) => Some(ShellMessage::ToggleCompare),
+        Key::Named(Named::F12) => Some(ShellMessage::ToggleOverlay),
+        _ => None,
+    }
+}
+
+/// Reports the window being moved or resized, to remember its geometry.
+fn window_geometry_changed<M>(
+    event: iced::Event,
+    _status: iced::event::Status,
+    window: iced::window::Id,
+) -> Option<ShellMessage<M>> {
+    match event {
+        iced::Event::Window(iced::window::Event::Resized(size)) => {
+            Some(ShellMessage::WindowResized(window, size))
+        }
+        iced::Event::Window(iced::window::Event::Moved(position)) => {
+            Some(ShellMessage::WindowMoved(window, position))
+        }
+        _ => None,
+    }
+}
+
+/// Reports a window gaining or losing the focus.
+fn focus_changed<M>(
+    event: iced::Event,
+    _status: iced::event::Status,
+    window: iced::window::Id,
+) -> Option<ShellMessage<M>> {
+    match event {
+        iced::Event::Window(iced::window::Event::Focused) => {
+            Some(ShellMessage::FocusChanged(window, true))
+        }
+        iced::Event::Window(iced::window::Event::Unfocused) => {
+            Some(ShellMessage::FocusChanged(window, false))
+        }
+        _ => None,
+    }
+}
+
+/// Renders the view of a version of the core under `label`, side by side
+/// with the other one.
+fn compare_pane<'a, M: 'a>(
+    label: &str,
+    content: Element<'a, ShellMessage<M>>,
+) -> Element<'a, ShellMessage<M>> {
+    column![
+        text(label.to_string())
+            .size(12)
+            .color(Color::from_rgb(0.4, 0.4, 0.4)),
+        container(content).height(Length::Fill),
+    ]
+    .padding(4)
+    .width(Length::Fill)
+    .into()
+}
+
+/// Renders the indicator of the reload mode, and of the pending reload while
+/// `failed_attempts` of `reload_attempts` failed. A pending reload can be
+/// confirmed with a button.
+fn status_bar<'a, M: Clone + 'a>(
+    auto_reload: bool,
+    strategy: ReloadStrategy,
+    reload_pending: bool,
+    failed_attempts: u32,
+    reload_attempts: u32,
+) -> Element<'a, ShellMessage<M>> {
+    let status = match (auto_reload, reload_pending) {
+        _ if failed_attempts > 0 => format!(
+            "Reload pending, retrying ({} of {} attempts failed)",
+            failed_attempts, reload_attempts
+        ),
+        (true, false) => "Auto-reload on (F5 reload, F6 pause)".to_string(),
+        (true, true) => match strategy {
+            ReloadStrategy::OnIdle(idle) => format!(
+                "New version available, reloading after {} s without input (F5 reload now)",
+                idle.as_secs_f32()
+            ),
+            _ => "New version available (F5 reload, F6 pause)".to_string(),
+        },
+        (false, false) => "Auto-reload paused (F5 reload, F6 resume)".to_string(),
+        (false, true) => {
+            "Auto-reload paused, new version available (F5 reload, F6 resume)".to_string()
+        }
+    };
+
+    let mut bar = row![text(status).size(12).color(Color::from_rgb(0.4, 0.4, 0.4))]
+        .spacing(8)
+        .align_y(Alignment::Center);
+    if reload_pending && failed_attempts == 0 {
+        bar = bar.push(button(text("Reload").size(12)).on_press(ShellMessage::Reload));
+    }
+
+    Container::new(bar)
+        .padding([2, 8])
+        .width(Length::Fill)
+        .align_x(Alignment::End)
+        .into()
+}
+
+/// Reports the keyboard, mouse and touch input, delaying a reload waiting for
+/// the UI to be idle.
+fn user_input<M>(
+    event: iced::Event,
+    _status: iced::event::Status,
+    _window: iced::window::Id,
+) -> Option<ShellMessage<M>> {
+    match event {
+        iced::Event::Keyboard(_) | iced::Event::Mouse(_) | iced::Event::Touch(_) => {
+            Some(ShellMessage::UserInput)
+        }
+        _ => None,
+    }
+}
+
+/// Renders the error shown instead of the core's view after it panicked.
+pub(crate) fn panic_view<'a, M: 'a>(panic: &str) -> Element<'a, ShellMessage<M>> {
+    let red = Color::from_rgb(0.85, 0.15, 0.15);
+
+    Container::new(
+        column![
+            text("The core panicked").size(24).color(red),
+            text(panic.to_string()).color(red),
+            text("Fix the code and rebuild it to reload the core."),
+        ]
+        .spacing(10)
+        .align_x(Alignment::Center),
+    )
+    .padding(20)
+    .center(Length::Fill)
+    .into()
+}
+
+/// Renders a notice about a failed reload or a rollback.
+pub(crate) fn notice_banner<'a, M: 'a>(notice: &str) -> Element<'a, ShellMessage<M>> {
+    Container::new(text(notice.to_string()).color(Color::BLACK))
+        .padding(8)
+        .width(Length::Fill)
+        .style(|_| container::Style {
+            background: Some(Color::from_rgb(0.98, 0.85, 0.4).into()),
+            ..container::Style::default()
+        })
+        .into()
+}
+
+/// Renders the compiler messages of a failed build, most severe first.
+fn diagnostics_panel<'a, M: 'a>(diagnostics: &[Diagnostic]) -> Element<'a, ShellMessage<M>> {
+    let mut sorted: Vec<&Diagnostic> = diagnostics.iter().collect();
+    sorted.sort_by_key(|diagnostic| diagnostic.level as u8);
+
+    let lines = sorted.into_iter().map(|diagnostic| {
+        let (label, color) = match diagnostic.level {
+            DiagnosticLevel::Error => ("error", Color::from_rgb(0.85, 0.15, 0.15)),
+            DiagnosticLevel::Warning => ("warning", Color::from_rgb(0.85, 0.55, 0.0)),
+            DiagnosticLevel::Note => ("note", Color::from_rgb(0.4, 0.4, 0.4)),
+        };
+
+        let location = match &diagnostic.file {
+            Some(file) => format!("{}:{}:{}", file, diagnostic.line, diagnostic.column),
+            None => String::new(),
+        };
+
+        row![
+            text(label).color(color),
+            text(location).color(Color::from_rgb(0.3, 0.3, 0.6)),
+            text(diagnostic.message.clone()),
+        ]
+        .spacing(10)
+        .into()
+    });
+
+    Container::new(scrollable(
+        Column::with_children(lines).spacing(4).padding(8),
+    ))
+    .width(Length::Fill)
+    .max_height(240)
+    .style(|_| container::Style {
+        background: Some(Color::from_rgb(0.96, 0.96, 0.96).into()),
+        ..container::Style::default()
+    })
+    .into()
+}
@@ -0,0 +1,200 @@
+use crate::recording::{Replay, ReplayConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often the soak test takes a step.
+pub(crate) const SOAK_STEP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many recorded messages are dispatched at most between two reloads.
+const MESSAGES_PER_RELOAD: usize = 5;
+
+/// How much the resident memory may grow past the measurement after the
+/// first reload before the soak test fails.
+const MAX_MEMORY_GROWTH: u64 = 128 * 1024 * 1024;
+
+/// Whether a soak test of this process failed, which makes
+/// [`crate::HotReload::run`] exit with a failure status once the shell shut
+/// down.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Records that the soak test failed, before the shell shuts down.
+pub(crate) fn record_failure() {
+    FAILED.store(true, Ordering::Relaxed);
+}
+
+/// Whether a soak test of this process failed.
+pub(crate) fn failed() -> bool {
+    FAILED.load(Ordering::Relaxed)
+}
+
+/// How many reloads the soak test does, and the recording the messages
+/// dispatched in between are picked from.
+pub(crate) struct SoakConfig<M> {
+    pub(crate) reloads: u32,
+    pub(crate) messages: Option<ReplayConfig<M>>,
+}
+
+/// What the soak test does next.
+pub(crate) enum SoakStep<M> {
+    /// A reload is still in progress.
+    Wait,
+
+    /// Dispatch the messages to the core, then reload it.
+    Reload(Vec<M>),
+
+    /// All reloads succeeded with the memory staying bounded.
+    Passed,
+
+    /// A reload failed, the core panicked or the memory grew too much.
+    Failed(String),
+}
+
+/// Reloads the core over and over while dispatching random recorded
+/// messages to it, to find crashes and leaks which only show after many
+/// reloads.
+pub(crate) struct SoakTest<M> {
+    reloads: u32,
+    messages: Vec<M>,
+    rng: u64,
+
+    /// The reload counter of the shell when the last reload was requested,
+    /// until it moves past it.
+    requested_at: Option<u32>,
+    completed: u32,
+    baseline_memory: Option<u64>,
+    peak_memory: Option<u64>,
+    failure: Option<String>,
+}
+
+impl<M: Clone> SoakTest<M> {
+    /// Loads the recording of `config`, if any. The test runs without
+    /// messages if it can't be loaded.
+    pub(crate) fn new(config: SoakConfig<M>) -> Self {
+        let messages = match config.messages.as_ref().map(Replay::load) {
+            Some(Ok(replay)) => replay.messages,
+            Some(Err(e)) => {
+                log::error!("Failed to load the messages of the soak test: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+
+        Self {
+            reloads: config.reloads,
+            messages,
+            // Xorshift never leaves zero.
+            rng: seed | 1,
+            requested_at: None,
+            completed: 0,
+            baseline_memory: None,
+            peak_memory: None,
+            failure: None,
+        }
+    }
+
+    /// Marks the test as failed, e.g. because a reload failed for good.
+    pub(crate) fn fail(&mut self, reason: impl Into<String>) {
+        self.failure.get_or_insert_with(|| reason.into());
+    }
+
+    /// Decides the next step given the reload counter of the shell and
+    /// whether a reload is in progress.
+    pub(crate) fn step(&mut self, reloads: u32, loading: bool) -> SoakStep<M> {
+        if let Some(reason) = self.failure.take() {
+            return SoakStep::Failed(reason);
+        }
+
+        if loading {
+            return SoakStep::Wait;
+        }
+
+        if let Some(requested_at) = self.requested_at {
+            if reloads == requested_at {
+                return SoakStep::Wait;
+            }
+
+            self.requested_at = None;
+            self.completed += 1;
+            log::info!("Soak test: reload {} of {}", self.completed, self.reloads);
+
+            if let Err(reason) = self.check_memory() {
+                return SoakStep::Failed(reason);
+            }
+        }
+
+        if self.completed >= self.reloads {
+            return SoakStep::Passed;
+        }
+
+        self.requested_at = Some(reloads);
+        let count = if self.messages.is_empty() {
+            0
+        } else {
+            self.random(MESSAGES_PER_RELOAD + 1)
+        };
+        let mut messages = Vec::with_capacity(count);
+        for _ in 0..count {
+            let index = self.random(self.messages.len());
+            messages.push(self.messages[index].clone());
+        }
+        SoakStep::Reload(messages)
+    }
+
+    /// Summarizes a test which passed.
+    pub(crate) fn summary(&self) -> String {
+        let memory = match (self.baseline_memory, self.peak_memory) {
+            (Some(baseline), Some(peak)) => format!(
+                ", resident memory {} MiB after the first reload, {} MiB at most",
+                baseline / 1024 / 1024,
+                peak / 1024 / 1024
+            ),
+            _ => String::new(),
+        };
+        format!("{} reloads{}", self.completed, memory)
+    }
+
+    /// Measures the resident memory, the first measurement being the
+    /// baseline, which the first reload already grew to its usual size.
+    fn check_memory(&mut self) -> Result<(), String> {
+        let Some(resident) = resident_memory() else {
+            return Ok(());
+        };
+        let baseline = *self.baseline_memory.get_or_insert(resident);
+        self.peak_memory = Some(self.peak_memory.unwrap_or(0).max(resident));
+
+        if resident > baseline + MAX_MEMORY_GROWTH {
+            return Err(format!(
+                "The resident memory grew from {} MiB to {} MiB over {} reloads",
+                baseline / 1024 / 1024,
+                resident / 1024 / 1024,
+                self.completed
+            ));
+        }
+        Ok(())
+    }
+
+    /// A random number below `bound`.
+    fn random(&mut self, bound: usize) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng % bound as u64) as usize
+    }
+}
+
+/// The resident memory of the process in bytes, only known on Linux.
+fn resident_memory() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    // The second field is the resident set in pages, which are 4 KiB on the
+    // platforms the shell runs on.
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
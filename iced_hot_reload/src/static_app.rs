@@ -0,0 +1,121 @@
+use crate::commands;
+use crate::message::ShellMessage;
+use crate::subscriptions;
+use hot_reload_interface::{AppInterface, CoreEvent};
+use iced::{window, Element, Subscription, Task, Theme};
+use std::marker::PhantomData;
+
+/// Runs the core `A`, linked into the binary, as a plain iced application
+/// starting from the default state.
+///
+/// Nothing is loaded or watched and the core is called directly instead of
+/// through the FFI vtable, so a release build of the shell ships as a
+/// regular single binary. Together with the default `hot-reload` feature
+/// disabled, the loading and watching code isn't even compiled.
+///
+/// ```ignore
+/// iced_hot_reload::run_static::<CoreApp, AppState, Message>("Application")
+/// ```
+pub fn run_static<A, S, M>(title: impl Into<String>) -> iced::Result
+where
+    A: AppInterface<S, M> + From<S> + 'static,
+    S: Default + 'static,
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    run_app(title.into(), A::from(S::default()))
+}
+
+/// Runs `app` as a plain iced application.
+pub(crate) fn run_app<A, S, M>(title: String, app: A) -> iced::Result
+where
+    A: AppInterface<S, M> + 'static,
+    S: 'static,
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    iced::application(StaticApp::title, StaticApp::update, StaticApp::view)
+        .subscription(StaticApp::subscription)
+        .theme(StaticApp::theme)
+        .scale_factor(StaticApp::scale_factor)
+        .exit_on_close_request(false)
+        .run_with(move || {
+            let app = StaticApp::<A, S, M> {
+                title,
+                app,
+                _types: PhantomData,
+            };
+            (app, Task::none())
+        })
+}
+
+/// Hosts a core linked into the binary, see [`run_static`].
+///
+/// Commands and subscriptions of the core behave like in the hot-reloading
+/// shell, so the core doesn't notice the difference.
+pub struct StaticApp<A, S, M> {
+    /// The window title, unless the core provides its own.
+    title: String,
+
+    /// The core.
+    app: A,
+
+    _types: PhantomData<fn() -> (S, M)>,
+}
+
+impl<A, S, M> StaticApp<A, S, M>
+where
+    A: AppInterface<S, M>,
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    pub fn title(&self) -> String {
+        self.app.title().unwrap_or_else(|| self.title.clone())
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.app.theme().unwrap_or_default()
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.app.scale_factor().unwrap_or(1.0)
+    }
+
+    pub fn update(&mut self, message: ShellMessage<M>) -> Task<ShellMessage<M>> {
+        match message {
+            ShellMessage::App(message) => commands::into_task(self.app.update(message)),
+            ShellMessage::KeyPressed(key) => {
+                match subscriptions::key_binding(&self.app.subscriptions(), &key) {
+                    Some(message) => Task::done(ShellMessage::App(message)),
+                    None => Task::none(),
+                }
+            }
+            ShellMessage::CoreEvent(_, event) => match self.app.on_event(&event) {
+                Some(message) => Task::done(ShellMessage::App(message)),
+                None => Task::none(),
+            },
+            ShellMessage::CloseRequested(window) => {
+                match self.app.on_event(&CoreEvent::CloseRequested) {
+                    Some(message) => Task::done(ShellMessage::App(message)),
+                    None => window::close(window),
+                }
+            }
+            ShellMessage::CommandResult { request, result } => {
+                match self.app.command_result(&request, &result) {
+                    Some(message) => Task::done(ShellMessage::App(message)),
+                    None => Task::none(),
+                }
+            }
+            _ => Task::none(),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, ShellMessage<M>> {
+        self.app.view().map(ShellMessage::App)
+    }
+
+    pub fn subscription(&self) -> Subscription<ShellMessage<M>> {
+        Subscription::batch([
+            subscriptions::materialize(&self.app.subscriptions()),
+            subscriptions::core_events(),
+            window::close_requests().map(ShellMessage::CloseRequested),
+        ])
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+/// When a detected change of the library is applied, see
+/// [`crate::HotReloadBuilder::reload_strategy`].
+///
+/// Pausing automatic reloading with F6 holds back every change regardless of
+/// the strategy, and F5 always reloads right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReloadStrategy {
+    /// Reloads as soon as the library changed.
+    #[default]
+    Immediate,
+
+    /// Waits until there was no keyboard, mouse or touch input for the given
+    /// time, so a reload doesn't interrupt a drag or typing.
+    OnIdle(Duration),
+
+    /// Waits until the user confirms the reload with F5 or the button in the
+    /// status bar.
+    Manual,
+}
+
+impl ReloadStrategy {
+    /// Whether a pending change is applied now, given the time of the last
+    /// input.
+    pub(crate) fn is_due(&self, last_input: Instant) -> bool {
+        match self {
+            Self::Immediate => true,
+            Self::OnIdle(idle) => last_input.elapsed() >= *idle,
+            Self::Manual => false,
+        }
+    }
+}
+
+/// Whether the library is checked for changes when the main window gains the
+/// focus, see [`crate::HotReloadBuilder::check_on_focus`].
+///
+/// Switching from the editor to the app is when a change is expected, so
+/// checking then picks it up without waiting for the watcher or the next
+/// poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusCheck {
+    /// Checks when the window gains the focus, and watches or polls the
+    /// library as usual.
+    Also,
+
+    /// Only checks when the window gains the focus, without watching or
+    /// polling the library.
+    Only,
+}
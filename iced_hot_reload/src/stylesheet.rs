@@ -0,0 +1,138 @@
+use hot_reload_interface::{set_host_stylesheet, Stylesheet};
+use iced::Theme;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The stylesheet file watched by the shell, see
+/// [`crate::HotReloadBuilder::stylesheet`].
+///
+/// Every version read successfully is handed to the cores through
+/// [`set_host_stylesheet`]. An invalid version keeps the previous one in
+/// place until the file is fixed.
+pub(crate) struct StylesheetFile {
+    /// The TOML or JSON file describing the stylesheet.
+    path: PathBuf,
+
+    /// When the file was last read.
+    modified: Option<SystemTime>,
+
+    /// The theme built from the palette of the stylesheet, if it sets one.
+    theme: Option<Theme>,
+
+    /// Why the file couldn't be read the last time.
+    error: Option<String>,
+}
+
+impl StylesheetFile {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        let mut stylesheet = Self {
+            path,
+            modified: None,
+            theme: None,
+            error: None,
+        };
+        stylesheet.reload();
+        stylesheet
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The theme the palette of the stylesheet describes, if any.
+    pub(crate) fn theme(&self) -> Option<Theme> {
+        self.theme.clone()
+    }
+
+    /// Why the file couldn't be read the last time, if it couldn't.
+    pub(crate) fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Reads the file again.
+    pub(crate) fn reload(&mut self) {
+        self.modified = modified(&self.path);
+
+        match read(&self.path) {
+            Ok(stylesheet) => {
+                log::trace!("Read the stylesheet from {}", self.path.display());
+                self.theme = stylesheet.theme();
+                self.error = None;
+                set_host_stylesheet(stylesheet);
+            }
+            Err(e) => {
+                log::error!("Invalid stylesheet {}: {}", self.path.display(), e);
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Reads the file again if it was modified since it was last read, for
+    /// when the file can't be watched.
+    pub(crate) fn refresh(&mut self) {
+        if modified(&self.path) != self.modified {
+            self.reload();
+        }
+    }
+}
+
+/// Reads the stylesheet at `path`, written in JSON if the extension says so
+/// and in TOML otherwise.
+fn read(path: &Path) -> Result<Stylesheet, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&contents).map_err(|e| e.to_string())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stylesheets_are_read_as_toml_or_json() {
+        let dir =
+            std::env::temp_dir().join(format!("iced_hot_reload_stylesheet_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("style.toml");
+        std::fs::write(
+            &toml_path,
+            "padding = 8.0\n\n[palette]\nprimary = \"#3b82f6\"\n",
+        )
+        .unwrap();
+        let json_path = dir.join("style.json");
+        std::fs::write(&json_path, r#"{ "spacing": 4.0 }"#).unwrap();
+        let invalid_path = dir.join("invalid.toml");
+        std::fs::write(&invalid_path, "padding = \"wide\"").unwrap();
+
+        let from_toml = read(&toml_path).unwrap();
+        assert_eq!(from_toml.padding, 8.0);
+        assert_eq!(from_toml.palette.primary.as_deref(), Some("#3b82f6"));
+        assert!(from_toml.theme().is_some());
+
+        // The settings missing from the file keep their defaults.
+        let from_json = read(&json_path).unwrap();
+        assert_eq!(from_json.spacing, 4.0);
+        assert_eq!(from_json.padding, Stylesheet::default().padding);
+        assert!(from_json.theme().is_none());
+
+        assert!(read(&invalid_path).is_err());
+        assert!(read(&dir.join("missing.toml")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
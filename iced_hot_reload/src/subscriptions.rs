@@ -0,0 +1,166 @@
+use crate::message::ShellMessage;
+use hot_reload_interface::{CoreEvent, CoreSubscription, KeyName};
+use iced::event::{self, Event, Status};
+use iced::futures::channel::mpsc::{unbounded, UnboundedSender};
+use iced::futures::{SinkExt, Stream, StreamExt};
+use iced::keyboard::{self, Key, Modifiers};
+use iced::mouse::{self, ScrollDelta};
+use iced::{stream, window, Subscription};
+use std::thread;
+use std::time::Duration;
+
+/// Turns the subscriptions declared by the core into iced subscriptions.
+///
+/// Every subscription is identified by its declaration, so iced keeps the
+/// running ones alive across updates and reloads as long as the core keeps
+/// declaring them, and stops the others.
+pub(crate) fn materialize<M>(subscriptions: &[CoreSubscription<M>]) -> Subscription<ShellMessage<M>>
+where
+    M: std::fmt::Debug + Clone + Send + 'static,
+{
+    let mut listens_to_keys = false;
+
+    let mut materialized: Vec<_> = subscriptions
+        .iter()
+        .filter_map(|subscription| match subscription {
+            CoreSubscription::Every {
+                interval_ms,
+                message,
+            } => Some(Subscription::run_with_id(
+                ("core_every", *interval_ms, format!("{:?}", message)),
+                every(Duration::from_millis(*interval_ms), message.clone()),
+            )),
+            CoreSubscription::KeyPress { .. } => {
+                listens_to_keys = true;
+                None
+            }
+            // Sent by the shell when its assets watcher reports a change.
+            CoreSubscription::AssetsChanged { .. } => None,
+        })
+        .collect();
+
+    // Key presses are matched against the core's bindings in the update, as
+    // the keyboard subscription can't capture them.
+    if listens_to_keys {
+        materialized.push(keyboard::on_key_press(key_pressed));
+    }
+
+    Subscription::batch(materialized)
+}
+
+/// Looks up the message bound to the key named `key` by the core.
+pub(crate) fn key_binding<M: Clone>(subscriptions: &[CoreSubscription<M>], key: &str) -> Option<M> {
+    subscriptions
+        .iter()
+        .find_map(|subscription| match subscription {
+            CoreSubscription::KeyPress { key: name, message } if name.as_str() == key => {
+                Some(message.clone())
+            }
+            _ => None,
+        })
+}
+
+/// The messages the core wants to receive when the assets change.
+#[cfg(feature = "hot-reload")]
+pub(crate) fn assets_changed<M: Clone>(subscriptions: &[CoreSubscription<M>]) -> Vec<M> {
+    subscriptions
+        .iter()
+        .filter_map(|subscription| match subscription {
+            CoreSubscription::AssetsChanged { message } => Some(message.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Forwards the events of the windows to the shell, to be passed to the
+/// core as [`CoreEvent`]s. Close requests are reported by
+/// `window::close_requests` instead.
+pub(crate) fn core_events<M: Send + 'static>() -> Subscription<ShellMessage<M>> {
+    event::listen_with(core_event)
+}
+
+fn core_event<M>(event: Event, status: Status, window: window::Id) -> Option<ShellMessage<M>> {
+    let event = match event {
+        Event::Window(window::Event::Resized(size)) => CoreEvent::WindowResized {
+            width: size.width,
+            height: size.height,
+        },
+        Event::Window(window::Event::Focused) => CoreEvent::WindowFocused,
+        Event::Window(window::Event::Unfocused) => CoreEvent::WindowUnfocused,
+        // The input handled by a widget isn't meant for the core.
+        _ if status == Status::Captured => return None,
+        Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+            CoreEvent::KeyPressed {
+                key: KeyName::new(&key_name(key)?),
+                modifiers: modifiers.bits(),
+            }
+        }
+        Event::Keyboard(keyboard::Event::KeyReleased { key, modifiers, .. }) => {
+            CoreEvent::KeyReleased {
+                key: KeyName::new(&key_name(key)?),
+                modifiers: modifiers.bits(),
+            }
+        }
+        Event::Mouse(mouse::Event::CursorMoved { position }) => CoreEvent::CursorMoved {
+            x: position.x,
+            y: position.y,
+        },
+        Event::Mouse(mouse::Event::ButtonPressed(button)) => CoreEvent::MousePressed {
+            button: button.into(),
+        },
+        Event::Mouse(mouse::Event::ButtonReleased(button)) => CoreEvent::MouseReleased {
+            button: button.into(),
+        },
+        Event::Mouse(mouse::Event::WheelScrolled {
+            delta: ScrollDelta::Lines { x, y } | ScrollDelta::Pixels { x, y },
+        }) => CoreEvent::WheelScrolled { x, y },
+        _ => return None,
+    };
+
+    Some(ShellMessage::CoreEvent(window, event))
+}
+
+/// Reports every key press to the shell by the name the core binds keys with.
+fn key_pressed<M>(key: Key, _modifiers: Modifiers) -> Option<ShellMessage<M>> {
+    Some(ShellMessage::KeyPressed(key_name(key)?))
+}
+
+/// The name of `key` in the bindings of the core: characters are named by
+/// themselves, named keys by their iced variant.
+fn key_name(key: Key) -> Option<String> {
+    match key {
+        Key::Named(named) => Some(format!("{:?}", named)),
+        Key::Character(character) => Some(character.to_string()),
+        Key::Unidentified => None,
+    }
+}
+
+/// Yields `message` every `interval` until the subscription is dropped.
+fn every<M: Clone + Send + 'static>(
+    interval: Duration,
+    message: M,
+) -> impl Stream<Item = ShellMessage<M>> {
+    stream::channel(16, move |mut output| async move {
+        let (timer_tx, mut timer_rx) = unbounded();
+        thread::spawn(move || tick(interval, message, timer_tx));
+
+        while let Some(message) = timer_rx.next().await {
+            if output.send(message).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Sends `message` every `interval` until the receiver goes away.
+fn tick<M: Clone>(interval: Duration, message: M, output: UnboundedSender<ShellMessage<M>>) {
+    loop {
+        thread::sleep(interval);
+        if output
+            .unbounded_send(ShellMessage::App(message.clone()))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{Read, Result};
+use std::path::Path;
+
+/// How much of a library is read to find its headers, which linkers place
+/// at its start.
+const HEADER_LEN: u64 = 64 * 1024;
+
+/// The ELF program header type of the thread-local storage template.
+const PT_TLS: u32 = 7;
+
+/// The Mach-O header flag of images with thread-local variables.
+const MH_HAS_TLV_DESCRIPTORS: u32 = 0x0080_0000;
+
+/// The index of the TLS table among the PE data directories.
+const PE_TLS_DIRECTORY: usize = 9;
+
+/// Whether the library at `path` has thread-local storage, judging by its
+/// ELF, Mach-O or PE headers.
+///
+/// Destructors of thread-locals run when their thread exits, which for the
+/// main thread and the worker threads of the shell is long after a replaced
+/// core was unloaded, so they would call into unmapped code. Formats which
+/// aren't recognized count as having none.
+pub(crate) fn has_thread_locals(path: &Path) -> Result<bool> {
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(HEADER_LEN)
+        .read_to_end(&mut header)?;
+
+    let found = match header.get(..4) {
+        Some(b"\x7fELF") => elf_has_tls(&header),
+        Some([0xcf, 0xfa, 0xed, 0xfe]) => {
+            read_u32(&header, 24, true).map(|flags| flags & MH_HAS_TLV_DESCRIPTORS != 0)
+        }
+        Some([b'M', b'Z', ..]) => pe_has_tls(&header),
+        _ => None,
+    };
+    Ok(found.unwrap_or(false))
+}
+
+/// Looks for a `PT_TLS` program header in a 64-bit ELF file.
+fn elf_has_tls(header: &[u8]) -> Option<bool> {
+    // 64-bit only, in either byte order.
+    if *header.get(4)? != 2 {
+        return Some(false);
+    }
+    let little_endian = *header.get(5)? == 1;
+
+    let program_headers = read_u64(header, 32, little_endian)? as usize;
+    let entry_size = read_u16(header, 54, little_endian)? as usize;
+    let count = read_u16(header, 56, little_endian)? as usize;
+
+    Some((0..count).any(|index| {
+        read_u32(header, program_headers + index * entry_size, little_endian) == Some(PT_TLS)
+    }))
+}
+
+/// Looks for a non-empty TLS directory in a PE file.
+fn pe_has_tls(header: &[u8]) -> Option<bool> {
+    let pe_header = read_u32(header, 0x3c, true)? as usize;
+    if header.get(pe_header..pe_header + 4)? != b"PE\0\0" {
+        return Some(false);
+    }
+
+    // The optional header follows the 20 bytes of the COFF header, and its
+    // data directories start later in PE32+ than in PE32.
+    let optional_header = pe_header + 4 + 20;
+    let data_directories = match read_u16(header, optional_header, true)? {
+        0x20b => optional_header + 112,
+        0x10b => optional_header + 96,
+        _ => return Some(false),
+    };
+
+    let tls_size = read_u32(header, data_directories + PE_TLS_DIRECTORY * 8 + 4, true)?;
+    Some(tls_size != 0)
+}
+
+fn read_u16(bytes: &[u8], offset: usize, little_endian: bool) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if little_endian {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize, little_endian: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+fn read_u64(bytes: &[u8], offset: usize, little_endian: bool) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?.try_into().ok()?;
+    Some(if little_endian {
+        u64::from_le_bytes(bytes)
+    } else {
+        u64::from_be_bytes(bytes)
+    })
+}
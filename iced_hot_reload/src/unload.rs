@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// When libraries replaced by a reload are unloaded.
+///
+/// Unloading a library while anything still refers to its code or data, e.g.
+/// thread-local destructors or closures leaked by the core, crashes the
+/// process. Keeping libraries loaded for longer trades memory for safety;
+/// [`UnloadPolicy::KeepAlive`] does so only for libraries with thread-local
+/// storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnloadPolicy {
+    /// Unloads a library once it was replaced for at least the given time.
+    After(Duration),
+
+    /// Like [`Self::After`], except that libraries with thread-local storage
+    /// are kept loaded for the rest of the process.
+    ///
+    /// The destructors of thread-locals run when their thread exits. Threads
+    /// outliving a core, like the main thread or the worker threads of the
+    /// shell, would then call into a library which was unloaded and crash,
+    /// so the loader keeps such libraries mapped, and their copies on disk.
+    /// Whether a library has thread-local storage is read from its headers.
+    KeepAlive(Duration),
+
+    /// Never unloads a library before the shell exits.
+    Never,
+
+    /// Never unloads a library at all, not even when the shell exits, and
+    /// keeps its copy on disk, logging the addresses it is mapped at.
+    ///
+    /// A diagnostic mode for crashes after a reload: sanitizers, debuggers
+    /// and backtraces can symbolize frames of any core loaded during the
+    /// session, since no code is ever unmapped.
+    Leak,
+}
+
+impl Default for UnloadPolicy {
+    /// Unloads libraries as soon as iced no longer uses them.
+    fn default() -> Self {
+        Self::After(Duration::ZERO)
+    }
+}
+
+/// The replaced libraries kept loaded according to the [`UnloadPolicy`].
+///
+/// `L` is the library type of the [`crate::backend::LoaderBackend`], which
+/// unloads the libraries handed back by [`LingeringLibraries::expire`].
+pub(crate) struct LingeringLibraries<L> {
+    policy: UnloadPolicy,
+
+    /// How many of the most recently replaced libraries are never unloaded,
+    /// regardless of the policy.
+    keep: usize,
+
+    /// The replaced libraries with the time they were replaced, oldest first.
+    libraries: VecDeque<(L, Instant)>,
+}
+
+impl<L> LingeringLibraries<L> {
+    pub(crate) fn new(policy: UnloadPolicy, keep: usize) -> Self {
+        Self {
+            policy,
+            keep,
+            libraries: VecDeque::new(),
+        }
+    }
+
+    /// Takes over a library which is no longer used.
+    pub(crate) fn push(&mut self, lib: L) {
+        self.libraries.push_back((lib, Instant::now()));
+    }
+
+    /// Returns the libraries the policy no longer keeps, to be unloaded.
+    pub(crate) fn expire(&mut self) -> Vec<L> {
+        let mut expired = Vec::new();
+        let (UnloadPolicy::After(grace_period) | UnloadPolicy::KeepAlive(grace_period)) =
+            self.policy
+        else {
+            return expired;
+        };
+
+        while self.libraries.len() > self.keep
+            && self
+                .libraries
+                .front()
+                .is_some_and(|(_, replaced_at)| replaced_at.elapsed() >= grace_period)
+        {
+            expired.extend(self.libraries.pop_front().map(|(lib, _)| lib));
+        }
+
+        expired
+    }
+
+    /// Returns all the libraries regardless of the policy, e.g. to unload
+    /// them before the shell exits.
+    pub(crate) fn drain(&mut self) -> Vec<L> {
+        self.libraries.drain(..).map(|(lib, _)| lib).collect()
+    }
+
+    /// Whether some libraries wait for their grace period to end.
+    pub(crate) fn is_waiting(&self) -> bool {
+        matches!(
+            self.policy,
+            UnloadPolicy::After(_) | UnloadPolicy::KeepAlive(_)
+        ) && self.libraries.len() > self.keep
+    }
+}
@@ -0,0 +1,166 @@
+use hot_reload_interface::ViewNode;
+use iced::Element;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Parses the contents of a view file into the nodes it describes.
+pub(crate) type ParseViewFn<M> = fn(&str) -> Result<ViewNode<M>, String>;
+
+/// Parses a view file written in RON, e.g.
+/// `Center(content: Text(content: "Counter: {counter}"))`.
+pub(crate) fn parse_ron<M: DeserializeOwned>(contents: &str) -> Result<ViewNode<M>, String> {
+    ron::from_str(contents).map_err(|e| e.to_string())
+}
+
+/// The view of the core described in a watched file instead of built by its
+/// `view`, see [`crate::HotReloadBuilder::view_file`].
+///
+/// Texts may refer to the state of the core with placeholders like
+/// `{counter}` or `{player.name}`, filled in whenever the state changes.
+pub(crate) struct ViewFile<M> {
+    /// The file describing the view.
+    path: PathBuf,
+
+    /// Parses the contents of the file.
+    parse: ParseViewFn<M>,
+
+    /// When the file was last read.
+    modified: Option<SystemTime>,
+
+    /// The view described by the file, or why it couldn't be read.
+    view: Result<ViewNode<M>, String>,
+
+    /// The state of the core the placeholders are filled in from.
+    state: Value,
+}
+
+impl<M: Clone + 'static> ViewFile<M> {
+    pub(crate) fn new(path: PathBuf, parse: ParseViewFn<M>) -> Self {
+        let mut view_file = Self {
+            path,
+            parse,
+            modified: None,
+            view: Err("The view file wasn't read yet".to_string()),
+            state: Value::Null,
+        };
+        view_file.reload();
+        view_file
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the file again.
+    pub(crate) fn reload(&mut self) {
+        self.modified = modified(&self.path);
+        self.view = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))
+            .and_then(|contents| (self.parse)(&contents));
+
+        match &self.view {
+            Ok(_) => log::trace!("Read the view from {}", self.path.display()),
+            Err(e) => log::error!("Invalid view file {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// Reads the file again if it was modified since it was last read, for
+    /// when the file can't be watched.
+    pub(crate) fn refresh(&mut self) {
+        if modified(&self.path) != self.modified {
+            self.reload();
+        }
+    }
+
+    /// Updates the state the placeholders are filled in from.
+    pub(crate) fn set_state(&mut self, state: &[u8]) {
+        match serde_json::from_slice(state) {
+            Ok(state) => self.state = state,
+            Err(e) => log::error!("Failed to parse the state for the view file: {}", e),
+        }
+    }
+
+    /// Builds the view, or returns why the file couldn't be read.
+    pub(crate) fn view(&self) -> Result<Element<'static, M>, String> {
+        match &self.view {
+            Ok(view) => Ok(bind(view.clone(), &self.state).into_element()),
+            Err(e) => Err(e.clone()),
+        }
+    }
+}
+
+/// Fills the placeholders in the texts of `node` in from `state`.
+fn bind<M>(node: ViewNode<M>, state: &Value) -> ViewNode<M> {
+    match node {
+        ViewNode::Column {
+            children,
+            spacing,
+            centered,
+        } => ViewNode::Column {
+            children: children
+                .into_iter()
+                .map(|child| bind(child, state))
+                .collect(),
+            spacing,
+            centered,
+        },
+        ViewNode::Row {
+            children,
+            spacing,
+            centered,
+        } => ViewNode::Row {
+            children: children
+                .into_iter()
+                .map(|child| bind(child, state))
+                .collect(),
+            spacing,
+            centered,
+        },
+        ViewNode::Text { content, size } => ViewNode::Text {
+            content: fill_in(&content, state),
+            size,
+        },
+        ViewNode::Button { content, on_press } => ViewNode::Button {
+            content: Box::new(bind(*content, state)),
+            on_press,
+        },
+        ViewNode::Center { content } => ViewNode::Center {
+            content: Box::new(bind(*content, state)),
+        },
+        node @ ViewNode::Space { .. } => node,
+    }
+}
+
+/// Replaces each `{path}` in `content` with the value at the dotted `path`
+/// of `state`. Placeholders the state has no value for are kept.
+fn fill_in(content: &str, state: &Value) -> String {
+    let mut filled = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+
+        let path = &rest[start + 1..start + len];
+        let pointer = format!("/{}", path.replace('.', "/"));
+        filled.push_str(&rest[..start]);
+        match state.pointer(&pointer) {
+            Some(Value::String(value)) => filled.push_str(value),
+            Some(value) => filled.push_str(&value.to_string()),
+            None => filled.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+
+    filled.push_str(rest);
+    filled
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,93 @@
+use crate::remote::Transport;
+use hot_reload_interface::{Request, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{Error, ErrorKind, Result};
+use std::marker::PhantomData;
+use std::path::Path;
+use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+/// A core compiled to `wasm32` and executed by wasmtime.
+///
+/// The module runs sandboxed in its own linear memory, so a trap in the
+/// core can't corrupt the shell, and dropping the instance releases it
+/// completely, which native libraries can't guarantee.
+pub(crate) struct WasmCore<M> {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<u32, u32>,
+    dealloc: TypedFunc<(u32, u32), ()>,
+    handle: TypedFunc<(u32, u32), u64>,
+    _message: PhantomData<M>,
+}
+
+impl<M: Serialize + DeserializeOwned + 'static> WasmCore<M> {
+    /// Instantiates the module at `path` and creates the core from `state`.
+    pub(crate) fn spawn(path: &Path, state: &[u8]) -> Result<Box<dyn Transport<M>>> {
+        log::trace!("Instantiate {}", path.display());
+
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, path).map_err(wasm_error)?;
+        let mut store = Store::new(&engine, ());
+        let instance = Instance::new(&mut store, &module, &[]).map_err(wasm_error)?;
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "The module exports no memory"))?;
+        let alloc = instance
+            .get_typed_func(&mut store, "hot_reload_alloc")
+            .map_err(wasm_error)?;
+        let dealloc = instance
+            .get_typed_func(&mut store, "hot_reload_dealloc")
+            .map_err(wasm_error)?;
+        let handle = instance
+            .get_typed_func(&mut store, "hot_reload_handle")
+            .map_err(wasm_error)?;
+
+        let mut core = Self {
+            store,
+            memory,
+            alloc,
+            dealloc,
+            handle,
+            _message: PhantomData,
+        };
+        core.init(state)?;
+
+        Ok(Box::new(core))
+    }
+}
+
+impl<M: Serialize + DeserializeOwned> Transport<M> for WasmCore<M> {
+    fn request(&mut self, request: &Request<M>) -> Result<Response<M>> {
+        let request = serde_json::to_vec(request)?;
+        let len = request.len() as u32;
+
+        let ptr = self.alloc.call(&mut self.store, len).map_err(wasm_error)?;
+        self.memory
+            .write(&mut self.store, ptr as usize, &request)
+            .map_err(wasm_error)?;
+
+        // The core frees the request buffer itself.
+        let packed = self
+            .handle
+            .call(&mut self.store, (ptr, len))
+            .map_err(wasm_error)?;
+        let (response_ptr, response_len) = ((packed >> 32) as u32, packed as u32);
+
+        let mut response = vec![0; response_len as usize];
+        self.memory
+            .read(&self.store, response_ptr as usize, &mut response)
+            .map_err(wasm_error)?;
+        self.dealloc
+            .call(&mut self.store, (response_ptr, response_len))
+            .map_err(wasm_error)?;
+
+        Ok(serde_json::from_slice(&response)?)
+    }
+}
+
+/// Converts a wasmtime error, e.g. a trap raised by a panic in the core.
+fn wasm_error(error: impl std::fmt::Display) -> Error {
+    Error::other(format!("wasm: {}", error))
+}
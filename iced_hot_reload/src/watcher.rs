@@ -0,0 +1,232 @@
+use crate::message::ShellMessage;
+use iced::futures::channel::mpsc::{unbounded, UnboundedSender};
+use iced::futures::{SinkExt, Stream, StreamExt};
+use iced::stream;
+use log::{error, trace};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// How long the files of a watched directory have to stay untouched before
+/// their changes are reported.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How long a watched file has to stay untouched, with its size unchanged,
+/// before its change is reported.
+///
+/// Linkers write the artifact in several chunks, so the first event usually
+/// arrives while the file is still incomplete. Checking the size keeps the
+/// period short, which most of the latency of a reload used to be spent in.
+const QUIET_PERIOD: Duration = Duration::from_millis(60);
+
+/// The interval used to poll for changes when the file watcher can't be created.
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the dynamic library at `lib_path` and yields `ShellMessage::LibraryChanged`
+/// once the file has been rewritten and the writes have settled.
+///
+/// The parent directory is watched instead of the file itself, because cargo
+/// replaces the artifact rather than writing into it, which would silently
+/// invalidate a watch placed on the file. If the native watcher can't be set
+/// up, the stream falls back to emitting `ShellMessage::Tick` periodically.
+pub fn watch<M: Send + 'static>(lib_path: PathBuf) -> impl Stream<Item = ShellMessage<M>> {
+    watch_file(lib_path, || ShellMessage::LibraryChanged)
+}
+
+/// Watches the file at `lib_path` like [`watch`], yielding the message
+/// returned by `changed` instead.
+pub fn watch_file<M: Send + 'static>(
+    lib_path: PathBuf,
+    changed: fn() -> ShellMessage<M>,
+) -> impl Stream<Item = ShellMessage<M>> {
+    stream::channel(16, move |mut output| async move {
+        let (notify_tx, mut notify_rx) = unbounded();
+
+        let _watcher = match create_watcher(&lib_path, changed, notify_tx.clone()) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                error!("Failed to watch {}: {}", lib_path.display(), e);
+                thread::spawn(move || poll(notify_tx));
+                None
+            }
+        };
+
+        while let Some(message) = notify_rx.next().await {
+            if output.send(message).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Watches the directory at `dir` and everything below it, and yields the
+/// message returned by `changed` for the files that were written, created
+/// or removed once the writes have settled.
+///
+/// Unlike the library, the directory can't be polled, so nothing is yielded
+/// if the native watcher can't be set up.
+pub fn watch_dir<M: Send + 'static>(
+    dir: PathBuf,
+    changed: fn(Vec<PathBuf>) -> ShellMessage<M>,
+) -> impl Stream<Item = ShellMessage<M>> {
+    stream::channel(16, move |mut output| async move {
+        let (notify_tx, mut notify_rx) = unbounded();
+
+        let (events_tx, events_rx) = std::sync::mpsc::channel();
+        let _watcher = notify::recommended_watcher(events_tx)
+            .and_then(|mut watcher| {
+                watcher.watch(&dir, RecursiveMode::Recursive)?;
+                Ok(watcher)
+            })
+            .inspect(|_| {
+                trace!("Watching {} for changes", dir.display());
+                thread::spawn(move || collect_changes(events_rx, changed, notify_tx));
+            })
+            .inspect_err(|e| error!("Failed to watch {}: {}", dir.display(), e));
+
+        while let Some(message) = notify_rx.next().await {
+            if output.send(message).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Creates the native watcher and spawns the debouncing thread feeding `output`.
+fn create_watcher<M: Send + 'static>(
+    lib_path: &Path,
+    changed: fn() -> ShellMessage<M>,
+    output: UnboundedSender<ShellMessage<M>>,
+) -> notify::Result<notify::RecommendedWatcher> {
+    let watch_dir = match lib_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let (events_tx, events_rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events_tx)?;
+    watcher.watch(&watch_dir, RecursiveMode::NonRecursive)?;
+
+    trace!("Watching {} for changes", watch_dir.display());
+
+    let lib_path = lib_path.to_path_buf();
+    thread::spawn(move || debounce(&lib_path, events_rx, changed, output));
+
+    Ok(watcher)
+}
+
+/// Collapses bursts of file system events into a single message returned by
+/// `changed`.
+fn debounce<M>(
+    lib_path: &Path,
+    events: Receiver<notify::Result<Event>>,
+    changed: fn() -> ShellMessage<M>,
+    output: UnboundedSender<ShellMessage<M>>,
+) {
+    loop {
+        match events.recv() {
+            Ok(Ok(event)) if touches(&event, lib_path) => (),
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => {
+                error!("File watcher error: {}", e);
+                continue;
+            }
+            Err(_) => return,
+        }
+
+        // Wait until the linker stops writing.
+        let mut size = file_size(lib_path);
+        loop {
+            match events.recv_timeout(QUIET_PERIOD) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => {
+                    let settled_size = file_size(lib_path);
+                    if settled_size.is_some() && settled_size == size {
+                        break;
+                    }
+                    size = settled_size;
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        trace!("{} changed", lib_path.display());
+        if output.unbounded_send(changed()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Collects the files changed by bursts of file system events into a single
+/// message returned by `changed`.
+fn collect_changes<M>(
+    events: Receiver<notify::Result<Event>>,
+    changed: fn(Vec<PathBuf>) -> ShellMessage<M>,
+    output: UnboundedSender<ShellMessage<M>>,
+) {
+    loop {
+        let mut paths = Vec::new();
+        match events.recv() {
+            Ok(Ok(event)) if changes(&event) => paths.extend(event.paths),
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => {
+                error!("File watcher error: {}", e);
+                continue;
+            }
+            Err(_) => return,
+        }
+
+        // Wait until the editor or exporter stops writing.
+        loop {
+            match events.recv_timeout(DEBOUNCE) {
+                Ok(Ok(event)) if changes(&event) => paths.extend(event.paths),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        paths.sort();
+        paths.dedup();
+        trace!("{} files changed", paths.len());
+        if output.unbounded_send(changed(paths)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Whether `event` wrote, created or removed a file.
+fn changes(event: &Event) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any
+    )
+}
+
+/// Whether `event` rewrote the file located at `lib_path`.
+fn touches(event: &Event, lib_path: &Path) -> bool {
+    let modifies = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any
+    );
+
+    modifies
+        && event
+            .paths
+            .iter()
+            .any(|path| path.file_name() == lib_path.file_name())
+}
+
+/// The size of the file at `path`, `None` while it doesn't exist.
+fn file_size(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+/// Emits `ShellMessage::Tick` periodically until the receiver goes away.
+fn poll<M>(output: UnboundedSender<ShellMessage<M>>) {
+    while output.unbounded_send(ShellMessage::Tick).is_ok() {
+        thread::sleep(FALLBACK_POLL_INTERVAL);
+    }
+}
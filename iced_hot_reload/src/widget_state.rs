@@ -0,0 +1,154 @@
+use iced::advanced::widget::operation::{Focusable, Outcome, Scrollable};
+use iced::advanced::widget::{operate, Id, Operation};
+use iced::widget::scrollable::AbsoluteOffset;
+use iced::{Rectangle, Task, Vector};
+
+/// Identifies a widget across views built by different versions of the core:
+/// by its id if it has one, by its position among the widgets of its kind
+/// otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WidgetKey {
+    Id(Id),
+    Position(usize),
+}
+
+/// Assigns the [`WidgetKey`]s of one kind of widgets in the order they are
+/// visited.
+#[derive(Default)]
+struct Keys {
+    anonymous: usize,
+}
+
+impl Keys {
+    fn next(&mut self, id: Option<&Id>) -> WidgetKey {
+        match id {
+            Some(id) => WidgetKey::Id(id.clone()),
+            None => {
+                self.anonymous += 1;
+                WidgetKey::Position(self.anonymous - 1)
+            }
+        }
+    }
+}
+
+/// The state kept by iced in the widget tree rather than by the core, which
+/// is lost when a reload rebuilds the tree: the scroll offsets and the
+/// focused widget.
+///
+/// Text the user typed is part of the state of the core and survives the
+/// reload anyway.
+#[derive(Debug, Clone, Default)]
+pub struct WidgetState {
+    offsets: Vec<(WidgetKey, AbsoluteOffset)>,
+    focused: Option<WidgetKey>,
+}
+
+impl WidgetState {
+    fn is_empty(&self) -> bool {
+        self.offsets.is_empty() && self.focused.is_none()
+    }
+}
+
+/// Reads the [`WidgetState`] of the current view.
+pub(crate) fn capture() -> Task<WidgetState> {
+    operate(Capture::default())
+}
+
+/// Applies `state`, captured from the view of the previous core, to the
+/// current view. Widgets which can't be matched are left alone.
+pub(crate) fn restore<T: Send + 'static>(state: WidgetState) -> Task<T> {
+    if state.is_empty() {
+        return Task::none();
+    }
+
+    operate(Restore {
+        state,
+        scrollables: Keys::default(),
+        focusables: Keys::default(),
+    })
+}
+
+#[derive(Default)]
+struct Capture {
+    state: WidgetState,
+    scrollables: Keys,
+    focusables: Keys,
+}
+
+impl Operation<WidgetState> for Capture {
+    fn container(
+        &mut self,
+        _id: Option<&Id>,
+        _bounds: Rectangle,
+        operate_on_children: &mut dyn FnMut(&mut dyn Operation<WidgetState>),
+    ) {
+        operate_on_children(self);
+    }
+
+    fn scrollable(
+        &mut self,
+        _state: &mut dyn Scrollable,
+        id: Option<&Id>,
+        _bounds: Rectangle,
+        _content_bounds: Rectangle,
+        translation: Vector,
+    ) {
+        let key = self.scrollables.next(id);
+        self.state.offsets.push((
+            key,
+            AbsoluteOffset {
+                x: translation.x,
+                y: translation.y,
+            },
+        ));
+    }
+
+    fn focusable(&mut self, state: &mut dyn Focusable, id: Option<&Id>) {
+        let key = self.focusables.next(id);
+        if state.is_focused() {
+            self.state.focused = Some(key);
+        }
+    }
+
+    fn finish(&self) -> Outcome<WidgetState> {
+        Outcome::Some(self.state.clone())
+    }
+}
+
+struct Restore {
+    state: WidgetState,
+    scrollables: Keys,
+    focusables: Keys,
+}
+
+impl<T> Operation<T> for Restore {
+    fn container(
+        &mut self,
+        _id: Option<&Id>,
+        _bounds: Rectangle,
+        operate_on_children: &mut dyn FnMut(&mut dyn Operation<T>),
+    ) {
+        operate_on_children(self);
+    }
+
+    fn scrollable(
+        &mut self,
+        state: &mut dyn Scrollable,
+        id: Option<&Id>,
+        _bounds: Rectangle,
+        _content_bounds: Rectangle,
+        _translation: Vector,
+    ) {
+        let key = self.scrollables.next(id);
+        if let Some((_, offset)) = self.state.offsets.iter().find(|(saved, _)| *saved == key) {
+            state.scroll_to(*offset);
+        }
+    }
+
+    fn focusable(&mut self, state: &mut dyn Focusable, id: Option<&Id>) {
+        let key = self.focusables.next(id);
+        if self.state.focused.as_ref() == Some(&key) {
+            state.focus();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::message::ShellMessage;
+use crate::offscreen;
+use iced::advanced::layout::{Limits, Node};
+use iced::advanced::widget::tree::{Tag, Tree};
+use iced::widget::{button, row, scrollable, text, text_input, Column};
+use iced::{padding, Color, Element, Point, Rectangle, Size, Vector};
+use std::collections::HashSet;
+
+/// The paragraph of the text widgets drawn by the shell's renderer.
+type Paragraph = <iced::Renderer as iced::advanced::text::Renderer>::Paragraph;
+
+/// A widget of the view of the core, with the bounds it was laid out in.
+struct WidgetNode {
+    /// The type of the widget, as far as it can be told from its state and
+    /// layout.
+    name: &'static str,
+
+    bounds: Rectangle,
+
+    children: Vec<WidgetNode>,
+}
+
+/// The structure of the view of the core as a collapsible tree, shown in the
+/// dev overlay to debug layouts.
+///
+/// Widgets don't report their type, so it is told from the state the widget
+/// keeps, e.g. a text input, or for stateless widgets from how their
+/// children are laid out, e.g. side by side for a row. Nodes are identified
+/// by the indices leading to them, e.g. `0/2`, so nodes stay expanded while
+/// the view changes around them.
+pub(crate) struct WidgetTreeInspector {
+    /// The view of the core when it was last refreshed.
+    root: Option<WidgetNode>,
+
+    /// The paths of the expanded nodes. The root is expanded initially.
+    expanded: HashSet<String>,
+}
+
+impl WidgetTreeInspector {
+    pub(crate) fn new() -> Self {
+        Self {
+            root: None,
+            expanded: HashSet::from([String::new()]),
+        }
+    }
+
+    /// Lays out `element` in a window of `size` and shows its structure.
+    pub(crate) fn refresh<M>(&mut self, element: &Element<'_, M>, size: Size) {
+        let renderer = offscreen::software_renderer();
+        let widget = element.as_widget();
+        let mut tree = Tree::new(widget);
+        let node = widget.layout(&mut tree, &renderer, &Limits::new(Size::ZERO, size));
+
+        self.root = Some(inspect(&tree, &node, Vector::ZERO));
+    }
+
+    /// Expands the node at `path` if it is collapsed, collapses it
+    /// otherwise.
+    pub(crate) fn toggle(&mut self, path: String) {
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+    }
+
+    /// Renders the widget tree.
+    pub(crate) fn view<M: Clone + 'static>(&self) -> Element<'_, ShellMessage<M>> {
+        let refresh = button(text("Refresh").size(12)).on_press(ShellMessage::RefreshWidgetTree);
+
+        let tree: Element<_> = match &self.root {
+            Some(root) => self
+                .push_node(Column::new().spacing(2), root, String::new(), 0)
+                .into(),
+            None => text("-").size(12).into(),
+        };
+
+        Column::new()
+            .spacing(4)
+            .push(refresh)
+            .push(scrollable(tree).height(240))
+            .into()
+    }
+
+    /// Adds the line of `node` to `lines`, followed by its children if it is
+    /// expanded.
+    fn push_node<'a, M: Clone + 'static>(
+        &'a self,
+        lines: Column<'a, ShellMessage<M>>,
+        node: &'a WidgetNode,
+        path: String,
+        depth: u16,
+    ) -> Column<'a, ShellMessage<M>> {
+        let indent = f32::from(depth * 12);
+        let bounds = format!(
+            "{}×{} at {}, {}",
+            node.bounds.width, node.bounds.height, node.bounds.x, node.bounds.y
+        );
+
+        if node.children.is_empty() {
+            let line = row![
+                text(node.name).size(12).color(Color::WHITE),
+                text(bounds).size(12).color(Color::from_rgb(0.7, 0.7, 0.7)),
+            ]
+            .spacing(6)
+            .padding(padding::left(indent));
+            return lines.push(line);
+        }
+
+        let expanded = self.expanded.contains(&path);
+        let marker = if expanded { "▾" } else { "▸" };
+        let toggle = button(
+            text(format!(
+                "{} {} [{}] {}",
+                marker,
+                node.name,
+                node.children.len(),
+                bounds
+            ))
+            .size(12),
+        )
+        .padding([0, 4])
+        .style(button::text)
+        .on_press(ShellMessage::ToggleWidgetNode(path.clone()));
+        let mut lines = lines.push(row![toggle].padding(padding::left(indent)));
+
+        if !expanded {
+            return lines;
+        }
+
+        for (index, child) in node.children.iter().enumerate() {
+            let child_path = if path.is_empty() {
+                index.to_string()
+            } else {
+                format!("{}/{}", path, index)
+            };
+            lines = self.push_node(lines, child, child_path, depth + 1);
+        }
+
+        lines
+    }
+}
+
+/// Describes the widget with the state `tree` laid out as `node`, positioned
+/// relative to `offset`.
+fn inspect(tree: &Tree, node: &Node, offset: Vector) -> WidgetNode {
+    let bounds = node.bounds() + offset;
+    let origin = Vector::new(bounds.x, bounds.y);
+
+    // The layout of a widget may have more children than it has child
+    // widgets, e.g. the text of an input, so only the child widgets are
+    // shown.
+    let children: Vec<WidgetNode> = tree
+        .children
+        .iter()
+        .zip(node.children())
+        .map(|(tree, node)| inspect(tree, node, origin))
+        .collect();
+
+    WidgetNode {
+        name: name(tree, &children),
+        bounds,
+        children,
+    }
+}
+
+/// Tells the type of the widget from its state, or from the layout of its
+/// `children` if it has none.
+fn name(tree: &Tree, children: &[WidgetNode]) -> &'static str {
+    if tree.tag == Tag::of::<iced::advanced::widget::text::State<Paragraph>>() {
+        return "Text";
+    }
+    if tree.tag == Tag::of::<text_input::State<Paragraph>>() {
+        return "TextInput";
+    }
+    if tree.tag != Tag::stateless() {
+        return "Widget";
+    }
+
+    match children {
+        [] => "Leaf",
+        [_] => "Container",
+        [first, rest @ ..] => {
+            let Point { x, y } = first.bounds.position();
+            if rest.iter().all(|child| child.bounds.y == y) {
+                "Row"
+            } else if rest.iter().all(|child| child.bounds.x == x) {
+                "Column"
+            } else {
+                "Stack"
+            }
+        }
+    }
+}
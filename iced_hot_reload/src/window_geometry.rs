@@ -0,0 +1,168 @@
+use iced::{window, Point, Size};
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The size and position of the shell window, restored on the next start.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WindowGeometry {
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+
+    /// The position of the window, unless it wasn't moved yet.
+    pub(crate) x: Option<f32>,
+    pub(crate) y: Option<f32>,
+}
+
+impl WindowGeometry {
+    pub(crate) fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    pub(crate) fn position(&self) -> Option<window::Position> {
+        let (Some(x), Some(y)) = (self.x, self.y) else {
+            return None;
+        };
+
+        Some(window::Position::Specific(Point::new(x, y)))
+    }
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        let size = window::Settings::default().size;
+
+        Self {
+            width: size.width,
+            height: size.height,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+/// Keeps the geometry of the shell window in a file of the config
+/// directory, see [`default_path`].
+///
+/// The geometry is written whenever the window is moved or resized, so it
+/// is kept even if the shell doesn't exit cleanly. Reloads don't touch the
+/// window, so they keep its geometry anyway.
+pub(crate) struct WindowGeometryStore {
+    /// The file the geometry is written to.
+    path: PathBuf,
+
+    /// The last geometry written.
+    geometry: WindowGeometry,
+}
+
+impl WindowGeometryStore {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            geometry: load(&path).unwrap_or_default(),
+            path,
+        }
+    }
+
+    pub(crate) fn resized(&mut self, size: Size) {
+        self.update(WindowGeometry {
+            width: size.width,
+            height: size.height,
+            ..self.geometry
+        });
+    }
+
+    pub(crate) fn moved(&mut self, position: Point) {
+        self.update(WindowGeometry {
+            x: Some(position.x),
+            y: Some(position.y),
+            ..self.geometry
+        });
+    }
+
+    fn update(&mut self, geometry: WindowGeometry) {
+        if geometry == self.geometry {
+            return;
+        }
+
+        self.geometry = geometry;
+        let written = fs::create_dir_all(self.path.parent().unwrap_or(Path::new(".")))
+            .and_then(|()| Ok(serde_json::to_vec(&self.geometry)?))
+            .and_then(|json| fs::write(&self.path, json));
+
+        if let Err(e) = written {
+            error!(
+                "Failed to save the window geometry to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Reads the geometry saved at `path`, if there is one.
+pub(crate) fn load(path: &Path) -> Option<WindowGeometry> {
+    let json = fs::read(path)
+        .inspect_err(|e| trace!("No window geometry restored from {}: {}", path.display(), e))
+        .ok()?;
+
+    serde_json::from_slice(&json)
+        .inspect_err(|e| error!("Invalid window geometry in {}: {}", path.display(), e))
+        .ok()
+}
+
+/// The file the geometry of the shell of the core `lib_name` is kept in,
+/// in the config directory of the user.
+pub(crate) fn default_path(lib_name: &str) -> Option<PathBuf> {
+    Some(
+        config_dir()?
+            .join("iced_hot_reload")
+            .join(lib_name)
+            .join("window.json"),
+    )
+}
+
+/// The config directory of the user, e.g. `~/.config` on Linux.
+fn config_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).map(PathBuf::from);
+
+    if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_geometry_is_restored_by_the_next_store() {
+        let dir =
+            std::env::temp_dir().join(format!("iced_hot_reload_geometry_{}", std::process::id()));
+        let path = dir.join("core").join("window.json");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut store = WindowGeometryStore::new(path.clone());
+        assert_eq!(store.geometry, WindowGeometry::default());
+        assert!(!path.exists());
+
+        store.resized(Size::new(640.0, 480.0));
+        store.moved(Point::new(10.0, 20.0));
+
+        let restored = WindowGeometryStore::new(path.clone()).geometry;
+        assert_eq!(restored.size(), Size::new(640.0, 480.0));
+        assert_eq!(
+            restored.position(),
+            Some(window::Position::Specific(Point::new(10.0, 20.0)))
+        );
+
+        fs::write(&path, b"{").unwrap();
+        assert_eq!(load(&path), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
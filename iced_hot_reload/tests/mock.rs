@@ -0,0 +1,112 @@
+//! Drives the shell through failed reloads with a mocked core.
+
+#![cfg(feature = "hot-reload")]
+
+use iced::widget::text;
+use iced::Element;
+use iced_hot_reload::{HotReload, MockBackend, MockFailure, MockShell};
+use shared_types::{AppState, Message};
+
+fn update(state: &mut AppState, message: Message) {
+    match message {
+        Message::Increment => state.counter += 1,
+        Message::Decrement => state.counter -= 1,
+    }
+}
+
+fn view(state: &AppState) -> Element<'static, Message> {
+    text(format!("Counter: {}", state.counter)).into()
+}
+
+/// Starts a shell giving up on a reload after `attempts` failed loads.
+fn shell(attempts: u32) -> (MockShell<Message>, MockBackend<Message>) {
+    let backend = MockBackend::new(update, view);
+    let shell = HotReload::<Message>::builder()
+        .reload_attempts(attempts)
+        .build()
+        .mock(backend.clone())
+        .expect("The mocked core failed to start");
+
+    (shell, backend)
+}
+
+fn counter(shell: &MockShell<Message>) -> i32 {
+    let state = shell.save_state().expect("The core can't save its state");
+    serde_json::from_slice::<AppState>(&state)
+        .expect("The state isn't an AppState")
+        .counter
+}
+
+/// Reloads after every load of the reload failed with `failure`, returning
+/// the notice the shell shows.
+fn failed_reload(failure: MockFailure) -> String {
+    let (mut shell, backend) = shell(2);
+    shell.send(Message::Increment);
+    backend.fail_next_load(failure.clone());
+    backend.fail_next_load(failure);
+
+    backend.rebuild();
+    shell.reload().render();
+
+    assert_eq!(backend.loads(), 3);
+    assert_eq!(counter(&shell), 1, "The current core wasn't kept");
+    shell
+        .notice()
+        .expect("The failed reload wasn't reported")
+        .to_string()
+}
+
+#[test]
+fn missing_symbol_keeps_the_current_core() {
+    let notice = failed_reload(MockFailure::MissingSymbol("create_app".into()));
+
+    assert!(notice.contains("after 2 attempts"), "{}", notice);
+    assert!(notice.contains("create_app"), "{}", notice);
+}
+
+#[test]
+fn null_create_keeps_the_current_core() {
+    let notice = failed_reload(MockFailure::NullCreate);
+
+    assert!(notice.contains("Failed to initialize"), "{}", notice);
+}
+
+#[test]
+fn bad_abi_keeps_the_current_core() {
+    let notice = failed_reload(MockFailure::BadAbi(0));
+
+    assert!(notice.contains("ABI version 0"), "{}", notice);
+}
+
+#[test]
+fn panic_rolls_back_to_the_previous_core() {
+    let (mut shell, backend) = shell(2);
+    shell.send(Message::Increment);
+    backend.fail_next_load(MockFailure::Panic("broken".into()));
+
+    backend.rebuild();
+    shell.reload().send(Message::Increment);
+    assert_eq!(shell.panic().as_deref(), Some("broken"));
+
+    shell.tick();
+    assert_eq!(shell.panic(), None);
+    let notice = shell.notice().expect("The rollback wasn't reported");
+    assert!(notice.contains("Rolled back"), "{}", notice);
+
+    shell.send(Message::Increment);
+    assert_eq!(counter(&shell), 2);
+}
+
+#[test]
+fn failed_load_is_retried() {
+    let (mut shell, backend) = shell(3);
+    shell.send(Message::Increment);
+    backend.fail_next_load(MockFailure::MissingSymbol("create_app".into()));
+
+    backend.rebuild();
+    shell.reload();
+
+    assert_eq!(backend.loads(), 3);
+    assert_eq!(shell.notice(), None);
+    assert_eq!(counter(&shell), 1);
+}
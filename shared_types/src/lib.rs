@@ -1,29 +1,19 @@
-use iced::Element;
+use serde::{Deserialize, Serialize};
 
-pub type AppInterfacePtr = *mut Box<dyn AppInterface>;
-pub type CreateFn = unsafe extern "C" fn(AppState) -> AppInterfacePtr;
-pub type DestroyFn = unsafe extern "C" fn(AppInterfacePtr);
-
-/// All UI events/messages passed between shell and core.
+/// All UI events/messages of the example application.
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
-    Tick,
     Increment,
     Decrement,
-    Reload,
 }
 
-/// The state of the application
-#[derive(Debug, Clone)]
-#[repr(C)]
+/// The state of the example application.
+///
+/// The state is serialized when the core is reloaded. Thanks to
+/// `#[serde(default)]` fields can be added without losing the rest of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppState {
     pub counter: i32,
 }
-
-/// Represents the contract between app and core.
-pub trait AppInterface {
-    fn update(&mut self, message: Message);
-    fn view(&self) -> Element<'static, Message>;
-    fn state(&self) -> &AppState;
-}